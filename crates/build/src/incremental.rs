@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spin_common::sha256;
+use spin_manifest::schema::v2;
+
+use crate::{SortableBuildInfo, construct_workdir, manifest::ComponentBuildInfo};
+
+const BUILD_STATE_FILE: &str = "build-state.json";
+
+/// The fingerprints of the components built in an application, used to skip
+/// building components whose inputs haven't changed.
+///
+/// This is stored in the application's `.spin` directory.
+pub(crate) struct BuildState {
+    path: PathBuf,
+    components: Mutex<HashMap<String, ComponentBuildRecord>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ComponentBuildRecord {
+    /// The fingerprint of the build command and inputs.
+    fingerprint: String,
+    /// The digest of the build output.
+    output_digest: String,
+}
+
+impl BuildState {
+    /// Loads the build state for the application. A missing or unreadable
+    /// state file is treated as if nothing has been built.
+    pub fn load(app_dir: &Path) -> Self {
+        let path = app_dir.join(".spin").join(BUILD_STATE_FILE);
+        let components = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            components: Mutex::new(components),
+        }
+    }
+
+    /// Whether the component was last built with the given fingerprint, and
+    /// its output is as that build left it.
+    pub fn is_up_to_date(&self, build_info: &ComponentBuildInfo, fingerprint: &str) -> bool {
+        let components = self.components.lock().unwrap();
+        let Some(record) = components.get(&build_info.id) else {
+            return false;
+        };
+        record.fingerprint == fingerprint
+            && output_path(build_info, self.app_dir())
+                .and_then(|output| sha256::hex_digest_from_file(output).ok())
+                .is_some_and(|digest| digest == record.output_digest)
+    }
+
+    /// Records a successful build of the component.
+    pub fn record(&self, build_info: &ComponentBuildInfo, fingerprint: String) {
+        let mut components = self.components.lock().unwrap();
+        match output_path(build_info, self.app_dir())
+            .and_then(|output| sha256::hex_digest_from_file(output).ok())
+        {
+            Some(output_digest) => {
+                components.insert(
+                    build_info.id.clone(),
+                    ComponentBuildRecord {
+                        fingerprint,
+                        output_digest,
+                    },
+                );
+            }
+            None => {
+                // Without an output to check, the component can't be skipped.
+                components.remove(&build_info.id);
+            }
+        }
+    }
+
+    /// Saves the build state to the application's `.spin` directory.
+    pub fn save(&self) -> Result<()> {
+        let components = self.components.lock().unwrap();
+        if components.is_empty() && !self.path.exists() {
+            return Ok(());
+        }
+        let sorted = components.iter().collect::<BTreeMap<_, _>>();
+        std::fs::create_dir_all(self.path.parent().unwrap())?;
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&sorted)?)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+
+    /// The IDs of the components whose fingerprint has changed since they were
+    /// last built. Components with no recorded build are not included.
+    pub fn stale_components(&self, components: &[ComponentBuildInfo]) -> Vec<String> {
+        let recorded = self.components.lock().unwrap().clone();
+        components
+            .iter()
+            .filter(|c| recorded.contains_key(&c.id))
+            .filter(|c| match fingerprint(c, self.app_dir()) {
+                Ok(Some(fingerprint)) => !self.is_up_to_date(c, &fingerprint),
+                // The component no longer declares inputs, so it's always built.
+                Ok(None) => false,
+                Err(_) => true,
+            })
+            .map(|c| c.id.clone())
+            .collect()
+    }
+
+    fn app_dir(&self) -> &Path {
+        // The state file is always at `<app_dir>/.spin/<file>`.
+        self.path.parent().and_then(Path::parent).unwrap()
+    }
+}
+
+/// Computes a fingerprint of the component's build commands, the contents of
+/// its inputs, and the contents of its local dependencies.
+///
+/// Returns `None` if the component has no build, or declares no inputs, in
+/// which case it must always be built.
+pub(crate) fn fingerprint(
+    build_info: &ComponentBuildInfo,
+    app_dir: &Path,
+) -> Result<Option<String>> {
+    let Some(build) = &build_info.build else {
+        return Ok(None);
+    };
+    if build.inputs().is_empty() {
+        return Ok(None);
+    }
+
+    let workdir = construct_workdir(app_dir, build.workdir.as_ref())?;
+    let mut entries = vec![];
+    for command in build.commands() {
+        entries.push(format!("command:{command}"));
+    }
+    entries.push(format!(
+        "workdir:{}",
+        build.workdir.as_deref().unwrap_or("")
+    ));
+    if build.preinit {
+        entries.push("preinit".to_owned());
+    }
+
+    let mut input_files = BTreeMap::new();
+    for pattern in build.inputs() {
+        let full_pattern = workdir.join(pattern);
+        let matches = glob::glob(&full_pattern.to_string_lossy())
+            .with_context(|| format!("invalid input glob '{pattern}'"))?;
+        for path in matches.flatten().filter(|p| p.is_file()) {
+            let digest = sha256::hex_digest_from_file(&path)
+                .with_context(|| format!("failed to read input {}", path.display()))?;
+            input_files.insert(path, digest);
+        }
+    }
+    for (path, digest) in &input_files {
+        let relative = path.strip_prefix(&workdir).unwrap_or(path);
+        entries.push(format!("input:{}:{digest}", relative.display()));
+    }
+
+    for dependency in SortableBuildInfo::from(build_info).local_dependency_paths {
+        let digest = sha256::hex_digest_from_file(app_dir.join(&dependency))
+            .unwrap_or_else(|_| "missing".to_owned());
+        entries.push(format!("dependency:{dependency}:{digest}"));
+    }
+
+    Ok(Some(sha256::hex_digest_from_bytes(entries.join("\n"))))
+}
+
+fn output_path(build_info: &ComponentBuildInfo, app_dir: &Path) -> Option<PathBuf> {
+    match build_info.source.as_ref()? {
+        v2::ComponentSource::Local(path) => Some(app_dir.join(path)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component_with_inputs(id: &str, inputs: &[&str]) -> ComponentBuildInfo {
+        let build = toml::from_str(&format!("command = \"make\"\ninputs = {inputs:?}")).unwrap();
+        ComponentBuildInfo {
+            id: id.into(),
+            source: Some(v2::ComponentSource::Local(format!("{id}.wasm"))),
+            build: Some(build),
+            dependencies: Default::default(),
+            targets: None,
+        }
+    }
+
+    #[test]
+    fn unchanged_components_are_up_to_date() -> Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        std::fs::create_dir(app_dir.path().join("src"))?;
+        std::fs::write(app_dir.path().join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(app_dir.path().join("app.wasm"), "wasm")?;
+        let component = component_with_inputs("app", &["src/**/*.rs"]);
+
+        let fingerprint1 = fingerprint(&component, app_dir.path())?.unwrap();
+        let state = BuildState::load(app_dir.path());
+        state.record(&component, fingerprint1.clone());
+        state.save()?;
+
+        let state = BuildState::load(app_dir.path());
+        assert!(state.is_up_to_date(&component, &fingerprint1));
+        assert!(
+            state
+                .stale_components(std::slice::from_ref(&component))
+                .is_empty()
+        );
+
+        std::fs::write(app_dir.path().join("src/main.rs"), "fn main() { }")?;
+        let fingerprint2 = fingerprint(&component, app_dir.path())?.unwrap();
+        assert!(!state.is_up_to_date(&component, &fingerprint2));
+        assert_eq!(
+            vec!["app"],
+            state.stale_components(std::slice::from_ref(&component))
+        );
+
+        // Modifying the output also makes the component out of date
+        std::fs::write(app_dir.path().join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(app_dir.path().join("app.wasm"), "other wasm")?;
+        assert!(!state.is_up_to_date(&component, &fingerprint1));
+        Ok(())
+    }
+
+    #[test]
+    fn components_without_inputs_have_no_fingerprint() -> Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        let component = component_with_inputs("app", &[]);
+        assert!(fingerprint(&component, app_dir.path())?.is_none());
+
+        // Watched files aren't taken to be the build's inputs.
+        let mut component = component_with_inputs("app", &[]);
+        component.build.as_mut().unwrap().watch = vec!["src/**/*.rs".into()];
+        assert!(fingerprint(&component, app_dir.path())?.is_none());
+        Ok(())
+    }
+}
@@ -2,23 +2,33 @@
 
 //! A library for building Spin components.
 
+mod incremental;
 mod manifest;
+mod parallel;
 
 use anyhow::{Context, Result, anyhow, bail};
+use incremental::BuildState;
 use manifest::ComponentBuildInfo;
+use parallel::Cancellation;
 use spin_common::{paths::parent_dir, ui::quoted_path};
 use spin_manifest::schema::v2;
 use std::{
     collections::HashSet,
+    io::BufRead,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
-use subprocess::{Exec, Redirection};
+use subprocess::{Popen, Redirection};
 
 use crate::manifest::component_build_configs;
 
 const LAST_BUILD_PROFILE_FILE: &str = "last-build.txt";
 const LAST_BUILD_ANON_VALUE: &str = "<anonymous>";
 
+/// How often a running build command checks whether it has been cancelled.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// If present, run the build command of each component.
 pub async fn build(
     manifest_file: &Path,
@@ -26,6 +36,7 @@ pub async fn build(
     component_ids: &[String],
     target_checks: TargetChecking,
     wit_generation: GenerateDependencyWits,
+    options: BuildCommandOptions,
     cache_root: Option<PathBuf>,
 ) -> Result<()> {
     let build_info = component_build_configs(manifest_file, profile)
@@ -59,7 +70,8 @@ pub async fn build(
         }
     }
 
-    let build_result = build_components(components_to_build, &app_dir);
+    let build_result =
+        build_components(components_to_build, &app_dir, options.rebuild, options.jobs);
 
     // Emit any required warnings now, so that they don't bury any errors.
     if let Some(e) = build_info.load_error() {
@@ -137,11 +149,18 @@ pub async fn build_default(
         &[],
         TargetChecking::Check,
         GenerateDependencyWits::Generate,
+        BuildCommandOptions::default(),
         cache_root,
     )
     .await
 }
 
+/// The number of component builds to run at once if not otherwise specified:
+/// the available parallelism of the machine.
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
 fn components_to_build(
     component_ids: &[String],
     components: Vec<ComponentBuildInfo>,
@@ -199,6 +218,8 @@ async fn regenerate_wits(
 fn build_components(
     components_to_build: Vec<ComponentBuildInfo>,
     app_dir: &Path,
+    rebuild: Rebuild,
+    jobs: usize,
 ) -> anyhow::Result<()> {
     if components_to_build.iter().all(|c| c.build.is_none()) {
         println!("None of the components have a build command.");
@@ -219,18 +240,109 @@ fn build_components(
         );
     }
 
-    components_to_build
-        .into_iter()
-        .map(|c| build_component(c, app_dir))
-        .collect::<Result<Vec<_>, _>>()?;
+    // Components are built concurrently once their dependencies are built. If
+    // there's a cycle, dependencies can't be respected, so don't try.
+    let dependencies = if has_cycle {
+        vec![vec![]; components_to_build.len()]
+    } else {
+        dependency_indices(&components_to_build)
+    };
+    // Whether several builds may run at once. If so, their output is prefixed
+    // with the component ID so that it can be told apart.
+    let concurrent = jobs > 1
+        && components_to_build
+            .iter()
+            .filter(|c| c.build.is_some())
+            .count()
+            > 1;
+
+    let build_state = BuildState::load(app_dir);
+    let cancellation = Arc::new(Cancellation::default());
+    let (builds_finished, _) = tokio::sync::watch::channel(false);
+    cancel_on_interrupt(cancellation.clone(), builds_finished.subscribe());
+    let build_result = parallel::run_in_dependency_order(
+        &components_to_build,
+        &dependencies,
+        jobs,
+        &cancellation,
+        |c, cancellation| {
+            if !rebuild.always()
+                && let Some(fingerprint) = component_fingerprint(c, app_dir)
+                && build_state.is_up_to_date(c, &fingerprint)
+            {
+                terminal::step!("Fresh", "component {} is up to date", c.id);
+                return Ok(());
+            }
+            build_component(c, app_dir, concurrent, cancellation)?;
+            // The fingerprint is taken after the build in case the build
+            // updates its own inputs (e.g. lock files).
+            if let Some(fingerprint) = component_fingerprint(c, app_dir) {
+                build_state.record(c, fingerprint);
+            }
+            Ok(())
+        },
+    );
+    builds_finished.send_replace(true);
+    if let Err(e) = build_state.save() {
+        tracing::warn!("Failed to save build state: {e:?}");
+    }
+    build_result?;
 
     terminal::step!("Finished", "building all Spin components");
     Ok(())
 }
 
+/// Concurrent build commands run in their own process groups, so that
+/// cancelling one also stops the processes it started, but this means they
+/// don't receive the terminal's Ctrl+C. Instead, when Spin is interrupted, the
+/// running builds are cancelled and Spin exits once they have stopped.
+///
+/// This only applies while the builds are running: once they have finished,
+/// Ctrl+C is left to whatever Spin does next (e.g. `spin up --build` stopping
+/// its triggers).
+fn cancel_on_interrupt(
+    cancellation: Arc<Cancellation>,
+    mut builds_finished: tokio::sync::watch::Receiver<bool>,
+) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    runtime.spawn(async move {
+        let mut finished_before_interrupt = builds_finished.clone();
+        tokio::select! {
+            interrupted = tokio::signal::ctrl_c() => {
+                if interrupted.is_ok() {
+                    cancellation.cancel();
+                    _ = builds_finished.wait_for(|finished| *finished).await;
+                    std::process::exit(130);
+                }
+            }
+            // The `Ref` which `wait_for` returns isn't `Send`, so it mustn't
+            // be part of the `select!` output.
+            _ = async { _ = finished_before_interrupt.wait_for(|finished| *finished).await } => {}
+        }
+    });
+}
+
+/// The component's build fingerprint, or `None` if it must always be built.
+fn component_fingerprint(build_info: &ComponentBuildInfo, app_dir: &Path) -> Option<String> {
+    incremental::fingerprint(build_info, app_dir).unwrap_or_else(|e| {
+        tracing::warn!(
+            "Failed to check whether component {} has changed: {e:?}",
+            build_info.id
+        );
+        None
+    })
+}
+
 /// Run the build command of the component.
-fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()> {
-    match build_info.build {
+fn build_component(
+    build_info: &ComponentBuildInfo,
+    app_dir: &Path,
+    concurrent: bool,
+    cancellation: &Cancellation,
+) -> Result<()> {
+    match &build_info.build {
         Some(b) => {
             let command_count = b.commands().len();
 
@@ -262,21 +374,41 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                     println!("Working directory: {}", quoted_path(&workdir));
                 }
 
-                let exit_status = Exec::shell(command)
-                    .cwd(workdir)
-                    .stdout(Redirection::None)
-                    .stderr(Redirection::None)
-                    .stdin(Redirection::None)
-                    .popen()
-                    .map_err(|err| {
-                        anyhow!(
-                            "Cannot spawn build process '{:?}' for component {}: {}",
-                            &b.command,
-                            build_info.id,
-                            err
-                        )
-                    })?
-                    .wait()?;
+                let (stdout, stderr) = if concurrent {
+                    (Redirection::Pipe, Redirection::Merge)
+                } else {
+                    (Redirection::None, Redirection::None)
+                };
+                let mut process = spawn_build_process(
+                    command, &workdir, concurrent, stdout, stderr,
+                )
+                .map_err(|err| {
+                    anyhow!(
+                        "Cannot spawn build process '{:?}' for component {}: {}",
+                        &b.command,
+                        build_info.id,
+                        err
+                    )
+                })?;
+
+                let output_forwarder = process.stdout.take().map(|output| {
+                    let id = build_info.id.clone();
+                    std::thread::spawn(move || forward_prefixed_output(output, &id))
+                });
+
+                let exit_status = loop {
+                    if let Some(status) = process.wait_timeout(CANCELLATION_POLL_INTERVAL)? {
+                        break status;
+                    }
+                    if cancellation.is_cancelled() {
+                        kill_build_process(&mut process, concurrent)?;
+                        bail!("Build of component {} was cancelled", build_info.id);
+                    }
+                };
+
+                if let Some(forwarder) = output_forwarder {
+                    let _ = forwarder.join();
+                }
 
                 if !exit_status.success() {
                     bail!(
@@ -287,12 +419,142 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                 }
             }
 
+            if b.preinit {
+                preinit_component(build_info, app_dir)?;
+            }
+
             Ok(())
         }
         _ => Ok(()),
     }
 }
 
+/// Starts a build command.
+///
+/// If builds are running concurrently, the command is started in its own
+/// process group, so that cancelling it also stops the processes it starts
+/// (the compiler, rather than just the shell). A process outside the
+/// terminal's foreground group is stopped if it reads from the terminal, so
+/// such a command is given an empty stdin instead. A build running on its own
+/// stays in the foreground, so that it can prompt for input.
+#[cfg(unix)]
+fn spawn_build_process(
+    command: &str,
+    workdir: &Path,
+    concurrent: bool,
+    stdout: Redirection,
+    stderr: Redirection,
+) -> subprocess::Result<Popen> {
+    let stdin = if concurrent {
+        Redirection::File(std::fs::File::open("/dev/null")?)
+    } else {
+        Redirection::None
+    };
+    // `Exec` can't set the process group, so this spawns the shell that
+    // `Exec::shell` would.
+    Popen::create(
+        &["sh", "-c", command],
+        subprocess::PopenConfig {
+            stdin,
+            stdout,
+            stderr,
+            cwd: Some(workdir.into()),
+            setpgid: concurrent,
+            ..Default::default()
+        },
+    )
+}
+
+#[cfg(not(unix))]
+fn spawn_build_process(
+    command: &str,
+    workdir: &Path,
+    _concurrent: bool,
+    stdout: Redirection,
+    stderr: Redirection,
+) -> subprocess::Result<Popen> {
+    subprocess::Exec::shell(command)
+        .cwd(workdir)
+        .stdout(stdout)
+        .stderr(stderr)
+        .stdin(Redirection::None)
+        .popen()
+}
+
+/// Stops a build command and, if builds are running concurrently, all the
+/// processes it started. A build running on its own is only cancelled when
+/// Spin is interrupted, and the processes it started share Spin's terminal, so
+/// they receive the interrupt themselves.
+fn kill_build_process(process: &mut Popen, concurrent: bool) -> Result<()> {
+    if let Some(pid) = process.pid()
+        && concurrent
+    {
+        #[cfg(unix)]
+        {
+            use nix::{sys::signal, unistd::Pid};
+            // The group may already have exited.
+            _ = signal::killpg(Pid::from_raw(pid as i32), signal::Signal::SIGTERM);
+        }
+        #[cfg(windows)]
+        {
+            _ = subprocess::Exec::cmd("taskkill")
+                .args(&["/T", "/F", "/PID", &pid.to_string()])
+                .stdout(Redirection::Pipe)
+                .stderr(Redirection::Merge)
+                .capture();
+        }
+    }
+    process.terminate()?;
+    process.wait()?;
+    Ok(())
+}
+
+/// Pre-initialize the component's build output in place.
+fn preinit_component(build_info: &ComponentBuildInfo, app_dir: &Path) -> Result<()> {
+    let Some(v2::ComponentSource::Local(source)) = &build_info.source else {
+        bail!(
+            "Component {} is set to be pre-initialized, but its source is not a local file",
+            build_info.id
+        );
+    };
+    let path = app_dir.join(source);
+    let module =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", quoted_path(&path)))?;
+    // A build command which finds nothing to do, such as `cargo build` when
+    // the sources haven't changed, leaves the module as it was pre-initialized.
+    if spin_componentize::preinit::is_preinitialized(&module)
+        .with_context(|| format!("Failed to read {}", quoted_path(&path)))?
+    {
+        terminal::step!(
+            "Fresh",
+            "component {} is already pre-initialized",
+            build_info.id
+        );
+        return Ok(());
+    }
+    terminal::step!("Pre-initializing", "component {}", build_info.id);
+
+    let preinitialized = spin_componentize::preinit::preinit(&module)
+        .with_context(|| format!("Failed to pre-initialize component {}", build_info.id))?;
+    std::fs::write(&path, preinitialized)
+        .with_context(|| format!("Failed to write {}", quoted_path(&path)))?;
+    Ok(())
+}
+
+/// Prints each line of a build command's output prefixed with the component ID.
+fn forward_prefixed_output(output: impl std::io::Read, component_id: &str) {
+    let mut reader = std::io::BufReader::new(output);
+    let mut line = vec![];
+    while let Ok(len) = reader.read_until(b'\n', &mut line) {
+        if len == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&line);
+        println!("[{component_id}] {}", text.trim_end_matches(['\r', '\n']));
+        line.clear();
+    }
+}
+
 /// Constructs the absolute working directory in which to run the build command.
 fn construct_workdir(app_dir: &Path, workdir: Option<impl AsRef<Path>>) -> Result<PathBuf> {
     let mut cwd = app_dir.to_owned();
@@ -363,6 +625,30 @@ impl PartialEq for SortableBuildInfo {
 
 impl Eq for SortableBuildInfo {}
 
+/// For each component, the indices of the components it has a local path
+/// dependency on.
+fn dependency_indices(components: &[ComponentBuildInfo]) -> Vec<Vec<usize>> {
+    let sortables = components
+        .iter()
+        .map(SortableBuildInfo::from)
+        .collect::<Vec<_>>();
+    sortables
+        .iter()
+        .map(|s1| {
+            sortables
+                .iter()
+                .enumerate()
+                .filter(|(_, s2)| {
+                    s2.source
+                        .as_ref()
+                        .is_some_and(|src| s1.local_dependency_paths.contains(src))
+                })
+                .map(|(index, _)| index)
+                .collect()
+        })
+        .collect()
+}
+
 /// Topo sort by local path dependency. Second result is if there was a cycle.
 fn sort(components: Vec<ComponentBuildInfo>) -> (Vec<ComponentBuildInfo>, bool) {
     let sortables = components
@@ -439,7 +725,8 @@ pub fn read_last_build_profile(app_dir: &Path) -> anyhow::Result<Option<String>>
 }
 
 /// Prints a warning to stderr if the given profile is not the same
-/// as the most recent build in the given application directory.
+/// as the most recent build in the given application directory, or if
+/// any components have changed since they were last built.
 pub fn warn_if_not_latest_build(manifest_path: &Path, profile: Option<&str>) {
     let Some(app_dir) = manifest_path.parent() else {
         return;
@@ -455,17 +742,46 @@ pub fn warn_if_not_latest_build(manifest_path: &Path, profile: Option<&str>) {
         }
     };
 
+    let profile_opt = match profile {
+        Some(p) => format!(" --profile {p}"),
+        None => "".to_string(),
+    };
+
     if profile != latest_build.as_deref() {
-        let profile_opt = match profile {
-            Some(p) => format!(" --profile {p}"),
-            None => "".to_string(),
-        };
         terminal::warn!(
             "You built a different profile more recently than the one you are running. If the app appears to be behaving like an older version then run `spin up --build{profile_opt}`."
         );
+        return;
+    }
+
+    match changed_since_last_build(manifest_path, app_dir, profile) {
+        Ok(changed) if !changed.is_empty() => {
+            terminal::warn!(
+                "Component(s) {} changed since they were last built. If the app appears to be behaving like an older version then run `spin up --build{profile_opt}`.",
+                changed.join(", ")
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::debug!("Failed to check whether components changed since last build: {e:?}")
+        }
     }
 }
 
+/// The IDs of the components whose build inputs or outputs have changed since
+/// they were last built.
+fn changed_since_last_build(
+    manifest_path: &Path,
+    app_dir: &Path,
+    profile: Option<&str>,
+) -> anyhow::Result<Vec<String>> {
+    let mut app_manifest = spin_manifest::manifest_from_file(manifest_path)?;
+    app_manifest.ensure_profile(profile)?;
+    spin_manifest::normalize::normalize_manifest(&mut app_manifest, profile)?;
+    let components = manifest::build_configs_from_manifest(&app_manifest);
+    Ok(BuildState::load(app_dir).stale_components(&components))
+}
+
 /// Specifies target environment checking behaviour
 pub enum TargetChecking {
     /// The build should check that all components are compatible with all target environments.
@@ -481,6 +797,40 @@ impl TargetChecking {
     }
 }
 
+/// Specifies whether components that haven't changed since they were last built are rebuilt
+pub enum Rebuild {
+    /// Components which declare their build `inputs` are skipped if their build
+    /// command, inputs and output (the component source) are unchanged since they
+    /// were last built. Components which don't declare inputs are always built.
+    IfChanged,
+    /// All components are built.
+    Always,
+}
+
+impl Rebuild {
+    /// Should the build run even for components which haven't changed?
+    fn always(&self) -> bool {
+        matches!(self, Self::Always)
+    }
+}
+
+/// Specifies how the components' build commands are run
+pub struct BuildCommandOptions {
+    /// Whether components that haven't changed are rebuilt.
+    pub rebuild: Rebuild,
+    /// The number of build commands to run at once.
+    pub jobs: usize,
+}
+
+impl Default for BuildCommandOptions {
+    fn default() -> Self {
+        Self {
+            rebuild: Rebuild::IfChanged,
+            jobs: default_jobs(),
+        }
+    }
+}
+
 /// Specifies dependency WIT generation behaviour
 pub enum GenerateDependencyWits {
     /// The build should generate WITs for component dependencies.
@@ -514,6 +864,10 @@ mod tests {
             &[],
             TargetChecking::Skip,
             GenerateDependencyWits::Skip,
+            BuildCommandOptions {
+                rebuild: Rebuild::Always,
+                jobs: 1,
+            },
             None,
         )
         .await
@@ -529,6 +883,10 @@ mod tests {
             &[],
             TargetChecking::Check,
             GenerateDependencyWits::Skip,
+            BuildCommandOptions {
+                rebuild: Rebuild::Always,
+                jobs: 1,
+            },
             None,
         )
         .await
@@ -544,6 +902,10 @@ mod tests {
             &[],
             TargetChecking::Check,
             GenerateDependencyWits::Skip,
+            BuildCommandOptions {
+                rebuild: Rebuild::Always,
+                jobs: 1,
+            },
             None,
         )
         .await
@@ -640,6 +1002,68 @@ mod tests {
         );
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn cancelled_builds_stop_the_processes_they_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut info = dummy_buildinfo("1");
+        info.build =
+            Some(toml::from_str(r#"command = "sleep 30 & echo $! > child.pid; wait""#).unwrap());
+        let pid_file = dir.path().join("child.pid");
+        let read_pid = || {
+            std::fs::read_to_string(&pid_file)
+                .ok()
+                .filter(|pid| pid.ends_with('\n'))
+                .map(|pid| pid.trim().parse::<u32>().unwrap())
+        };
+
+        let cancellation = Cancellation::default();
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for _ in 0..500 {
+                    if read_pid().is_some() {
+                        break;
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                cancellation.cancel();
+            });
+            build_component(&info, dir.path(), true, &cancellation)
+        });
+        assert!(result.is_err(), "build should have been cancelled");
+
+        // Killed processes may linger as zombies if nothing reaps them.
+        let pid = read_pid().expect("build should have started a child process");
+        let running = || {
+            std::fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        for _ in 0..100 {
+            if !running() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("child process {pid} is still running");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn builds_run_on_their_own_stay_in_spins_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let pgrp = "cut -d' ' -f5 /proc/$1/stat";
+        let mut info = dummy_buildinfo("1");
+        info.build = Some(
+            toml::from_str(&format!(
+                r#"command = "pgrp() {{ {pgrp}; }}; test $(pgrp $$) = $(pgrp $PPID)""#
+            ))
+            .unwrap(),
+        );
+
+        build_component(&info, dir.path(), false, &Cancellation::default()).unwrap();
+        build_component(&info, dir.path(), true, &Cancellation::default()).unwrap_err();
+    }
+
     #[test]
     fn if_no_dependencies_then_all_build() {
         let (cs, had_cycle) = sort(vec![dummy_buildinfo("1"), dummy_buildinfo("2")]);
@@ -683,6 +1107,17 @@ mod tests {
         assert!(!had_cycle);
     }
 
+    #[test]
+    fn dependency_indices_refer_to_local_dependencies() {
+        let deps = dependency_indices(&[
+            dummy_buildinfo("1"),
+            dummy_build_info_deps("2", &["3.wasm", "crikey.wasm"]),
+            dummy_buildinfo("3"),
+            dummy_build_info_deps("4", &["1.wasm", "2.wasm"]),
+        ]);
+        assert_eq!(vec![vec![], vec![2], vec![], vec![0, 1]], deps);
+    }
+
     #[test]
     fn circular_dependencies_dont_prevent_build() {
         let (cs, had_cycle) = sort(vec![
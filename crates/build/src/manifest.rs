@@ -115,7 +115,7 @@ pub async fn component_build_configs(
     }
 }
 
-fn build_configs_from_manifest(
+pub(crate) fn build_configs_from_manifest(
     manifest: &spin_manifest::schema::v2::AppManifest,
 ) -> Vec<ComponentBuildInfo> {
     manifest
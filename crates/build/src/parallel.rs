@@ -0,0 +1,218 @@
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{
+        Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Result, anyhow};
+
+/// Signals running tasks that they should stop because another task failed.
+#[derive(Default)]
+pub(crate) struct Cancellation(AtomicBool);
+
+impl Cancellation {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs `task` for each item on up to `jobs` threads, starting an item only
+/// once all the items it depends on have finished.
+///
+/// `dependencies[i]` lists the indices of the items that item `i` depends on.
+/// Items are started in the order given where dependencies allow. If a task
+/// fails or panics, no further items are started, running tasks are asked to
+/// stop via `cancellation`, and the first error is returned. Items are also
+/// asked to stop if something else cancels `cancellation`.
+pub(crate) fn run_in_dependency_order<T: Sync>(
+    items: &[T],
+    dependencies: &[Vec<usize>],
+    jobs: usize,
+    cancellation: &Cancellation,
+    task: impl Fn(&T, &Cancellation) -> Result<()> + Sync,
+) -> Result<()> {
+    let scheduler = Scheduler {
+        state: Mutex::new(SchedulerState {
+            pending: (0..items.len()).collect(),
+            finished: vec![false; items.len()],
+            running: 0,
+            error: None,
+        }),
+        changed: Condvar::new(),
+        cancellation,
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(index) = scheduler.next(dependencies) {
+                    // A panicking task must still be finished, or the other
+                    // threads would wait for it forever.
+                    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                        task(&items[index], scheduler.cancellation)
+                    }))
+                    .unwrap_or_else(|_| Err(anyhow!("Task panicked")));
+                    scheduler.finish(index, result);
+                }
+            });
+        }
+    });
+
+    match scheduler.state.into_inner().unwrap().error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+struct Scheduler<'a> {
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+    cancellation: &'a Cancellation,
+}
+
+struct SchedulerState {
+    pending: Vec<usize>,
+    finished: Vec<bool>,
+    running: usize,
+    error: Option<anyhow::Error>,
+}
+
+impl Scheduler<'_> {
+    /// Waits for an item which is ready to run, returning `None` once there is
+    /// nothing left to run.
+    fn next(&self, dependencies: &[Vec<usize>]) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.error.is_some() || state.pending.is_empty() || self.cancellation.is_cancelled()
+            {
+                return None;
+            }
+            let ready = state.pending.iter().position(|&index| {
+                dependencies[index]
+                    .iter()
+                    .all(|&dependency| state.finished[dependency])
+            });
+            if let Some(position) = ready {
+                state.running += 1;
+                return Some(state.pending.remove(position));
+            }
+            if state.running == 0 {
+                // Nothing is running that could unblock the pending items, so
+                // the dependencies can't be satisfied: run them in order anyway.
+                state.running += 1;
+                return Some(state.pending.remove(0));
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn finish(&self, index: usize, result: Result<()>) {
+        let mut state = self.state.lock().unwrap();
+        state.running -= 1;
+        state.finished[index] = true;
+        if let Err(e) = result
+            && state.error.is_none()
+        {
+            state.error = Some(e);
+            self.cancellation.cancel();
+        }
+        self.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependencies_finish_before_dependents_start() {
+        let finished = Mutex::new(vec![]);
+        let dependencies = vec![vec![], vec![0], vec![0, 1], vec![]];
+        run_in_dependency_order(
+            &[0, 1, 2, 3],
+            &dependencies,
+            4,
+            &Default::default(),
+            |&item, _| {
+                let finished_now = finished.lock().unwrap().clone();
+                for dependency in &dependencies[item] {
+                    assert!(finished_now.contains(dependency));
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                finished.lock().unwrap().push(item);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(4, finished.into_inner().unwrap().len());
+    }
+
+    #[test]
+    fn failure_stops_remaining_items() {
+        let started = Mutex::new(vec![]);
+        let dependencies = vec![vec![], vec![0], vec![1]];
+        let err = run_in_dependency_order(
+            &[0, 1, 2],
+            &dependencies,
+            2,
+            &Default::default(),
+            |&item, _| {
+                started.lock().unwrap().push(item);
+                if item == 1 {
+                    anyhow::bail!("item {item} failed");
+                }
+                Ok(())
+            },
+        )
+        .unwrap_err();
+        assert_eq!("item 1 failed", err.to_string());
+        assert_eq!(vec![0, 1], started.into_inner().unwrap());
+    }
+
+    #[test]
+    fn failure_cancels_running_items() {
+        let dependencies = vec![vec![], vec![]];
+        let err = run_in_dependency_order(
+            &[0, 1],
+            &dependencies,
+            2,
+            &Default::default(),
+            |&item, cancellation| {
+                if item == 0 {
+                    anyhow::bail!("item 0 failed");
+                }
+                while !cancellation.is_cancelled() {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                anyhow::bail!("item 1 was cancelled")
+            },
+        )
+        .unwrap_err();
+        assert_eq!("item 0 failed", err.to_string());
+    }
+
+    #[test]
+    fn panic_is_reported_as_failure() {
+        let dependencies = vec![vec![], vec![0], vec![]];
+        let err = run_in_dependency_order(
+            &[0, 1, 2],
+            &dependencies,
+            2,
+            &Default::default(),
+            |&item, _| {
+                if item == 0 {
+                    panic!("item 0 panicked");
+                }
+                Ok(())
+            },
+        )
+        .unwrap_err();
+        assert_eq!("Task panicked", err.to_string());
+    }
+}
@@ -11,6 +11,7 @@ use {
 };
 
 pub mod bugs;
+pub mod preinit;
 
 #[cfg(test)]
 mod abi_conformance;
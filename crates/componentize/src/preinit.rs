@@ -0,0 +1,536 @@
+//! Pre-initialization of Wasm modules and components with Wizer.
+//!
+//! The module's initialization function is run once, at build time, and the
+//! state that it leaves behind (the contents of the module's memories and the
+//! values of its mutable globals) is snapshotted into a new module. The new
+//! module starts in that state, so the initialization doesn't have to run
+//! each time it's instantiated. A component is pre-initialized by snapshotting
+//! the core module embedded in it which exports the initialization function.
+
+use {
+    anyhow::{Context, Result, anyhow, bail},
+    std::borrow::Cow,
+    wasm_encoder::{Component, CustomSection, Encode, Module, RawSection},
+    wasmparser::{ExternalKind, Parser, Payload, TypeRef, WasmFeatures},
+    wasmtime::{Caller, Engine, Extern, Linker, Store},
+    wasmtime_wizer::Wizer,
+};
+
+/// The export which is run to initialize the module. This is the name that
+/// the Wizer CLI uses, so modules written for it can be pre-initialized by
+/// Spin.
+pub const INIT_EXPORT: &str = "wizer.initialize";
+
+/// The WASI reactor initialization export. If the module has it, Wizer runs
+/// it before [`INIT_EXPORT`], as a runtime would, and removes it from the
+/// snapshot.
+const REACTOR_INIT_EXPORT: &str = "_initialize";
+
+/// The custom section which marks a module as pre-initialized.
+const PREINITIALIZED_SECTION: &str = "spin-preinitialized";
+
+/// Pre-initializes a core Wasm module or a component, by running its
+/// [`INIT_EXPORT`] function and snapshotting the state that it leaves behind.
+///
+/// Only a few deterministic WASI functions can be called during
+/// initialization: empty arguments and environment, and writing to stdout or
+/// stderr (which goes to stderr). Calling any other import fails the
+/// pre-initialization, since its result would be baked into the snapshot.
+/// Tables aren't snapshotted, so modules with instructions which change them
+/// are rejected, as are the other modules which Wizer doesn't support.
+///
+/// In a component, exactly one of the core modules at its top level must
+/// export [`INIT_EXPORT`]. That module is pre-initialized on its own, so calls
+/// to its imports, including WASI functions provided by an adapter, are
+/// subject to the same restrictions. A module or component which has already
+/// been pre-initialized is rejected; see [`is_preinitialized`].
+pub fn preinit(wasm: &[u8]) -> Result<Vec<u8>> {
+    if is_preinitialized(wasm)? {
+        bail!("module has already been pre-initialized");
+    }
+    if Parser::is_component(wasm) {
+        preinit_component(wasm)
+    } else {
+        preinit_module(wasm, false)
+    }
+}
+
+/// Whether a module or component is the result of [`preinit`].
+pub fn is_preinitialized(wasm: &[u8]) -> Result<bool> {
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CustomSection(reader) = payload.context("unable to parse binary")?
+            && reader.name() == PREINITIALIZED_SECTION
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Pre-initializes a core module. If the module is embedded in a component,
+/// which may run its reactor initialization export, that export is kept but
+/// made to do nothing.
+fn preinit_module(module: &[u8], in_component: bool) -> Result<Vec<u8>> {
+    if !exports_func(module, INIT_EXPORT)? {
+        bail!("module does not export an initialization function named {INIT_EXPORT:?}");
+    }
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    // None of the imports which the linker provides are async, so this
+    // doesn't need a runtime to drive it.
+    let snapshot = futures::executor::block_on(Wizer::new().init_func(INIT_EXPORT).run(
+        &mut store,
+        module,
+        async |store, module| {
+            let linker = allow_list_linker(store, module)?;
+            linker.instantiate_async(store, module).await
+        },
+    ))
+    .map_err(anyhow::Error::from)?;
+    let stub_reactor_init = in_component && exports_func(module, REACTOR_INIT_EXPORT)?;
+    finish(&snapshot, stub_reactor_init)
+}
+
+/// Pre-initializes the core module at the top level of a component which
+/// exports [`INIT_EXPORT`], and re-encodes the component around the result.
+fn preinit_component(component: &[u8]) -> Result<Vec<u8>> {
+    let mut sections = vec![];
+    let mut init_modules = vec![];
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(component) {
+        let payload = payload.context("unable to parse binary")?;
+        match payload {
+            Payload::Version { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            // Sections of nested modules and components are copied along
+            // with the section which contains them.
+            _ if depth > 1 => {}
+            Payload::ModuleSection {
+                ref unchecked_range,
+                ..
+            } => {
+                if exports_func(&component[unchecked_range.clone()], INIT_EXPORT)? {
+                    init_modules.push(sections.len());
+                }
+                sections.extend(payload.as_section());
+            }
+            _ => sections.extend(payload.as_section()),
+        }
+    }
+    let &[init_module] = init_modules.as_slice() else {
+        bail!(
+            "pre-initialization requires exactly one core module in the component to export {INIT_EXPORT:?}, but {} do",
+            init_modules.len()
+        );
+    };
+
+    let (_, range) = &sections[init_module];
+    let preinitialized = preinit_module(&component[range.clone()], true)?;
+    let mut result = Component::new();
+    for (index, (id, range)) in sections.iter().enumerate() {
+        let data = if index == init_module {
+            &preinitialized[..]
+        } else {
+            &component[range.clone()]
+        };
+        result.section(&RawSection { id: *id, data });
+    }
+
+    let component = result.finish();
+    wasmparser::Validator::new_with_features(WasmFeatures::all())
+        .validate_all(&component)
+        .map_err(|e| anyhow!("pre-initialized component is invalid: {e}"))?;
+    Ok(component)
+}
+
+/// Whether a core module exports a function with the given name.
+fn exports_func(module: &[u8], name: &str) -> Result<bool> {
+    for payload in Parser::new(0).parse_all(module) {
+        if let Payload::ExportSection(reader) = payload.context("unable to parse binary")? {
+            for export in reader {
+                let export = export?;
+                if export.name == name && export.kind == ExternalKind::Func {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Creates a linker which defines the functions in [`add_safe_imports`], and
+/// makes the module's other function imports fail if they're called.
+fn allow_list_linker(
+    store: &mut Store<()>,
+    module: &wasmtime::Module,
+) -> wasmtime::Result<Linker<()>> {
+    let mut linker = Linker::new(module.engine());
+    add_safe_imports(&mut linker)?;
+    for import in module.imports() {
+        let (module_name, name) = (import.module(), import.name());
+        if linker.get(&mut *store, module_name, name).is_ok() {
+            continue;
+        }
+        let wasmtime::ExternType::Func(ty) = import.ty() else {
+            wasmtime::bail!(
+                "cannot pre-initialize a module which imports {module_name}::{name}, which isn't a function"
+            );
+        };
+        let import_name = format!("{module_name}::{name}");
+        linker.func_new(module_name, name, ty, move |_, _, _| {
+            wasmtime::bail!(
+                "called {import_name} during pre-initialization, which only allows calls to deterministic WASI functions"
+            )
+        })?;
+    }
+    Ok(linker)
+}
+
+/// Defines the WASI functions which can safely be called during
+/// initialization, because they behave the same way whenever they're called.
+fn add_safe_imports(linker: &mut Linker<()>) -> wasmtime::Result<()> {
+    const WASI: &str = "wasi_snapshot_preview1";
+    const ERRNO_SUCCESS: i32 = 0;
+    const ERRNO_BADF: i32 = 8;
+
+    fn write_u32s(caller: &mut Caller<'_, ()>, values: &[(i32, u32)]) -> wasmtime::Result<()> {
+        let memory = memory(caller)?;
+        for (ptr, value) in values {
+            memory.write(&mut *caller, *ptr as u32 as usize, &value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Arguments and environment variables are always empty.
+    for (sizes_get, get) in [
+        ("args_sizes_get", "args_get"),
+        ("environ_sizes_get", "environ_get"),
+    ] {
+        linker.func_wrap(
+            WASI,
+            sizes_get,
+            |mut caller: Caller<'_, ()>,
+             count_ptr: i32,
+             buf_size_ptr: i32|
+             -> wasmtime::Result<i32> {
+                write_u32s(&mut caller, &[(count_ptr, 0), (buf_size_ptr, 0)])?;
+                Ok(ERRNO_SUCCESS)
+            },
+        )?;
+        linker.func_wrap(WASI, get, |_: i32, _: i32| ERRNO_SUCCESS)?;
+    }
+
+    // Output to stdout or stderr is passed through to stderr, so that it
+    // doesn't get mixed up with the output of the build.
+    linker.func_wrap(
+        WASI,
+        "fd_write",
+        |mut caller: Caller<'_, ()>,
+         fd: i32,
+         iovs: i32,
+         iovs_len: i32,
+         nwritten_ptr: i32|
+         -> wasmtime::Result<i32> {
+            if fd != 1 && fd != 2 {
+                return Ok(ERRNO_BADF);
+            }
+            let memory = memory(&mut caller)?;
+            let mut output = vec![];
+            for i in 0..iovs_len as u32 {
+                let mut iov = [0u8; 8];
+                memory.read(&caller, (iovs as u32 + i * 8) as usize, &mut iov)?;
+                let ptr = u32::from_le_bytes(iov[..4].try_into().unwrap());
+                let len = u32::from_le_bytes(iov[4..].try_into().unwrap());
+                let start = output.len();
+                output.resize(start + len as usize, 0);
+                memory.read(&caller, ptr as usize, &mut output[start..])?;
+            }
+            use std::io::Write;
+            _ = std::io::stderr().write_all(&output);
+            write_u32s(&mut caller, &[(nwritten_ptr, output.len() as u32)])?;
+            Ok(ERRNO_SUCCESS)
+        },
+    )?;
+
+    linker.func_wrap(WASI, "proc_exit", |code: i32| -> wasmtime::Result<()> {
+        wasmtime::bail!("exited with code {code} during pre-initialization")
+    })?;
+
+    Ok(())
+}
+
+fn memory(caller: &mut Caller<'_, ()>) -> wasmtime::Result<wasmtime::Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => wasmtime::bail!("module does not export a memory named \"memory\""),
+    }
+}
+
+/// The counts which are needed to add a function to a module.
+#[derive(Default)]
+struct FuncLayout {
+    /// The number of types which the module defines.
+    type_count: u32,
+    imported_func_count: u32,
+    /// The number of functions which the module defines.
+    func_count: u32,
+}
+
+impl FuncLayout {
+    fn parse(module: &[u8]) -> Result<Self> {
+        let mut layout = Self::default();
+        for payload in Parser::new(0).parse_all(module) {
+            match payload.context("unable to parse binary")? {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        layout.type_count += rec_group?.types().len() as u32;
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader.into_imports() {
+                        if let TypeRef::Func(_) = import?.ty {
+                            layout.imported_func_count += 1;
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => layout.func_count = reader.count(),
+                _ => {}
+            }
+        }
+        Ok(layout)
+    }
+
+    /// The index which a function added to the module will have.
+    fn next_func_index(&self) -> u32 {
+        self.imported_func_count + self.func_count
+    }
+}
+
+/// Marks Wizer's snapshot as pre-initialized. If `stub_reactor_init` is set,
+/// the reactor initialization export, which Wizer removes but the component
+/// still runs, is put back as a function which does nothing.
+fn finish(snapshot: &[u8], stub_reactor_init: bool) -> Result<Vec<u8>> {
+    let layout = FuncLayout::parse(snapshot)?;
+    if stub_reactor_init && layout.func_count == 0 {
+        bail!("cannot pre-initialize a module whose {REACTOR_INIT_EXPORT} export is imported");
+    }
+    let mut result = Module::new();
+    for payload in Parser::new(0).parse_all(snapshot) {
+        let payload = payload?;
+        if let Some((id, range)) = payload.as_section() {
+            let mut data = Cow::Borrowed(&snapshot[range]);
+            if stub_reactor_init && let Some(entry) = noop_func_entry(&payload, &layout) {
+                data = Cow::Owned(append_entry(&data, &entry)?);
+            }
+            result.section(&RawSection { id, data: &data });
+        }
+    }
+
+    result.section(&CustomSection {
+        name: PREINITIALIZED_SECTION.into(),
+        data: Cow::Borrowed(&[]),
+    });
+
+    let snapshot = result.finish();
+    wasmparser::Validator::new()
+        .validate_all(&snapshot)
+        .map_err(|e| anyhow!("pre-initialized module is invalid: {e}"))?;
+    Ok(snapshot)
+}
+
+/// The entry which a type, function, export or code section needs in order to
+/// define and export a reactor initialization function which does nothing, or
+/// `None` for other sections.
+fn noop_func_entry(payload: &Payload, layout: &FuncLayout) -> Option<Vec<u8>> {
+    let mut entry = vec![];
+    match payload {
+        // (func)
+        Payload::TypeSection(_) => entry.extend([0x60, 0x00, 0x00]),
+        Payload::FunctionSection(_) => layout.type_count.encode(&mut entry),
+        Payload::ExportSection(_) => {
+            REACTOR_INIT_EXPORT.encode(&mut entry);
+            // A function export
+            entry.push(0x00);
+            layout.next_func_index().encode(&mut entry);
+        }
+        // A body of two bytes: no locals, then `end`.
+        Payload::CodeSectionStart { .. } => entry.extend([0x02, 0x00, 0x0b]),
+        _ => return None,
+    }
+    Some(entry)
+}
+
+/// Appends an entry to the contents of a section, which start with the number
+/// of entries.
+fn append_entry(section: &[u8], entry: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = wasmparser::BinaryReader::new(section, 0);
+    let count = reader.read_var_u32()?;
+    let mut result = vec![];
+    (count + 1).encode(&mut result);
+    result.extend_from_slice(&section[reader.current_position()..]);
+    result.extend_from_slice(entry);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instantiate(module: &[u8]) -> Result<(Store<()>, wasmtime::Instance)> {
+        let engine = Engine::default();
+        let module = wasmtime::Module::new(&engine, module)?;
+        let mut store = Store::new(&engine, ());
+        let instance = wasmtime::Instance::new(&mut store, &module, &[])?;
+        Ok((store, instance))
+    }
+
+    #[test]
+    fn snapshots_memory_and_globals() -> Result<()> {
+        let module = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $counter (mut i32) (i32.const 0))
+                (data (i32.const 16) "before")
+                (func (export "wizer.initialize")
+                    (i32.store (i32.const 100) (i32.const 42))
+                    (global.set $counter (i32.const 7))
+                    (drop (memory.grow (i32.const 1))))
+                (func (export "counter") (result i32) (global.get $counter)))"#,
+        )?;
+
+        let snapshot = preinit(&module)?;
+
+        let (mut store, instance) = instantiate(&snapshot)?;
+        assert!(instance.get_func(&mut store, INIT_EXPORT).is_none());
+        let counter = instance.get_typed_func::<(), i32>(&mut store, "counter")?;
+        assert_eq!(7, counter.call(&mut store, ())?);
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        assert_eq!(2, memory.size(&store));
+        assert_eq!(b"before", &memory.data(&store)[16..22]);
+        assert_eq!(42, memory.data(&store)[100]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_nondeterministic_imports() -> Result<()> {
+        let module = wat::parse_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "wizer.initialize")
+                    (drop (call $random_get (i32.const 0) (i32.const 8)))))"#,
+        )?;
+
+        let err = preinit(&module).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("wasi_snapshot_preview1::random_get"),
+            "{err:#}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_modules_which_change_tables() -> Result<()> {
+        for init in [
+            "(table.set $table (i32.const 0) (ref.func $init))",
+            "(drop (table.grow $table (ref.null func) (i32.const 1)))",
+        ] {
+            let module = wat::parse_str(format!(
+                r#"(module
+                    (table $table 1 funcref)
+                    (func $init (export "wizer.initialize") {init}))"#
+            ))?;
+
+            let err = preinit(&module).unwrap_err();
+            assert!(
+                format!("{err:#}").contains("unsupported `table."),
+                "{err:#}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn allows_tables_which_initialization_leaves_alone() -> Result<()> {
+        let module = wat::parse_str(
+            r#"(module
+                (table $table 1 funcref)
+                (elem (i32.const 0) $answer)
+                (type $answer_type (func (result i32)))
+                (func $answer (result i32) (i32.const 42))
+                (func (export "wizer.initialize")
+                    (drop (call_indirect $table (type $answer_type) (i32.const 0))))
+                (func (export "answer") (result i32)
+                    (call_indirect $table (type $answer_type) (i32.const 0))))"#,
+        )?;
+
+        let snapshot = preinit(&module)?;
+
+        let (mut store, instance) = instantiate(&snapshot)?;
+        let answer = instance.get_typed_func::<(), i32>(&mut store, "answer")?;
+        assert_eq!(42, answer.call(&mut store, ())?);
+        Ok(())
+    }
+
+    #[test]
+    fn marks_modules_as_preinitialized() -> Result<()> {
+        let module = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "wizer.initialize")))"#,
+        )?;
+        assert!(!is_preinitialized(&module)?);
+
+        let snapshot = preinit(&module)?;
+        assert!(is_preinitialized(&snapshot)?);
+        let err = preinit(&snapshot).unwrap_err();
+        assert_eq!(err.to_string(), "module has already been pre-initialized");
+        Ok(())
+    }
+
+    #[test]
+    fn snapshots_the_module_in_a_component() -> Result<()> {
+        // This mirrors the way wit-component runs `_initialize` when the
+        // component is instantiated.
+        let component = wat::parse_str(
+            r#"(component
+                (core module $main
+                    (memory (export "memory") 1)
+                    (global $counter (mut i32) (i32.const 0))
+                    (func (export "_initialize")
+                        (global.set $counter (i32.add (global.get $counter) (i32.const 1))))
+                    (func (export "wizer.initialize")
+                        (global.set $counter (i32.mul (global.get $counter) (i32.const 10))))
+                    (func (export "counter") (result i32) (global.get $counter)))
+                (core module $start (import "" "" (func)) (start 0))
+                (core instance $main (instantiate $main))
+                (core instance $start_args (export "" (func $main "_initialize")))
+                (core instance (instantiate $start (with "" (instance $start_args))))
+                (func (export "counter") (result u32)
+                    (canon lift (core func $main "counter"))))"#,
+        )?;
+
+        let snapshot = preinit(&component)?;
+        assert!(is_preinitialized(&snapshot)?);
+
+        let engine = Engine::default();
+        let component = wasmtime::component::Component::new(&engine, &snapshot)?;
+        let mut store = Store::new(&engine, ());
+        let instance =
+            wasmtime::component::Linker::new(&engine).instantiate(&mut store, &component)?;
+        let counter = instance.get_typed_func::<(), (u32,)>(&mut store, "counter")?;
+        assert_eq!((10,), counter.call(&mut store, ())?);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_components_without_an_initialization_function() -> Result<()> {
+        let component = wat::parse_str("(component (core module))")?;
+        let err = preinit(&component).unwrap_err();
+        assert!(
+            err.to_string().contains("exactly one core module"),
+            "{err:#}"
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+mod quota;
+
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
@@ -6,6 +8,11 @@ use anyhow::anyhow;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
 use tokio::time;
 
+pub use quota::{
+    AppEgressQuota, DEFAULT_EGRESS_QUOTA_WINDOW, EgressQuota, EgressQuotaExceeded,
+    EgressQuotaLimits, EgressQuotaScope,
+};
+
 /// A semaphore paired with its configured permit limit, so utilization can be computed.
 ///
 /// The two are bound together because a semaphore's `available_permits()` is meaningless for
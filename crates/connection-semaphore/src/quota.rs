@@ -0,0 +1,523 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The default window over which egress quotas are enforced.
+pub const DEFAULT_EGRESS_QUOTA_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits on the volume of outbound traffic, enforced over a fixed time window.
+///
+/// Any limit set to `None` is not enforced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EgressQuotaLimits {
+    /// Maximum number of bytes that may be sent per window.
+    pub max_bytes_sent: Option<u64>,
+    /// Maximum number of bytes that may be received per window.
+    pub max_bytes_received: Option<u64>,
+    /// Maximum number of outbound requests (HTTP requests or socket connections) per window.
+    pub max_requests: Option<u64>,
+    /// The length of the window after which usage is reset.
+    pub window: Duration,
+}
+
+impl EgressQuotaLimits {
+    /// Returns true if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_bytes_sent.is_none()
+            && self.max_bytes_received.is_none()
+            && self.max_requests.is_none()
+    }
+}
+
+impl Default for EgressQuotaLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes_sent: None,
+            max_bytes_received: None,
+            max_requests: None,
+            window: DEFAULT_EGRESS_QUOTA_WINDOW,
+        }
+    }
+}
+
+/// Whether an egress quota limit applies to a single component or to the whole app.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EgressQuotaScope {
+    /// The limit applies to the traffic of one component.
+    Component,
+    /// The limit applies to the combined traffic of all of the app's components.
+    App,
+}
+
+impl EgressQuotaScope {
+    /// A short label for the scope, used in telemetry.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Component => "component",
+            Self::App => "app",
+        }
+    }
+}
+
+/// The error returned when an [`EgressQuota`] limit has been reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EgressQuotaExceeded {
+    /// The outbound request limit was reached.
+    Requests {
+        limit: u64,
+        window: Duration,
+        scope: EgressQuotaScope,
+    },
+    /// The sent bytes limit was reached.
+    BytesSent {
+        limit: u64,
+        window: Duration,
+        scope: EgressQuotaScope,
+    },
+    /// The received bytes limit was reached.
+    BytesReceived {
+        limit: u64,
+        window: Duration,
+        scope: EgressQuotaScope,
+    },
+}
+
+impl EgressQuotaExceeded {
+    /// A short label for the exceeded limit, used in telemetry.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Requests { .. } => "requests",
+            Self::BytesSent { .. } => "bytes_sent",
+            Self::BytesReceived { .. } => "bytes_received",
+        }
+    }
+
+    /// The scope of the exceeded limit.
+    pub fn scope(&self) -> EgressQuotaScope {
+        match self {
+            Self::Requests { scope, .. }
+            | Self::BytesSent { scope, .. }
+            | Self::BytesReceived { scope, .. } => *scope,
+        }
+    }
+}
+
+impl std::fmt::Display for EgressQuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let of = match self.scope() {
+            EgressQuotaScope::Component => "",
+            EgressQuotaScope::App => " for the app",
+        };
+        match self {
+            Self::Requests { limit, window, .. } => write!(
+                f,
+                "outbound request quota exceeded: limit is {limit} requests per {window:?}{of}"
+            ),
+            Self::BytesSent { limit, window, .. } => write!(
+                f,
+                "outbound bandwidth quota exceeded: limit is {limit} bytes sent per {window:?}{of}"
+            ),
+            Self::BytesReceived { limit, window, .. } => write!(
+                f,
+                "outbound bandwidth quota exceeded: limit is {limit} bytes received per {window:?}{of}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EgressQuotaExceeded {}
+
+/// Tracks outbound traffic volume for a single component against its [`EgressQuotaLimits`],
+/// and optionally against an [`AppEgressQuota`] shared with the app's other components.
+///
+/// Clones share the same usage counters, so a single `EgressQuota` should be created per
+/// component and shared by all of its instances.
+#[derive(Clone, Debug)]
+pub struct EgressQuota {
+    component: QuotaCounter,
+    app: Option<QuotaCounter>,
+    /// Identifier of the app this quota is scoped to, used in emitted telemetry.
+    app_id: Arc<str>,
+    /// Identifier of the component this quota is scoped to, used in emitted telemetry.
+    component_id: Arc<str>,
+}
+
+/// Tracks the combined outbound traffic volume of all of an app's components against
+/// app-wide [`EgressQuotaLimits`].
+///
+/// Add it to each component's [`EgressQuota`] with [`EgressQuota::with_app_quota`].
+#[derive(Clone, Debug)]
+pub struct AppEgressQuota(QuotaCounter);
+
+impl AppEgressQuota {
+    /// Creates a new `AppEgressQuota`.
+    pub fn new(limits: EgressQuotaLimits) -> Self {
+        Self(QuotaCounter::new(limits, EgressQuotaScope::App))
+    }
+}
+
+/// Usage counters for one set of [`EgressQuotaLimits`]. Clones share the counters.
+#[derive(Clone, Debug)]
+struct QuotaCounter {
+    limits: Arc<EgressQuotaLimits>,
+    usage: Arc<Mutex<EgressUsage>>,
+    scope: EgressQuotaScope,
+    /// Edge-trigger guard for the rejection warning; reset when a new window starts.
+    rejecting: Arc<AtomicBool>,
+}
+
+impl QuotaCounter {
+    fn new(limits: EgressQuotaLimits, scope: EgressQuotaScope) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            usage: Arc::new(Mutex::new(EgressUsage::new())),
+            scope,
+            rejecting: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Locks the usage counters, starting a new window if the current one has ended.
+    fn lock(&self) -> std::sync::MutexGuard<'_, EgressUsage> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if usage.window_start.elapsed() >= self.limits.window {
+            *usage = EgressUsage::new();
+            self.rejecting.store(false, Ordering::Relaxed);
+        }
+        usage
+    }
+
+    /// Returns the selected counter of `usage` with `amount` added, or an error if that
+    /// would exceed the limit.
+    fn check(
+        &self,
+        usage: &EgressUsage,
+        usage_kind: UsageKind,
+        amount: u64,
+    ) -> Result<u64, EgressQuotaExceeded> {
+        let count = usage_kind.count(usage).saturating_add(amount);
+        match usage_kind.limit(&self.limits) {
+            Some(limit) if count > limit => {
+                Err(usage_kind.exceeded(limit, self.limits.window, self.scope))
+            }
+            _ => Ok(count),
+        }
+    }
+}
+
+/// Selects one of the counters of an [`EgressUsage`] and the matching limit.
+#[derive(Clone, Copy)]
+enum UsageKind {
+    Requests,
+    BytesSent,
+    BytesReceived,
+}
+
+impl UsageKind {
+    fn count(self, usage: &EgressUsage) -> u64 {
+        match self {
+            Self::Requests => usage.requests,
+            Self::BytesSent => usage.bytes_sent,
+            Self::BytesReceived => usage.bytes_received,
+        }
+    }
+
+    fn counter(self, usage: &mut EgressUsage) -> &mut u64 {
+        match self {
+            Self::Requests => &mut usage.requests,
+            Self::BytesSent => &mut usage.bytes_sent,
+            Self::BytesReceived => &mut usage.bytes_received,
+        }
+    }
+
+    fn limit(self, limits: &EgressQuotaLimits) -> Option<u64> {
+        match self {
+            Self::Requests => limits.max_requests,
+            Self::BytesSent => limits.max_bytes_sent,
+            Self::BytesReceived => limits.max_bytes_received,
+        }
+    }
+
+    fn exceeded(
+        self,
+        limit: u64,
+        window: Duration,
+        scope: EgressQuotaScope,
+    ) -> EgressQuotaExceeded {
+        match self {
+            Self::Requests => EgressQuotaExceeded::Requests {
+                limit,
+                window,
+                scope,
+            },
+            Self::BytesSent => EgressQuotaExceeded::BytesSent {
+                limit,
+                window,
+                scope,
+            },
+            Self::BytesReceived => EgressQuotaExceeded::BytesReceived {
+                limit,
+                window,
+                scope,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct EgressUsage {
+    window_start: Instant,
+    requests: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl EgressUsage {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            requests: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+        }
+    }
+}
+
+impl EgressQuota {
+    /// Creates a new `EgressQuota` for the given component.
+    pub fn new(limits: EgressQuotaLimits, app_id: Arc<str>, component_id: Arc<str>) -> Self {
+        Self {
+            component: QuotaCounter::new(limits, EgressQuotaScope::Component),
+            app: None,
+            app_id,
+            component_id,
+        }
+    }
+
+    /// Also records this component's traffic against `app_quota`, which is shared with
+    /// the app's other components.
+    pub fn with_app_quota(mut self, app_quota: &AppEgressQuota) -> Self {
+        self.app = Some(app_quota.0.clone());
+        self
+    }
+
+    /// Returns the component limits enforced by this quota.
+    pub fn limits(&self) -> &EgressQuotaLimits {
+        &self.component.limits
+    }
+
+    /// Records the start of an outbound request or connection.
+    ///
+    /// `kind` is a label used in emitted telemetry, e.g. "http" or "wasi-sockets".
+    pub fn start_request(&self, kind: &'static str) -> Result<(), EgressQuotaExceeded> {
+        self.consume(kind, 1, UsageKind::Requests)?;
+        spin_telemetry::monotonic_counter!(
+            outbound_egress_requests = 1,
+            kind = kind,
+            app_id = &*self.app_id,
+            component_id = &*self.component_id
+        );
+        Ok(())
+    }
+
+    /// Records `bytes` sent by an outbound request or connection.
+    pub fn record_sent(&self, kind: &'static str, bytes: u64) -> Result<(), EgressQuotaExceeded> {
+        self.consume(kind, bytes, UsageKind::BytesSent)?;
+        spin_telemetry::monotonic_counter!(
+            outbound_egress_bytes_sent = bytes,
+            kind = kind,
+            app_id = &*self.app_id,
+            component_id = &*self.component_id
+        );
+        Ok(())
+    }
+
+    /// Records `bytes` received by an outbound request or connection.
+    pub fn record_received(
+        &self,
+        kind: &'static str,
+        bytes: u64,
+    ) -> Result<(), EgressQuotaExceeded> {
+        self.consume(kind, bytes, UsageKind::BytesReceived)?;
+        spin_telemetry::monotonic_counter!(
+            outbound_egress_bytes_received = bytes,
+            kind = kind,
+            app_id = &*self.app_id,
+            component_id = &*self.component_id
+        );
+        Ok(())
+    }
+
+    /// Adds `amount` to the usage selected by `usage_kind`, unless doing so would exceed
+    /// the component's or the app's limit.
+    ///
+    /// Usage that would exceed either limit is not recorded against either, so a rejected
+    /// operation does not count against the remainder of the window.
+    fn consume(
+        &self,
+        kind: &'static str,
+        amount: u64,
+        usage_kind: UsageKind,
+    ) -> Result<(), EgressQuotaExceeded> {
+        // The app's counters are only ever locked after a component's, so components
+        // contending for them can't deadlock.
+        let mut component_usage = self.component.lock();
+        let mut app_usage = self.app.as_ref().map(QuotaCounter::lock);
+        let component_count = self.component.check(&component_usage, usage_kind, amount);
+        let app_count = match (&self.app, &app_usage) {
+            (Some(app), Some(usage)) => app.check(usage, usage_kind, amount).map(Some),
+            _ => Ok(None),
+        };
+        match (component_count, app_count) {
+            (Ok(component_count), Ok(app_count)) => {
+                *usage_kind.counter(&mut component_usage) = component_count;
+                if let (Some(usage), Some(app_count)) = (&mut app_usage, app_count) {
+                    *usage_kind.counter(usage) = app_count;
+                }
+                Ok(())
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                drop(app_usage);
+                drop(component_usage);
+                self.report_rejection(kind, &err);
+                Err(err)
+            }
+        }
+    }
+
+    fn report_rejection(&self, kind: &'static str, err: &EgressQuotaExceeded) {
+        let counter = match err.scope() {
+            EgressQuotaScope::Component => &self.component,
+            EgressQuotaScope::App => self.app.as_ref().unwrap_or(&self.component),
+        };
+        spin_telemetry::monotonic_counter!(
+            outbound_egress_quota_rejected = 1,
+            kind = kind,
+            app_id = &*self.app_id,
+            component_id = &*self.component_id,
+            limit = err.label(),
+            scope = err.scope().label()
+        );
+        // Log a warning on the first rejection in a window to make it easier for operators
+        // to notice when limits are being hit, but avoid spamming.
+        if !counter.rejecting.swap(true, Ordering::Relaxed) {
+            tracing::warn!(
+                kind,
+                app_id = %self.app_id,
+                component_id = %self.component_id,
+                limit = err.label(),
+                scope = err.scope().label(),
+                "egress quota exceeded: {err}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(limits: EgressQuotaLimits) -> EgressQuota {
+        EgressQuota::new(limits, Arc::from("test-app"), Arc::from("test-component"))
+    }
+
+    #[test]
+    fn unlimited_quota_always_allows() {
+        let quota = quota(EgressQuotaLimits::default());
+        for _ in 0..100 {
+            quota.start_request("test").unwrap();
+            quota.record_sent("test", u64::MAX).unwrap();
+            quota.record_received("test", u64::MAX).unwrap();
+        }
+    }
+
+    #[test]
+    fn request_limit_enforced() {
+        let quota = quota(EgressQuotaLimits {
+            max_requests: Some(2),
+            ..Default::default()
+        });
+        quota.start_request("test").unwrap();
+        quota.start_request("test").unwrap();
+        assert!(matches!(
+            quota.start_request("test"),
+            Err(EgressQuotaExceeded::Requests { limit: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn byte_limits_enforced_independently() {
+        let quota = quota(EgressQuotaLimits {
+            max_bytes_sent: Some(10),
+            max_bytes_received: Some(20),
+            ..Default::default()
+        });
+        quota.record_sent("test", 10).unwrap();
+        assert!(matches!(
+            quota.record_sent("test", 1),
+            Err(EgressQuotaExceeded::BytesSent { limit: 10, .. })
+        ));
+        // A rejected chunk doesn't count against the remaining quota.
+        quota.record_received("test", 15).unwrap();
+        assert!(quota.record_received("test", 6).is_err());
+        quota.record_received("test", 5).unwrap();
+    }
+
+    #[test]
+    fn clones_share_usage() {
+        let quota = quota(EgressQuotaLimits {
+            max_requests: Some(1),
+            ..Default::default()
+        });
+        let clone = quota.clone();
+        quota.start_request("test").unwrap();
+        assert!(clone.start_request("test").is_err());
+    }
+
+    #[test]
+    fn app_quota_is_shared_by_components() {
+        let app_quota = AppEgressQuota::new(EgressQuotaLimits {
+            max_bytes_sent: Some(10),
+            ..Default::default()
+        });
+        let first = quota(EgressQuotaLimits {
+            max_bytes_sent: Some(8),
+            ..Default::default()
+        })
+        .with_app_quota(&app_quota);
+        let second = quota(EgressQuotaLimits::default()).with_app_quota(&app_quota);
+
+        first.record_sent("test", 6).unwrap();
+        assert!(matches!(
+            first.record_sent("test", 3),
+            Err(EgressQuotaExceeded::BytesSent {
+                limit: 8,
+                scope: EgressQuotaScope::Component,
+                ..
+            })
+        ));
+        assert!(matches!(
+            second.record_sent("test", 5),
+            Err(EgressQuotaExceeded::BytesSent {
+                limit: 10,
+                scope: EgressQuotaScope::App,
+                ..
+            })
+        ));
+        // Neither rejection was recorded against the app's usage.
+        second.record_sent("test", 4).unwrap();
+        assert!(first.record_sent("test", 1).is_err());
+    }
+
+    #[test]
+    fn usage_resets_after_window() {
+        let quota = quota(EgressQuotaLimits {
+            max_requests: Some(1),
+            window: Duration::from_millis(20),
+            ..Default::default()
+        });
+        quota.start_request("test").unwrap();
+        assert!(quota.start_request("test").is_err());
+        std::thread::sleep(Duration::from_millis(30));
+        quota.start_request("test").unwrap();
+    }
+}
@@ -37,6 +37,9 @@ const GB: usize = 1 << 30;
 /// This is currently only used for advanced (undocumented) use cases.
 pub struct Config {
     inner: wasmtime::Config,
+    /// The pooling allocator's limit on live component instances, or `None` if
+    /// the pooling allocator isn't used.
+    instance_limit: Option<u32>,
 }
 
 impl Config {
@@ -64,6 +67,7 @@ impl Config {
     pub fn disable_pooling(&mut self) -> &mut Self {
         self.inner
             .allocation_strategy(wasmtime::InstanceAllocationStrategy::OnDemand);
+        self.instance_limit = None;
         self
     }
 
@@ -75,6 +79,29 @@ impl Config {
             .cranelift_opt_level(wasmtime::OptLevel::None);
         self
     }
+
+    /// Compile for the given target triple instead of the host. Components
+    /// compiled for another target can only be precompiled, not run.
+    pub fn target(&mut self, target: &str) -> Result<&mut Self> {
+        self.inner.target(target)?;
+        Ok(self)
+    }
+
+    /// Enable a CPU feature (a Cranelift ISA flag, such as `has_avx2`) when
+    /// compiling.
+    ///
+    /// # Safety
+    ///
+    /// Code compiled with a CPU feature which the host doesn't have may crash
+    /// or misbehave when it's run, so this must only be used to precompile
+    /// components for another host. Wasmtime refuses to load precompiled
+    /// components which need CPU features that the loading host lacks.
+    pub unsafe fn enable_cpu_feature(&mut self, feature: &str) -> &mut Self {
+        unsafe {
+            self.inner.cranelift_flag_enable(feature);
+        }
+        self
+    }
 }
 
 impl Default for Config {
@@ -89,6 +116,7 @@ impl Default for Config {
         #[cfg(all(target_os = "linux", target_env = "musl"))]
         inner.native_unwind_info(false);
 
+        let mut instance_limit = None;
         if use_pooling_allocator_by_default() {
             // Baseline for the maximum number of instances in spin through
             // which a number of other defaults are derived below.
@@ -102,6 +130,8 @@ impl Default for Config {
             // knobs for each of these settings just yet and instead they're
             // generally set to defaults. Environment-variable-based fallbacks are
             // supported though as an escape valve for if this is a problem.
+            let total_component_instances = env("SPIN_WASMTIME_INSTANCE_COUNT", max_instances);
+            instance_limit = Some(total_component_instances);
             let mut pooling_config = PoolingAllocationConfig::default();
             pooling_config
                 // Configuration parameters which affect the total size of the
@@ -132,7 +162,7 @@ impl Default for Config {
                 //   table, so it's set generously large. This does affect
                 //   virtual memory reservation but it's just 8 bytes per table
                 //   slot.
-                .total_component_instances(env("SPIN_WASMTIME_INSTANCE_COUNT", max_instances))
+                .total_component_instances(total_component_instances)
                 .total_memories(env("SPIN_WASMTIME_TOTAL_MEMORIES", max_instances))
                 .total_tables(env("SPIN_WASMTIME_TOTAL_TABLES", 2 * max_instances))
                 .total_stacks(env("SPIN_WASMTIME_TOTAL_STACKS", max_instances))
@@ -182,7 +212,10 @@ impl Default for Config {
             inner.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config));
         }
 
-        return Self { inner };
+        return Self {
+            inner,
+            instance_limit,
+        };
 
         fn env<T>(name: &str, default: T) -> T
         where
@@ -275,6 +308,7 @@ impl State {
 /// A new [`EngineBuilder`] can be obtained with [`Engine::builder`].
 pub struct EngineBuilder<T: 'static> {
     engine: wasmtime::Engine,
+    instance_limit: Option<u32>,
     linker: Linker<T>,
     epoch_tick_interval: Duration,
     epoch_ticker_thread: bool,
@@ -286,6 +320,7 @@ impl<T: 'static> EngineBuilder<T> {
         let linker: Linker<T> = Linker::new(&engine);
         Ok(Self {
             engine,
+            instance_limit: config.instance_limit,
             linker,
             epoch_tick_interval: DEFAULT_EPOCH_TICK_INTERVAL,
             epoch_ticker_thread: true,
@@ -341,6 +376,7 @@ impl<T: 'static> EngineBuilder<T> {
         self.maybe_spawn_epoch_ticker();
         Engine {
             inner: self.engine,
+            instance_limit: self.instance_limit,
             linker: self.linker,
             epoch_tick_interval: self.epoch_tick_interval,
         }
@@ -351,6 +387,7 @@ impl<T: 'static> EngineBuilder<T> {
 /// Spin components.
 pub struct Engine<T: 'static> {
     inner: wasmtime::Engine,
+    instance_limit: Option<u32>,
     linker: Linker<T>,
     epoch_tick_interval: Duration,
 }
@@ -372,6 +409,13 @@ impl<T: 'static> Engine<T> {
         Ok(self.linker.instantiate_pre(component)?)
     }
 
+    /// Returns the maximum number of component instances which can be live
+    /// at once, or `None` if the engine doesn't use the pooling allocator and
+    /// so has no such limit.
+    pub fn instance_limit(&self) -> Option<u32> {
+        self.instance_limit
+    }
+
     /// Checks that the given [`Component`] can be instantiated by this engine.
     pub fn check_component(
         &self,
@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use crate::{Key, Provider};
+
+/// Caching behaviour for a [`CachedProvider`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// How long a value is served from the cache before it is refreshed.
+    pub ttl: Duration,
+    /// How long past its TTL a value may still be served, either while it is
+    /// refreshed in the background or if refreshing it fails.
+    ///
+    /// `None` allows stale values to be served indefinitely.
+    pub max_stale: Option<Duration>,
+    /// Whether expired values are refreshed in the background while the stale
+    /// value is served. If false, callers wait for the refresh.
+    pub background_refresh: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60),
+            max_stale: Some(Duration::from_secs(3600)),
+            background_refresh: true,
+        }
+    }
+}
+
+/// A [`Provider`] which caches the values of another provider.
+///
+/// Both found and missing values are cached, so that repeated lookups of
+/// variables which the inner provider doesn't have do not reach the remote
+/// service either.
+#[derive(Clone)]
+pub struct CachedProvider {
+    inner: Arc<CacheInner>,
+}
+
+struct CacheInner {
+    provider: Box<dyn Provider>,
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+#[derive(Clone)]
+struct CacheEntry {
+    value: Option<String>,
+    fetched_at: Instant,
+    refreshing: bool,
+}
+
+impl CachedProvider {
+    /// Wraps `provider` in a cache.
+    pub fn new(provider: Box<dyn Provider>, config: CacheConfig) -> Self {
+        Self {
+            inner: Arc::new(CacheInner {
+                provider,
+                config,
+                entries: Default::default(),
+            }),
+        }
+    }
+}
+
+impl Debug for CachedProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't include cached values; they may be secrets.
+        f.debug_struct("CachedProvider")
+            .field("provider", &self.inner.provider)
+            .field("config", &self.inner.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CacheInner {
+    fn is_fresh(&self, entry: &CacheEntry, now: Instant) -> bool {
+        now.duration_since(entry.fetched_at) < self.config.ttl
+    }
+
+    fn is_servable(&self, entry: &CacheEntry, now: Instant) -> bool {
+        match self.config.max_stale {
+            Some(max_stale) => now.duration_since(entry.fetched_at) < self.config.ttl + max_stale,
+            None => true,
+        }
+    }
+
+    /// Fetches `key` from the inner provider and caches the result.
+    ///
+    /// If the fetch fails, a cached value is returned instead if it is not too stale.
+    async fn refresh(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let result = self.provider.get(&Key(key)).await;
+        let mut entries = self.entries.lock().unwrap();
+        match result {
+            Ok(value) => {
+                entries.insert(
+                    key.to_owned(),
+                    CacheEntry {
+                        value: value.clone(),
+                        fetched_at: Instant::now(),
+                        refreshing: false,
+                    },
+                );
+                Ok(value)
+            }
+            Err(err) => {
+                let Some(entry) = entries.get_mut(key) else {
+                    return Err(err);
+                };
+                entry.refreshing = false;
+                if !self.is_servable(entry, Instant::now()) {
+                    return Err(err);
+                }
+                tracing::warn!(
+                    "Failed to refresh variable {key:?} from {:?}; serving cached value: {err:#}",
+                    self.provider
+                );
+                Ok(entry.value.clone())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CachedProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let now = Instant::now();
+        let stale = {
+            let mut entries = self.inner.entries.lock().unwrap();
+            match entries.get_mut(key.as_str()) {
+                Some(entry) if self.inner.is_fresh(entry, now) => return Ok(entry.value.clone()),
+                Some(entry)
+                    if self.inner.config.background_refresh
+                        && self.inner.is_servable(entry, now) =>
+                {
+                    let start_refresh = !entry.refreshing;
+                    entry.refreshing = true;
+                    Some((entry.value.clone(), start_refresh))
+                }
+                _ => None,
+            }
+        };
+
+        if let Some((value, start_refresh)) = stale {
+            if start_refresh {
+                match tokio::runtime::Handle::try_current() {
+                    Ok(runtime) => {
+                        let inner = self.inner.clone();
+                        let key = key.as_str().to_owned();
+                        runtime.spawn(async move {
+                            // Errors are logged by `refresh`, which keeps the stale value.
+                            _ = inner.refresh(&key).await;
+                        });
+                    }
+                    Err(_) => return self.inner.refresh(key.as_str()).await,
+                }
+            }
+            return Ok(value);
+        }
+
+        self.inner.refresh(key.as_str()).await
+    }
+
+    fn may_resolve(&self, key: &Key) -> bool {
+        self.inner.provider.may_resolve(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// A provider which counts lookups and can be switched to failing.
+    #[derive(Debug, Default)]
+    struct CountingProvider {
+        calls: Arc<AtomicUsize>,
+        fail: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail.load(Ordering::SeqCst) {
+                anyhow::bail!("service unavailable");
+            }
+            Ok((key.as_str() == "known").then(|| format!("value-{n}")))
+        }
+    }
+
+    fn cached(config: CacheConfig) -> (CachedProvider, Arc<AtomicUsize>, Arc<AtomicBool>) {
+        let provider = CountingProvider::default();
+        let calls = provider.calls.clone();
+        let fail = provider.fail.clone();
+        (CachedProvider::new(Box::new(provider), config), calls, fail)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn values_are_cached_until_ttl() {
+        let (provider, calls, _) = cached(CacheConfig {
+            ttl: Duration::from_millis(50),
+            max_stale: Some(Duration::ZERO),
+            background_refresh: false,
+        });
+        let key = Key::new("known").unwrap();
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-0");
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-0");
+        assert_eq!(
+            provider.get(&Key::new("unknown").unwrap()).await.unwrap(),
+            None
+        );
+        provider.get(&Key::new("unknown").unwrap()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        tokio::time::advance(Duration::from_millis(60)).await;
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stale_values_are_refreshed_in_background() {
+        let (provider, calls, _) = cached(CacheConfig {
+            ttl: Duration::from_millis(20),
+            max_stale: None,
+            background_refresh: true,
+        });
+        let key = Key::new("known").unwrap();
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-0");
+
+        tokio::time::advance(Duration::from_millis(30)).await;
+        // The stale value is served while the refresh happens.
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-0");
+        // Let the background refresh run.
+        tokio::task::yield_now().await;
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-1");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stale_values_are_served_on_error() {
+        let (provider, _, fail) = cached(CacheConfig {
+            ttl: Duration::from_millis(20),
+            max_stale: Some(Duration::from_millis(100)),
+            background_refresh: false,
+        });
+        let key = Key::new("known").unwrap();
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-0");
+
+        fail.store(true, Ordering::SeqCst);
+        tokio::time::advance(Duration::from_millis(30)).await;
+        assert_eq!(provider.get(&key).await.unwrap().unwrap(), "value-0");
+
+        tokio::time::advance(Duration::from_millis(100)).await;
+        provider.get(&key).await.unwrap_err();
+    }
+}
@@ -1,3 +1,4 @@
+mod cache;
 pub mod provider;
 mod template;
 
@@ -7,6 +8,7 @@ use spin_locked_app::Variable;
 
 pub use async_trait;
 
+pub use cache::{CacheConfig, CachedProvider};
 pub use provider::Provider;
 use template::Part;
 pub use template::Template;
@@ -41,6 +43,22 @@ impl ProviderResolver {
             .add_component_variables(component_id, variables)
     }
 
+    /// Adds templates which are resolved outside of component variables,
+    /// such as `allowed_outbound_hosts` entries.
+    ///
+    /// These are only used to decide whether a required variable may be left
+    /// unresolved because every reference to it supplies a `default`.
+    pub fn add_templates(&mut self, templates: impl IntoIterator<Item = String>) -> Result<()> {
+        self.internal.add_templates(templates)
+    }
+
+    /// Returns whether the variable is referenced by at least one template,
+    /// and every reference supplies a `default`, so that it need not have a
+    /// value.
+    pub fn always_defaulted(&self, key: &str) -> bool {
+        self.internal.always_defaulted(key)
+    }
+
     /// Adds a variable Provider to the Resolver.
     pub fn add_provider(&mut self, provider: Box<dyn Provider>) {
         self.providers.push(provider);
@@ -68,29 +86,72 @@ impl ProviderResolver {
         futures::future::try_join_all(resolve_futs).await
     }
 
+    /// Resolves a variable value for the given path using previously resolved
+    /// variable values, e.g. a [`PreparedResolver`], instead of the providers.
+    pub fn resolve_with(
+        &self,
+        component_id: &str,
+        key: Key<'_>,
+        resolver: &impl SyncResolver,
+    ) -> Result<String> {
+        let template = self.internal.get_template(component_id, key)?;
+        resolver.resolve_template(template)
+    }
+
+    /// Resolves all variables for the given component using previously
+    /// resolved variable values.
+    pub fn resolve_all_with(
+        &self,
+        component_id: &str,
+        resolver: &impl SyncResolver,
+    ) -> Result<Vec<(String, String)>> {
+        let Some(keys2templates) = self.internal.component_configs.get(component_id) else {
+            return Ok(vec![]);
+        };
+        keys2templates
+            .iter()
+            .map(|(key, template)| Ok((key.to_string(), resolver.resolve_template(template)?)))
+            .collect()
+    }
+
     /// Resolves the given template.
     pub async fn resolve_template(&self, template: &Template) -> Result<String> {
         let mut resolved_parts: Vec<Cow<str>> = Vec::with_capacity(template.parts().len());
         for part in template.parts() {
             resolved_parts.push(match part {
                 Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var).await?.into(),
+                Part::Expr(expr) => expr
+                    .evaluate(self.try_resolve_variable(expr.var()).await?)?
+                    .into(),
             });
         }
         Ok(resolved_parts.concat())
     }
 
     /// Fully resolve all variables into a [`PreparedResolver`].
+    ///
+    /// Required variables without a value are only permitted if every
+    /// template referencing them supplies a `default`.
     pub async fn prepare(&self) -> Result<PreparedResolver> {
         let mut variables = HashMap::new();
         for name in self.internal.variables.keys() {
-            let value = self.resolve_variable(name).await?;
-            variables.insert(name.clone(), value);
+            match self.try_resolve_variable(name).await? {
+                Some(value) => {
+                    variables.insert(name.clone(), value);
+                }
+                None if self.internal.always_defaulted(name) => {}
+                None => return Err(unresolved_variable(name)),
+            }
         }
         Ok(PreparedResolver { variables })
     }
 
     /// Ensures that all required variables are not unresolvable
+    ///
+    /// A required variable is not checked if every template that references
+    /// it supplies a fallback with the `default` filter. Template syntax,
+    /// including filter names and arguments, is validated when component
+    /// variables are added.
     pub fn ensure_required_variables_resolvable(&self) -> Result<()> {
         let mut unresolvable_keys = vec![];
         for key in self.internal.required_variables() {
@@ -113,16 +174,22 @@ impl ProviderResolver {
         }
     }
 
-    async fn resolve_variable(&self, key: &str) -> Result<String> {
+    async fn try_resolve_variable(&self, key: &str) -> Result<Option<String>> {
         for provider in &self.providers {
             if let Some(value) = provider.get(&Key(key)).await.map_err(Error::Provider)? {
-                return Ok(value);
+                return Ok(Some(value));
             }
         }
-        self.internal.resolve_variable(key)
+        self.internal.try_resolve_variable(key)
     }
 }
 
+fn unresolved_variable(key: &str) -> Error {
+    Error::Provider(anyhow::anyhow!(
+        "no provider resolved required variable {key:?}"
+    ))
+}
+
 /// A variable resolver.
 #[derive(Debug, Default)]
 pub struct Resolver {
@@ -130,21 +197,23 @@ pub struct Resolver {
     variables: HashMap<String, Variable>,
     // component ID -> variable key -> variable value template
     component_configs: HashMap<String, HashMap<String, Template>>,
+    // templates resolved outside of component variables
+    other_templates: Vec<Template>,
 }
 
 impl SyncResolver for Resolver {
     fn resolve_variable(&self, key: &str) -> Result<String> {
+        self.try_resolve_variable(key)?
+            .ok_or_else(|| unresolved_variable(key))
+    }
+
+    fn try_resolve_variable(&self, key: &str) -> Result<Option<String>> {
         let var = self
             .variables
             .get(key)
             // This should have been caught by validate_template
             .ok_or_else(|| Error::InvalidName(key.to_string()))?;
-
-        var.default.clone().ok_or_else(|| {
-            Error::Provider(anyhow::anyhow!(
-                "no provider resolved required variable {key:?}"
-            ))
-        })
+        Ok(var.default.clone())
     }
 }
 
@@ -157,6 +226,7 @@ impl Resolver {
         Ok(Self {
             variables,
             component_configs: Default::default(),
+            other_templates: Default::default(),
         })
     }
 
@@ -182,6 +252,19 @@ impl Resolver {
         Ok(())
     }
 
+    /// Adds templates which are resolved outside of component variables.
+    ///
+    /// See [`ProviderResolver::add_templates`].
+    pub fn add_templates(&mut self, templates: impl IntoIterator<Item = String>) -> Result<()> {
+        for template in templates {
+            let template = Template::new(template)?;
+            if !template.is_literal() {
+                self.other_templates.push(template);
+            }
+        }
+        Ok(())
+    }
+
     /// Resolves a variable value for the given path.
     pub fn resolve(&self, component_id: &str, key: Key<'_>) -> Result<String> {
         let template = self.get_template(component_id, key)?;
@@ -190,14 +273,7 @@ impl Resolver {
 
     /// Resolves the given template.
     pub fn resolve_template(&self, template: &Template) -> Result<String> {
-        let mut resolved_parts: Vec<Cow<str>> = Vec::with_capacity(template.parts().len());
-        for part in template.parts() {
-            resolved_parts.push(match part {
-                Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var)?.into(),
-            });
-        }
-        Ok(resolved_parts.concat())
+        SyncResolver::resolve_template(self, template)
     }
 
     /// Gets a template for the given path.
@@ -215,19 +291,40 @@ impl Resolver {
     fn validate_template(&self, template: String) -> Result<Template> {
         let template = Template::new(template)?;
         // Validate template variables are valid
-        template.parts().try_for_each(|part| match part {
-            Part::Expr(var) if !self.variables.contains_key(var.as_ref()) => {
-                Err(Error::InvalidTemplate(format!("unknown variable {var:?}")))
+        template.exprs().try_for_each(|expr| {
+            if self.variables.contains_key(expr.var()) {
+                Ok(())
+            } else {
+                Err(Error::InvalidTemplate(format!(
+                    "unknown variable {:?}",
+                    expr.var()
+                )))
             }
-            _ => Ok(()),
         })?;
         Ok(template)
     }
 
     fn required_variables(&self) -> impl Iterator<Item = &str> {
-        self.variables
-            .iter()
-            .filter_map(|(name, variable)| variable.default.is_none().then_some(name.as_str()))
+        self.variables.iter().filter_map(|(name, variable)| {
+            (variable.default.is_none() && !self.always_defaulted(name)).then_some(name.as_str())
+        })
+    }
+
+    /// Returns whether the variable is referenced by at least one template,
+    /// and every reference supplies a `default`.
+    ///
+    /// Both component variable templates and templates added with
+    /// [`Resolver::add_templates`] are considered.
+    pub fn always_defaulted(&self, key: &str) -> bool {
+        let mut exprs = self
+            .component_configs
+            .values()
+            .flat_map(|templates| templates.values())
+            .chain(&self.other_templates)
+            .flat_map(|template| template.exprs())
+            .filter(|expr| expr.var() == key)
+            .peekable();
+        exprs.peek().is_some() && exprs.all(|expr| expr.has_default())
     }
 }
 
@@ -245,7 +342,12 @@ pub trait SyncResolver {
         for part in template.parts() {
             resolved_parts.push(match part {
                 Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var)?.into(),
+                Part::Expr(expr) if expr.has_default() => expr
+                    .evaluate(self.try_resolve_variable(expr.var())?)?
+                    .into(),
+                Part::Expr(expr) => expr
+                    .evaluate(Some(self.resolve_variable(expr.var())?))?
+                    .into(),
             });
         }
         Ok(resolved_parts.concat())
@@ -256,6 +358,14 @@ pub trait SyncResolver {
     /// This must be implemented, but consumers should not usually
     /// call it directly: call `resolve_template` instead.
     fn resolve_variable(&self, key: &str) -> Result<String>;
+
+    /// Resolves a variable to a string, or `None` if it has no value.
+    ///
+    /// This is used for expressions with a `default` filter. The default
+    /// implementation treats any error from `resolve_variable` as an error.
+    fn try_resolve_variable(&self, key: &str) -> Result<Option<String>> {
+        self.resolve_variable(key).map(Some)
+    }
 }
 
 /// A resolver who has resolved all variables.
@@ -264,6 +374,28 @@ pub struct PreparedResolver {
     variables: HashMap<String, String>,
 }
 
+impl PreparedResolver {
+    /// Returns the names of variables whose values differ from `other`,
+    /// including variables which only have a value in one of them.
+    pub fn changed_variables(&self, other: &PreparedResolver) -> Vec<String> {
+        let mut changed = self
+            .variables
+            .iter()
+            .filter(|(key, value)| other.variables.get(*key) != Some(value))
+            .map(|(key, _)| key.clone())
+            .chain(
+                other
+                    .variables
+                    .keys()
+                    .filter(|key| !self.variables.contains_key(*key))
+                    .cloned(),
+            )
+            .collect::<Vec<_>>();
+        changed.sort();
+        changed
+    }
+}
+
 impl SyncResolver for PreparedResolver {
     fn resolve_variable(&self, key: &str) -> Result<String> {
         self.variables
@@ -271,6 +403,10 @@ impl SyncResolver for PreparedResolver {
             .cloned()
             .ok_or(Error::InvalidName(key.to_string()))
     }
+
+    fn try_resolve_variable(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.variables.get(key).cloned())
+    }
 }
 
 /// A variable key
@@ -423,6 +559,77 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn resolve_variable_filters() {
+        assert_eq!(
+            test_resolve("{{ required | upper }}:{{ default | base64_encode }}")
+                .await
+                .unwrap(),
+            "PROVIDER-VALUE:ZGVmYXVsdC12YWx1ZQ=="
+        );
+    }
+
+    fn unprovided_resolver(template: &str) -> ProviderResolver {
+        let mut resolver = ProviderResolver::new([(
+            "unprovided".into(),
+            Variable {
+                description: None,
+                default: None,
+                secret: false,
+            },
+        )])
+        .unwrap();
+        resolver
+            .add_component_variables("test-component", [("test_key".into(), template.into())])
+            .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        resolver
+    }
+
+    #[tokio::test]
+    async fn template_default_satisfies_required_variable() {
+        let resolver = unprovided_resolver(r#"{{ unprovided | default: "fallback" }}"#);
+        resolver.ensure_required_variables_resolvable().unwrap();
+        assert_eq!(
+            resolver
+                .resolve("test-component", Key("test_key"))
+                .await
+                .unwrap(),
+            "fallback"
+        );
+        let prepared = resolver.prepare().await.unwrap();
+        let template = Template::new(r#"{{ unprovided | default: "fallback" }}"#).unwrap();
+        assert_eq!(prepared.resolve_template(&template).unwrap(), "fallback");
+
+        let resolver = unprovided_resolver("{{ unprovided | upper }}");
+        resolver.ensure_required_variables_resolvable().unwrap_err();
+        assert!(resolver.prepare().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn other_templates_can_require_defaulted_variable() {
+        let mut resolver = unprovided_resolver(r#"{{ unprovided | default: "fallback" }}"#);
+        resolver
+            .add_templates(["https://{{ unprovided }}".to_string()])
+            .unwrap();
+        resolver.ensure_required_variables_resolvable().unwrap_err();
+        assert!(resolver.prepare().await.is_err());
+    }
+
+    #[test]
+    fn changed_variables_are_reported() {
+        let prepared = |vars: &[(&str, &str)]| PreparedResolver {
+            variables: vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+        let old = prepared(&[("same", "1"), ("changed", "old"), ("removed", "x")]);
+        let new = prepared(&[("same", "1"), ("changed", "new"), ("added", "y")]);
+        assert_eq!(old.changed_variables(&new), ["added", "changed", "removed"]);
+        assert!(new.changed_variables(&new).is_empty());
+    }
+
     #[test]
     fn keys_good() {
         for key in ["a", "abc", "a1b2c3", "a_1", "a_1_b_3"] {
@@ -1,9 +1,16 @@
 use std::fmt::Display;
 
+use base64::Engine;
+
 use crate::{Error, Result};
 
 /// Template represents a simple string template that allows expressions in
 /// double curly braces, similar to Mustache or Liquid.
+///
+/// An expression names a variable, optionally followed by a pipeline of
+/// filters, e.g. `{{ db_host | default: "localhost" | lower }}`. Filters are
+/// applied from left to right. Because an expression ends at the first `}}`,
+/// filter arguments may not contain `}}`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Template {
     parts: Vec<Part>,
@@ -18,7 +25,7 @@ impl Template {
                 // Expression should be next
                 if let Some((expr, rest)) = expr_rest.split_once("}}") {
                     // Take up through the next '}}'...
-                    (Part::expr(expr.trim()), rest)
+                    (Part::Expr(Expr::parse(expr.trim())?), rest)
                 } else {
                     // ...or we have unmatched braces
                     return Err(Error::InvalidTemplate(
@@ -49,6 +56,18 @@ impl Template {
     pub(crate) fn parts(&self) -> std::slice::Iter<'_, Part> {
         self.parts.iter()
     }
+
+    /// Returns the names of the variables referenced by the template.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.exprs().map(Expr::var)
+    }
+
+    pub(crate) fn exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Expr(expr) => Some(expr),
+            Part::Lit(_) => None,
+        })
+    }
 }
 
 impl Display for Template {
@@ -64,7 +83,7 @@ impl Display for Template {
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Part {
     Lit(Box<str>),
-    Expr(Box<str>),
+    Expr(Expr),
 }
 
 impl Part {
@@ -72,11 +91,261 @@ impl Part {
         Self::Lit(lit.into())
     }
 
-    pub fn expr(expr: impl Into<Box<str>>) -> Self {
-        Self::Expr(expr.into())
+    #[cfg(test)]
+    pub fn expr(var: impl Into<Box<str>>) -> Self {
+        Self::Expr(Expr {
+            var: var.into(),
+            filters: vec![],
+        })
+    }
+}
+
+/// A template expression: a variable reference and the filters to apply to it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Expr {
+    var: Box<str>,
+    filters: Vec<Filter>,
+}
+
+impl Expr {
+    fn parse(expr: &str) -> Result<Self> {
+        let mut segments = split_pipeline(expr)?.into_iter();
+        let var = segments.next().unwrap_or_default().trim();
+        if var.is_empty() {
+            return Err(Error::InvalidTemplate(format!(
+                "missing variable name in expression {expr:?}"
+            )));
+        }
+        let filters = segments.map(Filter::parse).collect::<Result<_>>()?;
+        Ok(Self {
+            var: var.into(),
+            filters,
+        })
+    }
+
+    /// The name of the variable this expression refers to.
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    /// Whether this expression supplies a default for its variable, so that
+    /// it can be evaluated even if the variable has no value.
+    pub fn has_default(&self) -> bool {
+        self.filters.iter().any(|f| matches!(f, Filter::Default(_)))
+    }
+
+    /// Applies this expression's filters to the variable's value, which is
+    /// `None` if the variable could not be resolved.
+    pub fn evaluate(&self, value: Option<String>) -> Result<String> {
+        let value = self
+            .filters
+            .iter()
+            .try_fold(value, |value, filter| filter.apply(value, &self.var))?;
+        value.ok_or_else(|| {
+            Error::Provider(anyhow::anyhow!(
+                "no provider resolved required variable {:?}",
+                self.var
+            ))
+        })
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.var)?;
+        self.filters
+            .iter()
+            .try_for_each(|filter| write!(f, " | {filter}"))
+    }
+}
+
+/// A transformation applied to a value in a template expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Filter {
+    /// Replaces a missing or empty value with the given string.
+    Default(Box<str>),
+    Upper,
+    Lower,
+    Trim,
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    JsonEscape,
+}
+
+impl Filter {
+    fn parse(filter: &str) -> Result<Self> {
+        let (name, arg) = match filter.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(parse_string_literal(arg.trim())?)),
+            None => (filter.trim(), None),
+        };
+        let filter = match name {
+            "default" => {
+                let arg = arg.ok_or_else(|| {
+                    Error::InvalidTemplate(
+                        "filter 'default' requires a value, e.g. default: \"value\"".into(),
+                    )
+                })?;
+                return Ok(Self::Default(arg.into()));
+            }
+            "upper" => Self::Upper,
+            "lower" => Self::Lower,
+            "trim" => Self::Trim,
+            "base64_encode" => Self::Base64Encode,
+            "base64_decode" => Self::Base64Decode,
+            "url_encode" => Self::UrlEncode,
+            "json_escape" => Self::JsonEscape,
+            _ => return Err(Error::InvalidTemplate(format!("unknown filter {name:?}"))),
+        };
+        if arg.is_some() {
+            return Err(Error::InvalidTemplate(format!(
+                "filter {name:?} does not take a value"
+            )));
+        }
+        Ok(filter)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Default(_) => "default",
+            Self::Upper => "upper",
+            Self::Lower => "lower",
+            Self::Trim => "trim",
+            Self::Base64Encode => "base64_encode",
+            Self::Base64Decode => "base64_decode",
+            Self::UrlEncode => "url_encode",
+            Self::JsonEscape => "json_escape",
+        }
+    }
+
+    fn apply(&self, value: Option<String>, var: &str) -> Result<Option<String>> {
+        if let Self::Default(default) = self
+            && value.as_deref().is_none_or(str::is_empty)
+        {
+            return Ok(Some(default.to_string()));
+        }
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        Ok(Some(match self {
+            // A non-empty value passes through `default` unchanged.
+            Self::Default(_) => value,
+            Self::Upper => value.to_uppercase(),
+            Self::Lower => value.to_lowercase(),
+            Self::Trim => value.trim().to_string(),
+            Self::Base64Encode => base64::engine::general_purpose::STANDARD.encode(value),
+            Self::Base64Decode => {
+                // Don't include the value in errors; it may be a secret.
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(value.trim())
+                    .map_err(|_| {
+                        Error::InvalidTemplate(format!("variable {var:?} is not valid base64"))
+                    })?;
+                String::from_utf8(bytes).map_err(|_| {
+                    Error::InvalidTemplate(format!(
+                        "base64-decoded variable {var:?} is not valid UTF-8"
+                    ))
+                })?
+            }
+            Self::UrlEncode => {
+                percent_encoding::utf8_percent_encode(&value, URL_ENCODE_SET).to_string()
+            }
+            Self::JsonEscape => json_escape(&value),
+        }))
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default(default) => {
+                let escaped = default.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "default: \"{escaped}\"")
+            }
+            _ => f.write_str(self.name()),
+        }
     }
 }
 
+/// Characters escaped by `url_encode`: everything except RFC 3986 unreserved characters.
+const URL_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Splits an expression on `|` characters which are not inside string literals.
+fn split_pipeline(expr: &str) -> Result<Vec<&str>> {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in expr.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '|' if !in_string => {
+                segments.push(&expr[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_string {
+        return Err(Error::InvalidTemplate(format!(
+            "unterminated string in expression {expr:?}"
+        )));
+    }
+    segments.push(&expr[start..]);
+    Ok(segments)
+}
+
+/// Parses a double-quoted string, which may contain `\"` and `\\` escapes.
+fn parse_string_literal(literal: &str) -> Result<String> {
+    let invalid =
+        || Error::InvalidTemplate(format!("expected a double-quoted string, got {literal:?}"));
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped @ ('"' | '\\')) => value.push(escaped),
+                _ => return Err(invalid()),
+            },
+            '"' => return Err(invalid()),
+            c => value.push(c),
+        }
+    }
+    Ok(value)
+}
+
+/// Escapes a string for inclusion in a JSON string literal, without the
+/// surrounding quotes.
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{08}' => escaped.push_str("\\b"),
+            '\u{0c}' => escaped.push_str("\\f"),
+            c if c.is_control() && (c as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,4 +376,75 @@ mod tests {
     fn template_parts_bad() {
         Template::new("{{ matched }} {{ unmatched").unwrap_err();
     }
+
+    fn eval(expr: &str, value: Option<&str>) -> Result<String> {
+        Expr::parse(expr)?.evaluate(value.map(String::from))
+    }
+
+    #[test]
+    fn filters_apply_in_order() {
+        assert_eq!(eval("x | upper", Some("Ab")).unwrap(), "AB");
+        assert_eq!(eval("x | lower", Some("Ab")).unwrap(), "ab");
+        assert_eq!(eval("x | trim | upper", Some("  ab ")).unwrap(), "AB");
+        assert_eq!(
+            eval("x | base64_encode", Some("user:pass")).unwrap(),
+            "dXNlcjpwYXNz"
+        );
+        assert_eq!(
+            eval("x | base64_decode", Some("dXNlcjpwYXNz")).unwrap(),
+            "user:pass"
+        );
+        assert_eq!(
+            eval("x | url_encode", Some("a b&c/~")).unwrap(),
+            "a%20b%26c%2F~"
+        );
+        assert_eq!(
+            eval("x | json_escape", Some("say \"hi\"\n\\")).unwrap(),
+            r#"say \"hi\"\n\\"#
+        );
+        eval("x | base64_decode", Some("not base64!")).unwrap_err();
+    }
+
+    #[test]
+    fn default_applies_to_missing_and_empty_values() {
+        assert_eq!(
+            eval(r#"x | default: "localhost""#, None).unwrap(),
+            "localhost"
+        );
+        assert_eq!(
+            eval(r#"x | default: "localhost""#, Some("")).unwrap(),
+            "localhost"
+        );
+        assert_eq!(
+            eval(r#"x | default: "localhost""#, Some("db")).unwrap(),
+            "db"
+        );
+        assert_eq!(
+            eval(r#"x | default: "a | \"b\"" | upper"#, None).unwrap(),
+            r#"A | "B""#
+        );
+        eval("x | upper", None).unwrap_err();
+    }
+
+    #[test]
+    fn bad_filters_are_rejected() {
+        for tmpl in [
+            "{{ x | unknown }}",
+            "{{ x | default }}",
+            "{{ x | default: localhost }}",
+            "{{ x | upper: \"arg\" }}",
+            "{{ x | default: \"unterminated }}",
+            "{{ | upper }}",
+        ] {
+            Template::new(tmpl).expect_err(tmpl);
+        }
+    }
+
+    #[test]
+    fn template_display_round_trips() {
+        let tmpl = r#"a-{{ x | default: "q\"uote" | base64_encode }}-b"#;
+        let template = Template::new(tmpl).unwrap();
+        assert_eq!(template.to_string(), tmpl);
+        assert_eq!(Template::new(template.to_string()).unwrap(), template);
+    }
 }
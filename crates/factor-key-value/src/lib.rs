@@ -1,4 +1,5 @@
 mod host;
+mod metrics;
 pub mod runtime_config;
 mod util;
 
@@ -8,6 +9,7 @@ use std::{
 };
 
 use anyhow::ensure;
+use metrics::MeteredStoreManager;
 use spin_factor_otel::OtelFactorState;
 use spin_factors::{
     ConfigureAppContext, Factor, FactorData, FactorInstanceBuilder, InitContext, PrepareContext,
@@ -103,10 +105,12 @@ impl Factor for KeyValueFactor {
             .get(ctx.app_component().id())
             .expect("component should be in component_stores")
             .clone();
+        let component_id = ctx.app_component().id().to_string();
         let otel = OtelFactorState::from_prepare_context(&mut ctx)?;
         Ok(InstanceBuilder {
             store_manager: app_state.store_manager.clone(),
             allowed_stores,
+            component_id,
             otel,
         })
     }
@@ -187,6 +191,7 @@ pub struct InstanceBuilder {
     store_manager: Arc<AppStoreManager>,
     /// The allowed stores for this component instance.
     allowed_stores: HashSet<String>,
+    component_id: String,
     otel: OtelFactorState,
 }
 
@@ -197,11 +202,12 @@ impl FactorInstanceBuilder for InstanceBuilder {
         let Self {
             store_manager,
             allowed_stores,
+            component_id,
             otel,
         } = self;
         Ok(KeyValueDispatch::new_with_capacity(
             allowed_stores,
-            store_manager,
+            Arc::new(MeteredStoreManager::new(store_manager, component_id)),
             u32::MAX,
             otel,
         ))
@@ -0,0 +1,232 @@
+use std::{any::Any, sync::Arc, time::Instant};
+
+use spin_core::async_trait;
+use spin_telemetry::metrics::DataMetrics;
+
+use crate::{Cas, Error, Store, StoreManager, SwapError, v3};
+
+const SYSTEM: &str = "key_value";
+
+/// A [`StoreManager`] which records metrics for the stores it opens on
+/// behalf of a component.
+pub struct MeteredStoreManager {
+    inner: Arc<dyn StoreManager>,
+    component_id: String,
+}
+
+impl MeteredStoreManager {
+    pub fn new(inner: Arc<dyn StoreManager>, component_id: String) -> Self {
+        Self {
+            inner,
+            component_id,
+        }
+    }
+}
+
+#[async_trait]
+impl StoreManager for MeteredStoreManager {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
+        let metrics = DataMetrics::new(SYSTEM, &self.component_id, name);
+        let store = metrics
+            .time("open", error_type, self.inner.get(name))
+            .await?;
+        Ok(Arc::new(MeteredStore {
+            inner: store,
+            metrics,
+        }))
+    }
+
+    fn is_defined(&self, store_name: &str) -> bool {
+        self.inner.is_defined(store_name)
+    }
+
+    fn summary(&self, store_name: &str) -> Option<String> {
+        self.inner.summary(store_name)
+    }
+
+    fn metadata(&self) -> Arc<dyn Any> {
+        self.inner.metadata()
+    }
+}
+
+struct MeteredStore {
+    inner: Arc<dyn Store>,
+    metrics: DataMetrics,
+}
+
+#[async_trait]
+impl Store for MeteredStore {
+    async fn after_open(&self) -> Result<(), Error> {
+        self.inner.after_open().await
+    }
+
+    async fn get(&self, key: &str, max_result_bytes: usize) -> Result<Option<Vec<u8>>, Error> {
+        let value = self
+            .metrics
+            .time("get", error_type, self.inner.get(key, max_result_bytes))
+            .await?;
+        self.metrics
+            .record_bytes_read(value.as_ref().map_or(0, Vec::len));
+        Ok(value)
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.metrics
+            .time("set", error_type, self.inner.set(key, value))
+            .await?;
+        self.metrics.record_bytes_written(value.len());
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.metrics
+            .time("delete", error_type, self.inner.delete(key))
+            .await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        self.metrics
+            .time("exists", error_type, self.inner.exists(key))
+            .await
+    }
+
+    async fn get_keys(&self, max_result_bytes: usize) -> Result<Vec<String>, Error> {
+        let keys = self
+            .metrics
+            .time(
+                "get_keys",
+                error_type,
+                self.inner.get_keys(max_result_bytes),
+            )
+            .await?;
+        self.metrics
+            .record_bytes_read(keys.iter().map(String::len).sum());
+        Ok(keys)
+    }
+
+    async fn get_keys_async(
+        &self,
+        max_result_bytes: usize,
+    ) -> (
+        tokio::sync::mpsc::Receiver<String>,
+        tokio::sync::oneshot::Receiver<Result<(), v3::Error>>,
+    ) {
+        // Keys are streamed to the guest, so only the time to start listing
+        // is recorded.
+        let started = Instant::now();
+        let result = self.inner.get_keys_async(max_result_bytes).await;
+        self.metrics
+            .record_operation("get_keys_async", started, None);
+        result
+    }
+
+    async fn get_many(
+        &self,
+        keys: Vec<String>,
+        max_result_bytes: usize,
+    ) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
+        let values = self
+            .metrics
+            .time(
+                "get_many",
+                error_type,
+                self.inner.get_many(keys, max_result_bytes),
+            )
+            .await?;
+        self.metrics.record_bytes_read(
+            values
+                .iter()
+                .map(|(_, value)| value.as_ref().map_or(0, Vec::len))
+                .sum(),
+        );
+        Ok(values)
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        let bytes = key_values.iter().map(|(_, value)| value.len()).sum();
+        self.metrics
+            .time("set_many", error_type, self.inner.set_many(key_values))
+            .await?;
+        self.metrics.record_bytes_written(bytes);
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: Vec<String>) -> Result<(), Error> {
+        self.metrics
+            .time("delete_many", error_type, self.inner.delete_many(keys))
+            .await
+    }
+
+    async fn increment(&self, key: String, delta: i64) -> Result<i64, Error> {
+        self.metrics
+            .time("increment", error_type, self.inner.increment(key, delta))
+            .await
+    }
+
+    async fn new_compare_and_swap(
+        &self,
+        bucket_rep: u32,
+        key: &str,
+    ) -> Result<Arc<dyn Cas>, Error> {
+        let cas = self.inner.new_compare_and_swap(bucket_rep, key).await?;
+        Ok(Arc::new(MeteredCas {
+            inner: cas,
+            metrics: self.metrics.clone(),
+        }))
+    }
+}
+
+struct MeteredCas {
+    inner: Arc<dyn Cas>,
+    metrics: DataMetrics,
+}
+
+#[async_trait]
+impl Cas for MeteredCas {
+    async fn current(&self, max_result_bytes: usize) -> anyhow::Result<Option<Vec<u8>>, Error> {
+        let value = self
+            .metrics
+            .time(
+                "cas_current",
+                error_type,
+                self.inner.current(max_result_bytes),
+            )
+            .await?;
+        self.metrics
+            .record_bytes_read(value.as_ref().map_or(0, Vec::len));
+        Ok(value)
+    }
+
+    async fn swap(&self, value: Vec<u8>) -> anyhow::Result<(), SwapError> {
+        let bytes = value.len();
+        self.metrics
+            .time(
+                "cas_swap",
+                |err| match err {
+                    SwapError::CasFailed(_) => "cas_failed",
+                    SwapError::Other(_) => "other",
+                },
+                self.inner.swap(value),
+            )
+            .await?;
+        self.metrics.record_bytes_written(bytes);
+        Ok(())
+    }
+
+    async fn bucket_rep(&self) -> u32 {
+        self.inner.bucket_rep().await
+    }
+
+    async fn key(&self) -> String {
+        self.inner.key().await
+    }
+}
+
+fn error_type(err: &Error) -> &'static str {
+    match err {
+        Error::StoreTableFull => "store_table_full",
+        Error::NoSuchStore => "no_such_store",
+        Error::AccessDenied => "access_denied",
+        Error::Other(_) => "other",
+    }
+}
@@ -1,8 +1,10 @@
 use anyhow::bail;
 use spin_core::async_trait;
+use spin_core::wasmtime::component::Resource;
 use spin_factor_key_value::{Cas, KeyValueFactor, RuntimeConfig, Store, StoreManager, v3};
 use spin_factors::RuntimeFactors;
 use spin_factors_test::{TestEnvironment, toml};
+use spin_telemetry::testing::record_metrics;
 use spin_world::v2::key_value::{Error, HostStore};
 use std::{collections::HashSet, sync::Arc};
 
@@ -99,6 +101,59 @@ async fn errors_when_store_is_not_allowed() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn operations_are_metered() -> anyhow::Result<()> {
+    let (metrics, _guard) = record_metrics();
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_store_manager("default".into(), mock_store_manager());
+    let factors = TestFactors {
+        key_value: KeyValueFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["default"]
+    });
+    let mut state = env
+        .runtime_config(runtime_config)?
+        .build_instance_state()
+        .await?;
+
+    let store = state.key_value.open("default".to_owned()).await??.rep();
+    state
+        .key_value
+        .set(Resource::new_own(store), "key".into(), b"value".to_vec())
+        .await??;
+    assert!(
+        state
+            .key_value
+            .get(Resource::new_own(store), "key".into())
+            .await?
+            .is_err()
+    );
+
+    let operations = metrics.named("spin.data.operation_duration");
+    let operations = operations
+        .iter()
+        .map(|metric| {
+            assert_eq!(metric.attributes["db_system"], "key_value");
+            assert_eq!(metric.attributes["component_id"], "test-component");
+            assert_eq!(metric.attributes["store"], "default");
+            metric.attributes["operation"].as_str()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(operations, ["open", "set", "get"]);
+
+    let written = metrics.named("spin.data.bytes_written");
+    assert_eq!(written[0].value, "5");
+
+    let errors = metrics.named("spin.data.operation_errors");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].attributes["operation"], "get");
+    assert_eq!(errors[0].attributes["error_type"], "other");
+    Ok(())
+}
+
 fn mock_store_manager() -> Arc<dyn StoreManager> {
     Arc::new(MockStoreManager)
 }
@@ -124,11 +179,11 @@ struct MockStore;
 impl Store for MockStore {
     async fn get(&self, key: &str, max_result_bytes: usize) -> Result<Option<Vec<u8>>, Error> {
         let _ = (key, max_result_bytes);
-        todo!()
+        Err(Error::Other("unavailable".into()))
     }
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
         let _ = (key, value);
-        todo!()
+        Ok(())
     }
     async fn delete(&self, key: &str) -> Result<(), Error> {
         let _ = key;
@@ -1,4 +1,5 @@
 pub mod intercept;
+mod quota;
 pub mod runtime_config;
 mod spin;
 mod wasi;
@@ -17,7 +18,7 @@ use intercept::OutboundHttpInterceptor;
 use runtime_config::RuntimeConfig;
 use spin_factor_otel::OtelFactorState;
 use spin_factor_outbound_networking::{
-    ComponentTlsClientConfigs, ConnectionSemaphore, OutboundNetworkingFactor,
+    ComponentTlsClientConfigs, ConnectionSemaphore, EgressQuota, OutboundNetworkingFactor,
     build_connection_semaphore,
     config::{allowed_hosts::OutboundAllowedHosts, blocked_networks::BlockedNetworks},
 };
@@ -77,6 +78,7 @@ impl Factor for OutboundHttpFactor {
         let allowed_hosts = outbound_networking.allowed_hosts();
         let blocked_networks = outbound_networking.blocked_networks();
         let component_tls_configs = outbound_networking.component_tls_configs();
+        let egress_quota = outbound_networking.egress_quota();
         let otel = OtelFactorState::from_prepare_context(&mut ctx)?;
         Ok(InstanceState {
             wasi_http_ctx: WasiHttpCtx::new(),
@@ -90,6 +92,7 @@ impl Factor for OutboundHttpFactor {
                 wasi_http_clients: ctx.app_state().wasi_http_clients.clone(),
                 connection_pooling_enabled: ctx.app_state().connection_pooling_enabled,
                 semaphore: ctx.app_state().semaphore.clone(),
+                egress_quota,
                 otel,
             },
         })
@@ -122,6 +125,8 @@ struct InstanceHttpHooks {
     connection_pooling_enabled: bool,
     /// Semaphore to limit concurrent outbound connections.
     semaphore: ConnectionSemaphore,
+    /// The component's outbound traffic quota, if any limits are configured.
+    egress_quota: Option<EgressQuota>,
     /// Manages access to the OtelFactor state.
     otel: OtelFactorState,
 }
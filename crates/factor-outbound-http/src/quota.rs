@@ -0,0 +1,145 @@
+use std::{
+    pin::Pin,
+    task::{self, Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use http_body::{Body, Frame, SizeHint};
+use http_body_util::{BodyExt, combinators::UnsyncBoxBody};
+use spin_factor_outbound_networking::{EgressQuota, EgressQuotaExceeded};
+use spin_world::v1::http_types::HttpError;
+use wasmtime_wasi_http::p2::bindings::http::types::ErrorCode;
+
+/// The telemetry label for outbound HTTP traffic recorded against an [`EgressQuota`].
+pub(crate) const HTTP_EGRESS_KIND: &str = "http";
+
+/// Converts an egress quota error into the error code returned to the guest.
+pub(crate) fn quota_exceeded_error(err: EgressQuotaExceeded) -> ErrorCode {
+    ErrorCode::InternalError(Some(err.to_string()))
+}
+
+/// Converts an egress quota error into the error returned to a guest using
+/// the Spin HTTP interface.
+///
+/// That interface's errors can't carry a message, so it is logged instead, to
+/// tell the refusal apart from other causes of `too-many-requests`. This is at
+/// debug level as a guest may retry in a loop; the quota itself warns once per
+/// window.
+pub(crate) fn spin_quota_exceeded_error(err: EgressQuotaExceeded) -> HttpError {
+    tracing::debug!("Outbound HTTP request refused: {err}");
+    HttpError::TooManyRequests
+}
+
+/// The direction of the traffic metered by a [`QuotaBody`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
+pin_project_lite::pin_project! {
+    /// A [`Body`] that records the data passing through it against an [`EgressQuota`].
+    ///
+    /// Once the quota is exhausted, the body yields an error instead of the next data frame.
+    pub(crate) struct QuotaBody<B> {
+        #[pin]
+        body: B,
+        quota: EgressQuota,
+        direction: Direction,
+    }
+}
+
+impl<B> QuotaBody<B>
+where
+    B: Body<Data = Bytes, Error = ErrorCode> + Send + 'static,
+{
+    /// Wraps `body`, returning a boxed body that records its data against `quota`.
+    pub(crate) fn boxed(
+        body: B,
+        quota: EgressQuota,
+        direction: Direction,
+    ) -> UnsyncBoxBody<Bytes, ErrorCode> {
+        Self {
+            body,
+            quota,
+            direction,
+        }
+        .boxed_unsync()
+    }
+}
+
+impl<B: Body<Data = Bytes, Error = ErrorCode>> Body for QuotaBody<B> {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let me = self.project();
+        let frame = task::ready!(me.body.poll_frame(cx));
+        if let Some(Ok(frame)) = &frame
+            && let Some(data) = frame.data_ref()
+        {
+            let bytes = data.remaining() as u64;
+            let recorded = match me.direction {
+                Direction::Sent => me.quota.record_sent(HTTP_EGRESS_KIND, bytes),
+                Direction::Received => me.quota.record_received(HTTP_EGRESS_KIND, bytes),
+            };
+            if let Err(err) = recorded {
+                return Poll::Ready(Some(Err(quota_exceeded_error(err))));
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http_body_util::Full;
+    use spin_factor_outbound_networking::EgressQuotaLimits;
+
+    use super::*;
+
+    fn body(data: &'static [u8]) -> UnsyncBoxBody<Bytes, ErrorCode> {
+        Full::new(Bytes::from_static(data))
+            .map_err(|never| match never {})
+            .boxed_unsync()
+    }
+
+    fn quota(limits: EgressQuotaLimits) -> EgressQuota {
+        EgressQuota::new(limits, Arc::from("test-app"), Arc::from("test-component"))
+    }
+
+    #[tokio::test]
+    async fn body_within_quota_passes_through() {
+        let quota = quota(EgressQuotaLimits {
+            max_bytes_received: Some(5),
+            ..Default::default()
+        });
+        let body = QuotaBody::boxed(body(b"hello"), quota, Direction::Received);
+        let collected = body.collect().await.unwrap().to_bytes();
+        assert_eq!(collected, "hello");
+    }
+
+    #[tokio::test]
+    async fn body_exceeding_quota_errors() {
+        let quota = quota(EgressQuotaLimits {
+            max_bytes_sent: Some(4),
+            ..Default::default()
+        });
+        let body = QuotaBody::boxed(body(b"hello"), quota, Direction::Sent);
+        let err = body.collect().await.unwrap_err();
+        assert!(matches!(err, ErrorCode::InternalError(Some(_))));
+    }
+}
@@ -11,7 +11,11 @@ use spin_world::v1::{
 };
 use tracing::{Span, field::Empty, instrument};
 
-use crate::intercept::InterceptOutcome;
+use crate::{
+    intercept::InterceptOutcome,
+    quota::{HTTP_EGRESS_KIND, spin_quota_exceeded_error},
+    wasi::record_headers,
+};
 
 impl spin_http::Host for crate::InstanceState {
     #[instrument(name = "spin_outbound_http.send_request", skip_all,
@@ -94,6 +98,17 @@ impl spin_http::Host for crate::InstanceState {
             }
         }
 
+        record_headers(&span, req.headers(), "http.request.header");
+
+        // Requests completed by the interceptor never leave the host, so only
+        // count those that are actually sent against the egress quota.
+        if let Some(quota) = &self.hooks.egress_quota {
+            quota
+                .start_request(HTTP_EGRESS_KIND)
+                .and_then(|()| quota.record_sent(HTTP_EGRESS_KIND, req.body().len() as u64))
+                .map_err(spin_quota_exceeded_error)?;
+        }
+
         // Convert http::Request to reqwest::Request
         let req = reqwest::Request::try_from(req).map_err(|_| HttpError::InvalidUrl)?;
 
@@ -126,7 +141,15 @@ impl spin_http::Host for crate::InstanceState {
             otel_attribute::HTTP_RESPONSE_STATUS_CODE,
             resp.status().as_u16(),
         );
-        response_from_reqwest(resp).await
+        record_headers(&span, resp.headers(), "http.response.header");
+        let resp = response_from_reqwest(resp).await?;
+        if let Some(quota) = &self.hooks.egress_quota {
+            let received = resp.body.as_ref().map_or(0, |body| body.len() as u64);
+            quota
+                .record_received(HTTP_EGRESS_KIND, received)
+                .map_err(spin_quota_exceeded_error)?;
+        }
+        Ok(resp)
     }
 }
 
@@ -168,7 +191,10 @@ fn record_request_fields(span: &Span, req: &Request) {
     // <https://opentelemetry.io/docs/specs/semconv/http/http-spans/#name>
     span.record("otel.name", method)
         .record(otel_attribute::HTTP_REQUEST_METHOD, method)
-        .record(otel_attribute::URL_FULL, req.uri.clone());
+        .record(
+            otel_attribute::URL_FULL,
+            &*spin_telemetry::redaction::redact_url(&req.uri),
+        );
     if let Ok(uri) = req.uri.parse::<http::Uri>()
         && let Some(authority) = uri.authority()
     {
@@ -10,11 +10,7 @@ use std::{
 };
 
 use bytes::{Buf, Bytes};
-use http::{
-    HeaderMap, Uri,
-    header::{CONTENT_LENGTH, HOST},
-    uri::Scheme,
-};
+use http::{HeaderMap, Uri, header::HOST, uri::Scheme};
 use http_body::{Body, Frame, SizeHint};
 use http_body_util::{BodyExt, combinators::UnsyncBoxBody};
 use hyper_util::{
@@ -50,11 +46,12 @@ use wasmtime_wasi_http::{
     p3::{self, bindings::http::types as p3_types},
 };
 
-use spin_factor_outbound_networking::{ConnectionPermit, ConnectionSemaphore};
+use spin_factor_outbound_networking::{ConnectionPermit, ConnectionSemaphore, EgressQuota};
 
 use crate::{
     InstanceHttpHooks, OutboundHttpFactor, SelfRequestOrigin,
     intercept::{InterceptOutcome, OutboundHttpInterceptor},
+    quota::{Direction, HTTP_EGRESS_KIND, QuotaBody, quota_exceeded_error},
     wasi_2023_10_18, wasi_2023_11_10, wasi_2026_03_15,
 };
 
@@ -120,6 +117,7 @@ impl p3::WasiHttpHooks for InstanceHttpHooks {
             blocked_networks: self.blocked_networks.clone(),
             http_clients: self.wasi_http_clients.clone(),
             semaphore: self.semaphore.clone(),
+            egress_quota: self.egress_quota.clone(),
         };
         let config = OutgoingRequestConfig {
             use_tls: request.uri().scheme() == Some(&Scheme::HTTPS),
@@ -374,6 +372,7 @@ impl p2::WasiHttpHooks for InstanceHttpHooks {
             blocked_networks: self.blocked_networks.clone(),
             http_clients: self.wasi_http_clients.clone(),
             semaphore: self.semaphore.clone(),
+            egress_quota: self.egress_quota.clone(),
         };
         Ok(HostFutureIncomingResponse::Pending(
             wasmtime_wasi::runtime::spawn(
@@ -400,6 +399,7 @@ struct RequestSender {
     request_interceptor: Option<Arc<dyn OutboundHttpInterceptor>>,
     http_clients: HttpClients,
     semaphore: ConnectionSemaphore,
+    egress_quota: Option<EgressQuota>,
 }
 
 impl RequestSender {
@@ -445,15 +445,27 @@ impl RequestSender {
             }
         }
 
-        record_content_length_header(
-            &span,
-            request.headers(),
-            "http.request.header.content-length",
-        );
+        record_headers(&span, request.headers(), "http.request.header");
+
+        // Requests completed by the interceptor never leave the host, so only
+        // count those that are actually sent against the egress quota.
+        let egress_quota = self.egress_quota.clone();
+        if let Some(quota) = &egress_quota {
+            quota
+                .start_request(HTTP_EGRESS_KIND)
+                .map_err(quota_exceeded_error)?;
+            request = request.map(|body| QuotaBody::boxed(body, quota.clone(), Direction::Sent));
+        }
 
-        Ok(self
+        let mut resp = self
             .send_request(request, config, override_connect_addr)
-            .await?)
+            .await?;
+        if let Some(quota) = egress_quota {
+            resp.resp = resp
+                .resp
+                .map(|body| QuotaBody::boxed(body, quota, Direction::Received));
+        }
+        Ok(resp)
     }
 
     async fn prepare_request(
@@ -475,7 +487,10 @@ impl RequestSender {
             }
             *uri = builder.build().unwrap();
         }
-        tracing::Span::current().record(otel_attribute::URL_FULL, uri.to_string());
+        tracing::Span::current().record(
+            otel_attribute::URL_FULL,
+            &*spin_telemetry::redaction::redact_url(&uri.to_string()),
+        );
 
         let is_self_request = match request.uri().authority() {
             // Some SDKs require an authority, so we support e.g. http://self.alt/self-request
@@ -586,7 +601,7 @@ impl RequestSender {
             resp.status().as_u16(),
         );
 
-        record_content_length_header(&span, resp.headers(), "http.response.header.content-length");
+        record_headers(&span, resp.headers(), "http.response.header");
 
         Ok(IncomingResponse {
             resp,
@@ -922,19 +937,63 @@ fn dns_error(rcode: String, info_code: u16) -> ErrorCode {
     )
 }
 
-fn record_content_length_header(span: &Span, headers: &HeaderMap, attr_name: &'static str) {
-    if let Some(content_length) = headers.get(CONTENT_LENGTH)
-        && let Ok(size_str) = content_length.to_str()
-    {
-        span.set_attribute(attr_name, size_str.to_string());
+/// Records `headers` on `span` as `{prefix}.{name}` attributes, with
+/// credentials and secret values redacted.
+pub(crate) fn record_headers(span: &Span, headers: &HeaderMap, prefix: &str) {
+    for (name, value) in header_attributes(headers, prefix) {
+        span.set_attribute(name, value);
     }
 }
 
+/// Returns the span attributes for `headers`, joining repeated headers with
+/// commas and skipping values which aren't valid strings.
+fn header_attributes(headers: &HeaderMap, prefix: &str) -> Vec<(String, String)> {
+    headers
+        .keys()
+        .filter_map(|name| {
+            let values = headers
+                .get_all(name)
+                .iter()
+                .map(|value| value.to_str().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let value = spin_telemetry::redaction::redact_header(name.as_str(), &values.join(", "))
+                .into_owned();
+            Some((format!("{prefix}.{name}"), value))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use spin_factor_outbound_networking::LimitedSemaphore;
 
+    #[test]
+    fn recorded_headers_are_redacted() {
+        let secrets = spin_telemetry::redaction::SecretScope::new();
+        secrets.register("api_token", "tok-0123456789");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::CONTENT_LENGTH, "42".parse().unwrap());
+        headers.insert("authorization", "Bearer abc".parse().unwrap());
+        headers.insert("x-forwarded-token", "tok-0123456789".parse().unwrap());
+        headers.append("accept", "text/plain".parse().unwrap());
+        headers.append("accept", "text/html".parse().unwrap());
+
+        let mut attributes = header_attributes(&headers, "http.request.header");
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            [
+                ("http.request.header.accept", "text/plain, text/html"),
+                ("http.request.header.authorization", "[REDACTED]"),
+                ("http.request.header.content-length", "42"),
+                ("http.request.header.x-forwarded-token", "[REDACTED]"),
+            ]
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        );
+    }
+
     /// Regression test: the connect timeout must cover permit acquisition, not
     /// just the TCP handshake.  Before the fix, a fully-saturated semaphore
     /// caused `connect_tcp` to hang indefinitely; after the fix it returns
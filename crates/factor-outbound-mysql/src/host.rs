@@ -4,6 +4,7 @@ use anyhow::Result;
 use opentelemetry_semantic_conventions::attribute as otel_attribute;
 use spin_core::wasmtime::component::{Accessor, FutureReader, Resource, StreamReader};
 use spin_factor_outbound_networking::ConnectionPermit;
+use spin_telemetry::metrics::DataMetrics;
 use spin_telemetry::traces::{self, Blame};
 use spin_world::MAX_HOST_BUFFERED_BYTES;
 use spin_world::spin::mysql::mysql as v3;
@@ -15,7 +16,9 @@ use tracing::field::Empty;
 use tracing::{Level, instrument};
 
 use crate::client::Client;
-use crate::{InstanceState, InstanceStateInner, MysqlFactorData};
+use crate::{InstanceState, InstanceStateInner, MysqlFactorData, OpenConnection};
+
+const SYSTEM: &str = "mysql";
 
 impl<C: Client> InstanceStateInner<C> {
     async fn open_connection(
@@ -38,16 +41,29 @@ impl<C: Client> InstanceStateInner<C> {
             traces::mark_as_error(&err, Some(Blame::Guest));
             return Err(err);
         }
-        let client = C::build_client(address).await.map_err(|e| {
-            // The guest supplies the address and credentials; connection
-            // failures (wrong password, TLS error, unreachable host, etc.)
-            // are the guest's problem.
-            let err = v2::Error::ConnectionFailed(format!("{e:?}"));
-            traces::mark_as_error(&err, Some(Blame::Guest));
-            err
-        })?;
+        let metrics = DataMetrics::new(
+            SYSTEM,
+            &self.component_id,
+            &spin_factor_outbound_networking::address_metric_label(address),
+        );
+        let client = metrics
+            .time("open", |_| "connection_failed", C::build_client(address))
+            .await
+            .map_err(|e| {
+                // The guest supplies the address and credentials; connection
+                // failures (wrong password, TLS error, unreachable host, etc.)
+                // are the guest's problem.
+                let err = v2::Error::ConnectionFailed(format!("{e:?}"));
+                traces::mark_as_error(&err, Some(Blame::Guest));
+                err
+            })?;
         self.connections
-            .push((Arc::new(Mutex::new(client)), permit))
+            .push(OpenConnection {
+                client: Arc::new(Mutex::new(client)),
+                _open: metrics.connection_opened(),
+                metrics,
+                _permit: permit,
+            })
             .map_err(|_| {
                 // The guest exceeded the host-imposed connection limit.
                 let err = v2::Error::ConnectionFailed("too many connections".into());
@@ -56,10 +72,10 @@ impl<C: Client> InstanceStateInner<C> {
             })
     }
 
-    fn get_client(&mut self, connection: u32) -> Result<Arc<Mutex<C>>, v2::Error> {
+    fn get_client(&mut self, connection: u32) -> Result<(Arc<Mutex<C>>, DataMetrics), v2::Error> {
         self.connections
             .get(connection)
-            .map(|(conn, _permit)| conn.clone())
+            .map(|conn| (conn.client.clone(), conn.metrics.clone()))
             .ok_or_else(|| {
                 // The connection table is managed entirely by the host, so a
                 // missing handle indicates a host-side bug, not a guest mistake.
@@ -123,15 +139,21 @@ impl<C: Client, T> v3::HostConnectionWithStore<T> for MysqlFactorData<C> {
         params: Vec<v3::ParameterValue>,
     ) -> Result<(), v3::Error> {
         let state = accessor.with(|mut access| access.get().inner.clone());
-        let client = {
+        let (client, metrics) = {
             let mut state = state.lock().await;
             state.otel.reparent_tracing_span();
             state.get_client(connection.rep())?
         };
-        client
-            .lock()
-            .await
-            .execute(statement, params.into_iter().map(Into::into).collect())
+        metrics.record_bytes_written(statement.len());
+        metrics
+            .time(
+                "execute",
+                error_type,
+                client
+                    .lock()
+                    .await
+                    .execute(statement, params.into_iter().map(Into::into).collect()),
+            )
             .await
             .map_err(track_db_error_on_span)?;
         Ok(())
@@ -145,16 +167,23 @@ impl<C: Client, T> v3::HostConnectionWithStore<T> for MysqlFactorData<C> {
         params: Vec<v3::ParameterValue>,
     ) -> Result<QueryTuple, v3::Error> {
         let state = accessor.with(|mut access| access.get().inner.clone());
-        let client = {
+        let (client, metrics) = {
             let mut state = state.lock().await;
             state.otel.reparent_tracing_span();
             state.get_client(connection.rep())?
         };
 
-        let (columns, stream, future) =
-            C::query_async(client, statement, params, MAX_HOST_BUFFERED_BYTES)
-                .await
-                .map_err(|v| v3::Error::from(track_db_error_on_span(v2::Error::from(v))))?;
+        // Rows are streamed to the guest, so only the time to the start of
+        // the results is recorded.
+        metrics.record_bytes_written(statement.len());
+        let (columns, stream, future) = metrics
+            .time(
+                "query_async",
+                v3_error_type,
+                C::query_async(client, statement, params, MAX_HOST_BUFFERED_BYTES),
+            )
+            .await
+            .map_err(|v| v3::Error::from(track_db_error_on_span(v2::Error::from(v))))?;
 
         let (stream, future) = accessor
             .with(|mut access| {
@@ -204,13 +233,17 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
     ) -> Result<(), v2::Error> {
         let mut state = self.inner.lock().await;
         state.otel.reparent_tracing_span();
-        state
-            .get_client(connection.rep())?
-            .lock()
+        let (client, metrics) = state.get_client(connection.rep())?;
+        metrics.record_bytes_written(statement.len());
+        metrics
+            .time(
+                "execute",
+                error_type,
+                client.lock().await.execute(statement, params),
+            )
             .await
-            .execute(statement, params)
-            .await
-            .map_err(track_db_error_on_span)
+            .map_err(track_db_error_on_span)?;
+        Ok(())
     }
 
     #[instrument(name = "spin_outbound_mysql.query", skip(self, connection, params), err(level = Level::INFO),
@@ -223,13 +256,27 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
     ) -> Result<v2_types::RowSet, v2::Error> {
         let mut state = self.inner.lock().await;
         state.otel.reparent_tracing_span();
-        state
-            .get_client(connection.rep())?
-            .lock()
-            .await
-            .query(statement, params, MAX_HOST_BUFFERED_BYTES)
+        let (client, metrics) = state.get_client(connection.rep())?;
+        metrics.record_bytes_written(statement.len());
+        let rowset = metrics
+            .time(
+                "query",
+                error_type,
+                client
+                    .lock()
+                    .await
+                    .query(statement, params, MAX_HOST_BUFFERED_BYTES),
+            )
             .await
-            .map_err(track_db_error_on_span)
+            .map_err(track_db_error_on_span)?;
+        metrics.record_bytes_read(
+            rowset
+                .rows
+                .iter()
+                .flat_map(|row| row.iter().map(v2_types::DbValue::memory_size))
+                .sum(),
+        );
+        Ok(rowset)
     }
 
     async fn drop(&mut self, connection: Resource<v2::Connection>) -> Result<()> {
@@ -301,6 +348,26 @@ impl<C: Client> v1::Host for InstanceState<C> {
     }
 }
 
+fn error_type(err: &v2::Error) -> &'static str {
+    match err {
+        v2::Error::ConnectionFailed(_) => "connection_failed",
+        v2::Error::BadParameter(_) => "bad_parameter",
+        v2::Error::QueryFailed(_) => "query_failed",
+        v2::Error::ValueConversionFailed(_) => "value_conversion_failed",
+        v2::Error::Other(_) => "other",
+    }
+}
+
+fn v3_error_type(err: &v3::Error) -> &'static str {
+    match err {
+        v3::Error::ConnectionFailed(_) => "connection_failed",
+        v3::Error::BadParameter(_) => "bad_parameter",
+        v3::Error::QueryFailed(_) => "query_failed",
+        v3::Error::ValueConversionFailed(_) => "value_conversion_failed",
+        v3::Error::Other(_) => "other",
+    }
+}
+
 /// Only for actual DB client calls (execute/query).
 /// Blame is inferred from the error variant returned by the DB driver.
 fn track_db_error_on_span(err: v2::Error) -> v2::Error {
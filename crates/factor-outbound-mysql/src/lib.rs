@@ -13,6 +13,7 @@ use spin_factor_outbound_networking::{
     config::allowed_hosts::OutboundAllowedHosts,
 };
 use spin_factors::{Factor, FactorData, InitContext, RuntimeFactors, SelfInstanceBuilder};
+use spin_telemetry::metrics::{DataMetrics, OpenConnectionMetric};
 use spin_world::spin::mysql::mysql as v3;
 use spin_world::v1::mysql as v1;
 use spin_world::v2::mysql as v2;
@@ -68,6 +69,7 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundMysqlFactor<C> {
         Ok(InstanceState {
             inner: Arc::new(Mutex::new(InstanceStateInner {
                 allowed_hosts,
+                component_id: ctx.app_component().id().into(),
                 connections: Default::default(),
                 otel,
             })),
@@ -92,10 +94,20 @@ impl<C> OutboundMysqlFactor<C> {
 
 pub struct InstanceStateInner<C> {
     allowed_hosts: OutboundAllowedHosts,
-    connections: spin_resource_table::Table<(Arc<Mutex<C>>, ConnectionPermit)>,
+    component_id: Arc<str>,
+    connections: spin_resource_table::Table<OpenConnection<C>>,
     otel: OtelFactorState,
 }
 
+/// A connection in the connection table, with the metrics recorded for the
+/// operations made through it.
+struct OpenConnection<C> {
+    client: Arc<Mutex<C>>,
+    metrics: DataMetrics,
+    _open: OpenConnectionMetric,
+    _permit: ConnectionPermit,
+}
+
 pub struct InstanceState<C> {
     pub(crate) inner: Arc<Mutex<InstanceStateInner<C>>>,
     pub semaphore: ConnectionSemaphore,
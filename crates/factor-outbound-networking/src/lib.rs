@@ -20,16 +20,21 @@ use crate::{
     allowed_hosts::allowed_outbound_hosts, runtime_config::RuntimeConfig, tls::TlsClientConfigs,
 };
 pub use allowed_hosts::validate_service_chaining_for_components;
-pub use spin_connection_semaphore::{ConnectionPermit, ConnectionSemaphore, LimitedSemaphore};
+pub use spin_connection_semaphore::{
+    AppEgressQuota, ConnectionPermit, ConnectionSemaphore, EgressQuota, EgressQuotaExceeded,
+    EgressQuotaLimits, LimitedSemaphore,
+};
 
 pub use crate::tls::{ComponentTlsClientConfigs, TlsClientConfig};
 use config::allowed_hosts::AllowedHostsConfig;
+use config::audit::{AllowedHostsAudit, ComponentAllowedHostsAudit};
 use config::blocked_networks::BlockedNetworks;
 pub use spin_outbound_networking_config as config;
 
 #[derive(Default)]
 pub struct OutboundNetworkingFactor {
     disallowed_host_handler: Option<Arc<dyn DisallowedHostHandler>>,
+    audit_allowed_hosts: bool,
 }
 
 impl OutboundNetworkingFactor {
@@ -42,6 +47,15 @@ impl OutboundNetworkingFactor {
     pub fn set_disallowed_host_handler(&mut self, handler: impl DisallowedHostHandler + 'static) {
         self.disallowed_host_handler = Some(Arc::new(handler));
     }
+
+    /// Enables allowed hosts audit mode.
+    ///
+    /// In audit mode, outbound requests are not checked against components'
+    /// `allowed_outbound_hosts`; instead every attempted destination is
+    /// recorded to the app's [`AllowedHostsAudit`].
+    pub fn enable_allowed_hosts_audit(&mut self) {
+        self.audit_allowed_hosts = true;
+    }
 }
 
 impl Factor for OutboundNetworkingFactor {
@@ -74,6 +88,7 @@ impl Factor for OutboundNetworkingFactor {
             max_socket_connections,
             max_total_connections,
             wait_timeout,
+            egress_quotas,
         } = ctx.take_runtime_config().unwrap_or_default();
 
         let blocked_networks = BlockedNetworks::new(block_networks, block_private_networks);
@@ -99,6 +114,37 @@ impl Factor for OutboundNetworkingFactor {
             .unwrap_or_else(|| "<unnamed>".into())
             .into();
 
+        for component_id in egress_quotas.component_limits.keys() {
+            if ctx.app().get_component(component_id).is_none() {
+                tracing::warn!(
+                    "outbound_networking egress quota configured for component \
+                     {component_id:?}, which is not in this app"
+                );
+            }
+        }
+        let app_egress_quota = (!egress_quotas.app_limits.is_unlimited())
+            .then(|| AppEgressQuota::new(egress_quotas.app_limits.clone()));
+        let component_egress_quotas = ctx
+            .app()
+            .components()
+            .filter_map(|component| {
+                let limits = egress_quotas.limits_for(component.id());
+                if limits.is_unlimited() && app_egress_quota.is_none() {
+                    return None;
+                }
+                let mut quota =
+                    EgressQuota::new(limits.clone(), app_id.clone(), component.id().into());
+                if let Some(app_quota) = &app_egress_quota {
+                    quota = quota.with_app_quota(app_quota);
+                }
+                Some((component.id().to_string(), quota))
+            })
+            .collect();
+
+        let allowed_hosts_audit = self
+            .audit_allowed_hosts
+            .then(|| Arc::new(AllowedHostsAudit::new()));
+
         let socket_connection_semaphore =
             if max_socket_connections.is_some() || global_connection_semaphore.is_some() {
                 Some(ConnectionSemaphore::new(
@@ -119,6 +165,8 @@ impl Factor for OutboundNetworkingFactor {
             socket_connection_semaphore,
             global_connection_semaphore,
             app_id,
+            allowed_hosts_audit,
+            component_egress_quotas,
         })
     }
 
@@ -159,22 +207,36 @@ impl Factor for OutboundNetworkingFactor {
         .map(|res| res.map(Arc::new).map_err(Arc::new))
         .boxed()
         .shared();
-        let allowed_hosts = OutboundAllowedHosts::new(
+        let mut allowed_hosts = OutboundAllowedHosts::new(
             allowed_hosts_future.clone(),
             self.disallowed_host_handler.clone(),
         );
+        if let Some(audit) = &ctx.app_state().allowed_hosts_audit {
+            allowed_hosts = allowed_hosts.with_audit(ComponentAllowedHostsAudit::new(
+                audit.clone(),
+                ctx.app_component().id(),
+            ));
+        }
         let blocked_networks = ctx.app_state().blocked_networks.clone();
         let permit_state = ctx
             .app_state()
             .socket_connection_semaphore
             .clone()
             .map(SocketPermitState::new);
+        let egress_quota = ctx
+            .app_state()
+            .component_egress_quotas
+            .get(ctx.app_component().id())
+            .cloned();
 
         match ctx.instance_builder::<WasiFactor>() {
             Ok(wasi_builder) => {
                 if let Some(state) = permit_state {
                     wasi_builder.set_socket_permit_state(state);
                 }
+                if let Some(quota) = egress_quota.clone() {
+                    wasi_builder.set_egress_quota(quota);
+                }
 
                 let allowed_hosts = allowed_hosts.clone();
                 wasi_builder.outbound_socket_addr_check(move |addr, addr_use| {
@@ -223,6 +285,7 @@ impl Factor for OutboundNetworkingFactor {
             allowed_hosts,
             blocked_networks: ctx.app_state().blocked_networks.clone(),
             component_tls_client_configs: component_tls_configs,
+            egress_quota,
         })
     }
 }
@@ -244,6 +307,11 @@ pub struct AppState {
     /// Identifier of this app, used for tenant attribution on tracing events emitted by
     /// outbound factors' connection semaphores. Resolved once at configure-app time.
     app_id: Arc<str>,
+    /// Record of attempted outbound destinations when in allowed hosts audit mode.
+    allowed_hosts_audit: Option<Arc<AllowedHostsAudit>>,
+    /// Component ID -> egress quota, shared by all instances of the component.
+    /// Components without configured limits have no entry.
+    component_egress_quotas: HashMap<String, EgressQuota>,
 }
 
 impl AppState {
@@ -252,6 +320,11 @@ impl AppState {
     pub fn app_id(&self) -> Arc<str> {
         self.app_id.clone()
     }
+
+    /// Returns the app's allowed hosts audit, if audit mode is enabled.
+    pub fn allowed_hosts_audit(&self) -> Option<Arc<AllowedHostsAudit>> {
+        self.allowed_hosts_audit.clone()
+    }
 }
 
 /// Builds a [`ConnectionSemaphore`] for an outbound factor, incorporating the optional global
@@ -296,6 +369,7 @@ pub struct InstanceBuilder {
     allowed_hosts: OutboundAllowedHosts,
     blocked_networks: BlockedNetworks,
     component_tls_client_configs: ComponentTlsClientConfigs,
+    egress_quota: Option<EgressQuota>,
 }
 
 impl InstanceBuilder {
@@ -310,6 +384,11 @@ impl InstanceBuilder {
     pub fn component_tls_configs(&self) -> ComponentTlsClientConfigs {
         self.component_tls_client_configs.clone()
     }
+
+    /// Returns the component's egress quota, if any limits are configured for it or its app.
+    pub fn egress_quota(&self) -> Option<EgressQuota> {
+        self.egress_quota.clone()
+    }
 }
 
 impl FactorInstanceBuilder for InstanceBuilder {
@@ -345,3 +424,19 @@ pub fn record_address_fields(address: &str) {
         );
     }
 }
+
+/// Returns the server and database of a connection address, without any
+/// credentials, for use as a metric attribute.
+pub fn address_metric_label(address: &str) -> String {
+    let Ok(url) = Url::parse(address) else {
+        return "unknown".into();
+    };
+    let host = url.host_str().unwrap_or_default();
+    let database = url.path().trim_start_matches('/');
+    match (url.port(), database) {
+        (Some(port), "") => format!("{host}:{port}"),
+        (Some(port), database) => format!("{host}:{port}/{database}"),
+        (None, "") => host.to_owned(),
+        (None, database) => format!("{host}/{database}"),
+    }
+}
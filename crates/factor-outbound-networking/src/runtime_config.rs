@@ -1,7 +1,10 @@
 #[cfg(feature = "spin-cli")]
 pub mod spin;
 
+use std::collections::HashMap;
+
 pub use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use spin_connection_semaphore::EgressQuotaLimits;
 
 /// Runtime configuration for outbound networking.
 #[derive(Debug, Default)]
@@ -20,6 +23,31 @@ pub struct RuntimeConfig {
     pub max_total_connections: Option<usize>,
     /// If set, limits how long `acquire` will wait for a socket connection permit.
     pub wait_timeout: Option<std::time::Duration>,
+    /// Limits on outbound traffic volume per component and per app.
+    pub egress_quotas: EgressQuotaConfig,
+}
+
+/// Limits on outbound traffic volume (bytes and requests per time window).
+///
+/// Component limits are tracked separately for each component of the app, while
+/// the app limits are shared by all of its components.
+#[derive(Debug, Default)]
+pub struct EgressQuotaConfig {
+    /// Limits applied to components without component-specific limits.
+    pub default_limits: EgressQuotaLimits,
+    /// Component ID -> component-specific limits.
+    pub component_limits: HashMap<String, EgressQuotaLimits>,
+    /// Limits on the combined traffic of all of the app's components.
+    pub app_limits: EgressQuotaLimits,
+}
+
+impl EgressQuotaConfig {
+    /// Returns the limits that apply to the given component.
+    pub fn limits_for(&self, component_id: &str) -> &EgressQuotaLimits {
+        self.component_limits
+            .get(component_id)
+            .unwrap_or(&self.default_limits)
+    }
 }
 
 /// TLS configuration for one or more component(s) and host(s).
@@ -2,13 +2,16 @@ use anyhow::{Context, bail, ensure};
 use ip_network::IpNetwork;
 use rustls_pki_types::pem::PemObject;
 use serde::{Deserialize, Deserializer};
+use spin_connection_semaphore::EgressQuotaLimits;
 use spin_factors::runtime_config::toml::GetTomlValue;
 use std::{
     borrow::Cow,
+    collections::HashMap,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use super::ClientTlsRuntimeConfig;
+use super::{ClientTlsRuntimeConfig, EgressQuotaConfig};
 
 /// Spin's default handling of the runtime configuration for outbound networking.
 pub struct SpinRuntimeConfig {
@@ -32,6 +35,11 @@ impl SpinRuntimeConfig {
     /// ````toml
     /// [outbound_networking]
     /// block_networks = ["1.1.1.1/32", "private"]
+    /// egress_quota = { max_bytes_sent = 1048576, max_requests = 100, window_secs = 60 }
+    /// app_egress_quota = { max_bytes_sent = 10485760 }
+    ///
+    /// [outbound_networking.component_egress_quotas.example-component]
+    /// max_bytes_received = 10485760
     ///
     /// [[client_tls]]
     /// component_ids = ["example-component"]
@@ -69,6 +77,27 @@ impl SpinRuntimeConfig {
             }
         }
 
+        let default_limits = match outbound_networking.egress_quota {
+            Some(quota) => quota.limits(&EgressQuotaLimits::default())?,
+            None => EgressQuotaLimits::default(),
+        };
+        let component_limits = outbound_networking
+            .component_egress_quotas
+            .into_iter()
+            .map(|(component_id, quota)| {
+                let limits = quota.limits(&default_limits).with_context(|| {
+                    format!("invalid egress quota for component {component_id:?}")
+                })?;
+                Ok((component_id.to_string(), limits))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let app_limits = match outbound_networking.app_egress_quota {
+            Some(quota) => quota
+                .limits(&EgressQuotaLimits::default())
+                .context("invalid app egress quota")?,
+            None => EgressQuotaLimits::default(),
+        };
+
         let runtime_config = super::RuntimeConfig {
             blocked_ip_networks,
             block_private_networks,
@@ -76,6 +105,11 @@ impl SpinRuntimeConfig {
             max_socket_connections: outbound_networking.max_socket_connections,
             max_total_connections: outbound_networking.max_total_connections,
             wait_timeout: None,
+            egress_quotas: EgressQuotaConfig {
+                default_limits,
+                component_limits,
+                app_limits,
+            },
         };
         Ok(Some(runtime_config))
     }
@@ -225,6 +259,36 @@ struct OutboundNetworkingToml {
     block_networks: Vec<CidrOrPrivate>,
     max_socket_connections: Option<usize>,
     max_total_connections: Option<usize>,
+    egress_quota: Option<EgressQuotaToml>,
+    #[serde(default)]
+    component_egress_quotas: HashMap<spin_serde::KebabId, EgressQuotaToml>,
+    app_egress_quota: Option<EgressQuotaToml>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EgressQuotaToml {
+    max_bytes_sent: Option<u64>,
+    max_bytes_received: Option<u64>,
+    max_requests: Option<u64>,
+    window_secs: Option<u64>,
+}
+
+impl EgressQuotaToml {
+    /// Returns the limits described by this table, taking any unset values from `base`.
+    fn limits(self, base: &EgressQuotaLimits) -> anyhow::Result<EgressQuotaLimits> {
+        let window = match self.window_secs {
+            Some(0) => bail!("'window_secs' must be greater than zero"),
+            Some(secs) => Duration::from_secs(secs),
+            None => base.window,
+        };
+        Ok(EgressQuotaLimits {
+            max_bytes_sent: self.max_bytes_sent.or(base.max_bytes_sent),
+            max_bytes_received: self.max_bytes_received.or(base.max_bytes_received),
+            max_requests: self.max_requests.or(base.max_requests),
+            window,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -307,6 +371,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_egress_quotas() -> anyhow::Result<()> {
+        let config = SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [outbound_networking]
+                egress_quota = { max_bytes_sent = 1000, max_requests = 10 }
+                app_egress_quota = { max_bytes_sent = 8000 }
+
+                [outbound_networking.component_egress_quotas.big-component]
+                max_bytes_sent = 5000
+                window_secs = 5
+            })
+            .context("config_from_table")?
+            .context("expected config, got None")?;
+        let quotas = config.egress_quotas;
+
+        let defaults = quotas.limits_for("other-component");
+        assert_eq!(defaults.max_bytes_sent, Some(1000));
+        assert_eq!(defaults.max_bytes_received, None);
+        assert_eq!(defaults.max_requests, Some(10));
+        assert_eq!(
+            defaults.window,
+            spin_connection_semaphore::DEFAULT_EGRESS_QUOTA_WINDOW
+        );
+
+        let big = quotas.limits_for("big-component");
+        assert_eq!(big.max_bytes_sent, Some(5000));
+        assert_eq!(big.max_requests, Some(10));
+        assert_eq!(big.window, Duration::from_secs(5));
+
+        // App limits don't inherit the component defaults.
+        let app = &quotas.app_limits;
+        assert_eq!(app.max_bytes_sent, Some(8000));
+        assert_eq!(app.max_requests, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_egress_quota_zero_window_rejected() {
+        SpinRuntimeConfig::new("")
+            .config_from_table(&toml::toml! {
+                [outbound_networking]
+                egress_quota = { max_requests = 10, window_secs = 0 }
+            })
+            .unwrap_err();
+    }
+
     #[test]
     fn test_min_tls_config() -> anyhow::Result<()> {
         let config = SpinRuntimeConfig::new("/doesnt-matter");
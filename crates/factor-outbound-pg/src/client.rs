@@ -6,6 +6,7 @@ use anyhow::{Context, Result};
 use futures::stream::TryStreamExt as _;
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
+use spin_telemetry::metrics::DataMetrics;
 use spin_world::async_trait;
 use spin_world::spin::postgres4_2_0::postgres::{
     self as v4, Column, DbValue, ParameterValue, RowSet,
@@ -27,11 +28,13 @@ const CONNECTION_POOL_CACHE_CAPACITY: u64 = 16;
 pub trait ClientFactory: Default + Send + Sync + 'static {
     /// The type of client produced by `get_client`.
     type Client: Client;
-    /// Gets a client from the factory.
+    /// Gets a client from the factory, recording the usage of any connection
+    /// pool with `metrics`.
     async fn get_client(
         &self,
         address: &str,
         root_ca: Option<HashableCertificate>,
+        metrics: &DataMetrics,
     ) -> Result<Self::Client>;
 }
 
@@ -81,6 +84,7 @@ impl ClientFactory for PooledTokioClientFactory {
         &self,
         address: &str,
         root_ca: Option<HashableCertificate>,
+        metrics: &DataMetrics,
     ) -> Result<Self::Client> {
         let (root_ca, root_ca_hash) = match root_ca {
             None => (None, None),
@@ -93,7 +97,12 @@ impl ClientFactory for PooledTokioClientFactory {
             .map_err(ArcError)
             .context("establishing PostgreSQL connection pool")?;
 
-        Ok(PooledTokioClient(Arc::new(pool.get().await?)))
+        let client = pool.get().await?;
+
+        let status = pool.status();
+        metrics.record_pool_usage(status.size, status.available);
+
+        Ok(PooledTokioClient(Arc::new(client)))
     }
 }
 
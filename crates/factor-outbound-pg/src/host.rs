@@ -3,6 +3,7 @@
 use anyhow::Result;
 use opentelemetry_semantic_conventions::attribute as otel_attribute;
 use spin_core::wasmtime::component::{Accessor, FutureReader, Resource, StreamReader};
+use spin_telemetry::metrics::DataMetrics;
 use spin_telemetry::traces::{self, Blame};
 use spin_world::MAX_HOST_BUFFERED_BYTES;
 use spin_world::spin::postgres3_0_0::postgres::{self as v3};
@@ -18,6 +19,7 @@ use tracing::instrument;
 use crate::InstanceState;
 use crate::allowed_hosts::AllowedHostChecker;
 use crate::client::{Client, ClientFactory, HashableCertificate, QueryAsyncResult};
+use crate::metrics::{self, MeteredClient};
 
 impl<CF: ClientFactory> InstanceState<CF> {
     async fn open_connection<Conn: 'static>(
@@ -30,9 +32,13 @@ impl<CF: ClientFactory> InstanceState<CF> {
             traces::mark_as_error(&err, Some(Blame::Guest));
             err
         })?;
-        let client = self
-            .client_factory
-            .get_client(address, root_ca)
+        let metrics = self.data_metrics(address);
+        let client = metrics
+            .time(
+                "open",
+                |_| "connection_failed",
+                self.client_factory.get_client(address, root_ca, &metrics),
+            )
             .await
             .map_err(|e| {
                 // The guest supplies the address and credentials; connection
@@ -43,7 +49,7 @@ impl<CF: ClientFactory> InstanceState<CF> {
                 err
             })?;
         self.connections
-            .push((client, permit))
+            .push((MeteredClient::new(client, metrics), permit))
             .map_err(|_| {
                 // The guest exceeded the host-imposed connection limit.
                 let err = v4::Error::ConnectionFailed("too many connections".into());
@@ -56,7 +62,7 @@ impl<CF: ClientFactory> InstanceState<CF> {
     async fn get_client<Conn: 'static>(
         &self,
         connection: Resource<Conn>,
-    ) -> Result<&CF::Client, v4::Error> {
+    ) -> Result<&MeteredClient<CF::Client>, v4::Error> {
         self.connections
             .get(connection.rep())
             .map(|(client, _permit)| client)
@@ -69,6 +75,14 @@ impl<CF: ClientFactory> InstanceState<CF> {
             })
     }
 
+    fn data_metrics(&self, address: &str) -> DataMetrics {
+        DataMetrics::new(
+            metrics::SYSTEM,
+            &self.component_id,
+            &spin_factor_outbound_networking::address_metric_label(address),
+        )
+    }
+
     fn allowed_host_checker(&self) -> AllowedHostChecker {
         self.allowed_host_checker.clone()
     }
@@ -392,9 +406,13 @@ impl<CF: ClientFactory> crate::PgFactorData<CF> {
         address: &str,
         root_ca: Option<HashableCertificate>,
     ) -> Result<Resource<v4::Connection>, v4::Error> {
-        let (cf, semaphore) = accessor.with(|mut access| {
+        let (cf, semaphore, metrics) = accessor.with(|mut access| {
             let host = access.get();
-            (host.client_factory.clone(), host.semaphore.clone())
+            (
+                host.client_factory.clone(),
+                host.semaphore.clone(),
+                host.data_metrics(address),
+            )
         });
 
         let permit = semaphore.acquire().await.map_err(|_| {
@@ -403,16 +421,23 @@ impl<CF: ClientFactory> crate::PgFactorData<CF> {
             err
         })?;
 
-        let client = cf.get_client(address, root_ca).await.map_err(|e| {
-            let err = v4::Error::ConnectionFailed(format!("{e:?}"));
-            traces::mark_as_error(&err, Some(Blame::Guest));
-            err
-        })?;
+        let client = metrics
+            .time(
+                "open",
+                |_| "connection_failed",
+                cf.get_client(address, root_ca, &metrics),
+            )
+            .await
+            .map_err(|e| {
+                let err = v4::Error::ConnectionFailed(format!("{e:?}"));
+                traces::mark_as_error(&err, Some(Blame::Guest));
+                err
+            })?;
 
         accessor.with(|mut access| {
             let host = access.get();
             host.connections
-                .push((client, permit))
+                .push((MeteredClient::new(client, metrics), permit))
                 .map_err(|_| {
                     let err = v4::Error::ConnectionFailed("too many connections".into());
                     traces::mark_as_error(&err, Some(Blame::Guest));
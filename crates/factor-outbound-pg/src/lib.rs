@@ -1,6 +1,7 @@
 mod allowed_hosts;
 pub mod client;
 mod host;
+mod metrics;
 pub mod runtime_config;
 mod types;
 
@@ -83,6 +84,7 @@ impl<CF: ClientFactory> Factor for OutboundPgFactor<CF> {
 
         Ok(InstanceState {
             allowed_host_checker: AllowedHostChecker::new(allowed_hosts),
+            component_id: ctx.app_component().id().into(),
             client_factory: cf.clone(),
             connections: Default::default(),
             otel,
@@ -108,9 +110,10 @@ impl<C> OutboundPgFactor<C> {
 
 pub struct InstanceState<CF: ClientFactory> {
     allowed_host_checker: AllowedHostChecker,
+    component_id: Arc<str>,
     client_factory: Arc<CF>,
     connections: spin_resource_table::Table<(
-        CF::Client,
+        metrics::MeteredClient<CF::Client>,
         spin_factor_outbound_networking::ConnectionPermit,
     )>,
     otel: OtelFactorState,
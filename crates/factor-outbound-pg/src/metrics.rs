@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use spin_telemetry::metrics::{DataMetrics, OpenConnectionMetric};
+use spin_world::async_trait;
+use spin_world::spin::postgres4_2_0::postgres::{self as v4, ParameterValue, RowSet};
+
+use crate::client::{Client, QueryAsyncResult};
+
+pub(crate) const SYSTEM: &str = "postgresql";
+
+/// A [`Client`] which records metrics for the operations made through it.
+#[derive(Clone)]
+pub(crate) struct MeteredClient<C> {
+    inner: C,
+    metrics: DataMetrics,
+    _open: Arc<OpenConnectionMetric>,
+}
+
+impl<C: Client> MeteredClient<C> {
+    pub fn new(inner: C, metrics: DataMetrics) -> Self {
+        Self {
+            inner,
+            _open: Arc::new(metrics.connection_opened()),
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for MeteredClient<C> {
+    async fn execute(
+        &self,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<u64, v4::Error> {
+        self.metrics.record_bytes_written(statement.len());
+        self.metrics
+            .time("execute", error_type, self.inner.execute(statement, params))
+            .await
+    }
+
+    async fn query(
+        &self,
+        statement: String,
+        params: Vec<ParameterValue>,
+        max_result_bytes: usize,
+    ) -> Result<RowSet, v4::Error> {
+        self.metrics.record_bytes_written(statement.len());
+        let rowset = self
+            .metrics
+            .time(
+                "query",
+                error_type,
+                self.inner.query(statement, params, max_result_bytes),
+            )
+            .await?;
+        self.metrics.record_bytes_read(
+            rowset
+                .rows
+                .iter()
+                .flat_map(|row| row.iter().map(v4::DbValue::memory_size))
+                .sum(),
+        );
+        Ok(rowset)
+    }
+
+    async fn query_async(
+        &self,
+        statement: String,
+        params: Vec<ParameterValue>,
+        max_result_bytes: usize,
+    ) -> Result<QueryAsyncResult, v4::Error> {
+        // Rows are streamed to the guest, so only the time to the start of
+        // the results is recorded.
+        self.metrics.record_bytes_written(statement.len());
+        self.metrics
+            .time(
+                "query_async",
+                error_type,
+                self.inner.query_async(statement, params, max_result_bytes),
+            )
+            .await
+    }
+}
+
+fn error_type(err: &v4::Error) -> &'static str {
+    match err {
+        v4::Error::ConnectionFailed(_) => "connection_failed",
+        v4::Error::BadParameter(_) => "bad_parameter",
+        v4::Error::QueryFailed(_) => "query_failed",
+        v4::Error::ValueConversionFailed(_) => "value_conversion_failed",
+        v4::Error::Other(_) => "other",
+    }
+}
@@ -1,4 +1,5 @@
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use anyhow::Result;
 use opentelemetry_semantic_conventions::attribute as otel_attribute;
@@ -9,6 +10,7 @@ use spin_core::wasmtime::component::{Accessor, Resource};
 use spin_factor_otel::OtelFactorState;
 use spin_factor_outbound_networking::ConnectionSemaphore;
 use spin_factor_outbound_networking::config::blocked_networks::BlockedNetworks;
+use spin_telemetry::metrics::{DataMetrics, OpenConnectionMetric};
 use spin_world::MAX_HOST_BUFFERED_BYTES;
 use spin_world::spin::redis::redis as v3;
 use spin_world::v1::{redis as v1, redis_types};
@@ -18,17 +20,60 @@ use tracing::{Level, instrument};
 
 use crate::allowed_hosts::AllowedHostChecker;
 
+const SYSTEM: &str = "redis";
+
 pub struct InstanceState {
     pub(crate) allowed_host_checker: AllowedHostChecker,
     pub blocked_networks: BlockedNetworks,
     pub connections: spin_resource_table::Table<(
-        MultiplexedConnection,
+        RedisConnection,
         spin_factor_outbound_networking::ConnectionPermit,
     )>,
     pub semaphore: ConnectionSemaphore,
+    pub(crate) component_id: Arc<str>,
     pub otel: OtelFactorState,
 }
 
+/// A Redis connection, with the metrics recorded for the operations made
+/// through it.
+#[derive(Clone)]
+pub struct RedisConnection {
+    conn: MultiplexedConnection,
+    metrics: DataMetrics,
+    _open: Arc<OpenConnectionMetric>,
+}
+
+impl RedisConnection {
+    async fn open(
+        address: &str,
+        component_id: &str,
+        blocked_networks: BlockedNetworks,
+    ) -> Result<Self, v3::Error> {
+        let metrics = DataMetrics::new(
+            SYSTEM,
+            component_id,
+            &spin_factor_outbound_networking::address_metric_label(address),
+        );
+        let config =
+            AsyncConnectionConfig::new().set_dns_resolver(SpinDnsResolver(blocked_networks));
+        let conn = metrics
+            .time(
+                "open",
+                error_type,
+                redis::Client::open(address)
+                    .map_err(|_| v3::Error::InvalidAddress)?
+                    .get_multiplexed_async_connection_with_config(&config),
+            )
+            .await
+            .map_err(other_error_v3)?;
+        Ok(Self {
+            conn,
+            _open: Arc::new(metrics.connection_opened()),
+            metrics,
+        })
+    }
+}
+
 impl InstanceState {
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
         self.allowed_host_checker.is_address_allowed(address).await
@@ -43,13 +88,9 @@ impl InstanceState {
             .acquire()
             .await
             .map_err(|_| v2::Error::TooManyConnections)?;
-        let config = AsyncConnectionConfig::new()
-            .set_dns_resolver(SpinDnsResolver(self.blocked_networks.clone()));
-        let conn = redis::Client::open(address.as_str())
-            .map_err(|_| v2::Error::InvalidAddress)?
-            .get_multiplexed_async_connection_with_config(&config)
-            .await
-            .map_err(other_error_v2)?;
+        let conn =
+            RedisConnection::open(&address, &self.component_id, self.blocked_networks.clone())
+                .await?;
         self.connections
             .push((conn, permit))
             .map(Resource::new_own)
@@ -59,7 +100,7 @@ impl InstanceState {
     async fn get_conn(
         &mut self,
         connection: Resource<v2::Connection>,
-    ) -> Result<&mut MultiplexedConnection, v2::Error> {
+    ) -> Result<&mut RedisConnection, v2::Error> {
         self.connections
             .get_mut(connection.rep())
             .map(|(conn, _permit)| conn)
@@ -71,7 +112,7 @@ impl InstanceState {
     fn get_conn_v3(
         &mut self,
         connection: Resource<v3::Connection>,
-    ) -> Result<MultiplexedConnection, v3::Error> {
+    ) -> Result<RedisConnection, v3::Error> {
         self.connections
             .get(connection.rep())
             .map(|(conn, _permit)| conn.clone())
@@ -85,27 +126,32 @@ mod operations {
     use super::*;
 
     pub async fn publish(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         channel: String,
         payload: v3::Payload,
     ) -> Result<(), v3::Error> {
         // The `let () =` syntax is needed to suppress a warning when the result type is inferred.
         // You can read more about the issue here: <https://github.com/redis-rs/redis-rs/issues/1228>
+        conn.metrics.record_bytes_written(payload.len());
         let () = conn
-            .publish(&channel, &payload)
+            .metrics
+            .time("publish", error_type, conn.conn.publish(&channel, &payload))
             .await
             .map_err(other_error_v3)?;
         Ok(())
     }
 
     pub async fn get(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         key: String,
     ) -> Result<Option<Vec<u8>>, v3::Error> {
         let value = conn
-            .get::<_, Option<Vec<u8>>>(&key)
+            .metrics
+            .time("get", error_type, conn.conn.get::<_, Option<Vec<u8>>>(&key))
             .await
             .map_err(other_error_v3)?;
+        conn.metrics
+            .record_bytes_read(value.as_ref().map_or(0, Vec::len));
 
         // Currently there's no way to stream a `GET` result using the `redis`
         // crate without buffering, so the damage (in terms of host memory
@@ -122,76 +168,109 @@ mod operations {
     }
 
     pub async fn set(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         key: String,
         value: Vec<u8>,
     ) -> Result<(), v3::Error> {
         // The `let () =` syntax is needed to suppress a warning when the result type is inferred.
         // You can read more about the issue here: <https://github.com/redis-rs/redis-rs/issues/1228>
-        let () = conn.set(&key, &value).await.map_err(other_error_v3)?;
+        conn.metrics.record_bytes_written(value.len());
+        let () = conn
+            .metrics
+            .time("set", error_type, conn.conn.set(&key, &value))
+            .await
+            .map_err(other_error_v3)?;
         Ok(())
     }
 
-    pub async fn incr(conn: &mut MultiplexedConnection, key: String) -> Result<i64, v3::Error> {
-        conn.incr(&key, 1).await.map_err(other_error_v3)
+    pub async fn incr(conn: &mut RedisConnection, key: String) -> Result<i64, v3::Error> {
+        conn.metrics
+            .time("incr", error_type, conn.conn.incr(&key, 1))
+            .await
+            .map_err(other_error_v3)
     }
 
-    pub async fn del(
-        conn: &mut MultiplexedConnection,
-        keys: Vec<String>,
-    ) -> Result<u32, v3::Error> {
-        conn.del(&keys).await.map_err(other_error_v3)
+    pub async fn del(conn: &mut RedisConnection, keys: Vec<String>) -> Result<u32, v3::Error> {
+        conn.metrics
+            .time("del", error_type, conn.conn.del(&keys))
+            .await
+            .map_err(other_error_v3)
     }
 
     pub async fn sadd(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         key: String,
         values: Vec<String>,
     ) -> Result<u32, v3::Error> {
-        let value = conn.sadd(&key, &values).await.map_err(|e| {
-            if e.kind() == redis::ErrorKind::TypeError {
-                v3::Error::TypeError
-            } else {
-                v3::Error::Other(e.to_string())
-            }
-        })?;
+        let value = conn
+            .metrics
+            .time("sadd", error_type, conn.conn.sadd(&key, &values))
+            .await
+            .map_err(|e| {
+                if e.kind() == redis::ErrorKind::TypeError {
+                    v3::Error::TypeError
+                } else {
+                    v3::Error::Other(e.to_string())
+                }
+            })?;
         Ok(value)
     }
 
     pub async fn smembers(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         key: String,
     ) -> Result<Vec<String>, v3::Error> {
-        conn.smembers(&key).await.map_err(other_error_v3)
+        let members: Vec<String> = conn
+            .metrics
+            .time("smembers", error_type, conn.conn.smembers(&key))
+            .await
+            .map_err(other_error_v3)?;
+        conn.metrics
+            .record_bytes_read(members.iter().map(String::len).sum());
+        Ok(members)
     }
 
     pub async fn srem(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         key: String,
         values: Vec<String>,
     ) -> Result<u32, v3::Error> {
-        conn.srem(&key, &values).await.map_err(other_error_v3)
+        conn.metrics
+            .time("srem", error_type, conn.conn.srem(&key, &values))
+            .await
+            .map_err(other_error_v3)
     }
 
     pub async fn execute(
-        conn: &mut MultiplexedConnection,
+        conn: &mut RedisConnection,
         command: String,
         arguments: impl IntoIterator<Item = v3::RedisParameter>,
     ) -> Result<RedisResults, v3::Error> {
         let mut cmd = redis::cmd(&command);
+        let mut bytes_written = command.len();
         arguments.into_iter().for_each(|value| match value {
             v3::RedisParameter::Int64(v) => {
+                bytes_written += std::mem::size_of_val(&v);
                 cmd.arg(v);
             }
             v3::RedisParameter::Binary(v) => {
+                bytes_written += v.len();
                 cmd.arg(v);
             }
         });
-
-        let results = cmd
-            .query_async::<RedisResults>(conn)
+        conn.metrics.record_bytes_written(bytes_written);
+
+        let results = conn
+            .metrics
+            .time(
+                "execute",
+                error_type,
+                cmd.query_async::<RedisResults>(&mut conn.conn),
+            )
             .await
             .map_err(other_error_v3)?;
+        conn.metrics
+            .record_bytes_read(results.0.iter().map(memory_size).sum());
 
         // Currently there's no way to stream results using the `redis`
         // crate without buffering, so the damage (in terms of host memory
@@ -226,7 +305,7 @@ impl crate::RedisFactorData {
     fn get_conn<T: Send>(
         accessor: &Accessor<T, Self>,
         connection: Resource<v3::Connection>,
-    ) -> Result<MultiplexedConnection, v3::Error> {
+    ) -> Result<RedisConnection, v3::Error> {
         accessor.with(|mut access| {
             let host = access.get();
             host.otel.reparent_tracing_span();
@@ -242,15 +321,17 @@ impl<T: Send> v3::HostConnectionWithStore<T> for crate::RedisFactorData {
         accessor: &Accessor<T, Self>,
         address: String,
     ) -> Result<Resource<v3::Connection>, v3::Error> {
-        let (allowed_host_checker, blocked_networks, semaphore) = accessor.with(|mut access| {
-            let host = access.get();
-            host.otel.reparent_tracing_span();
-            (
-                host.allowed_host_checker.clone(),
-                host.blocked_networks.clone(),
-                host.semaphore.clone(),
-            )
-        });
+        let (allowed_host_checker, blocked_networks, semaphore, component_id) =
+            accessor.with(|mut access| {
+                let host = access.get();
+                host.otel.reparent_tracing_span();
+                (
+                    host.allowed_host_checker.clone(),
+                    host.blocked_networks.clone(),
+                    host.semaphore.clone(),
+                    host.component_id.clone(),
+                )
+            });
 
         if !allowed_host_checker
             .is_address_allowed(&address)
@@ -265,13 +346,7 @@ impl<T: Send> v3::HostConnectionWithStore<T> for crate::RedisFactorData {
             .await
             .map_err(|_| v3::Error::TooManyConnections)?;
 
-        let config =
-            AsyncConnectionConfig::new().set_dns_resolver(SpinDnsResolver(blocked_networks));
-        let conn = redis::Client::open(address.as_str())
-            .map_err(|_| v3::Error::InvalidAddress)?
-            .get_multiplexed_async_connection_with_config(&config)
-            .await
-            .map_err(other_error_v3)?;
+        let conn = RedisConnection::open(&address, &component_id, blocked_networks).await?;
 
         accessor.with(|mut access| {
             let host = access.get();
@@ -552,6 +627,18 @@ impl v2::HostConnection for crate::InstanceState {
     }
 }
 
+fn error_type(err: &redis::RedisError) -> &'static str {
+    if err.is_timeout() {
+        "timeout"
+    } else if err.is_io_error() {
+        "io"
+    } else if err.kind() == redis::ErrorKind::TypeError {
+        "type_error"
+    } else {
+        "other"
+    }
+}
+
 fn other_error_v2(e: impl std::fmt::Display) -> v2::Error {
     v2::Error::Other(e.to_string())
 }
@@ -74,6 +74,7 @@ impl Factor for OutboundRedisFactor {
             blocked_networks: outbound_networking.blocked_networks(),
             connections: spin_resource_table::Table::new(1024),
             semaphore: ctx.app_state().semaphore.clone(),
+            component_id: ctx.app_component().id().into(),
             otel,
         })
     }
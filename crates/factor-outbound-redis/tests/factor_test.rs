@@ -4,6 +4,7 @@ use spin_factor_outbound_redis::OutboundRedisFactor;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::{RuntimeFactors, anyhow};
 use spin_factors_test::{TestEnvironment, toml};
+use spin_telemetry::testing::record_metrics;
 use spin_world::v2::redis::{Error, HostConnection};
 
 #[derive(RuntimeFactors)]
@@ -41,3 +42,40 @@ async fn no_outbound_hosts_fails() -> anyhow::Result<()> {
     assert!(matches!(err, Error::InvalidAddress));
     Ok(())
 }
+
+#[tokio::test]
+async fn failed_connections_are_metered() -> anyhow::Result<()> {
+    let (metrics, _guard) = record_metrics();
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        redis: OutboundRedisFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        spin_manifest_version = 2
+        application.name = "test-app"
+        [[trigger.test]]
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["redis://127.0.0.1:*"]
+    });
+    let mut state = env.build_instance_state().await?;
+
+    // Nothing listens on the port once the listener is dropped.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let connection = state.redis.open(format!("redis://127.0.0.1:{port}")).await;
+    assert!(connection.is_err());
+
+    let errors = metrics.named("spin.data.operation_errors");
+    assert_eq!(errors.len(), 1);
+    let attributes = &errors[0].attributes;
+    assert_eq!(attributes["db_system"], "redis");
+    assert_eq!(attributes["component_id"], "test-component");
+    assert_eq!(attributes["store"], format!("127.0.0.1:{port}"));
+    assert_eq!(attributes["operation"], "open");
+    assert!(metrics.named("spin.data.open_connections").is_empty());
+    Ok(())
+}
@@ -1,10 +1,12 @@
 mod host;
+mod metrics;
 pub mod runtime_config;
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use host::InstanceState;
+use metrics::MeteredConnectionCreator;
 
 use async_trait::async_trait;
 use spin_factor_otel::OtelFactorState;
@@ -83,10 +85,24 @@ impl Factor for SqliteFactor {
             .get(ctx.app_component().id())
             .cloned()
             .unwrap_or_default();
+        let component_id: Arc<str> = ctx.app_component().id().into();
+        let connection_creators = ctx
+            .app_state()
+            .connection_creators
+            .iter()
+            .filter(|(label, _)| allowed_databases.contains(*label))
+            .map(|(label, creator)| {
+                let metered = MeteredConnectionCreator::new(creator.clone(), component_id.clone());
+                (
+                    label.clone(),
+                    Arc::new(metered) as Arc<dyn ConnectionCreator>,
+                )
+            })
+            .collect();
         let otel = OtelFactorState::from_prepare_context(&mut ctx)?;
         Ok(InstanceState::new(
             allowed_databases,
-            ctx.app_state().connection_creators.clone(),
+            connection_creators,
             otel,
         ))
     }
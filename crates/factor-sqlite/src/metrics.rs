@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use spin_factors::anyhow;
+use spin_telemetry::metrics::{DataMetrics, OpenConnectionMetric};
+use spin_world::spin::sqlite3_1_0::sqlite as v3;
+
+use crate::{Connection, ConnectionCreator, QueryAsyncResult};
+
+const SYSTEM: &str = "sqlite";
+
+/// A [`ConnectionCreator`] which records metrics for the connections it
+/// creates on behalf of a component.
+pub struct MeteredConnectionCreator {
+    inner: Arc<dyn ConnectionCreator>,
+    component_id: Arc<str>,
+}
+
+impl MeteredConnectionCreator {
+    pub fn new(inner: Arc<dyn ConnectionCreator>, component_id: Arc<str>) -> Self {
+        Self {
+            inner,
+            component_id,
+        }
+    }
+}
+
+#[async_trait]
+impl ConnectionCreator for MeteredConnectionCreator {
+    async fn create_connection(
+        &self,
+        label: &str,
+    ) -> Result<Arc<dyn Connection + 'static>, v3::Error> {
+        let metrics = DataMetrics::new(SYSTEM, &self.component_id, label);
+        let inner = metrics
+            .time("open", error_type, self.inner.create_connection(label))
+            .await?;
+        Ok(Arc::new(MeteredConnection {
+            inner,
+            _open: metrics.connection_opened(),
+            metrics,
+        }))
+    }
+}
+
+struct MeteredConnection {
+    inner: Arc<dyn Connection>,
+    metrics: DataMetrics,
+    _open: OpenConnectionMetric,
+}
+
+#[async_trait]
+impl Connection for MeteredConnection {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<v3::Value>,
+        max_result_bytes: usize,
+    ) -> Result<v3::QueryResult, v3::Error> {
+        self.metrics
+            .record_bytes_written(query.len() + values_size(&parameters));
+        let result = self
+            .metrics
+            .time(
+                "query",
+                error_type,
+                self.inner.query(query, parameters, max_result_bytes),
+            )
+            .await?;
+        self.metrics
+            .record_bytes_read(result.rows.iter().map(|row| values_size(&row.values)).sum());
+        Ok(result)
+    }
+
+    async fn query_async(
+        &self,
+        query: &str,
+        parameters: Vec<v3::Value>,
+        max_result_bytes: usize,
+    ) -> Result<QueryAsyncResult, v3::Error> {
+        // Rows are streamed to the guest, so only the time to the start of
+        // the results is recorded.
+        self.metrics
+            .record_bytes_written(query.len() + values_size(&parameters));
+        self.metrics
+            .time(
+                "query_async",
+                error_type,
+                self.inner.query_async(query, parameters, max_result_bytes),
+            )
+            .await
+    }
+
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        self.metrics.record_bytes_written(statements.len());
+        let started = Instant::now();
+        let result = self.inner.execute_batch(statements).await;
+        self.metrics.record_operation(
+            "execute_batch",
+            started,
+            result.as_ref().err().map(|_| "other"),
+        );
+        result
+    }
+
+    async fn changes(&self) -> Result<u64, v3::Error> {
+        self.inner.changes().await
+    }
+
+    async fn last_insert_rowid(&self) -> Result<i64, v3::Error> {
+        self.inner.last_insert_rowid().await
+    }
+
+    fn summary(&self) -> Option<String> {
+        self.inner.summary()
+    }
+}
+
+fn values_size(values: &[v3::Value]) -> usize {
+    values.iter().map(v3::Value::memory_size).sum()
+}
+
+fn error_type(err: &v3::Error) -> &'static str {
+    match err {
+        v3::Error::NoSuchDatabase => "no_such_database",
+        v3::Error::AccessDenied => "access_denied",
+        v3::Error::InvalidConnection => "invalid_connection",
+        v3::Error::DatabaseFull => "database_full",
+        v3::Error::Io(_) => "io",
+    }
+}
@@ -7,8 +7,10 @@ use spin_factor_sqlite::{QueryAsyncResult, RuntimeConfig, SqliteFactor};
 use spin_factors::{
     RuntimeFactors,
     anyhow::{self, Context as _, bail},
+    wasmtime::component::Resource,
 };
 use spin_factors_test::{TestEnvironment, toml};
+use spin_telemetry::testing::record_metrics;
 use spin_world::{async_trait, spin::sqlite3_1_0::sqlite as v3, v2::sqlite as v2};
 use v2::HostConnection as _;
 
@@ -96,6 +98,68 @@ async fn it_works_when_database_is_configured() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn operations_are_metered() -> anyhow::Result<()> {
+    let (metrics, _guard) = record_metrics();
+    let factors = TestFactors {
+        sqlite: SqliteFactor::new(),
+    };
+    let mut connection_creators = HashMap::new();
+    connection_creators.insert("foo".to_owned(), Arc::new(MockConnectionCreator) as _);
+    let runtime_config = TestFactorsRuntimeConfig {
+        sqlite: Some(RuntimeConfig {
+            connection_creators,
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            sqlite_databases = ["foo"]
+        })
+        .runtime_config(runtime_config)?;
+    let mut state = env.build_instance_state().await?;
+
+    let connection = state.sqlite.open("foo".into()).await?;
+    let rep = connection.rep();
+    let query = "SELECT * FROM test";
+    assert!(
+        state
+            .sqlite
+            .execute(connection, query.into(), vec![])
+            .await
+            .is_err()
+    );
+    v2::HostConnection::drop(&mut state.sqlite, Resource::new_own(rep)).await?;
+
+    let operations = metrics.named("spin.data.operation_duration");
+    let operations = operations
+        .iter()
+        .map(|metric| {
+            assert_eq!(metric.attributes["db_system"], "sqlite");
+            assert_eq!(metric.attributes["component_id"], "test-component");
+            assert_eq!(metric.attributes["store"], "foo");
+            metric.attributes["operation"].as_str()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(operations, ["open", "query"]);
+
+    let errors = metrics.named("spin.data.operation_errors");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].attributes["error_type"], "io");
+
+    assert_eq!(
+        metrics.named("spin.data.bytes_written")[0].value,
+        query.len().to_string()
+    );
+
+    // The connection is counted while it is open.
+    let open = metrics.named("spin.data.open_connections");
+    let open = open.iter().map(|m| m.value.as_str()).collect::<Vec<_>>();
+    assert_eq!(open, ["1", "-1"]);
+    Ok(())
+}
+
 /// A connection creator that returns a mock connection.
 struct MockConnectionCreator;
 
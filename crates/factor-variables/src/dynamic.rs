@@ -0,0 +1,57 @@
+use std::sync::{Arc, RwLock};
+
+use spin_expressions::{PreparedResolver, ProviderResolver};
+use spin_factors::anyhow;
+use tracing::{Level, instrument};
+
+use crate::runtime_config::RefreshConfig;
+
+/// Variable values which are resolved up front and periodically re-resolved.
+///
+/// Each instance uses the values current when it first reads a variable, so
+/// new instances see rotated values without restarting the app.
+pub struct DynamicVariables {
+    resolver: Arc<ProviderResolver>,
+    config: RefreshConfig,
+    current: RwLock<Option<Arc<PreparedResolver>>>,
+}
+
+impl DynamicVariables {
+    pub(crate) fn new(resolver: Arc<ProviderResolver>, config: RefreshConfig) -> Self {
+        Self {
+            resolver,
+            config,
+            current: Default::default(),
+        }
+    }
+
+    /// Returns when variables should be re-resolved.
+    pub fn config(&self) -> &RefreshConfig {
+        &self.config
+    }
+
+    /// Returns the current variable values, if they have been resolved.
+    pub fn current(&self) -> Option<Arc<PreparedResolver>> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Re-resolves all variables, returning the names of variables whose
+    /// values changed.
+    ///
+    /// If resolution fails, the previous values remain current.
+    #[instrument(name = "spin_variables.refresh", level = Level::INFO, skip(self), err(level = Level::WARN), fields(spin_variables.changed_keys))]
+    pub async fn refresh(&self) -> anyhow::Result<Vec<String>> {
+        let prepared = Arc::new(self.resolver.prepare().await?);
+        let previous = self.current.write().unwrap().replace(prepared.clone());
+        let changed = previous
+            .map(|previous| previous.changed_variables(&prepared))
+            .unwrap_or_default();
+        if !changed.is_empty() {
+            // Only names are recorded: values may be secrets.
+            let keys = changed.join(",");
+            tracing::Span::current().record("spin_variables.changed_keys", keys.as_str());
+            tracing::info!("Variables changed: {keys}");
+        }
+        Ok(changed)
+    }
+}
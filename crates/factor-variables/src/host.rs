@@ -1,4 +1,7 @@
+use std::{collections::HashMap, sync::Mutex};
+
 use spin_core::wasmtime::component::Accessor;
+use spin_expressions::{Key, PreparedResolver, ProviderResolver};
 use spin_factors::anyhow;
 use spin_telemetry::traces::{self, Blame};
 use spin_world::{
@@ -6,23 +9,35 @@ use spin_world::{
 };
 use tracing::instrument;
 
-use crate::{InstanceState, VariablesFactorData};
+use crate::{InstanceState, VariablesFactorData, secrets::ComponentSecrets};
 
 impl<T: Send> v3::HostWithStore<T> for VariablesFactorData {
     #[instrument(name = "spin_variables.get", skip(accessor), fields(otel.kind = "client"))]
     async fn get(accessor: &Accessor<T, Self>, key: String) -> Result<String, v3::Error> {
-        let (resolver, component_id) = accessor.with(|mut access| {
+        let (resolver, component_id, snapshot, secrets, resolved) = accessor.with(|mut access| {
             let host = access.get();
             host.otel.reparent_tracing_span();
-            (host.expression_resolver.clone(), host.component_id.clone())
+            (
+                host.expression_resolver.clone(),
+                host.component_id.clone(),
+                host.snapshot(),
+                host.secrets.clone(),
+                host.resolved.clone(),
+            )
         });
 
-        let key = spin_expressions::Key::new(&key).map_err(expressions_to_variables_err_v3)?;
+        let key = Key::new(&key).map_err(expressions_to_variables_err_v3)?;
 
-        resolver
-            .resolve(&component_id, key)
-            .await
-            .map_err(expressions_to_variables_err_v3)
+        resolve_consistently(
+            &resolver,
+            &component_id,
+            snapshot.as_deref(),
+            &secrets,
+            &resolved,
+            key,
+        )
+        .await
+        .map_err(expressions_to_variables_err_v3)
     }
 }
 
@@ -36,11 +51,17 @@ impl v2::Host for InstanceState {
     #[instrument(name = "spin_variables.get", skip(self), fields(otel.kind = "client"))]
     async fn get(&mut self, key: String) -> Result<String, v2::Error> {
         self.otel.reparent_tracing_span();
-        let key = spin_expressions::Key::new(&key).map_err(expressions_to_variables_err)?;
-        self.expression_resolver
-            .resolve(&self.component_id, key)
-            .await
-            .map_err(expressions_to_variables_err)
+        let key = Key::new(&key).map_err(expressions_to_variables_err)?;
+        resolve_consistently(
+            &self.expression_resolver,
+            &self.component_id,
+            self.snapshot().as_deref(),
+            &self.secrets,
+            &self.resolved,
+            key,
+        )
+        .await
+        .map_err(expressions_to_variables_err)
     }
 
     fn convert_error(&mut self, error: v2::Error) -> anyhow::Result<v2::Error> {
@@ -79,10 +100,27 @@ impl wasi_config::store::Host for InstanceState {
 
     #[instrument(name = "wasi_config.get_all", skip(self), fields(otel.kind = "client"))]
     async fn get_all(&mut self) -> Result<Vec<(String, String)>, wasi_config::store::Error> {
-        let all = self
-            .expression_resolver
-            .resolve_all(&self.component_id)
-            .await;
+        let all = match self.snapshot() {
+            Some(snapshot) => self
+                .expression_resolver
+                .resolve_all_with(&self.component_id, snapshot.as_ref()),
+            None => {
+                self.expression_resolver
+                    .resolve_all(&self.component_id)
+                    .await
+            }
+        };
+        let all = all.map(|all| {
+            // Prefer values this instance has already seen.
+            let mut resolved = self.resolved.lock().unwrap();
+            all.into_iter()
+                .map(|(key, value)| {
+                    self.secrets.register(&key, &value);
+                    let value = resolved.entry(key.clone()).or_insert(value).clone();
+                    (key, value)
+                })
+                .collect::<Vec<_>>()
+        });
         all.map_err(|e| {
             match expressions_to_variables_err(e) {
                 v2::Error::Undefined(msg) => wasi_config::store::Error::Io(msg), // this shouldn't happen but just in case
@@ -101,6 +139,37 @@ impl wasi_config::store::Host for InstanceState {
     }
 }
 
+/// Resolves a variable, returning the same value as any earlier lookup by
+/// this instance.
+///
+/// If the instance has a snapshot of dynamic variables, the variable is
+/// resolved from that rather than from the providers. Values of secret
+/// variables are registered for redaction.
+async fn resolve_consistently(
+    resolver: &ProviderResolver,
+    component_id: &str,
+    snapshot: Option<&PreparedResolver>,
+    secrets: &ComponentSecrets,
+    resolved: &Mutex<HashMap<String, String>>,
+    key: Key<'_>,
+) -> spin_expressions::Result<String> {
+    if let Some(value) = resolved.lock().unwrap().get(key.as_str()) {
+        return Ok(value.clone());
+    }
+    let name = key.as_str().to_owned();
+    let value = match snapshot {
+        Some(snapshot) => resolver.resolve_with(component_id, key, snapshot)?,
+        None => resolver.resolve(component_id, key).await?,
+    };
+    secrets.register(&name, &value);
+    Ok(resolved
+        .lock()
+        .unwrap()
+        .entry(name)
+        .or_insert(value)
+        .clone())
+}
+
 /// Convert a `spin_expressions::Error` to a `v2::Error`, setting the current span's status and fault attribute.
 fn expressions_to_variables_err(err: spin_expressions::Error) -> v2::Error {
     use spin_expressions::Error;
@@ -1,17 +1,27 @@
+mod dynamic;
 mod host;
 pub mod runtime_config;
+mod secrets;
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
 
+pub use dynamic::DynamicVariables;
 use runtime_config::RuntimeConfig;
-use spin_expressions::{ProviderResolver as ExpressionResolver, Template};
+use secrets::{ComponentSecrets, SecretVariables};
+use spin_expressions::{PreparedResolver, ProviderResolver as ExpressionResolver, Template};
 use spin_factor_otel::OtelFactorState;
 use spin_factors::{
     ConfigureAppContext, Factor, FactorData, InitContext, PrepareContext, RuntimeFactors,
     SelfInstanceBuilder, anyhow,
 };
+use spin_locked_app::MetadataKey;
 use spin_world::spin::variables::variables as v3;
 
+const ALLOWED_HOSTS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("allowed_outbound_hosts");
+
 /// A factor for providing variables to components.
 #[derive(Default)]
 pub struct VariablesFactor {
@@ -51,15 +61,29 @@ impl Factor for VariablesFactor {
                 component.id(),
                 component.config().map(|(k, v)| (k.into(), v.into())),
             )?;
+            // Outbound hosts are resolved with the prepared variables, so a
+            // variable they reference must have a value.
+            let allowed_hosts = component
+                .get_metadata(ALLOWED_HOSTS_KEY)?
+                .unwrap_or_default();
+            expression_resolver.add_templates(allowed_hosts)?;
         }
 
-        let providers = ctx.take_runtime_config().unwrap_or_default();
+        // Secret values are registered for redaction from logs and telemetry
+        // as they are resolved.
+        let secrets = SecretVariables::new(app);
+        let RuntimeConfig { providers, refresh } = ctx.take_runtime_config().unwrap_or_default();
         for provider in providers {
-            expression_resolver.add_provider(provider);
+            expression_resolver.add_provider(secrets.wrap_provider(provider));
         }
 
+        let expression_resolver = Arc::new(expression_resolver);
+        let dynamic_variables = refresh
+            .map(|config| Arc::new(DynamicVariables::new(expression_resolver.clone(), config)));
         Ok(AppState {
-            expression_resolver: Arc::new(expression_resolver),
+            expression_resolver,
+            dynamic_variables,
+            secrets,
         })
     }
 
@@ -69,10 +93,16 @@ impl Factor for VariablesFactor {
     ) -> anyhow::Result<InstanceState> {
         let component_id = ctx.app_component().id().to_string();
         let expression_resolver = ctx.app_state().expression_resolver.clone();
+        let dynamic_variables = ctx.app_state().dynamic_variables.clone();
+        let secrets = ctx.app_state().secrets.component(&component_id);
         let otel = OtelFactorState::from_prepare_context(&mut ctx)?;
         Ok(InstanceState {
             component_id,
             expression_resolver,
+            dynamic_variables,
+            snapshot: Default::default(),
+            secrets,
+            resolved: Default::default(),
             otel,
         })
     }
@@ -80,6 +110,8 @@ impl Factor for VariablesFactor {
 
 pub struct AppState {
     expression_resolver: Arc<ExpressionResolver>,
+    dynamic_variables: Option<Arc<DynamicVariables>>,
+    secrets: SecretVariables,
 }
 
 impl AppState {
@@ -94,11 +126,29 @@ impl AppState {
     pub fn expression_resolver(&self) -> &Arc<ExpressionResolver> {
         &self.expression_resolver
     }
+
+    /// Returns the dynamic variables for the app, if variable refresh is
+    /// enabled in the runtime config.
+    pub fn dynamic_variables(&self) -> Option<&Arc<DynamicVariables>> {
+        self.dynamic_variables.as_ref()
+    }
 }
 
 pub struct InstanceState {
     component_id: String,
     expression_resolver: Arc<ExpressionResolver>,
+    dynamic_variables: Option<Arc<DynamicVariables>>,
+    /// If variable refresh is enabled, the variable values current when this
+    /// instance first read a variable. This isn't taken when the instance is
+    /// prepared, as a pooled instance may be prepared long before it is used.
+    snapshot: OnceLock<Option<Arc<PreparedResolver>>>,
+    /// Component variables derived from secrets, whose values are registered
+    /// for redaction when resolved.
+    secrets: ComponentSecrets,
+    /// Values already returned to this instance. Each variable is resolved at
+    /// most once per instance, so the guest sees consistent values for the
+    /// whole request even if a provider's cache refreshes in the meantime.
+    resolved: Arc<Mutex<HashMap<String, String>>>,
     otel: OtelFactorState,
 }
 
@@ -106,6 +156,18 @@ impl InstanceState {
     pub fn expression_resolver(&self) -> &Arc<ExpressionResolver> {
         &self.expression_resolver
     }
+
+    /// Returns the snapshot of dynamic variables this instance resolves
+    /// from, taking it on first use.
+    fn snapshot(&self) -> Option<Arc<PreparedResolver>> {
+        self.snapshot
+            .get_or_init(|| {
+                self.dynamic_variables
+                    .as_ref()
+                    .and_then(|dynamic| dynamic.current())
+            })
+            .clone()
+    }
 }
 
 impl SelfInstanceBuilder for InstanceState {}
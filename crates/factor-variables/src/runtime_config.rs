@@ -1,9 +1,14 @@
+use std::time::Duration;
+
 use spin_expressions::Provider;
 
 /// The runtime configuration for the variables factor.
 #[derive(Default)]
 pub struct RuntimeConfig {
     pub providers: Vec<Box<dyn Provider>>,
+    /// If set, variables are resolved up front and re-resolved as configured,
+    /// rather than being resolved on every lookup.
+    pub refresh: Option<RefreshConfig>,
 }
 
 impl IntoIterator for RuntimeConfig {
@@ -14,3 +19,12 @@ impl IntoIterator for RuntimeConfig {
         self.providers.into_iter()
     }
 }
+
+/// When dynamic variables are re-resolved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RefreshConfig {
+    /// Re-resolve variables on this schedule.
+    pub interval: Option<Duration>,
+    /// Re-resolve variables when the process receives `SIGUSR1`.
+    pub on_signal: bool,
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use spin_expressions::{Key, Provider, Template};
+use spin_factors::{App, anyhow};
+use spin_telemetry::redaction::SecretScope;
+
+/// The variables in an app whose values must be redacted from logs and
+/// telemetry.
+#[derive(Default)]
+pub(crate) struct SecretVariables {
+    /// The app's registered secret values, which stop being redacted once the
+    /// app and all of its instances are dropped.
+    scope: Arc<SecretScope>,
+    /// Application variables marked `secret`.
+    app: Arc<HashSet<String>>,
+    /// Component variables whose templates reference a secret, by component.
+    components: HashMap<String, Arc<HashSet<String>>>,
+}
+
+impl SecretVariables {
+    /// Finds the secret variables in the app, registering any secret defaults
+    /// for redaction.
+    pub fn new(app: &App) -> Self {
+        let scope = Arc::new(SecretScope::new());
+        let app_secrets = app
+            .variables()
+            .filter(|(_, variable)| variable.secret)
+            .map(|(name, variable)| {
+                if let Some(default) = &variable.default {
+                    scope.register(&format!("{name}#default"), default);
+                }
+                name.clone()
+            })
+            .collect::<HashSet<_>>();
+        let components = app
+            .components()
+            .map(|component| {
+                let keys = component
+                    .config()
+                    .filter(|(_, value)| {
+                        Template::new(value.as_str()).is_ok_and(|template| {
+                            template.variables().any(|var| app_secrets.contains(var))
+                        })
+                    })
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                (component.id().to_owned(), Arc::new(keys))
+            })
+            .collect();
+        Self {
+            scope,
+            app: Arc::new(app_secrets),
+            components,
+        }
+    }
+
+    /// Wraps the provider so that the values it returns for secret variables
+    /// are registered for redaction.
+    pub fn wrap_provider(&self, provider: Box<dyn Provider>) -> Box<dyn Provider> {
+        if self.app.is_empty() {
+            return provider;
+        }
+        Box::new(SecretRegisteringProvider {
+            inner: provider,
+            secrets: self.app.clone(),
+            scope: self.scope.clone(),
+        })
+    }
+
+    /// Returns the component's variables whose values must be redacted.
+    pub fn component(&self, component_id: &str) -> ComponentSecrets {
+        ComponentSecrets {
+            component_id: component_id.to_owned(),
+            keys: self
+                .components
+                .get(component_id)
+                .cloned()
+                .unwrap_or_default(),
+            scope: self.scope.clone(),
+        }
+    }
+}
+
+/// The variables of one component whose values must be redacted.
+#[derive(Clone)]
+pub(crate) struct ComponentSecrets {
+    component_id: String,
+    /// Component variables whose templates reference a secret.
+    keys: Arc<HashSet<String>>,
+    scope: Arc<SecretScope>,
+}
+
+impl ComponentSecrets {
+    /// Registers the resolved value of the variable for redaction if it is
+    /// secret, replacing any value previously resolved for it.
+    pub fn register(&self, key: &str, value: &str) {
+        if self.keys.contains(key) {
+            self.scope
+                .register(&format!("{}.{key}", self.component_id), value);
+        }
+    }
+}
+
+/// A provider which registers the values of secret variables for redaction.
+struct SecretRegisteringProvider {
+    inner: Box<dyn Provider>,
+    secrets: Arc<HashSet<String>>,
+    scope: Arc<SecretScope>,
+}
+
+#[spin_world::async_trait]
+impl Provider for SecretRegisteringProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let value = self.inner.get(key).await?;
+        if let Some(value) = &value
+            && self.secrets.contains(key.as_str())
+        {
+            self.scope.register(key.as_str(), value);
+        }
+        Ok(value)
+    }
+
+    fn may_resolve(&self, key: &Key) -> bool {
+        self.inner.may_resolve(key)
+    }
+}
+
+impl std::fmt::Debug for SecretRegisteringProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
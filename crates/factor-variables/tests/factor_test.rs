@@ -1,7 +1,13 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use spin_expressions::{Key, Provider};
-use spin_factor_variables::{VariablesFactor, runtime_config::RuntimeConfig};
-use spin_factors::{RuntimeFactors, anyhow};
+use spin_factor_variables::{
+    VariablesFactor,
+    runtime_config::{RefreshConfig, RuntimeConfig},
+};
+use spin_factors::{App, RuntimeFactors, anyhow};
 use spin_factors_test::{TestEnvironment, toml};
+use spin_telemetry::redaction::redact;
 use spin_world::v2::variables::Host;
 
 #[derive(RuntimeFactors)]
@@ -16,7 +22,10 @@ async fn provider_works() -> anyhow::Result<()> {
     };
     let providers = vec![Box::new(MockProvider) as _];
     let runtime_config = TestFactorsRuntimeConfig {
-        variables: Some(RuntimeConfig { providers }),
+        variables: Some(RuntimeConfig {
+            providers,
+            refresh: None,
+        }),
     };
     let env = TestEnvironment::new(factors)
         .extend_manifest(toml! {
@@ -35,9 +44,178 @@ async fn provider_works() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn values_are_consistent_within_an_instance() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+    };
+    let providers = vec![Box::new(CountingProvider::default()) as _];
+    let runtime_config = TestFactorsRuntimeConfig {
+        variables: Some(RuntimeConfig {
+            providers,
+            refresh: None,
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [variables]
+            foo = { required = true }
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            variables = { baz = "{{ foo }}" }
+        })
+        .runtime_config(runtime_config)?;
+
+    let mut state = env.build_instance_state().await?;
+    let first = state.variables.get("baz".into()).await?;
+    assert_eq!(state.variables.get("baz".into()).await?, first);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dynamic_variables_are_refreshed_for_new_instances() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+    };
+    let providers = vec![Box::new(CountingProvider::default()) as _];
+    let runtime_config = TestFactorsRuntimeConfig {
+        variables: Some(RuntimeConfig {
+            providers,
+            refresh: Some(RefreshConfig {
+                on_signal: true,
+                ..Default::default()
+            }),
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [variables]
+            foo = { required = true }
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            variables = { baz = "{{ foo }}" }
+        })
+        .runtime_config(runtime_config)?;
+
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    let dynamic = configured_app
+        .app_state::<VariablesFactor>()?
+        .dynamic_variables()
+        .expect("refresh should be enabled")
+        .clone();
+    assert!(dynamic.refresh().await?.is_empty());
+
+    let get = async || -> anyhow::Result<String> {
+        let builders = env.factors.prepare(&configured_app, "test-component")?;
+        let mut state = env.factors.build_instance_state(builders)?;
+        Ok(state.variables.get("baz".into()).await?)
+    };
+    // Instances use the resolved values rather than querying the provider.
+    assert_eq!(get().await?, "0");
+    assert_eq!(get().await?, "0");
+
+    assert_eq!(dynamic.refresh().await?, ["foo"]);
+    assert_eq!(get().await?, "1");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pooled_instances_see_variables_refreshed_after_they_were_prepared() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+    };
+    let providers = vec![Box::new(CountingProvider::default()) as _];
+    let runtime_config = TestFactorsRuntimeConfig {
+        variables: Some(RuntimeConfig {
+            providers,
+            refresh: Some(RefreshConfig {
+                on_signal: true,
+                ..Default::default()
+            }),
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [variables]
+            foo = { required = true }
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            variables = { baz = "{{ foo }}" }
+        })
+        .runtime_config(runtime_config)?;
+
+    let app = App::new("test-app", env.build_locked_app().await?);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    let dynamic = configured_app
+        .app_state::<VariablesFactor>()?
+        .dynamic_variables()
+        .expect("refresh should be enabled")
+        .clone();
+    assert!(dynamic.refresh().await?.is_empty());
+
+    // As a warm pool does, prepare the instance before it is needed.
+    let builders = env.factors.prepare(&configured_app, "test-component")?;
+    let mut pooled = env.factors.build_instance_state(builders)?;
+
+    assert_eq!(dynamic.refresh().await?, ["foo"]);
+    assert_eq!(pooled.variables.get("baz".into()).await?, "1");
+
+    // Once the instance has read a variable, later refreshes don't change it.
+    assert_eq!(dynamic.refresh().await?, ["foo"]);
+    assert_eq!(pooled.variables.get("baz".into()).await?, "1");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn secret_values_are_redacted() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+    };
+    let providers = vec![Box::new(MockProvider) as _];
+    let runtime_config = TestFactorsRuntimeConfig {
+        variables: Some(RuntimeConfig {
+            providers,
+            refresh: None,
+        }),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [variables]
+            foo = { required = true, secret = true }
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            variables = { baz = "{{ foo | base64_encode }}" }
+        })
+        .runtime_config(runtime_config)?;
+
+    let mut state = env.build_instance_state().await?;
+    let val = state.variables.get("baz".into()).await?;
+    assert_eq!(
+        redact(&format!("foo=bar baz={val}")),
+        "foo=bar baz=[REDACTED]"
+    );
+    Ok(())
+}
+
 #[derive(Debug)]
 struct MockProvider;
 
+/// A provider which returns a different value on every lookup.
+#[derive(Debug, Default)]
+struct CountingProvider(AtomicUsize);
+
+#[spin_world::async_trait]
+impl Provider for CountingProvider {
+    async fn get(&self, _key: &Key) -> anyhow::Result<Option<String>> {
+        Ok(Some(self.0.fetch_add(1, Ordering::SeqCst).to_string()))
+    }
+}
+
 #[spin_world::async_trait]
 impl Provider for MockProvider {
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
@@ -4,9 +4,21 @@ use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 
 use async_trait::async_trait;
+use futures::{
+    StreamExt as _,
+    channel::{mpsc, oneshot},
+};
+use spin_connection_semaphore::{EgressQuota, EgressQuotaExceeded};
 use tokio::io::{AsyncRead, AsyncWrite};
+use wasmtime::component::{
+    Destination, Source, StreamConsumer, StreamProducer, StreamReader,
+    StreamResult as P3StreamResult, VecBuffer,
+};
+use wasmtime::{AsContextMut, StoreContextMut};
 use wasmtime_wasi::cli::{IsTerminal, StdinStream, StdoutStream};
-use wasmtime_wasi::p2::{InputStream, OutputStream, Pollable, StreamError};
+use wasmtime_wasi::p2::{
+    DynInputStream, DynOutputStream, InputStream, OutputStream, Pollable, StreamError, StreamResult,
+};
 
 /// A [`OutputStream`] that writes to a `Write` type.
 ///
@@ -171,3 +183,206 @@ impl<T: Read + Send + Sync + 'static> StdinStream for PipeReadStream<T> {
         Box::new(self.clone())
     }
 }
+
+/// The telemetry label for socket traffic recorded against an [`EgressQuota`].
+pub(crate) const SOCKETS_EGRESS_KIND: &str = "wasi-sockets";
+
+/// An [`InputStream`] that records the bytes read from the wrapped stream
+/// against an [`EgressQuota`].
+///
+/// Once the quota is exhausted, reads fail and the data read from the inner
+/// stream is discarded.
+pub(crate) struct MeteredInputStream {
+    inner: DynInputStream,
+    quota: EgressQuota,
+}
+
+impl MeteredInputStream {
+    pub fn new(inner: DynInputStream, quota: EgressQuota) -> Self {
+        Self { inner, quota }
+    }
+}
+
+#[async_trait]
+impl InputStream for MeteredInputStream {
+    fn read(&mut self, size: usize) -> StreamResult<bytes::Bytes> {
+        let bytes = self.inner.read(size)?;
+        self.quota
+            .record_received(SOCKETS_EGRESS_KIND, bytes.len() as u64)
+            .map_err(|e| StreamError::LastOperationFailed(e.into()))?;
+        Ok(bytes)
+    }
+
+    async fn cancel(&mut self) {
+        self.inner.cancel().await
+    }
+}
+
+#[async_trait]
+impl Pollable for MeteredInputStream {
+    async fn ready(&mut self) {
+        self.inner.ready().await
+    }
+}
+
+/// An [`OutputStream`] that records the bytes written to the wrapped stream
+/// against an [`EgressQuota`].
+///
+/// Writes that would exceed the quota fail without being passed on.
+pub(crate) struct MeteredOutputStream {
+    inner: DynOutputStream,
+    quota: EgressQuota,
+}
+
+impl MeteredOutputStream {
+    pub fn new(inner: DynOutputStream, quota: EgressQuota) -> Self {
+        Self { inner, quota }
+    }
+}
+
+#[async_trait]
+impl OutputStream for MeteredOutputStream {
+    fn write(&mut self, bytes: bytes::Bytes) -> StreamResult<()> {
+        self.quota
+            .record_sent(SOCKETS_EGRESS_KIND, bytes.len() as u64)
+            .map_err(|e| StreamError::LastOperationFailed(e.into()))?;
+        self.inner.write(bytes)
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        self.inner.flush()
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        self.inner.check_write()
+    }
+
+    async fn cancel(&mut self) {
+        self.inner.cancel().await
+    }
+}
+
+#[async_trait]
+impl Pollable for MeteredOutputStream {
+    async fn ready(&mut self) {
+        self.inner.ready().await
+    }
+}
+
+/// The direction of the traffic on a metered WASI 0.3 stream.
+#[derive(Clone, Copy)]
+pub(crate) enum EgressDirection {
+    Sent,
+    Received,
+}
+
+/// The most bytes read from a metered WASI 0.3 stream at a time.
+const METERED_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Returns a stream which passes on the bytes from `reader`, recording them
+/// against an [`EgressQuota`].
+///
+/// Once the quota is exhausted, the stream is closed, the data read from
+/// `reader` is discarded and the quota error is sent on the returned channel,
+/// so that it can be reported to the guest in the operation's result.
+pub(crate) fn meter_stream(
+    mut store: impl AsContextMut,
+    reader: StreamReader<u8>,
+    quota: EgressQuota,
+    direction: EgressDirection,
+) -> wasmtime::Result<(StreamReader<u8>, oneshot::Receiver<EgressQuotaExceeded>)> {
+    let (tx, rx) = mpsc::channel(1);
+    let (exceeded_tx, exceeded_rx) = oneshot::channel();
+    let metered = StreamReader::new(store.as_context_mut(), MeteredProducer { rx })?;
+    reader.pipe(
+        store,
+        MeteredConsumer {
+            tx,
+            quota,
+            direction,
+            exceeded: Some(exceeded_tx),
+        },
+    )?;
+    Ok((metered, exceeded_rx))
+}
+
+/// Reads chunks of the unmetered stream, recording them against the quota
+/// before passing them on to the [`MeteredProducer`].
+struct MeteredConsumer {
+    tx: mpsc::Sender<Vec<u8>>,
+    quota: EgressQuota,
+    direction: EgressDirection,
+    exceeded: Option<oneshot::Sender<EgressQuotaExceeded>>,
+}
+
+impl MeteredConsumer {
+    fn record(&self, bytes: u64) -> Result<(), EgressQuotaExceeded> {
+        match self.direction {
+            EgressDirection::Sent => self.quota.record_sent(SOCKETS_EGRESS_KIND, bytes),
+            EgressDirection::Received => self.quota.record_received(SOCKETS_EGRESS_KIND, bytes),
+        }
+    }
+}
+
+impl<D> StreamConsumer<D> for MeteredConsumer {
+    type Item = u8;
+
+    fn poll_consume(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut store: StoreContextMut<D>,
+        mut source: Source<'_, Self::Item>,
+        finish: bool,
+    ) -> Poll<wasmtime::Result<P3StreamResult>> {
+        match self.tx.poll_ready(cx) {
+            Poll::Pending if finish => return Poll::Ready(Ok(P3StreamResult::Cancelled)),
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(_)) => return Poll::Ready(Ok(P3StreamResult::Dropped)),
+            Poll::Ready(Ok(())) => {}
+        }
+        let len = source.remaining(&mut store).min(METERED_CHUNK_SIZE);
+        let mut bytes = Vec::with_capacity(len);
+        source.read(&mut store, &mut bytes)?;
+        if bytes.is_empty() {
+            return Poll::Ready(Ok(P3StreamResult::Completed));
+        }
+        if let Err(err) = self.record(bytes.len() as u64) {
+            if let Some(exceeded) = self.exceeded.take() {
+                _ = exceeded.send(err);
+            }
+            return Poll::Ready(Ok(P3StreamResult::Dropped));
+        }
+        if self.tx.start_send(bytes).is_err() {
+            return Poll::Ready(Ok(P3StreamResult::Dropped));
+        }
+        Poll::Ready(Ok(P3StreamResult::Completed))
+    }
+}
+
+/// Writes the chunks passed on by a [`MeteredConsumer`] to the metered stream.
+struct MeteredProducer {
+    rx: mpsc::Receiver<Vec<u8>>,
+}
+
+impl<D> StreamProducer<D> for MeteredProducer {
+    type Item = u8;
+    type Buffer = VecBuffer<u8>;
+
+    fn poll_produce<'a>(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        _store: StoreContextMut<'a, D>,
+        mut destination: Destination<'a, Self::Item, Self::Buffer>,
+        finish: bool,
+    ) -> Poll<wasmtime::Result<P3StreamResult>> {
+        match self.rx.poll_next_unpin(cx) {
+            Poll::Pending if finish => Poll::Ready(Ok(P3StreamResult::Cancelled)),
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Some(bytes)) => {
+                destination.set_buffer(bytes.into());
+                Poll::Ready(Ok(P3StreamResult::Completed))
+            }
+            Poll::Ready(None) => Poll::Ready(Ok(P3StreamResult::Dropped)),
+        }
+    }
+}
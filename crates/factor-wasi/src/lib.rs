@@ -14,6 +14,7 @@ use std::{
 };
 
 use io::{PipeReadStream, PipedWriteStream};
+use spin_connection_semaphore::EgressQuota;
 use spin_factors::{
     AppComponent, Factor, FactorInstanceBuilder, InitContext, PrepareContext, RuntimeFactors,
     RuntimeFactorsInstanceState, anyhow,
@@ -73,6 +74,7 @@ impl WasiFactor {
                 table,
             },
             permit_state: state.socket_permit_state.clone(),
+            egress_quota: state.egress_quota.clone(),
             getter,
         })
     }
@@ -184,6 +186,7 @@ trait InitContextExt: InitContext<WasiFactor> {
                 table,
             },
             permit_state: state.socket_permit_state.clone(),
+            egress_quota: state.egress_quota.clone(),
             getter: Self::get_wasi_sockets,
         }
     }
@@ -402,6 +405,7 @@ impl Factor for WasiFactor {
         let mut builder = InstanceBuilder {
             ctx: wasi_ctx,
             socket_permit_state: None,
+            egress_quota: None,
         };
 
         // Apply environment variables
@@ -460,6 +464,7 @@ impl MountFilesContext<'_> {
 pub struct InstanceBuilder {
     ctx: WasiCtxBuilder,
     socket_permit_state: Option<Arc<SocketPermitState>>,
+    egress_quota: Option<EgressQuota>,
 }
 
 impl InstanceBuilder {
@@ -533,10 +538,12 @@ impl FactorInstanceBuilder for InstanceBuilder {
         let InstanceBuilder {
             ctx: mut wasi_ctx,
             socket_permit_state,
+            egress_quota,
         } = self;
         Ok(InstanceState {
             ctx: wasi_ctx.build(),
             socket_permit_state,
+            egress_quota,
         })
     }
 }
@@ -547,6 +554,11 @@ impl InstanceBuilder {
         self.socket_permit_state = Some(state);
     }
 
+    /// Sets the egress quota against which socket connections and traffic are recorded.
+    pub fn set_egress_quota(&mut self, quota: EgressQuota) {
+        self.egress_quota = Some(quota);
+    }
+
     pub fn outbound_socket_addr_check<F, Fut>(&mut self, check: F)
     where
         F: Fn(SocketAddr, SocketAddrUse) -> Fut + Send + Sync + Clone + 'static,
@@ -570,6 +582,7 @@ impl InstanceBuilder {
 pub struct InstanceState {
     ctx: WasiCtx,
     socket_permit_state: Option<Arc<SocketPermitState>>,
+    egress_quota: Option<EgressQuota>,
 }
 
 impl InstanceState {
@@ -4,6 +4,12 @@
 //! [`SpinSockets`] — the types needed to intercept WASI TCP/UDP socket
 //! creation and enforce a per-app cap on the number of concurrently open
 //! sockets.
+//!
+//! The same interception records socket connections and traffic against the
+//! component's [`EgressQuota`], if one is configured. TCP data is metered by
+//! wrapping the connection's streams: the input and output stream resources
+//! for WASI 0.2, and the stream readers passed to and returned from `send` and
+//! `receive` for WASI 0.3.
 
 use std::{
     collections::HashMap,
@@ -11,7 +17,9 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use spin_connection_semaphore::{ConnectionPermit, ConnectionSemaphore};
+use spin_connection_semaphore::{
+    ConnectionPermit, ConnectionSemaphore, EgressQuota, EgressQuotaExceeded,
+};
 use wasmtime::component::{HasData, Resource};
 use wasmtime_wasi::p2::bindings::sockets::network::{
     ErrorCode as SocketErrorCode, Host as NetworkHost, Network,
@@ -20,9 +28,28 @@ use wasmtime_wasi::p2::bindings::sockets::tcp::{self as p2_tcp, IpSocketAddress,
 use wasmtime_wasi::p2::bindings::sockets::tcp_create_socket as p2_tcp_create;
 use wasmtime_wasi::p2::bindings::sockets::udp as p2_udp;
 use wasmtime_wasi::p2::bindings::sockets::udp_create_socket as p2_udp_create;
+use wasmtime_wasi::p2::pipe::{ClosedInputStream, ClosedOutputStream};
 use wasmtime_wasi::p2::{DynInputStream, DynOutputStream, DynPollable};
 use wasmtime_wasi::sockets::{TcpSocket, UdpSocket, WasiSockets, WasiSocketsCtxView};
 
+use crate::io::{
+    EgressDirection, MeteredInputStream, MeteredOutputStream, SOCKETS_EGRESS_KIND, meter_stream,
+};
+
+/// Converts an egress quota error into the given WASI 0.2 socket error.
+///
+/// WASI 0.2 socket errors can't carry a message, so it is logged instead, to
+/// tell the refusal apart from the host's own limits and permission errors.
+/// This is at debug level as a guest may retry in a loop; the quota itself
+/// warns once per window.
+fn p2_quota_exceeded(
+    err: EgressQuotaExceeded,
+    code: SocketErrorCode,
+) -> wasmtime_wasi::p2::SocketError {
+    tracing::debug!("Socket operation refused: {err}");
+    code.into()
+}
+
 /// Shared state for tracking per-socket semaphore permits. Permits are
 /// acquired when a socket is allocated (at `start_connect` for TCP, at
 /// `create_udp_socket` for UDP) and released when the socket resource is dropped.
@@ -46,6 +73,7 @@ impl SocketPermitState {
 pub struct SpinSocketsView<'a, T> {
     pub(crate) inner: WasiSocketsCtxView<'a>,
     pub(crate) permit_state: Option<Arc<SocketPermitState>>,
+    pub(crate) egress_quota: Option<EgressQuota>,
     pub(crate) getter: fn(&mut T) -> WasiSocketsCtxView<'_>,
 }
 
@@ -116,6 +144,48 @@ impl<T> SpinSocketsView<'_, T> {
                 .remove(&socket_rep);
         }
     }
+
+    /// Records a new outbound connection against the egress quota, if any.
+    pub(crate) fn start_egress_request(&self) -> Result<(), EgressQuotaExceeded> {
+        match &self.egress_quota {
+            Some(quota) => quota.start_request(SOCKETS_EGRESS_KIND),
+            None => Ok(()),
+        }
+    }
+
+    /// Records `bytes` sent against the egress quota, if any.
+    pub(crate) fn record_egress_sent(&self, bytes: u64) -> Result<(), EgressQuotaExceeded> {
+        match &self.egress_quota {
+            Some(quota) => quota.record_sent(SOCKETS_EGRESS_KIND, bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Records `bytes` received against the egress quota, if any.
+    pub(crate) fn record_egress_received(&self, bytes: u64) -> Result<(), EgressQuotaExceeded> {
+        match &self.egress_quota {
+            Some(quota) => quota.record_received(SOCKETS_EGRESS_KIND, bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the given connection streams in the resource table with
+    /// wrappers that record their traffic against `quota`.
+    fn meter_streams(
+        &mut self,
+        input: &Resource<DynInputStream>,
+        output: &Resource<DynOutputStream>,
+        quota: EgressQuota,
+    ) -> Result<(), wasmtime::component::ResourceTableError> {
+        let stream = self.inner.table.get_mut(input)?;
+        let unmetered = std::mem::replace(stream, Box::new(ClosedInputStream));
+        *stream = Box::new(MeteredInputStream::new(unmetered, quota.clone()));
+
+        let stream = self.inner.table.get_mut(output)?;
+        let unmetered = std::mem::replace(stream, Box::new(ClosedOutputStream));
+        *stream = Box::new(MeteredOutputStream::new(unmetered, quota));
+        Ok(())
+    }
 }
 
 impl<T> p2_tcp::Host for SpinSocketsView<'_, T> {}
@@ -149,6 +219,9 @@ impl<T> p2_tcp::HostTcpSocket for SpinSocketsView<'_, T> {
             tracing::warn!("TCP socket connection refused: connection quota exhausted");
             return Err(SocketErrorCode::NewSocketLimit.into());
         };
+        if let Err(err) = self.start_egress_request() {
+            return Err(p2_quota_exceeded(err, SocketErrorCode::NewSocketLimit));
+        }
         let result =
             p2_tcp::HostTcpSocket::start_connect(&mut self.inner, this, network, remote_address)
                 .await;
@@ -164,7 +237,11 @@ impl<T> p2_tcp::HostTcpSocket for SpinSocketsView<'_, T> {
         this: Resource<TcpSocket>,
     ) -> wasmtime_wasi::p2::SocketResult<(Resource<DynInputStream>, Resource<DynOutputStream>)>
     {
-        p2_tcp::HostTcpSocket::finish_connect(&mut self.inner, this)
+        let (input, output) = p2_tcp::HostTcpSocket::finish_connect(&mut self.inner, this)?;
+        if let Some(quota) = self.egress_quota.clone() {
+            self.meter_streams(&input, &output, quota)?;
+        }
+        Ok((input, output))
     }
 
     fn start_listen(&mut self, this: Resource<TcpSocket>) -> wasmtime_wasi::p2::SocketResult<()> {
@@ -485,7 +562,13 @@ impl<T> p2_udp::HostIncomingDatagramStream for SpinSocketsView<'_, T> {
         this: Resource<p2_udp::IncomingDatagramStream>,
         max_results: u64,
     ) -> wasmtime_wasi::p2::SocketResult<Vec<p2_udp::IncomingDatagram>> {
-        p2_udp::HostIncomingDatagramStream::receive(&mut self.inner, this, max_results)
+        let datagrams =
+            p2_udp::HostIncomingDatagramStream::receive(&mut self.inner, this, max_results)?;
+        let bytes = datagrams.iter().map(|d| d.data.len() as u64).sum();
+        if let Err(err) = self.record_egress_received(bytes) {
+            return Err(p2_quota_exceeded(err, SocketErrorCode::AccessDenied));
+        }
+        Ok(datagrams)
     }
 
     fn subscribe(
@@ -513,6 +596,10 @@ impl<T> p2_udp::HostOutgoingDatagramStream for SpinSocketsView<'_, T> {
         this: Resource<p2_udp::OutgoingDatagramStream>,
         datagrams: Vec<p2_udp::OutgoingDatagram>,
     ) -> wasmtime_wasi::p2::SocketResult<u64> {
+        let bytes = datagrams.iter().map(|d| d.data.len() as u64).sum();
+        if let Err(err) = self.record_egress_sent(bytes) {
+            return Err(p2_quota_exceeded(err, SocketErrorCode::AccessDenied));
+        }
         p2_udp::HostOutgoingDatagramStream::send(&mut self.inner, this, datagrams).await
     }
 
@@ -540,6 +627,9 @@ impl<T> p2_udp_create::Host for SpinSocketsView<'_, T> {
             tracing::warn!("UDP socket creation refused: connection quota exhausted");
             return Err(SocketErrorCode::NewSocketLimit.into());
         };
+        if let Err(err) = self.start_egress_request() {
+            return Err(p2_quota_exceeded(err, SocketErrorCode::NewSocketLimit));
+        }
         let sock = p2_udp_create::Host::create_udp_socket(&mut self.inner, address_family)?;
         self.register_permit(sock.rep(), permit);
         Ok(sock)
@@ -548,8 +638,13 @@ impl<T> p2_udp_create::Host for SpinSocketsView<'_, T> {
 
 // ===== p3 impls =====
 
-use wasmtime::AsContextMut as _;
-use wasmtime::component::{Access, Accessor};
+use futures::{
+    FutureExt as _,
+    channel::oneshot,
+    future::{Either, select},
+};
+use wasmtime::AsContextMut;
+use wasmtime::component::{Access, Accessor, FutureConsumer, FutureReader, Source};
 use wasmtime_wasi::p3::bindings::sockets::types::{
     self as p3_types, Duration as p3_Duration, ErrorCode as p3_ErrorCode, Host as p3_Host,
     HostTcpSocket as p3_HostTcpSocket, HostTcpSocketWithStore, HostUdpSocket as p3_HostUdpSocket,
@@ -558,6 +653,61 @@ use wasmtime_wasi::p3::bindings::sockets::types::{
 };
 use wasmtime_wasi::p3::sockets::SocketResult as P3SocketResult;
 
+/// Returns a future which resolves to the result of a WASI 0.3 TCP `send` or
+/// `receive`, or to an error if the egress quota closed its stream first.
+///
+/// This is the counterpart of the error returned by a metered WASI 0.2 stream:
+/// without it, the guest would only see its stream end.
+fn with_quota_error<S: AsContextMut>(
+    mut store: S,
+    result: FutureReader<Result<(), p3_ErrorCode>>,
+    exceeded: oneshot::Receiver<EgressQuotaExceeded>,
+) -> wasmtime::Result<FutureReader<Result<(), p3_ErrorCode>>>
+where
+    S::Data: 'static,
+{
+    let (result_tx, result_rx) = oneshot::channel();
+    result.pipe(&mut store, ForwardResult(Some(result_tx)))?;
+    FutureReader::new(store, async move {
+        let result = match select(exceeded, result_rx).await {
+            Either::Left((Ok(err), _)) => Ok(err),
+            Either::Left((Err(_), result)) => Err(result.await),
+            // The quota may have closed the stream just before the result arrived.
+            Either::Right((result, exceeded)) => match exceeded.now_or_never() {
+                Some(Ok(err)) => Ok(err),
+                _ => Err(result),
+            },
+        };
+        match result {
+            Ok(err) => Ok(Err(p3_ErrorCode::Other(Some(err.to_string())))),
+            Err(Ok(result)) => Ok(result),
+            Err(Err(_)) => Err(wasmtime::format_err!("socket operation result was dropped")),
+        }
+    })
+}
+
+/// Forwards the value of a [`FutureReader`] to a channel.
+struct ForwardResult<T>(Option<oneshot::Sender<T>>);
+
+impl<D, T: wasmtime::component::Lift + Send + 'static> FutureConsumer<D> for ForwardResult<T> {
+    type Item = T;
+
+    fn poll_consume(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        mut store: wasmtime::StoreContextMut<D>,
+        mut source: Source<'_, Self::Item>,
+        _finish: bool,
+    ) -> std::task::Poll<wasmtime::Result<()>> {
+        let mut value = None;
+        source.read(&mut store, &mut value)?;
+        if let (Some(value), Some(tx)) = (value, self.0.take()) {
+            _ = tx.send(value);
+        }
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
 impl<T> p3_Host for SpinSocketsView<'_, T> {
     fn convert_error_code(
         &mut self,
@@ -755,6 +905,9 @@ impl<T> p3_HostUdpSocket for SpinSocketsView<'_, T> {
             tracing::warn!("UDP socket creation refused: connection quota exhausted");
             return Err(p3_ErrorCode::Other(Some("connection quota exhausted".into())).into());
         };
+        if let Err(err) = self.start_egress_request() {
+            return Err(p3_ErrorCode::Other(Some(err.to_string())).into());
+        }
         let sock = p3_HostUdpSocket::create(&mut self.inner, address_family)?;
         self.register_permit(sock.rep(), permit);
         Ok(sock)
@@ -852,6 +1005,9 @@ impl<T: Send + 'static> HostTcpSocketWithStore<T> for SpinSockets<T> {
                 return Err(p3_ErrorCode::Other(Some("connection quota exhausted".into())).into());
             }
         };
+        if let Err(err) = store.with(|mut access| access.get().start_egress_request()) {
+            return Err(p3_ErrorCode::Other(Some(err.to_string())).into());
+        }
         let getter = store.with(|mut store| store.get().getter);
         let wasi_accessor = store.with_getter::<WasiSockets>(getter);
         let result: P3SocketResult<()> = <WasiSockets as HostTcpSocketWithStore<T>>::connect(
@@ -883,8 +1039,14 @@ impl<T: Send + 'static> HostTcpSocketWithStore<T> for SpinSockets<T> {
         data: wasmtime::component::StreamReader<u8>,
     ) -> wasmtime::Result<wasmtime::component::FutureReader<Result<(), p3_ErrorCode>>> {
         let getter = store.get().getter;
+        let Some(quota) = store.get().egress_quota.clone() else {
+            let wasi_store = Access::<T, WasiSockets>::new(store.as_context_mut(), getter);
+            return <WasiSockets as HostTcpSocketWithStore<T>>::send(wasi_store, socket, data);
+        };
+        let (data, exceeded) = meter_stream(&mut store, data, quota, EgressDirection::Sent)?;
         let wasi_store = Access::<T, WasiSockets>::new(store.as_context_mut(), getter);
-        <WasiSockets as HostTcpSocketWithStore<T>>::send(wasi_store, socket, data)
+        let result = <WasiSockets as HostTcpSocketWithStore<T>>::send(wasi_store, socket, data)?;
+        with_quota_error(&mut store, result, exceeded)
     }
 
     fn receive(
@@ -895,8 +1057,16 @@ impl<T: Send + 'static> HostTcpSocketWithStore<T> for SpinSockets<T> {
         wasmtime::component::FutureReader<Result<(), p3_ErrorCode>>,
     )> {
         let getter = store.get().getter;
+        let quota = store.get().egress_quota.clone();
         let wasi_store = Access::<T, WasiSockets>::new(store.as_context_mut(), getter);
-        <WasiSockets as HostTcpSocketWithStore<T>>::receive(wasi_store, socket)
+        let (data, result) =
+            <WasiSockets as HostTcpSocketWithStore<T>>::receive(wasi_store, socket)?;
+        let Some(quota) = quota else {
+            return Ok((data, result));
+        };
+        let (data, exceeded) = meter_stream(&mut store, data, quota, EgressDirection::Received)?;
+        let result = with_quota_error(&mut store, result, exceeded)?;
+        Ok((data, result))
     }
 }
 
@@ -907,6 +1077,10 @@ impl<T: 'static> HostUdpSocketWithStore<T> for SpinSockets<T> {
         data: Vec<u8>,
         remote_address: Option<p3_IpSocketAddress>,
     ) -> P3SocketResult<()> {
+        let sent = store.with(|mut access| access.get().record_egress_sent(data.len() as u64));
+        if let Err(err) = sent {
+            return Err(p3_ErrorCode::Other(Some(err.to_string())).into());
+        }
         let getter = store.with(|mut store| store.get().getter);
         let wasi_accessor = store.with_getter::<WasiSockets>(getter);
         <WasiSockets as HostUdpSocketWithStore<T>>::send(
@@ -924,6 +1098,13 @@ impl<T: 'static> HostUdpSocketWithStore<T> for SpinSockets<T> {
     ) -> P3SocketResult<(Vec<u8>, p3_IpSocketAddress)> {
         let getter = store.with(|mut store| store.get().getter);
         let wasi_accessor = store.with_getter::<WasiSockets>(getter);
-        <WasiSockets as HostUdpSocketWithStore<T>>::receive(&wasi_accessor, socket).await
+        let (data, remote_address) =
+            <WasiSockets as HostUdpSocketWithStore<T>>::receive(&wasi_accessor, socket).await?;
+        let received =
+            store.with(|mut access| access.get().record_egress_received(data.len() as u64));
+        if let Err(err) = received {
+            return Err(p3_ErrorCode::Other(Some(err.to_string())).into());
+        }
+        Ok((data, remote_address))
     }
 }
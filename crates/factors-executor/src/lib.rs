@@ -1,7 +1,14 @@
 use std::time::{Duration, Instant};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{Arc, Mutex, RwLock},
+};
 
 use anyhow::Context;
+use futures::{StreamExt, TryStreamExt};
 use spin_app::{App, AppComponent};
 use spin_core::{Component, async_trait, wasmtime::CallHook};
 use spin_factors::{
@@ -48,6 +55,16 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
         self.hooks.push(Box::new(hooks));
     }
 
+    /// Runs the shutdown hooks of this executor.
+    ///
+    /// This should be called once the trigger using this executor has stopped.
+    pub fn shutdown(&self) -> anyhow::Result<()> {
+        for hooks in &self.hooks {
+            hooks.shutdown()?;
+        }
+        Ok(())
+    }
+
     /// Loads a [`App`] with this executor.
     pub async fn load_app(
         self: Arc<Self>,
@@ -56,6 +73,50 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
         component_loader: &impl ComponentLoader<T, U>,
         trigger_type: Option<&str>,
         trigger_dependencies_composer: impl TriggerDependenciesComposer,
+    ) -> anyhow::Result<FactorsExecutorApp<T, U>> {
+        let executor_app = self.configure_app(app, runtime_config).await?;
+        executor_app
+            .load_components(
+                component_loader,
+                trigger_type,
+                trigger_dependencies_composer,
+            )
+            .await?;
+        Ok(executor_app)
+    }
+
+    /// Configures the given app without loading any of its components. Use
+    /// [`FactorsExecutorApp::load_components`] to load them.
+    pub async fn configure_app(
+        self: Arc<Self>,
+        app: App,
+        runtime_config: T::RuntimeConfig,
+    ) -> anyhow::Result<FactorsExecutorApp<T, U>> {
+        self.configure_app_with_hooks(app, runtime_config, false)
+            .await
+    }
+
+    /// Configures the given app to replace one which this executor has
+    /// already configured, e.g. when the app is reloaded.
+    ///
+    /// This runs the [`ExecutorHooks::reconfigure_app`] hooks in place of
+    /// [`ExecutorHooks::configure_app`], so that setup which should only
+    /// happen once isn't repeated. Once the app has replaced the running one,
+    /// call [`FactorsExecutorApp::commit_reload`].
+    pub async fn reconfigure_app(
+        self: Arc<Self>,
+        app: App,
+        runtime_config: T::RuntimeConfig,
+    ) -> anyhow::Result<FactorsExecutorApp<T, U>> {
+        self.configure_app_with_hooks(app, runtime_config, true)
+            .await
+    }
+
+    async fn configure_app_with_hooks(
+        self: Arc<Self>,
+        app: App,
+        runtime_config: T::RuntimeConfig,
+        reconfigure: bool,
     ) -> anyhow::Result<FactorsExecutorApp<T, U>> {
         let configured_app = self
             .factors
@@ -63,34 +124,21 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
             .context("failed to configure app")?;
 
         for hooks in &self.hooks {
-            hooks.configure_app(&configured_app).await?;
-        }
-
-        let components = match trigger_type {
-            Some(trigger_type) => configured_app
-                .app()
-                .triggers_with_type(trigger_type)
-                .filter_map(|t| t.component().ok())
-                .collect::<Vec<_>>(),
-            None => configured_app.app().components().collect(),
-        };
-        let mut component_instance_pres = HashMap::with_capacity(components.len());
-
-        for component in components {
-            let instance_pre = component_loader
-                .load_instance_pre(
-                    &self.core_engine,
-                    &component,
-                    &trigger_dependencies_composer,
-                )
-                .await?;
-            component_instance_pres.insert(component.id().to_string(), instance_pre);
+            if reconfigure {
+                hooks.reconfigure_app(&configured_app).await?;
+            } else {
+                hooks.configure_app(&configured_app).await?;
+            }
         }
 
         Ok(FactorsExecutorApp {
             executor: self.clone(),
-            configured_app,
-            component_instance_pres,
+            configured_app: Arc::new(configured_app),
+            instance_pres: Arc::new(InstancePres {
+                by_component: Default::default(),
+                reload_listeners: Default::default(),
+                deferred: Default::default(),
+            }),
         })
     }
 }
@@ -106,11 +154,35 @@ where
         Ok(())
     }
 
+    /// Reconfigure app hooks run in place of [`Self::configure_app`] when
+    /// [`FactorsExecutor::reconfigure_app`] configures an app to replace the
+    /// running one.
+    ///
+    /// By default this runs [`Self::configure_app`]. Hooks which do one-time
+    /// setup, such as initializing data, should skip it here. The running app
+    /// must not be changed, as the reload may still be rejected; that belongs
+    /// in [`Self::app_reloaded`].
+    async fn reconfigure_app(&self, configured_app: &ConfiguredApp<T>) -> anyhow::Result<()> {
+        self.configure_app(configured_app).await
+    }
+
+    /// App reloaded hooks run by [`FactorsExecutorApp::commit_reload`], once an
+    /// app configured by [`FactorsExecutor::reconfigure_app`] has replaced the
+    /// running one.
+    fn app_reloaded(&self, configured_app: &ConfiguredApp<T>) {
+        let _ = configured_app;
+    }
+
     /// Prepare instance hooks run immediately before [`FactorsExecutorApp::prepare`] returns.
     fn prepare_instance(&self, builder: &mut FactorsInstanceBuilder<T, U>) -> anyhow::Result<()> {
         let _ = builder;
         Ok(())
     }
+
+    /// Shutdown hooks run by [`FactorsExecutor::shutdown`] once the trigger has stopped.
+    fn shutdown(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 /// A ComponentLoader is responsible for loading Wasmtime [`Component`]s.
@@ -175,18 +247,110 @@ pub enum TriggerDependencyData {
 type InstancePre<T, U> =
     spin_core::InstancePre<InstanceState<<T as RuntimeFactors>::InstanceState, U>>;
 
+type ReloadListener<T, U> =
+    Box<dyn Fn(&str, &InstancePre<T, U>) -> anyhow::Result<ApplyReload> + Send + Sync>;
+
+type ApplyReload = Box<dyn FnOnce() + Send>;
+
+type LoadInstancePre<T, U> = Box<
+    dyn Fn() -> Pin<Box<dyn Future<Output = anyhow::Result<InstancePre<T, U>>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// The [`InstancePre`]s of an app's components, which may be replaced while
+/// the app is running if a component is reloaded.
+struct InstancePres<T: RuntimeFactors, U: 'static> {
+    // Maps component IDs -> InstancePres
+    by_component: RwLock<HashMap<String, InstancePre<T, U>>>,
+    reload_listeners: Mutex<Vec<ReloadListener<T, U>>>,
+    // Maps component IDs -> components whose loading has been deferred
+    deferred: Mutex<HashMap<String, Arc<DeferredComponent<T, U>>>>,
+}
+
+impl<T: RuntimeFactors, U: 'static> InstancePres<T, U> {
+    fn is_loaded(&self, component_id: &str) -> bool {
+        self.by_component.read().unwrap().contains_key(component_id)
+    }
+
+    /// Calls the reload listeners with a component's new [`InstancePre`],
+    /// then puts it in place.
+    ///
+    /// Nothing is changed unless every listener succeeds.
+    fn replace(&self, component_id: &str, instance_pre: InstancePre<T, U>) -> anyhow::Result<()> {
+        let mut by_component = self.by_component.write().unwrap();
+        let applies = self
+            .reload_listeners
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|listener| listener(component_id, &instance_pre))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for apply in applies {
+            apply();
+        }
+        by_component.insert(component_id.to_owned(), instance_pre);
+        Ok(())
+    }
+}
+
+/// A component which is loaded in the background, or when it's first used
+/// if that's sooner.
+struct DeferredComponent<T: RuntimeFactors, U: 'static> {
+    load: LoadInstancePre<T, U>,
+    // The outcome of loading the component, once it has been loaded
+    loaded: tokio::sync::OnceCell<Result<(), String>>,
+}
+
+impl<T: RuntimeFactors, U: 'static> DeferredComponent<T, U> {
+    async fn ensure_loaded(
+        &self,
+        instance_pres: &InstancePres<T, U>,
+        component_id: &str,
+    ) -> anyhow::Result<()> {
+        let loaded = self
+            .loaded
+            .get_or_init(|| async {
+                let instance_pre = (self.load)().await.map_err(|err| format!("{err:#}"))?;
+                instance_pres
+                    .replace(component_id, instance_pre)
+                    .map_err(|err| format!("{err:#}"))
+            })
+            .await;
+        loaded
+            .clone()
+            .map_err(|err| anyhow::anyhow!("failed to load component {component_id:?}: {err}"))
+    }
+}
+
 /// A FactorsExecutorApp represents a loaded Spin app, ready for instantiation.
 ///
 /// It is generic over the executor's [`RuntimeFactors`] and any ad-hoc additional
 /// per-instance state needed by the caller.
+///
+/// Cloning is cheap: clones share the configured app and its loaded components,
+/// so several triggers can run the same app.
 pub struct FactorsExecutorApp<T: RuntimeFactors, U: 'static> {
     executor: Arc<FactorsExecutor<T, U>>,
-    configured_app: ConfiguredApp<T>,
-    // Maps component IDs -> InstancePres
-    component_instance_pres: HashMap<String, InstancePre<T, U>>,
+    configured_app: Arc<ConfiguredApp<T>>,
+    instance_pres: Arc<InstancePres<T, U>>,
+}
+
+impl<T: RuntimeFactors, U: 'static> Clone for FactorsExecutorApp<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            executor: self.executor.clone(),
+            configured_app: self.configured_app.clone(),
+            instance_pres: self.instance_pres.clone(),
+        }
+    }
 }
 
 impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
+    pub fn executor(&self) -> &Arc<FactorsExecutor<T, U>> {
+        &self.executor
+    }
+
     pub fn engine(&self) -> &spin_core::Engine<InstanceState<T::InstanceState, U>> {
         &self.executor.core_engine
     }
@@ -199,26 +363,240 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
         self.configured_app.app()
     }
 
-    pub fn get_component(&self, component_id: &str) -> anyhow::Result<&Component> {
-        Ok(self.get_instance_pre(component_id)?.component())
+    /// Runs the [`ExecutorHooks::app_reloaded`] hooks. This should be called
+    /// once this app, configured by [`FactorsExecutor::reconfigure_app`], has
+    /// replaced the running app.
+    pub fn commit_reload(&self) {
+        for hooks in &self.executor.hooks {
+            hooks.app_reloaded(&self.configured_app);
+        }
+    }
+
+    /// Loads the components run by triggers of the given type, or all of the
+    /// app's components if `trigger_type` is `None`. Components which are
+    /// already loaded are skipped, so the components of several trigger types
+    /// can be loaded, each with their own trigger dependencies composer.
+    ///
+    /// Components are loaded concurrently, up to the available parallelism.
+    pub async fn load_components(
+        &self,
+        component_loader: &impl ComponentLoader<T, U>,
+        trigger_type: Option<&str>,
+        trigger_dependencies_composer: impl TriggerDependenciesComposer,
+    ) -> anyhow::Result<()> {
+        let composer = &trigger_dependencies_composer;
+        // The loads are built in a loop rather than by closures over the
+        // components, which the compiler can't prove are `Send`.
+        let mut loads = vec![];
+        for component in self.components_for_trigger_type(trigger_type) {
+            if self.instance_pres.is_loaded(component.id()) {
+                continue;
+            }
+            loads.push(async move {
+                let instance_pre = component_loader
+                    .load_instance_pre(&self.executor.core_engine, &component, composer)
+                    .await?;
+                anyhow::Ok((component.id().to_owned(), instance_pre))
+            });
+        }
+        let mut loads = futures::stream::iter(loads).buffer_unordered(load_parallelism());
+        while let Some((component_id, instance_pre)) = loads.try_next().await? {
+            self.instance_pres
+                .by_component
+                .write()
+                .unwrap()
+                .insert(component_id, instance_pre);
+        }
+        Ok(())
+    }
+
+    /// Like [`FactorsExecutorApp::load_components`], but doesn't load the
+    /// components yet. Each component is loaded when it's first used (see
+    /// [`FactorsExecutorApp::ensure_component_loaded`]), or by
+    /// [`FactorsExecutorApp::load_deferred_components`] if that's sooner.
+    pub fn defer_components(
+        &self,
+        component_loader: Arc<impl ComponentLoader<T, U> + Send + 'static>,
+        trigger_type: Option<&str>,
+        trigger_dependencies_composer: impl TriggerDependenciesComposer + 'static,
+    ) {
+        let composer = Arc::new(trigger_dependencies_composer);
+        let components = self.components_for_trigger_type(trigger_type);
+        let mut deferred = self.instance_pres.deferred.lock().unwrap();
+        for component in components {
+            let component_id = component.id().to_owned();
+            if self.instance_pres.is_loaded(&component_id) || deferred.contains_key(&component_id) {
+                continue;
+            }
+            let executor = self.executor.clone();
+            let app = self.app().clone();
+            let loader = component_loader.clone();
+            let composer = composer.clone();
+            let id = component_id.clone();
+            let load: LoadInstancePre<T, U> = Box::new(move || {
+                let executor = executor.clone();
+                let app = app.clone();
+                let loader = loader.clone();
+                let composer = composer.clone();
+                let id = id.clone();
+                Box::pin(async move {
+                    let component = app
+                        .get_component(&id)
+                        .with_context(|| format!("no such component {id:?}"))?;
+                    loader
+                        .load_instance_pre(&executor.core_engine, &component, composer.as_ref())
+                        .await
+                })
+            });
+            deferred.insert(
+                component_id,
+                Arc::new(DeferredComponent {
+                    load,
+                    loaded: Default::default(),
+                }),
+            );
+        }
+    }
+
+    /// Loads all the components whose loading was deferred and which haven't
+    /// been loaded yet, concurrently up to the available parallelism.
+    pub async fn load_deferred_components(&self) -> anyhow::Result<()> {
+        let deferred = self
+            .instance_pres
+            .deferred
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, component)| (id.clone(), component.clone()))
+            .collect::<Vec<_>>();
+        futures::stream::iter(deferred)
+            .map(|(component_id, component)| async move {
+                component
+                    .ensure_loaded(&self.instance_pres, &component_id)
+                    .await
+            })
+            .buffer_unordered(load_parallelism())
+            .try_collect()
+            .await
+    }
+
+    /// Waits until the given component is loaded, loading it now if its
+    /// loading was deferred and hasn't started yet.
+    pub async fn ensure_component_loaded(&self, component_id: &str) -> anyhow::Result<()> {
+        let deferred = self
+            .instance_pres
+            .deferred
+            .lock()
+            .unwrap()
+            .get(component_id)
+            .cloned();
+        match deferred {
+            Some(component) => {
+                component
+                    .ensure_loaded(&self.instance_pres, component_id)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns whether the given component has been loaded.
+    pub fn is_component_loaded(&self, component_id: &str) -> bool {
+        self.instance_pres.is_loaded(component_id)
+    }
+
+    /// Returns whether all of the components whose loading was deferred
+    /// have been loaded.
+    pub fn deferred_components_loaded(&self) -> bool {
+        self.instance_pres
+            .deferred
+            .lock()
+            .unwrap()
+            .values()
+            .all(|component| matches!(component.loaded.get(), Some(Ok(()))))
+    }
+
+    fn components_for_trigger_type(&self, trigger_type: Option<&str>) -> Vec<AppComponent<'_>> {
+        match trigger_type {
+            // `triggers_with_type` would tie the components' lifetime to
+            // `trigger_type`'s.
+            Some(trigger_type) => self
+                .app()
+                .triggers()
+                .filter(|t| t.trigger_type() == trigger_type)
+                .filter_map(|t| t.component().ok())
+                .collect(),
+            None => self.app().components().collect(),
+        }
+    }
+
+    pub fn get_component(&self, component_id: &str) -> anyhow::Result<Component> {
+        Ok(self.get_instance_pre(component_id)?.component().clone())
     }
 
-    pub fn get_instance_pre(&self, component_id: &str) -> anyhow::Result<&InstancePre<T, U>> {
-        self.component_instance_pres
+    pub fn get_instance_pre(&self, component_id: &str) -> anyhow::Result<InstancePre<T, U>> {
+        self.instance_pres
+            .by_component
+            .read()
+            .unwrap()
             .get(component_id)
+            .cloned()
             .with_context(|| format!("no such component {component_id:?}"))
     }
 
+    /// Returns a [`ComponentReloader`] which can replace this app's components
+    /// while it is running.
+    pub fn component_reloader(&self) -> ComponentReloader<T, U> {
+        ComponentReloader {
+            executor: self.executor.clone(),
+            app: self.app().clone(),
+            instance_pres: self.instance_pres.clone(),
+        }
+    }
+
+    /// Adds a listener which is called with the new [`InstancePre`] whenever
+    /// a component is reloaded, or loaded after its loading was deferred, e.g.
+    /// to refresh anything derived from the component's exports.
+    ///
+    /// The listener returns a function which applies its changes. These are
+    /// only called once every listener has succeeded: if any listener fails,
+    /// the reload is abandoned, no changes are applied and the component's
+    /// previous [`InstancePre`] stays in use.
+    pub fn on_component_reload<A: FnOnce() + Send + 'static>(
+        &self,
+        listener: impl Fn(&str, &InstancePre<T, U>) -> anyhow::Result<A> + Send + Sync + 'static,
+    ) {
+        self.instance_pres
+            .reload_listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(move |component_id, instance_pre| {
+                let apply = listener(component_id, instance_pre)?;
+                Ok(Box::new(apply) as ApplyReload)
+            }));
+    }
+
     /// Returns an instance builder for the given component ID.
     pub fn prepare(&self, component_id: &str) -> anyhow::Result<FactorsInstanceBuilder<'_, T, U>> {
+        let instance_pre = self.get_instance_pre(component_id)?;
+        self.prepare_with_instance_pre(component_id, instance_pre)
+    }
+
+    /// Returns an instance builder for the given component ID which
+    /// instantiates the given [`InstancePre`] of the component, e.g. one
+    /// obtained from [`Self::get_instance_pre`] before the component was
+    /// reloaded.
+    pub fn prepare_with_instance_pre(
+        &self,
+        component_id: &str,
+        instance_pre: InstancePre<T, U>,
+    ) -> anyhow::Result<FactorsInstanceBuilder<'_, T, U>> {
         let app_component = self
             .configured_app
             .app()
             .get_component(component_id)
             .with_context(|| format!("no such component {component_id:?}"))?;
 
-        let instance_pre = self.component_instance_pres.get(component_id).unwrap();
-
         let factor_builders = self
             .executor
             .factors
@@ -242,6 +620,61 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
     }
 }
 
+/// A ComponentReloader replaces the components of a running
+/// [`FactorsExecutorApp`], for example after they have been rebuilt.
+pub struct ComponentReloader<T: RuntimeFactors, U: 'static> {
+    executor: Arc<FactorsExecutor<T, U>>,
+    app: App,
+    instance_pres: Arc<InstancePres<T, U>>,
+}
+
+impl<T: RuntimeFactors, U: 'static> Clone for ComponentReloader<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            executor: self.executor.clone(),
+            app: self.app.clone(),
+            instance_pres: self.instance_pres.clone(),
+        }
+    }
+}
+
+impl<T: RuntimeFactors, U: Send + 'static> ComponentReloader<T, U> {
+    /// Reloads the given component from its source, so that new instances of
+    /// it use the reloaded code. Instances which are already running are
+    /// unaffected.
+    ///
+    /// Returns `false` if the component is not loaded by this app, e.g.
+    /// because it is handled by a different trigger.
+    pub async fn reload_component(
+        &self,
+        component_id: &str,
+        component_loader: &impl ComponentLoader<T, U>,
+        trigger_dependencies_composer: &impl TriggerDependenciesComposer,
+    ) -> anyhow::Result<bool> {
+        if !self.instance_pres.is_loaded(component_id) {
+            return Ok(false);
+        }
+        let component = self
+            .app
+            .get_component(component_id)
+            .with_context(|| format!("no such component {component_id:?}"))?;
+        let instance_pre = component_loader
+            .load_instance_pre(
+                &self.executor.core_engine,
+                &component,
+                trigger_dependencies_composer,
+            )
+            .await?;
+        self.instance_pres.replace(component_id, instance_pre)?;
+        Ok(true)
+    }
+}
+
+/// The number of components to load at once.
+fn load_parallelism() -> usize {
+    std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
 /// A FactorsInstanceBuilder manages the instantiation of a Spin component instance.
 ///
 /// It is generic over the executor's [`RuntimeFactors`] and any ad-hoc additional
@@ -250,7 +683,7 @@ pub struct FactorsInstanceBuilder<'a, F: RuntimeFactors, U: 'static> {
     app_component: AppComponent<'a>,
     store_builder: spin_core::StoreBuilder,
     factor_builders: F::InstanceBuilders,
-    instance_pre: &'a InstancePre<F, U>,
+    instance_pre: InstancePre<F, U>,
     factors: &'a F,
 }
 
@@ -498,6 +931,149 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn reloading_replaces_component() -> anyhow::Result<()> {
+        let (executor, app) = test_app().await?;
+
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader, None, ())
+            .await?;
+
+        let reloaded = Arc::new(Mutex::new(vec![]));
+        let listener_reloaded = reloaded.clone();
+        factors_app.on_component_reload(move |component_id, _| {
+            let listener_reloaded = listener_reloaded.clone();
+            let component_id = component_id.to_owned();
+            Ok(move || listener_reloaded.lock().unwrap().push(component_id))
+        });
+
+        let reloader = factors_app.component_reloader();
+        assert!(
+            reloader
+                .reload_component("empty", &DummyComponentLoader, &())
+                .await?
+        );
+        assert!(
+            !reloader
+                .reload_component("not-loaded", &DummyComponentLoader, &())
+                .await?
+        );
+        assert_eq!(vec!["empty"], *reloaded.lock().unwrap());
+
+        let (_instance, _store) = factors_app.prepare("empty")?.instantiate(()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_reload_applies_no_listeners() -> anyhow::Result<()> {
+        let (executor, app) = test_app().await?;
+
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader, None, ())
+            .await?;
+        let applied = Arc::new(Mutex::new(false));
+        let listener_applied = applied.clone();
+        factors_app.on_component_reload(move |_, _| {
+            let listener_applied = listener_applied.clone();
+            Ok(move || *listener_applied.lock().unwrap() = true)
+        });
+        factors_app.on_component_reload(|_, _| -> anyhow::Result<fn()> {
+            anyhow::bail!("listener failed")
+        });
+
+        let reloader = factors_app.component_reloader();
+        let err = reloader
+            .reload_component("empty", &DummyComponentLoader, &())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "listener failed");
+        assert!(!*applied.lock().unwrap());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deferred_component_is_loaded_on_demand() -> anyhow::Result<()> {
+        let (executor, app) = test_app().await?;
+
+        let factors_app = executor.configure_app(app, Default::default()).await?;
+        factors_app.defer_components(Arc::new(DummyComponentLoader), None, ());
+        assert!(!factors_app.is_component_loaded("empty"));
+        assert!(!factors_app.deferred_components_loaded());
+
+        factors_app.ensure_component_loaded("empty").await?;
+        assert!(factors_app.is_component_loaded("empty"));
+        assert!(factors_app.deferred_components_loaded());
+        factors_app.load_deferred_components().await?;
+
+        let (_instance, _store) = factors_app.prepare("empty")?.instantiate(()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reconfiguring_runs_reconfigure_and_reloaded_hooks() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors);
+        let locked = env.build_locked_app().await?;
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let mut executor = FactorsExecutor::new(engine_builder, env.factors)?;
+        let calls = Arc::new(Mutex::new(vec![]));
+        executor.add_hooks(RecordingHooks(calls.clone()));
+        let executor = Arc::new(executor);
+
+        executor
+            .clone()
+            .configure_app(App::new("test-app", locked.clone()), Default::default())
+            .await?;
+        let reloaded = executor
+            .reconfigure_app(App::new("test-app", locked), Default::default())
+            .await?;
+        assert_eq!(vec!["configure", "reconfigure"], *calls.lock().unwrap());
+
+        reloaded.commit_reload();
+        assert_eq!(
+            vec!["configure", "reconfigure", "reloaded"],
+            *calls.lock().unwrap()
+        );
+        Ok(())
+    }
+
+    /// Returns an executor and the default test app, whose only component is "empty".
+    async fn test_app() -> anyhow::Result<(Arc<FactorsExecutor<TestFactors, ()>>, App)> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors);
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        Ok((executor, app))
+    }
+
+    /// Records which of its configuration hooks have been run.
+    struct RecordingHooks(Arc<Mutex<Vec<&'static str>>>);
+
+    #[async_trait]
+    impl ExecutorHooks<TestFactors, ()> for RecordingHooks {
+        async fn configure_app(&self, _: &ConfiguredApp<TestFactors>) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push("configure");
+            Ok(())
+        }
+
+        async fn reconfigure_app(&self, _: &ConfiguredApp<TestFactors>) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push("reconfigure");
+            Ok(())
+        }
+
+        fn app_reloaded(&self, _: &ConfiguredApp<TestFactors>) {
+            self.0.lock().unwrap().push("reloaded");
+        }
+    }
+
     struct DummyComponentLoader;
 
     #[async_trait]
@@ -14,10 +14,33 @@ pub struct HttpTriggerConfig {
     /// The HTTP executor the component requires
     #[serde(default)]
     pub executor: Option<HttpExecutorType>,
+    /// Limits on requests to this route, overriding the server-wide limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_limits: Option<RequestLimits>,
+    /// Compression of responses (and optionally requests) on this route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionConfig>,
+    /// The number of instances of the component to keep instantiated ahead of
+    /// requests, overriding the server-wide setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_instances: Option<usize>,
 }
 
 impl HttpTriggerConfig {
     pub fn lookup_key(&self, trigger_id: &str) -> anyhow::Result<crate::routes::TriggerLookupKey> {
+        if let Some(static_files) = self.route.static_files() {
+            anyhow::ensure!(
+                self.component.is_none() && self.static_response.is_none(),
+                "Static file routes cannot specify component or static_response - {trigger_id} has one"
+            );
+            anyhow::ensure!(
+                static_files.directory.is_some() != static_files.component_files.is_some(),
+                "Static file routes must specify either directory or component_files - {trigger_id} has neither or both"
+            );
+            return Ok(crate::routes::TriggerLookupKey::Trigger(
+                trigger_id.to_string(),
+            ));
+        }
         match (&self.component, &self.static_response) {
             (None, None) => Err(anyhow::anyhow!(
                 "Triggers must specify either component or static_response - {trigger_id} has neither"
@@ -33,6 +56,107 @@ impl HttpTriggerConfig {
     }
 }
 
+/// Limits applied to incoming requests before they are passed to a component.
+///
+/// Any limit set to `None` is not enforced.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RequestLimits {
+    /// The maximum size of the request body, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_size: Option<u64>,
+    /// The maximum combined size of the request header names and values, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_header_size: Option<usize>,
+    /// The maximum number of request headers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_header_count: Option<usize>,
+    /// The maximum rate at which the request body is read, in bytes per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_upload_rate: Option<u64>,
+}
+
+impl RequestLimits {
+    /// Returns these limits, taking any unset limits from `defaults`.
+    pub fn or(&self, defaults: &RequestLimits) -> RequestLimits {
+        RequestLimits {
+            max_body_size: self.max_body_size.or(defaults.max_body_size),
+            max_header_size: self.max_header_size.or(defaults.max_header_size),
+            max_header_count: self.max_header_count.or(defaults.max_header_count),
+            max_upload_rate: self.max_upload_rate.or(defaults.max_upload_rate),
+        }
+    }
+}
+
+/// Opt-in compression for a route.
+///
+/// Responses are compressed with gzip or brotli when the client accepts it via
+/// `Accept-Encoding`, the response has an allowed content type, and it is not
+/// already encoded.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// The response content types to compress. An entry ending in `/*`
+    /// matches any subtype, e.g. `text/*`.
+    #[serde(default = "default_compressible_types")]
+    pub content_types: Vec<String>,
+    /// Responses with a `Content-Length` smaller than this are not compressed.
+    /// Responses without a `Content-Length` are always eligible.
+    #[serde(default = "default_min_compression_size")]
+    pub min_size: u64,
+    /// Whether to decompress request bodies sent with a `Content-Encoding`
+    /// before passing them to the component.
+    #[serde(default)]
+    pub decompress_requests: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            content_types: default_compressible_types(),
+            min_size: default_min_compression_size(),
+            decompress_requests: false,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Returns whether responses with the given content type may be compressed.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        self.content_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(prefix) => essence
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('/')),
+                None => allowed.eq_ignore_ascii_case(&essence),
+            })
+    }
+}
+
+fn default_compressible_types() -> Vec<String> {
+    [
+        "text/*",
+        "application/javascript",
+        "application/json",
+        "application/wasm",
+        "application/xml",
+        "image/svg+xml",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_min_compression_size() -> u64 {
+    1024
+}
+
 /// The executor for the HTTP component.
 /// The component can either implement the Spin HTTP interface,
 /// the `wasi-http` interface, or the Wagi CGI interface.
@@ -123,4 +247,101 @@ mod tests {
         assert_eq!(config.entrypoint, "_start");
         assert_eq!(config.argv, "${SCRIPT_NAME} ${ARGS}");
     }
+
+    #[test]
+    fn route_request_limits_override_defaults() {
+        let config: HttpTriggerConfig = toml::toml! {
+            route = "/upload"
+            component = "uploader"
+            request_limits = { max_body_size = 1024, max_upload_rate = 512 }
+        }
+        .try_into()
+        .unwrap();
+        let defaults = RequestLimits {
+            max_body_size: Some(64),
+            max_header_count: Some(10),
+            ..Default::default()
+        };
+        let limits = config.request_limits.unwrap().or(&defaults);
+        assert_eq!(
+            limits,
+            RequestLimits {
+                max_body_size: Some(1024),
+                max_header_size: None,
+                max_header_count: Some(10),
+                max_upload_rate: Some(512),
+            }
+        );
+    }
+
+    #[test]
+    fn route_can_set_warm_instances() {
+        let config: HttpTriggerConfig = toml::toml! {
+            route = "/latency-sensitive"
+            component = "fast"
+            warm_instances = 4
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.warm_instances, Some(4));
+    }
+
+    #[test]
+    fn static_route_is_handled_by_trigger() {
+        let config: HttpTriggerConfig = toml::toml! {
+            route = { static = "/assets/...", directory = "dist", fallback = "index.html" }
+        }
+        .try_into()
+        .unwrap();
+        let static_files = config.route.static_files().unwrap();
+        assert_eq!(static_files.directory.as_deref(), Some("dist"));
+        assert_eq!(static_files.index, ["index.html"]);
+        assert!(static_files.compress);
+        assert_eq!(
+            config.lookup_key("assets").unwrap(),
+            crate::routes::TriggerLookupKey::Trigger("assets".into())
+        );
+
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "frontend"
+            route = { static = "/assets/...", directory = "dist" }
+        }
+        .try_into()
+        .unwrap();
+        config.lookup_key("assets").unwrap_err();
+    }
+
+    #[test]
+    fn static_route_default_matches_manifest_defaults() {
+        let config: HttpTriggerConfig = toml::toml! {
+            route = { static = "/assets/...", directory = "dist" }
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(
+            config.route.static_files().unwrap(),
+            &spin_http_routes::HttpStaticRoute {
+                route: "/assets/...".into(),
+                directory: Some("dist".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn compression_content_type_allowlist() {
+        let config: HttpTriggerConfig = toml::toml! {
+            route = "/..."
+            component = "web"
+            compression = { content_types = ["text/*", "application/json"] }
+        }
+        .try_into()
+        .unwrap();
+        let compression = config.compression.unwrap();
+        assert_eq!(compression.min_size, 1024);
+        assert!(compression.allows_content_type("text/html; charset=utf-8"));
+        assert!(compression.allows_content_type("Application/JSON"));
+        assert!(!compression.allows_content_type("textual/html"));
+        assert!(!compression.allows_content_type("image/png"));
+    }
 }
@@ -125,6 +125,8 @@ fn apply_profile_overrides(manifest: &mut AppManifest, profile: Option<&str>) {
                         command: profile_build.command.clone(),
                         workdir: None,
                         watch: vec![],
+                        inputs: vec![],
+                        preinit: false,
                     })
                 }
                 Some(build) => {
@@ -186,6 +186,29 @@ pub struct ComponentBuildConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[schemars(with = "Vec<json_schema::WatchCommand>")]
     pub watch: Vec<String>,
+    /// The files the build reads. This is a set of paths or glob patterns (relative
+    /// to the build working directory). If set, `spin build` skips the component
+    /// when its build command, the contents of the matching files and its output
+    /// are unchanged since it was last built. If omitted, the component is always
+    /// built. The inputs must include every file the build depends on, or changes
+    /// to the others will be missed.
+    ///
+    /// Example: `inputs = ["Cargo.toml", "Cargo.lock", "src/**/*.rs"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+    /// Whether to pre-initialize the component after building it. The build must
+    /// produce a core Wasm module which exports a `wizer.initialize` function, or
+    /// a component which contains exactly one such module. That function is run
+    /// at build time, and the state it leaves is snapshotted into the module, so
+    /// that it doesn't have to run each time the component is instantiated. Only
+    /// deterministic WASI functions can be called during pre-initialization, and
+    /// the module must not contain instructions which change its tables. If the
+    /// build leaves the output as it was already pre-initialized, it isn't
+    /// pre-initialized again.
+    ///
+    /// Example: `preinit = true`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preinit: bool,
 }
 
 impl ComponentBuildConfig {
@@ -197,6 +220,14 @@ impl ComponentBuildConfig {
         };
         as_vec.into_iter()
     }
+
+    /// The paths or glob patterns (relative to the build working directory) of
+    /// the files the build reads. If none of the matching files has changed since
+    /// the last build, `spin build` skips the component. If there are none, the
+    /// component is always built.
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
 }
 
 /// The command or commands to build the application. If multiple commands
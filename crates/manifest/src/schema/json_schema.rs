@@ -38,6 +38,71 @@ pub struct HttpTriggerSchema {
     /// `executor = { type = "wagi" }
     #[schemars(default, schema_with = "toml_table")]
     executor: Option<toml::Table>,
+    /// Limits on requests to this route, overriding the server-wide limits.
+    ///
+    /// Example: `request_limits = { max_body_size = 1048576 }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_limits: Option<HttpRequestLimits>,
+    /// Compression of responses (and optionally requests) on this route.
+    ///
+    /// Example: `compression = { content_types = ["text/*"] }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compression: Option<HttpCompression>,
+    /// The number of instances of the component to keep instantiated ahead of requests,
+    /// overriding the server-wide setting.
+    ///
+    /// Example: `warm_instances = 4`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    warm_instances: Option<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HttpRequestLimits {
+    /// The maximum size of the request body, in bytes. Requests with larger bodies are
+    /// rejected with `413 Content Too Large`.
+    ///
+    /// Example: `max_body_size = 1048576`
+    #[serde(default)]
+    pub max_body_size: Option<u64>,
+    /// The maximum combined size of the request header names and values, in bytes.
+    /// Requests with larger headers are rejected with `431 Request Header Fields Too Large`.
+    ///
+    /// Example: `max_header_size = 8192`
+    #[serde(default)]
+    pub max_header_size: Option<usize>,
+    /// The maximum number of request headers. Requests with more headers are rejected
+    /// with `431 Request Header Fields Too Large`.
+    ///
+    /// Example: `max_header_count = 100`
+    #[serde(default)]
+    pub max_header_count: Option<usize>,
+    /// The maximum rate at which the request body is read, in bytes per second.
+    ///
+    /// Example: `max_upload_rate = 65536`
+    #[serde(default)]
+    pub max_upload_rate: Option<u64>,
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HttpCompression {
+    /// The response content types to compress. An entry ending in `/*` matches any subtype.
+    ///
+    /// Example: `content_types = ["text/*", "application/json"]`
+    #[serde(default)]
+    pub content_types: Vec<String>,
+    /// Responses with a `Content-Length` smaller than this, in bytes, are not compressed.
+    ///
+    /// Example: `min_size = 1024`
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    /// Whether to decompress request bodies sent with a `Content-Encoding` before passing
+    /// them to the component. Defaults to false.
+    #[serde(default)]
+    pub decompress_requests: bool,
 }
 
 #[allow(dead_code)]
@@ -64,6 +129,10 @@ pub enum HttpRouteSchema {
     ///
     /// Learn more: https://spinframework.dev/v3/http-trigger#private-endpoints
     Private(HttpPrivateEndpoint),
+    /// The trigger serves static files directly from the host, without a component.
+    ///
+    /// Example: `route = { static = "/assets/...", directory = "dist" }`
+    Static(HttpStaticRoute),
 }
 
 #[allow(dead_code)]
@@ -74,6 +143,45 @@ pub struct HttpPrivateEndpoint {
     pub private: bool,
 }
 
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HttpStaticRoute {
+    /// The route under which files are served. This should end with a trailing wildcard.
+    ///
+    /// Example: `static = "/assets/..."`
+    #[serde(rename = "static")]
+    pub route: String,
+    /// The directory to serve files from. Relative paths are resolved against the
+    /// directory containing the manifest. Exactly one of `directory` or `component_files`
+    /// must be specified.
+    ///
+    /// Example: `directory = "dist"`
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// The ID of a component whose `files` should be served.
+    ///
+    /// Example: `component_files = "frontend"`
+    #[serde(default)]
+    pub component_files: Option<String>,
+    /// The file names to serve when a directory is requested. Defaults to `["index.html"]`.
+    #[serde(default)]
+    pub index: Vec<String>,
+    /// A file to serve when no file matches the request path, for single-page applications.
+    ///
+    /// Example: `fallback = "index.html"`
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// Whether to serve precompressed `.br` and `.gz` variants of files. Defaults to true.
+    #[serde(default)]
+    pub compress: Option<bool>,
+    /// The `cache-control` header to send with files.
+    ///
+    /// Example: `cache_control = "public, max-age=3600"`
+    #[serde(default)]
+    pub cache_control: Option<String>,
+}
+
 #[allow(dead_code)]
 #[derive(JsonSchema)]
 #[schemars(deny_unknown_fields)]
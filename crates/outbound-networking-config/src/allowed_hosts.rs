@@ -6,6 +6,8 @@ use futures_util::future::{BoxFuture, Shared};
 use spin_expressions::SyncResolver;
 use url::Host;
 
+use crate::audit::ComponentAllowedHostsAudit;
+
 /// The domain used for service chaining.
 pub const SERVICE_CHAINING_DOMAIN: &str = "spin.internal";
 /// The domain suffix used for service chaining.
@@ -19,6 +21,7 @@ pub type SharedFutureResult<T> = Shared<BoxFuture<'static, Result<Arc<T>, Arc<an
 pub struct OutboundAllowedHosts {
     allowed_hosts_future: SharedFutureResult<AllowedHostsConfig>,
     disallowed_host_handler: Option<Arc<dyn DisallowedHostHandler>>,
+    audit: Option<ComponentAllowedHostsAudit>,
 }
 
 impl OutboundAllowedHosts {
@@ -30,13 +33,25 @@ impl OutboundAllowedHosts {
         Self {
             allowed_hosts_future,
             disallowed_host_handler,
+            audit: None,
         }
     }
 
+    /// Switches this instance to audit mode.
+    ///
+    /// In audit mode every URL is allowed, and every attempted destination is
+    /// recorded to the given audit instead of being checked against the
+    /// configured allowed hosts.
+    pub fn with_audit(mut self, audit: ComponentAllowedHostsAudit) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
     /// Checks address against allowed hosts
     ///
     /// Calls the [`DisallowedHostHandler`] if set and URL is disallowed.
     /// If `url` cannot be parsed, `{scheme}://` is prepended to `url` and retried.
+    /// In audit mode, the URL is recorded and always allowed.
     pub async fn check_url(&self, url: &str, scheme: &str) -> anyhow::Result<bool> {
         tracing::debug!("Checking outbound networking request to '{url}'");
         let url = match OutboundUrl::parse(url, scheme) {
@@ -49,6 +64,11 @@ impl OutboundAllowedHosts {
             }
         };
 
+        if let Some(audit) = &self.audit {
+            audit.record(url.scheme(), &url.authority());
+            return Ok(true);
+        }
+
         let allowed_hosts = self.resolve().await?;
         let is_allowed = allowed_hosts.allows(&url);
         if !is_allowed {
@@ -61,9 +81,14 @@ impl OutboundAllowedHosts {
     /// Checks if allowed hosts permit relative requests
     ///
     /// Calls the [`DisallowedHostHandler`] if set and relative requests are
-    /// disallowed.
+    /// disallowed. In audit mode, the request is recorded and always allowed.
     pub async fn check_relative_url(&self, schemes: &[&str]) -> anyhow::Result<bool> {
         tracing::debug!("Checking relative outbound networking request with schemes {schemes:?}");
+        if let Some(audit) = &self.audit {
+            let scheme = schemes.first().unwrap_or(&"");
+            audit.record(scheme, "self");
+            return Ok(true);
+        }
         let allowed_hosts = self.resolve().await?;
         let is_allowed = allowed_hosts.allows_relative_url(schemes);
         if !is_allowed {
@@ -768,6 +793,34 @@ mod test {
     use super::*;
     use std::net::{Ipv4Addr, Ipv6Addr};
 
+    #[tokio::test]
+    async fn audit_mode_allows_and_records_disallowed_hosts() -> anyhow::Result<()> {
+        use futures_util::FutureExt as _;
+
+        use crate::audit::AllowedHostsAudit;
+
+        let config =
+            AllowedHostsConfig::parse(&["https://allowed.example.com"], &dummy_resolver(), &[])?;
+        let allowed_hosts = OutboundAllowedHosts::new(
+            futures_util::future::ready(Ok(Arc::new(config)))
+                .boxed()
+                .shared(),
+            None,
+        );
+        let url = "https://other.example.com:8443/path";
+        assert!(!allowed_hosts.check_url(url, "https").await?);
+
+        let audit = Arc::new(AllowedHostsAudit::new());
+        let audited =
+            allowed_hosts.with_audit(ComponentAllowedHostsAudit::new(audit.clone(), "component"));
+        assert!(audited.check_url(url, "https").await?);
+        assert_eq!(
+            audit.allowed_outbound_hosts()["component"],
+            ["https://other.example.com:8443"]
+        );
+        Ok(())
+    }
+
     #[test]
     fn outbound_url_handles_at_in_paths() {
         let url = "https://example.com/file@0.1.0.json";
@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context as _;
+
+/// Records the outbound destinations attempted by each component of an app.
+///
+/// When auditing, outbound requests are allowed regardless of the configured
+/// `allowed_outbound_hosts`; the recorded destinations can then be turned into
+/// the minimal `allowed_outbound_hosts` list that would have permitted them.
+#[derive(Debug, Default)]
+pub struct AllowedHostsAudit {
+    /// Component ID -> attempted `scheme://authority` destinations
+    destinations: Mutex<BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl AllowedHostsAudit {
+    /// Creates a new, empty audit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an attempt by the given component to reach `scheme://authority`.
+    pub fn record(&self, component_id: &str, scheme: &str, authority: &str) {
+        let destination = format!("{scheme}://{authority}");
+        let is_new = self
+            .destinations
+            .lock()
+            .unwrap()
+            .entry(component_id.to_owned())
+            .or_default()
+            .insert(destination.clone());
+        if is_new {
+            tracing::info!(
+                component_id,
+                "Audit: recorded outbound network destination '{destination}'"
+            );
+        }
+    }
+
+    /// Returns whether no destinations have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.destinations.lock().unwrap().is_empty()
+    }
+
+    /// Returns, for each component that made outbound requests, the minimal
+    /// `allowed_outbound_hosts` list that would have permitted them.
+    pub fn allowed_outbound_hosts(&self) -> BTreeMap<String, Vec<String>> {
+        self.destinations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, hosts)| (id.clone(), hosts.iter().cloned().collect()))
+            .collect()
+    }
+
+    /// Records the destinations recorded by `other`, e.g. the audit of an
+    /// earlier configuration of the same app.
+    pub fn merge(&self, other: &AllowedHostsAudit) {
+        let other = other.destinations.lock().unwrap().clone();
+        let mut destinations = self.destinations.lock().unwrap();
+        for (id, hosts) in other {
+            destinations.entry(id).or_default().extend(hosts);
+        }
+    }
+
+    /// Records the destinations listed in audit TOML previously rendered by
+    /// [`Self::to_toml`], e.g. by another trigger of the same app.
+    pub fn merge_toml(&self, toml: &str) -> anyhow::Result<()> {
+        let root: toml::Table = toml.parse()?;
+        let Some(components) = root.get("component") else {
+            return Ok(());
+        };
+        let components = components
+            .as_table()
+            .context("expected `component` to be a table")?;
+        let mut destinations = self.destinations.lock().unwrap();
+        for (id, component) in components {
+            let hosts = component
+                .get("allowed_outbound_hosts")
+                .and_then(toml::Value::as_array)
+                .with_context(|| {
+                    format!("expected `allowed_outbound_hosts` array for component '{id}'")
+                })?;
+            let recorded = destinations.entry(id.clone()).or_default();
+            for host in hosts {
+                let host = host
+                    .as_str()
+                    .with_context(|| format!("expected strings for component '{id}'"))?;
+                recorded.insert(host.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the audited `allowed_outbound_hosts` as manifest TOML, with one
+    /// `[component.<id>]` table per component.
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        let components = self
+            .allowed_outbound_hosts()
+            .into_iter()
+            .map(|(id, hosts)| {
+                let mut table = toml::Table::new();
+                table.insert("allowed_outbound_hosts".into(), hosts.into());
+                (id, toml::Value::Table(table))
+            })
+            .collect::<toml::Table>();
+        let mut root = toml::Table::new();
+        root.insert("component".into(), toml::Value::Table(components));
+        Ok(toml::to_string(&root)?)
+    }
+}
+
+/// An [`AllowedHostsAudit`] scoped to a single component.
+#[derive(Clone, Debug)]
+pub struct ComponentAllowedHostsAudit {
+    audit: Arc<AllowedHostsAudit>,
+    component_id: Arc<str>,
+}
+
+impl ComponentAllowedHostsAudit {
+    /// Creates a new audit scope for the given component.
+    pub fn new(audit: Arc<AllowedHostsAudit>, component_id: impl Into<Arc<str>>) -> Self {
+        Self {
+            audit,
+            component_id: component_id.into(),
+        }
+    }
+
+    /// Records an attempt by this component to reach `scheme://authority`.
+    pub fn record(&self, scheme: &str, authority: &str) {
+        self.audit.record(&self.component_id, scheme, authority);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records_unique_destinations_per_component() {
+        let audit = Arc::new(AllowedHostsAudit::new());
+        let first = ComponentAllowedHostsAudit::new(audit.clone(), "first");
+        let second = ComponentAllowedHostsAudit::new(audit.clone(), "second");
+
+        first.record("https", "example.com");
+        first.record("https", "example.com");
+        first.record("tcp", "db.example.com:5432");
+        second.record("http", "self");
+
+        let hosts = audit.allowed_outbound_hosts();
+        assert_eq!(
+            hosts["first"],
+            ["https://example.com", "tcp://db.example.com:5432"]
+        );
+        assert_eq!(hosts["second"], ["http://self"]);
+    }
+
+    #[test]
+    fn renders_manifest_toml() {
+        let audit = AllowedHostsAudit::new();
+        audit.record("my-component", "redis", "cache:6379");
+        audit.record("my-component", "https", "api.example.com");
+
+        let rendered: toml::Table = audit.to_toml().unwrap().parse().unwrap();
+        let expected = toml::toml! {
+            [component.my-component]
+            allowed_outbound_hosts = ["https://api.example.com", "redis://cache:6379"]
+        };
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn merges_previously_rendered_toml() {
+        let earlier = AllowedHostsAudit::new();
+        earlier.record("first", "https", "example.com");
+        earlier.record("second", "redis", "cache:6379");
+
+        let audit = AllowedHostsAudit::new();
+        audit.record("first", "https", "api.example.com");
+        audit.merge_toml(&earlier.to_toml().unwrap()).unwrap();
+
+        let hosts = audit.allowed_outbound_hosts();
+        assert_eq!(
+            hosts["first"],
+            ["https://api.example.com", "https://example.com"]
+        );
+        assert_eq!(hosts["second"], ["redis://cache:6379"]);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod allowed_hosts;
+pub mod audit;
 pub mod blocked_networks;
@@ -90,6 +90,11 @@ impl Router {
                         let based_route = sanitize_with_base(base, raw_route);
                         Some(Ok(RoutingEntry { based_route, raw_route, lookup_key }))
                     }
+                    HttpTriggerRouteConfig::Static(static_route) => {
+                        let raw_route = &static_route.route;
+                        let based_route = sanitize_with_base(base, raw_route);
+                        Some(Ok(RoutingEntry { based_route, raw_route, lookup_key }))
+                    }
                     HttpTriggerRouteConfig::Private(endpoint) => if endpoint.private {
                         None
                     } else {
@@ -434,6 +439,18 @@ pub enum HttpTriggerRouteConfig {
     Route(String),
     /// A route that is not routable, but indicates a private endpoint.
     Private(HttpPrivateEndpoint),
+    /// A route that serves static files directly from the host.
+    Static(HttpStaticRoute),
+}
+
+impl HttpTriggerRouteConfig {
+    /// Returns the static file configuration if this is a static route.
+    pub fn static_files(&self) -> Option<&HttpStaticRoute> {
+        match self {
+            Self::Static(static_route) => Some(static_route),
+            _ => None,
+        }
+    }
 }
 
 /// Indicates that a trigger is a private endpoint (not routable).
@@ -444,6 +461,63 @@ pub struct HttpPrivateEndpoint {
     pub private: bool,
 }
 
+/// A route which serves static files without instantiating a component.
+///
+/// Files are served either from a directory on the host or from the `files`
+/// mounted into a component. Exactly one of `directory` or `component_files`
+/// must be specified.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HttpStaticRoute {
+    /// The route under which files are served, e.g. `/assets/...`.
+    #[serde(rename = "static")]
+    pub route: String,
+    /// The directory to serve files from. Relative paths are resolved
+    /// against the directory containing the application manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// The ID of a component whose `files` mounts should be served.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component_files: Option<String>,
+    /// The file names to look for when a directory is requested.
+    #[serde(default = "default_index_files")]
+    pub index: Vec<String>,
+    /// A file, relative to the served root, to respond with when no file
+    /// matches the request path. This supports single-page applications
+    /// which do their own client-side routing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+    /// Whether to serve precompressed (`.br` or `.gz`) variants of files
+    /// to clients which accept them.
+    #[serde(default = "default_true")]
+    pub compress: bool,
+    /// The value of the `cache-control` header to send with files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+}
+
+impl Default for HttpStaticRoute {
+    fn default() -> Self {
+        Self {
+            route: Default::default(),
+            directory: None,
+            component_files: None,
+            index: default_index_files(),
+            fallback: None,
+            compress: default_true(),
+            cache_control: None,
+        }
+    }
+}
+
+fn default_index_files() -> Vec<String> {
+    vec!["index.html".to_owned()]
+}
+
+fn default_true() -> bool {
+    true
+}
+
 impl Default for HttpTriggerRouteConfig {
     fn default() -> Self {
         Self::Route(Default::default())
@@ -773,6 +847,31 @@ mod route_tests {
         assert!(e.to_string().contains("comp-bad component"));
     }
 
+    #[test]
+    fn static_routes_are_routed_to_trigger() {
+        let routes = Router::build(
+            "/base",
+            [
+                (&component_key("comp-/"), &"/...".into()),
+                (
+                    &trigger_key("assets"),
+                    &HttpTriggerRouteConfig::Static(HttpStaticRoute {
+                        route: "/assets/...".into(),
+                        directory: Some("dist".into()),
+                        ..Default::default()
+                    }),
+                ),
+            ],
+            None,
+        )
+        .unwrap();
+
+        let m = routes.route("/base/assets/css/site.css").unwrap();
+        assert_eq!(&trigger_key("assets"), m.lookup_key());
+        assert_eq!("/assets/...", m.raw_route());
+        assert_eq!("/css/site.css", m.trailing_wildcard());
+    }
+
     #[test]
     fn trailing_wildcard_is_captured() {
         let routes = component_router("/", [("comp", "/...")], None).unwrap();
@@ -22,7 +22,7 @@ use spin_factors::{
 };
 use spin_key_value_spin::{SpinKeyValueRuntimeConfig, SpinKeyValueStore};
 use spin_sqlite as sqlite;
-use spin_trigger::cli::UserProvidedPath;
+use spin_trigger::cli::{ComponentLogConfig, UserProvidedPath};
 use toml::Value;
 
 pub mod variables;
@@ -48,6 +48,8 @@ pub struct ResolvedRuntimeConfig<T> {
     ///
     /// `None` is used for an "unset" log directory.
     pub log_dir: Option<PathBuf>,
+    /// The format and rotation of component log files.
+    pub component_logs: ComponentLogConfig,
     /// The maximum memory allocation limit.
     pub max_instance_memory: Option<usize>,
     /// The input TOML, for informational summaries.
@@ -181,6 +183,7 @@ where
 
         let toml = toml_resolver.toml();
         let log_dir = toml_resolver.log_dir()?;
+        let component_logs = toml_resolver.component_logs()?;
         let max_instance_memory = toml_resolver.max_instance_memory()?;
 
         let source = TomlRuntimeConfigSource::new(
@@ -201,6 +204,7 @@ where
             sqlite_resolver,
             state_dir,
             log_dir,
+            component_logs,
             max_instance_memory,
             toml,
         })
@@ -216,6 +220,11 @@ where
         self.log_dir.clone()
     }
 
+    /// The format and rotation of component log files.
+    pub fn component_logs(&self) -> &ComponentLogConfig {
+        &self.component_logs
+    }
+
     /// The maximum memory allocation limit.
     pub fn max_instance_memory(&self) -> Option<usize> {
         self.max_instance_memory
@@ -314,6 +323,17 @@ impl<'a> TomlResolver<'a> {
         }
     }
 
+    /// Get the configured format and rotation of component log files.
+    pub fn component_logs(&self) -> anyhow::Result<ComponentLogConfig> {
+        let Some(table) = self.table.get("component_logs") else {
+            return Ok(Default::default());
+        };
+        table
+            .clone()
+            .try_into()
+            .context("invalid [component_logs] runtime config")
+    }
+
     /// Get the configured maximum memory allocation limit.
     pub fn max_instance_memory(&self) -> anyhow::Result<Option<usize>> {
         self.table
@@ -690,6 +710,34 @@ mod tests {
         resolve_toml(toml, "config.toml").unwrap();
     }
 
+    #[test]
+    fn component_logs_are_resolved() {
+        define_test_factor!(sqlite: SqliteFactor);
+
+        let toml = toml::toml! {
+            [component_logs]
+            format = "json"
+            max_size_mb = 10
+            max_files = 3
+        };
+        let resolved = resolve_toml(toml, "config.toml").unwrap();
+        assert_eq!(
+            resolved.component_logs(),
+            &ComponentLogConfig {
+                format: Some(spin_trigger::cli::ComponentLogFormat::Json),
+                max_size_mb: Some(10),
+                max_age_hours: None,
+                max_files: Some(3),
+            }
+        );
+
+        let toml = toml::toml! {
+            [component_logs]
+            max_size = 10
+        };
+        assert!(resolve_toml(toml, "config.toml").is_err());
+    }
+
     #[test]
     fn fails_to_resolve_with_unused_key() {
         define_test_factor!(sqlite: SqliteFactor);
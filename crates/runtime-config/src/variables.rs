@@ -1,32 +1,192 @@
+use std::time::Duration;
+
 use serde::Deserialize;
-use spin_expressions::Provider;
-use spin_factor_variables::runtime_config::RuntimeConfig;
+use spin_expressions::{CacheConfig, CachedProvider, Provider};
+use spin_factor_variables::runtime_config::{RefreshConfig, RuntimeConfig};
 use spin_factors::runtime_config::toml::GetTomlValue;
+use spin_variables_aws::{
+    AwsSecretsManagerProvider, AwsSecretsManagerVariablesConfig, AwsSsmProvider,
+    AwsSsmVariablesConfig,
+};
 use spin_variables_azure::{AzureKeyVaultProvider, AzureKeyVaultVariablesConfig};
+use spin_variables_directory::{DirectoryVariablesConfig, DirectoryVariablesProvider};
 use spin_variables_env::{EnvVariablesConfig, EnvVariablesProvider};
 use spin_variables_static::StaticVariablesProvider;
 use spin_variables_vault::VaultVariablesProvider;
 
 /// Resolves a runtime configuration for the variables factor from a TOML table.
 pub fn runtime_config_from_toml(table: &impl GetTomlValue) -> anyhow::Result<RuntimeConfig> {
-    // Always include the environment variable provider.
-    let var_provider = vec![Box::<EnvVariablesProvider>::default() as _];
+    let refresh = table
+        .get("variables_refresh")
+        .map(|value| -> anyhow::Result<RefreshConfig> {
+            value
+                .clone()
+                .try_into::<VariablesRefreshConfig>()?
+                .try_into()
+        })
+        .transpose()?;
+
+    let providers = described_providers_from_toml(table)?
+        .into_iter()
+        .map(|described| described.provider)
+        .collect();
+    Ok(RuntimeConfig { providers, refresh })
+}
+
+/// A variables provider with a description of where it gets values from.
+///
+/// The description is suitable for display: it does not include credentials.
+pub struct DescribedVariableProvider {
+    /// A description of the provider, e.g. `vault`.
+    pub description: String,
+    /// The provider.
+    pub provider: Box<dyn Provider>,
+}
+
+/// Resolves the variables providers configured in a TOML table, in order of
+/// precedence, including the default environment variable provider.
+pub fn described_providers_from_toml(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Vec<DescribedVariableProvider>> {
     let value = table
         .get("variables_provider")
         .or_else(|| table.get("config_provider"));
-    let Some(array) = value else {
-        return Ok(RuntimeConfig {
-            providers: var_provider,
-        });
+    let provider_configs: Vec<CachedVariableProviderConfiguration> = match value {
+        Some(array) => array.clone().try_into()?,
+        None => vec![],
     };
-
-    let provider_configs: Vec<VariableProviderConfiguration> = array.clone().try_into()?;
     let mut providers = provider_configs
         .into_iter()
-        .map(VariableProviderConfiguration::into_provider)
+        .map(|config| {
+            Ok(DescribedVariableProvider {
+                description: config.describe(),
+                provider: config.into_provider()?,
+            })
+        })
         .collect::<anyhow::Result<Vec<_>>>()?;
-    providers.extend(var_provider);
-    Ok(RuntimeConfig { providers })
+    // Always include the environment variable provider.
+    providers.push(DescribedVariableProvider {
+        description: "env (SPIN_VARIABLE_*, .env)".into(),
+        provider: Box::<EnvVariablesProvider>::default(),
+    });
+    Ok(providers)
+}
+
+/// Settings for re-resolving variables while the app is running.
+///
+/// When refresh is configured, variables can also be re-resolved with a
+/// `POST /variables/refresh` request to the admin server, if one is enabled
+/// with `SPIN_ADMIN_LISTEN_ADDRESS`.
+///
+/// ```toml
+/// [variables_refresh]
+/// interval_secs = 300
+/// on_signal = true
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariablesRefreshConfig {
+    /// Re-resolve variables on this schedule.
+    #[serde(default)]
+    interval_secs: Option<u64>,
+    /// Re-resolve variables when the process receives `SIGUSR1`.
+    #[serde(default)]
+    on_signal: bool,
+}
+
+impl TryFrom<VariablesRefreshConfig> for RefreshConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(config: VariablesRefreshConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            config.interval_secs != Some(0),
+            "variables_refresh interval_secs must be greater than zero"
+        );
+        anyhow::ensure!(
+            config.interval_secs.is_some() || config.on_signal,
+            "variables_refresh must set interval_secs or on_signal"
+        );
+        Ok(RefreshConfig {
+            interval: config.interval_secs.map(Duration::from_secs),
+            on_signal: config.on_signal,
+        })
+    }
+}
+
+/// A variable provider configuration with optional caching of its values.
+#[derive(Debug, Deserialize)]
+pub struct CachedVariableProviderConfiguration {
+    /// The provider configuration.
+    #[serde(flatten)]
+    pub provider: VariableProviderConfiguration,
+    /// If set, values from the provider are cached.
+    #[serde(default)]
+    pub cache: Option<VariableCacheConfig>,
+}
+
+impl CachedVariableProviderConfiguration {
+    /// Returns a description of the provider which does not include credentials.
+    pub fn describe(&self) -> String {
+        match &self.cache {
+            Some(cache) => format!(
+                "{} (cached for {}s)",
+                self.provider.describe(),
+                cache.ttl_secs
+            ),
+            None => self.provider.describe(),
+        }
+    }
+
+    /// Returns the provider for the configuration, wrapped in a cache if configured.
+    pub fn into_provider(self) -> anyhow::Result<Box<dyn Provider>> {
+        let provider = self.provider.into_provider()?;
+        Ok(match self.cache {
+            Some(cache) => Box::new(CachedProvider::new(provider, cache.try_into()?)),
+            None => provider,
+        })
+    }
+}
+
+/// Caching settings for a variable provider.
+///
+/// ```toml
+/// [[variables_provider]]
+/// type = "vault"
+/// # ...
+/// cache = { ttl_secs = 300, max_stale_secs = 3600 }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariableCacheConfig {
+    /// How long values are cached before being refreshed.
+    ttl_secs: u64,
+    /// How long past the TTL values may be served while refreshing or if the
+    /// provider fails. If omitted, stale values are served indefinitely.
+    #[serde(default)]
+    max_stale_secs: Option<u64>,
+    /// Whether to refresh expired values in the background. Defaults to true.
+    #[serde(default = "default_true")]
+    background_refresh: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl TryFrom<VariableCacheConfig> for CacheConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(config: VariableCacheConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            config.ttl_secs > 0,
+            "variables provider cache ttl_secs must be greater than zero"
+        );
+        Ok(CacheConfig {
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_stale: config.max_stale_secs.map(Duration::from_secs),
+            background_refresh: config.background_refresh,
+        })
+    }
 }
 
 /// A runtime configuration used in the Spin CLI for one type of variable provider.
@@ -35,15 +195,49 @@ pub fn runtime_config_from_toml(table: &impl GetTomlValue) -> anyhow::Result<Run
 pub enum VariableProviderConfiguration {
     /// A provider that uses Azure Key Vault.
     AzureKeyVault(AzureKeyVaultVariablesConfig),
+    /// A provider that uses AWS Secrets Manager.
+    AwsSecretsManager(AwsSecretsManagerVariablesConfig),
+    /// A provider that uses AWS Systems Manager Parameter Store.
+    AwsSsm(AwsSsmVariablesConfig),
     /// A static provider of variables.
     Static(StaticVariablesProvider),
     /// A provider that uses HashiCorp Vault.
     Vault(VaultVariablesProvider),
     /// An environment variable provider.
     Env(EnvVariablesConfig),
+    /// A provider that reads variables from the files in a directory.
+    Directory(DirectoryVariablesConfig),
 }
 
 impl VariableProviderConfiguration {
+    /// Returns a description of the provider which does not include credentials.
+    pub fn describe(&self) -> String {
+        match self {
+            VariableProviderConfiguration::AzureKeyVault(config) => {
+                format!("azure_key_vault ({})", config.vault_url)
+            }
+            VariableProviderConfiguration::AwsSecretsManager(config) => {
+                match config.region.as_deref() {
+                    Some(region) => format!("aws_secrets_manager ({region})"),
+                    None => "aws_secrets_manager".into(),
+                }
+            }
+            VariableProviderConfiguration::AwsSsm(config) => match config.region.as_deref() {
+                Some(region) => format!("aws_ssm ({region})"),
+                None => "aws_ssm".into(),
+            },
+            VariableProviderConfiguration::Static(_) => "static".into(),
+            VariableProviderConfiguration::Vault(_) => "vault".into(),
+            VariableProviderConfiguration::Env(config) => format!(
+                "env ({}_*)",
+                config.prefix.as_deref().unwrap_or("SPIN_VARIABLE")
+            ),
+            VariableProviderConfiguration::Directory(config) => {
+                format!("directory ({})", config.path.display())
+            }
+        }
+    }
+
     /// Returns the provider for the configuration.
     pub fn into_provider(self) -> anyhow::Result<Box<dyn Provider>> {
         let provider: Box<dyn Provider> = match self {
@@ -57,7 +251,100 @@ impl VariableProviderConfiguration {
             VariableProviderConfiguration::AzureKeyVault(config) => Box::new(
                 AzureKeyVaultProvider::create(config.vault_url.clone(), config.try_into()?)?,
             ),
+            VariableProviderConfiguration::AwsSecretsManager(config) => {
+                Box::new(AwsSecretsManagerProvider::new(config)?)
+            }
+            VariableProviderConfiguration::AwsSsm(config) => Box::new(AwsSsmProvider::new(config)?),
+            VariableProviderConfiguration::Directory(config) => {
+                Box::new(DirectoryVariablesProvider::new(config)?)
+            }
         };
         Ok(provider)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn providers_can_be_cached() {
+        let toml = toml::toml! {
+            [[variables_provider]]
+            type = "static"
+            values = { foo = "bar" }
+            cache = { ttl_secs = 30 }
+
+            [[variables_provider]]
+            type = "static"
+            values = { baz = "qux" }
+        };
+        let config = runtime_config_from_toml(&toml).unwrap();
+        let providers = config
+            .providers
+            .iter()
+            .map(|p| format!("{p:?}"))
+            .collect::<Vec<_>>();
+        assert_eq!(providers.len(), 3);
+        assert!(providers[0].starts_with("CachedProvider"));
+        assert!(!providers[1].starts_with("CachedProvider"));
+    }
+
+    #[test]
+    fn zero_cache_ttl_is_rejected() {
+        let toml = toml::toml! {
+            [[variables_provider]]
+            type = "static"
+            values = { foo = "bar" }
+            cache = { ttl_secs = 0 }
+        };
+        assert!(runtime_config_from_toml(&toml).is_err());
+    }
+
+    #[test]
+    fn aws_providers_are_configured() {
+        let toml = toml::toml! {
+            [[variables_provider]]
+            type = "aws_secrets_manager"
+            region = "us-east-1"
+            endpoint_url = "http://localhost:4566"
+            prefix = "my-app/"
+            json_key = "value"
+
+            [[variables_provider]]
+            type = "aws_ssm"
+            prefix = "/my-app/"
+        };
+        let config = runtime_config_from_toml(&toml).unwrap();
+        assert_eq!(config.providers.len(), 3);
+
+        let toml = toml::toml! {
+            [[variables_provider]]
+            type = "aws_ssm"
+            access_key = "only-half"
+        };
+        assert!(runtime_config_from_toml(&toml).is_err());
+    }
+
+    #[test]
+    fn refresh_is_configured() {
+        let toml = toml::toml! {
+            [variables_refresh]
+            interval_secs = 60
+        };
+        let config = runtime_config_from_toml(&toml).unwrap();
+        assert_eq!(
+            config.refresh,
+            Some(RefreshConfig {
+                interval: Some(Duration::from_secs(60)),
+                on_signal: false,
+            })
+        );
+
+        let toml = toml::toml! {
+            [variables_refresh]
+            on_signal = false
+        };
+        assert!(runtime_config_from_toml(&toml).is_err());
+    }
+}
@@ -6,9 +6,10 @@ use anyhow::Context as _;
 use spin_factors_executor::FactorsExecutor;
 use spin_runtime_config::ResolvedRuntimeConfig;
 use spin_trigger::cli::{
-    FactorsConfig, InitialKvSetterHook, KeyValueDefaultStoreSummaryHook, MaxInstanceMemoryHook,
-    RuntimeFactorsBuilder, SqlStatementExecutorHook, SqliteDefaultStoreSummaryHook,
-    StdioLoggingExecutorHooks, VariablesValidatorHook,
+    AllowedHostsAuditHook, FactorsConfig, InitialKvSetterHook, KeyValueDefaultStoreSummaryHook,
+    MaxInstanceMemoryHook, RuntimeFactorsBuilder, SqlStatementExecutorHook,
+    SqliteDefaultStoreSummaryHook, StdioLoggingExecutorHooks, VariablesRefreshHook,
+    VariablesValidatorHook,
 };
 use spin_variables_static::StaticVariablesProvider;
 
@@ -49,7 +50,7 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
         // This is a hack b/c we know the version of this crate will be the same as the version of Spin
         let spin_version = env!("CARGO_PKG_VERSION");
 
-        let factors = TriggerFactors::new(
+        let mut factors = TriggerFactors::new(
             runtime_config.state_dir(),
             config.working_dir.clone(),
             args.allow_transient_write,
@@ -57,6 +58,14 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
             spin_version,
         )
         .context("failed to create factors")?;
+
+        if args.audit_outbound_hosts.is_some() {
+            terminal::warn!(
+                "Auditing outbound network destinations: 'allowed_outbound_hosts' will not be enforced."
+            );
+            factors.outbound_networking.enable_allowed_hosts_audit();
+        }
+
         Ok((factors, runtime_config))
     }
 
@@ -70,6 +79,10 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
             config.follow_components.clone(),
             runtime_config.log_dir(),
             config.truncate_logs,
+            config
+                .component_logs
+                .clone()
+                .or(runtime_config.component_logs().clone()),
         ));
         executor.add_hooks(SqlStatementExecutorHook::new(
             args.sqlite_statements.clone(),
@@ -78,6 +91,10 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
         executor.add_hooks(SqliteDefaultStoreSummaryHook);
         executor.add_hooks(KeyValueDefaultStoreSummaryHook);
         executor.add_hooks(VariablesValidatorHook);
+        executor.add_hooks(VariablesRefreshHook::default());
+        if let Some(output) = &args.audit_outbound_hosts {
+            executor.add_hooks(AllowedHostsAuditHook::new(output.clone()));
+        }
 
         let max_instance_memory = args
             .max_instance_memory
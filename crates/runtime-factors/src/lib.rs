@@ -121,6 +121,17 @@ pub struct TriggerAppArgs {
     #[clap(long, env = "SPIN_MAX_INSTANCE_MEMORY")]
     pub max_instance_memory: Option<usize>,
 
+    /// Audit outbound network destinations instead of enforcing
+    /// `allowed_outbound_hosts`. All outbound connections are allowed and the
+    /// attempted destinations are recorded per component.
+    ///
+    /// On shutdown, the minimal `allowed_outbound_hosts` that would have permitted
+    /// them are printed, or written as manifest TOML to FILE if one is given.
+    /// `spin up` empties FILE when it starts, and each of the app's triggers
+    /// then adds its destinations to it.
+    #[clap(long, value_name = "FILE", require_equals = true)]
+    pub audit_outbound_hosts: Option<Option<PathBuf>>,
+
     /// Variable(s) to be passed to the app
     ///
     /// A single key-value pair can be passed as `key=value`, or `key=@file` to
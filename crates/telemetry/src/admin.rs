@@ -0,0 +1,216 @@
+//! The admin server, enabled by setting `SPIN_ADMIN_LISTEN_ADDRESS`.
+//!
+//! The server is configured when telemetry is initialized but only runs once [`start`] is called,
+//! which only the process running an app's triggers does. It serves metrics in the Prometheus exposition format at `/metrics`, and runs the actions
+//! registered with [`register_action`] in response to `POST` requests to their paths.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, LazyLock, Mutex, OnceLock},
+};
+
+use anyhow::Context;
+use http_body_util::Full;
+use http1::{Method, Request, Response, StatusCode, header::CONTENT_TYPE};
+use hyper::{body::Bytes, server::conn::http1 as server, service::service_fn};
+use hyper_util::rt::TokioIo;
+use prometheus::{Encoder, Registry, TextEncoder};
+use tokio::net::TcpListener;
+
+/// An action run by a `POST` request to the admin server. On success, the returned message is
+/// sent as the response body.
+pub type AdminAction =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = anyhow::Result<String>> + Send>> + Send + Sync>;
+
+static ACTIONS: LazyLock<Mutex<HashMap<String, AdminAction>>> = LazyLock::new(Default::default);
+
+/// The address to serve on and the registry to serve metrics from, if the server is enabled.
+static CONFIG: OnceLock<(SocketAddr, Registry)> = OnceLock::new();
+
+/// Registers an action to run when the admin server receives a `POST` request to `path`,
+/// replacing any action already registered there.
+///
+/// Actions can be registered whether or not the admin server is enabled.
+pub fn register_action(path: impl Into<String>, action: AdminAction) {
+    ACTIONS.lock().unwrap().insert(path.into(), action);
+}
+
+/// Removes the action registered at `path`, if any.
+pub fn unregister_action(path: &str) {
+    ACTIONS.lock().unwrap().remove(path);
+}
+
+/// Enables the admin server, to be started by [`start`].
+pub(crate) fn configure(address: SocketAddr, registry: Registry) {
+    if CONFIG.set((address, registry)).is_err() {
+        tracing::warn!("The admin server is already configured");
+    }
+}
+
+/// Starts the admin server if `SPIN_ADMIN_LISTEN_ADDRESS` enabled it, and does nothing otherwise.
+///
+/// This should only be called by the process which runs the app's triggers: the server reports
+/// the metrics recorded in the calling process, and other Spin processes (such as `spin up`,
+/// which shares its environment with the trigger process) would contend for the address. If the
+/// address can't be bound, for instance because another trigger process of the same app already
+/// serves on it, an error is returned and no server runs.
+pub fn start() -> anyhow::Result<()> {
+    match CONFIG.get() {
+        Some((address, registry)) => serve(*address, registry.clone()),
+        None => Ok(()),
+    }
+}
+
+/// Serves the admin server on `address`.
+///
+/// The address is bound before returning so that a bad address is reported at startup; the server
+/// itself runs in the background for the lifetime of the process.
+fn serve(address: SocketAddr, registry: Registry) -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind(address)
+        .with_context(|| format!("failed to bind admin server to {address}"))?;
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("Error accepting admin connection: {err:?}");
+                    continue;
+                }
+            };
+            let registry = registry.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let registry = registry.clone();
+                    async move { Ok::<_, Infallible>(handle(&registry, request).await) }
+                });
+                if let Err(err) = server::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::debug!("Error serving admin connection: {err:?}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+async fn handle<B>(registry: &Registry, request: Request<B>) -> Response<Full<Bytes>> {
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => metrics(registry),
+        (&Method::POST, path) => {
+            let action = ACTIONS.lock().unwrap().get(path).cloned();
+            match action {
+                Some(action) => run_action(path, action).await,
+                None => plain_response(StatusCode::NOT_FOUND, "not found"),
+            }
+        }
+        _ => plain_response(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+fn metrics(registry: &Registry) -> Response<Full<Bytes>> {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    if let Err(err) = encoder.encode(&registry.gather(), &mut body) {
+        tracing::warn!("Error encoding metrics: {err:?}");
+        return plain_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to encode metrics",
+        );
+    }
+    Response::builder()
+        .header(CONTENT_TYPE, encoder.format_type())
+        .body(Full::new(body.into()))
+        .unwrap()
+}
+
+async fn run_action(path: &str, action: AdminAction) -> Response<Full<Bytes>> {
+    match action().await {
+        Ok(message) => Response::new(Full::new(message.into())),
+        Err(err) => {
+            tracing::warn!("Admin action {path} failed: {err:?}");
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Full::new(format!("{err:#}").into()))
+                .unwrap()
+        }
+    }
+}
+
+fn plain_response(status: StatusCode, message: &'static str) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::from_static(message.as_bytes())))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    fn request(method: Method, path: &str) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_run_registered_actions() {
+        let registry = Registry::new();
+        let path = "/test/action";
+        let response = handle(&registry, request(Method::POST, path)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        register_action(path, Arc::new(|| Box::pin(async { Ok("done".into()) })));
+        let response = handle(&registry, request(Method::POST, path)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "done");
+
+        let response = handle(&registry, request(Method::GET, path)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        register_action(
+            path,
+            Arc::new(|| Box::pin(async { Err(anyhow::anyhow!("broken")) })),
+        );
+        let response = handle(&registry, request(Method::POST, path)).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        unregister_action(path);
+        let response = handle(&registry, request(Method::POST, path)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn serves_prometheus_metrics() -> anyhow::Result<()> {
+        let registry = Registry::new();
+        let counter = prometheus::IntCounter::new("test_requests", "Test requests")?;
+        registry.register(Box::new(counter.clone()))?;
+        counter.inc_by(3);
+
+        let address = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        serve(address, registry)?;
+
+        let response = reqwest::get(format!("http://{address}/metrics")).await?;
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        assert!(response.text().await?.contains("test_requests 3"));
+
+        // A bad address is reported rather than failing in the background.
+        assert!(serve(address, Registry::new()).is_err());
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
 use std::env::VarError;
+use std::net::SocketAddr;
+
+use anyhow::Context;
 
 use opentelemetry_otlp::{
     OTEL_EXPORTER_OTLP_ENDPOINT, OTEL_EXPORTER_OTLP_LOGS_ENDPOINT,
@@ -11,6 +14,7 @@ const OTEL_EXPORTER_OTLP_TRACES_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_TRACES_PROT
 const OTEL_EXPORTER_OTLP_METRICS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL";
 const OTEL_EXPORTER_OTLP_LOGS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_LOGS_PROTOCOL";
 const SPIN_DISABLE_LOG_TO_TRACING: &str = "SPIN_DISABLE_LOG_TO_TRACING";
+const SPIN_ADMIN_LISTEN_ADDRESS: &str = "SPIN_ADMIN_LISTEN_ADDRESS";
 
 /// Returns a boolean indicating if the OTEL tracing layer should be enabled.
 ///
@@ -54,6 +58,32 @@ pub fn otel_logs_enabled() -> bool {
     ]) && !otel_sdk_disabled()
 }
 
+/// Returns the address of the admin server, which exposes metrics for Prometheus to scrape at
+/// `/metrics`.
+///
+/// The admin server is enabled if the environment variable `SPIN_ADMIN_LISTEN_ADDRESS` is set and
+/// not empty.
+///
+/// Note that this is overridden if OTEL_SDK_DISABLED is set and not empty.
+pub fn admin_listen_address() -> anyhow::Result<Option<SocketAddr>> {
+    if otel_sdk_disabled() {
+        return Ok(None);
+    }
+    match std::env::var(SPIN_ADMIN_LISTEN_ADDRESS) {
+        Ok(address) if !address.is_empty() => address
+            .parse()
+            .map(Some)
+            .with_context(|| format!("invalid {SPIN_ADMIN_LISTEN_ADDRESS} '{address}'")),
+        _ => Ok(None),
+    }
+}
+
+/// Returns a boolean indicating if `SPIN_ADMIN_LISTEN_ADDRESS` is set, even if the admin server
+/// is disabled by OTEL_SDK_DISABLED.
+pub(crate) fn admin_listen_address_set() -> bool {
+    any_vars_set(&[SPIN_ADMIN_LISTEN_ADDRESS])
+}
+
 /// Returns a boolean indicating if the compatibility layer that emits tracing events from
 /// applications logs should be disabled.
 ///
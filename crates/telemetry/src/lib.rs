@@ -1,18 +1,23 @@
 use std::io::IsTerminal;
 
 use anyhow::Context;
+use env::admin_listen_address;
+use env::admin_listen_address_set;
 use env::otel_logs_enabled;
 use env::otel_metrics_enabled;
+use env::otel_sdk_disabled;
 use env::otel_tracing_enabled;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
 use tracing_subscriber::{EnvFilter, Layer, fmt, prelude::*, registry};
 
+pub mod admin;
 mod alert_in_dev;
 pub mod detector;
 pub mod env;
 pub mod logs;
 pub mod metrics;
 mod propagation;
+pub mod redaction;
 pub mod traces;
 
 #[cfg(feature = "testing")]
@@ -28,7 +33,9 @@ pub use propagation::inject_trace_context;
 /// [Layer] emits [tracing] events to stderr, another sends spans to an OTel collector, and another
 /// sends metrics to an OTel collector.
 ///
-/// Configuration for the OTel layers is pulled from the environment.
+/// Configuration for the OTel layers is pulled from the environment. Metrics may also be exposed
+/// for Prometheus to scrape by setting `SPIN_ADMIN_LISTEN_ADDRESS` (see
+/// [`env::admin_listen_address`]).
 ///
 /// Examples of emitting traces from Spin:
 ///
@@ -60,7 +67,7 @@ pub use propagation::inject_trace_context;
 pub fn init(spin_version: String, histogram_buckets: Vec<HistogramBuckets>) -> anyhow::Result<()> {
     // This layer will print all tracing library log messages to stderr.
     let fmt_layer = fmt::layer()
-        .with_writer(std::io::stderr)
+        .with_writer(|| redaction::RedactingWriter(std::io::stderr()))
         .with_ansi(std::io::stderr().is_terminal())
         .with_filter(
             // Filter directives explained here https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html#directives
@@ -83,9 +90,10 @@ pub fn init(spin_version: String, histogram_buckets: Vec<HistogramBuckets>) -> a
         None
     };
 
-    let otel_metrics_layer = if otel_metrics_enabled() {
+    let admin_address = admin_listen_address()?;
+    let otel_metrics_layer = if otel_metrics_enabled() || admin_address.is_some() {
         Some(
-            metrics::otel_metrics_layer(spin_version.clone(), histogram_buckets)
+            metrics::otel_metrics_layer(spin_version.clone(), histogram_buckets, admin_address)
                 .context("failed to initialize otel metrics")?,
         )
     } else {
@@ -102,6 +110,12 @@ pub fn init(spin_version: String, histogram_buckets: Vec<HistogramBuckets>) -> a
         .with(alert_in_dev_layer)
         .init();
 
+    if otel_sdk_disabled() && admin_listen_address_set() {
+        tracing::warn!(
+            "SPIN_ADMIN_LISTEN_ADDRESS is ignored because OTEL_SDK_DISABLED is set; the admin server will not be started"
+        );
+    }
+
     // Used to propagate trace information in the standard W3C TraceContext format. Even if the otel
     // layer is disabled we still want to propagate trace context.
     opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
@@ -19,9 +19,12 @@ static LOGGER: OnceLock<SdkLogger> = OnceLock::new();
 
 /// Handle an application log. Has the potential to both forward the log to OTel and to emit it as a
 /// tracing event.
+///
+/// Registered secret values are redacted from the log.
 pub fn handle_app_log(buf: &[u8], component_id: &str) {
-    app_log_to_otel(buf, component_id);
-    app_log_to_tracing_event(buf);
+    let buf = crate::redaction::redact_bytes(buf);
+    app_log_to_otel(&buf, component_id);
+    app_log_to_tracing_event(&buf);
 }
 
 /// Forward the app log to OTel.
@@ -1,3 +1,5 @@
+use std::{net::SocketAddr, sync::Arc, time::Instant};
+
 use anyhow::{Result, bail};
 use opentelemetry::global;
 use opentelemetry_otlp::WithHttpConfig;
@@ -14,7 +16,10 @@ use tracing::Subscriber;
 use tracing_opentelemetry::MetricsLayer;
 use tracing_subscriber::{Layer, registry::LookupSpan};
 
-use crate::{detector::SpinResourceDetector, env::OtlpProtocol};
+use crate::{
+    detector::SpinResourceDetector,
+    env::{OtlpProtocol, otel_metrics_enabled},
+};
 
 /// A custom histogram bucketing for a named metric.
 ///
@@ -30,7 +35,9 @@ pub struct HistogramBuckets {
     pub boundaries: Vec<f64>,
 }
 
-/// Constructs a layer for the tracing subscriber that sends metrics to an OTEL collector.
+/// Constructs a layer for the tracing subscriber that sends metrics to an OTEL collector and/or
+/// exposes them for Prometheus to scrape from the admin server at `admin_address` once it is
+/// started with [`crate::admin::start`].
 ///
 /// It pulls OTEL configuration from the environment based on the variables defined
 /// [here](https://opentelemetry.io/docs/specs/otel/protocol/exporter/) and
@@ -38,6 +45,7 @@ pub struct HistogramBuckets {
 pub(crate) fn otel_metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
     spin_version: String,
     histogram_buckets: Vec<HistogramBuckets>,
+    admin_address: Option<SocketAddr>,
 ) -> Result<impl Layer<S>> {
     let resource = Resource::builder()
         .with_detectors(&[
@@ -51,25 +59,38 @@ pub(crate) fn otel_metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
         ])
         .build();
 
-    // This will configure the exporter based on the OTEL_EXPORTER_* environment variables. We
-    // currently default to using the HTTP exporter but in the future we could select off of the
-    // combination of OTEL_EXPORTER_OTLP_PROTOCOL and OTEL_EXPORTER_OTLP_TRACES_PROTOCOL to
-    // determine whether we should use http/protobuf or grpc.
-    let exporter = match OtlpProtocol::metrics_protocol_from_env() {
-        OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
-            .with_tonic()
-            .build()?,
-        OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
-            .with_http()
-            .with_http_client(crate::rustls_reqwest_client()?)
-            .build()?,
-        OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
-    };
-
-    let reader = PeriodicReader::builder(exporter, Tokio).build();
-    let mut provider_builder = SdkMeterProvider::builder()
-        .with_reader(reader)
-        .with_resource(resource);
+    let mut provider_builder = SdkMeterProvider::builder().with_resource(resource);
+
+    if otel_metrics_enabled() {
+        // This will configure the exporter based on the OTEL_EXPORTER_* environment variables. We
+        // currently default to using the HTTP exporter but in the future we could select off of the
+        // combination of OTEL_EXPORTER_OTLP_PROTOCOL and OTEL_EXPORTER_OTLP_TRACES_PROTOCOL to
+        // determine whether we should use http/protobuf or grpc.
+        let exporter = match OtlpProtocol::metrics_protocol_from_env() {
+            OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
+                .with_tonic()
+                .build()?,
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .with_http_client(crate::rustls_reqwest_client()?)
+                .build()?,
+            OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
+        };
+        provider_builder =
+            provider_builder.with_reader(PeriodicReader::builder(exporter, Tokio).build());
+    }
+
+    if let Some(admin_address) = admin_address {
+        // The Prometheus exporter is a pull-based reader: the same instruments are collected
+        // into the registry whenever `/metrics` is scraped.
+        let registry = prometheus::Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()?;
+        provider_builder = provider_builder.with_reader(exporter);
+        crate::admin::configure(admin_address, registry);
+    }
+
     // Apply any caller-supplied histogram bucket overrides as views. This crate stays agnostic
     // about which metrics need custom boundaries — the owning crate describes them.
     for buckets in histogram_buckets {
@@ -160,3 +181,136 @@ pub use counter;
 pub use gauge;
 pub use histogram;
 pub use monotonic_counter;
+
+/// Standard metrics for the operations components perform against data stores.
+///
+/// Metrics are tagged with the data system (e.g. `sqlite`), the component ID
+/// and the store: the store or database label for key-value and SQLite, or the
+/// server address for outbound databases.
+#[derive(Clone, Debug)]
+pub struct DataMetrics {
+    system: &'static str,
+    component_id: Arc<str>,
+    store: Arc<str>,
+}
+
+impl DataMetrics {
+    pub fn new(system: &'static str, component_id: &str, store: &str) -> Self {
+        Self {
+            system,
+            component_id: component_id.into(),
+            store: store.into(),
+        }
+    }
+
+    /// Records the duration of an operation and, if it failed, the type of
+    /// error.
+    pub fn record_operation(
+        &self,
+        operation: &'static str,
+        started: Instant,
+        error_type: Option<&str>,
+    ) {
+        histogram!(
+            spin.data.operation_duration = started.elapsed().as_secs_f64(),
+            db_system = self.system,
+            component_id = &*self.component_id,
+            store = &*self.store,
+            operation = operation,
+            // According to the OpenTelemetry spec, instruments measuring durations should use "s" as the unit.
+            unit = "s"
+        );
+        if let Some(error_type) = error_type {
+            monotonic_counter!(
+                spin.data.operation_errors = 1u64,
+                db_system = self.system,
+                component_id = &*self.component_id,
+                store = &*self.store,
+                operation = operation,
+                error_type = error_type
+            );
+        }
+    }
+
+    /// Runs an operation, recording its duration and, if it fails, the error
+    /// type given by `error_type`.
+    pub async fn time<T, E>(
+        &self,
+        operation: &'static str,
+        error_type: impl FnOnce(&E) -> &'static str,
+        op: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let started = Instant::now();
+        let result = op.await;
+        self.record_operation(operation, started, result.as_ref().err().map(error_type));
+        result
+    }
+
+    /// Records bytes received from the store.
+    pub fn record_bytes_read(&self, bytes: usize) {
+        if bytes > 0 {
+            monotonic_counter!(
+                spin.data.bytes_read = bytes as u64,
+                db_system = self.system,
+                component_id = &*self.component_id,
+                store = &*self.store,
+                unit = "By"
+            );
+        }
+    }
+
+    /// Records bytes sent to the store.
+    pub fn record_bytes_written(&self, bytes: usize) {
+        if bytes > 0 {
+            monotonic_counter!(
+                spin.data.bytes_written = bytes as u64,
+                db_system = self.system,
+                component_id = &*self.component_id,
+                store = &*self.store,
+                unit = "By"
+            );
+        }
+    }
+
+    /// Records a connection being opened. The connection is recorded as
+    /// closed when the returned guard is dropped.
+    pub fn connection_opened(&self) -> OpenConnectionMetric {
+        self.record_open_connections(1);
+        OpenConnectionMetric(self.clone())
+    }
+
+    /// Records the usage of the host connection pool serving the store.
+    pub fn record_pool_usage(&self, size: usize, available: usize) {
+        gauge!(
+            spin.data.pool_connections = size as u64,
+            db_system = self.system,
+            component_id = &*self.component_id,
+            store = &*self.store
+        );
+        gauge!(
+            spin.data.pool_connections_idle = available as u64,
+            db_system = self.system,
+            component_id = &*self.component_id,
+            store = &*self.store
+        );
+    }
+
+    fn record_open_connections(&self, delta: i64) {
+        counter!(
+            spin.data.open_connections = delta,
+            db_system = self.system,
+            component_id = &*self.component_id,
+            store = &*self.store
+        );
+    }
+}
+
+/// Tracks an open connection in the `spin.data.open_connections` metric.
+#[derive(Debug)]
+pub struct OpenConnectionMetric(DataMetrics);
+
+impl Drop for OpenConnectionMetric {
+    fn drop(&mut self) {
+        self.0.record_open_connections(-1);
+    }
+}
@@ -1,5 +1,19 @@
-use tracing::level_filters::LevelFilter;
-use tracing_subscriber::fmt::format::FmtSpan;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    subscriber::DefaultGuard,
+};
+use tracing_subscriber::{
+    Layer,
+    fmt::format::FmtSpan,
+    layer::{Context, SubscriberExt},
+};
 
 /// Initializes telemetry integration for libtest environments.
 pub fn init_test_telemetry() {
@@ -15,3 +29,90 @@ pub fn init_test_telemetry() {
         }
     });
 }
+
+/// A metric emitted with one of the [`crate::metrics`] macros.
+#[derive(Clone, Debug)]
+pub struct RecordedMetric {
+    /// The kind of metric, e.g. `histogram`.
+    pub kind: String,
+    /// The name of the metric, e.g. `spin.data.operation_duration`.
+    pub name: String,
+    pub value: String,
+    pub attributes: HashMap<String, String>,
+}
+
+/// Metrics emitted on the current thread since [`record_metrics`] was called.
+#[derive(Clone, Default)]
+pub struct RecordedMetrics(Arc<Mutex<Vec<RecordedMetric>>>);
+
+impl RecordedMetrics {
+    /// Returns the recorded metrics with the given name.
+    pub fn named(&self, name: &str) -> Vec<RecordedMetric> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|metric| metric.name == name)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Records the metrics emitted on the current thread until the returned guard
+/// is dropped.
+pub fn record_metrics() -> (RecordedMetrics, DefaultGuard) {
+    let metrics = RecordedMetrics::default();
+    let subscriber = tracing_subscriber::registry().with(MetricsRecorder(metrics.clone()));
+    (metrics, tracing::subscriber::set_default(subscriber))
+}
+
+struct MetricsRecorder(RecordedMetrics);
+
+impl<S: Subscriber> Layer<S> for MetricsRecorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MetricVisitor::default();
+        event.record(&mut visitor);
+        if let Some(metric) = visitor.metric {
+            (self.0).0.lock().unwrap().push(RecordedMetric {
+                attributes: visitor.attributes,
+                ..metric
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct MetricVisitor {
+    metric: Option<RecordedMetric>,
+    attributes: HashMap<String, String>,
+}
+
+impl Visit for MetricVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format!("{value:?}"));
+    }
+}
+
+impl MetricVisitor {
+    fn record(&mut self, field: &Field, value: String) {
+        const KINDS: &[&str] = &["monotonic_counter", "counter", "histogram", "gauge"];
+        let metric = field.name().split_once('.').and_then(|(kind, name)| {
+            KINDS.contains(&kind).then(|| RecordedMetric {
+                kind: kind.to_owned(),
+                name: name.to_owned(),
+                value: value.clone(),
+                attributes: HashMap::new(),
+            })
+        });
+        match metric {
+            Some(metric) => self.metric = Some(metric),
+            None => {
+                self.attributes.insert(field.name().to_owned(), value);
+            }
+        }
+    }
+}
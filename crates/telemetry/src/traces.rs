@@ -53,7 +53,7 @@ pub(crate) fn otel_tracing_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
 
     let tracer_provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
         .with_resource(resource)
-        .with_span_processor(span_processor)
+        .with_span_processor(crate::redaction::RedactingSpanProcessor(span_processor))
         .build();
 
     global::set_tracer_provider(tracer_provider.clone());
@@ -98,3 +98,13 @@ pub fn mark_as_error<E: std::fmt::Display>(err: &E, blame: Option<Blame>) {
         current_span.set_attribute("error.blame", blame.as_str());
     }
 }
+
+/// Returns the OTel trace ID of the current span, if it belongs to a valid trace.
+pub fn current_trace_id() -> Option<String> {
+    use opentelemetry::trace::TraceContextExt as _;
+    let context = tracing::Span::current().context();
+    let span_context = context.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
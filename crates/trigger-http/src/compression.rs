@@ -0,0 +1,571 @@
+//! Response compression and request decompression for routes which opt in.
+
+use std::{
+    io::{self, Write},
+    mem,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode, header};
+use http_body::{Body as _, Frame};
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+use spin_http::config::CompressionConfig;
+use wasmtime_wasi_http::p2::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// The brotli quality used for responses. Higher levels compress better but
+/// are too slow to apply to every response on the fly.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// The most a request body may grow to when it is decompressed, if the route
+/// has no smaller body size limit. This protects the server from highly
+/// compressed "decompression bombs".
+const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A content coding the server can compress responses with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+}
+
+/// Chooses a response encoding from the request's `Accept-Encoding` header.
+///
+/// The encoding with the highest quality value wins, with brotli preferred
+/// over gzip on ties. Returns `None` if the client accepts neither, or if the
+/// request is one whose response has no body.
+pub(crate) fn negotiate(method: &Method, headers: &HeaderMap) -> Option<Encoding> {
+    if method == Method::HEAD {
+        return None;
+    }
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut wildcard = None;
+    for item in headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+    {
+        let mut parts = item.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let quality = parts
+            .find_map(|param| param.strip_prefix("q="))
+            .and_then(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match name.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "*" => wildcard = Some(quality),
+            _ => {}
+        }
+    }
+    let brotli = brotli.or(wildcard).unwrap_or(0.0);
+    let gzip = gzip.or(wildcard).unwrap_or(0.0);
+    if brotli <= 0.0 && gzip <= 0.0 {
+        None
+    } else if brotli >= gzip {
+        Some(Encoding::Brotli)
+    } else {
+        Some(Encoding::Gzip)
+    }
+}
+
+/// Compresses `response` with `encoding` if it is eligible under `config`.
+pub(crate) fn compress_response(
+    response: Response<Body>,
+    config: &CompressionConfig,
+    encoding: Encoding,
+) -> Response<Body> {
+    if !should_compress(&response, config) {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.name()),
+    );
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    // The compressed representation is no longer byte-for-byte identical.
+    if let Some(etag) = parts.headers.get(header::ETAG)
+        && !etag.as_bytes().starts_with(b"W/")
+    {
+        let mut weak = b"W/".to_vec();
+        weak.extend_from_slice(etag.as_bytes());
+        if let Ok(weak) = HeaderValue::from_bytes(&weak) {
+            parts.headers.insert(header::ETAG, weak);
+        }
+    }
+    let coder = match encoding {
+        Encoding::Brotli => Coder::BrotliEncoder(Box::new(brotli::CompressorWriter::new(
+            Vec::new(),
+            BROTLI_BUFFER_SIZE,
+            BROTLI_QUALITY,
+            BROTLI_WINDOW,
+        ))),
+        Encoding::Gzip => Coder::GzipEncoder(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        )),
+    };
+    Response::from_parts(parts, CodingBody::new(body, coder).boxed_unsync())
+}
+
+fn should_compress(response: &Response<Body>, config: &CompressionConfig) -> bool {
+    let headers = response.headers();
+    if matches!(
+        response.status(),
+        StatusCode::NO_CONTENT | StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED
+    ) || response.status().is_informational()
+    {
+        return false;
+    }
+    if headers.contains_key(header::CONTENT_ENCODING) || headers.contains_key(header::CONTENT_RANGE)
+    {
+        return false;
+    }
+    if headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.to_ascii_lowercase().contains("no-transform"))
+    {
+        return false;
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if !content_type.is_some_and(|ct| config.allows_content_type(ct)) {
+        return false;
+    }
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .or_else(|| response.body().size_hint().exact());
+    content_length.is_none_or(|len| len >= config.min_size)
+}
+
+/// Decompresses the body of `req` if it has a supported `Content-Encoding`.
+///
+/// The decompressed body may not exceed `max_size` bytes, or
+/// [`DEFAULT_MAX_DECOMPRESSED_SIZE`] if there is no limit. Returns the status
+/// code to reject the request with if its encoding is not supported.
+pub(crate) fn decompress_request(
+    req: Request<Body>,
+    max_size: Option<u64>,
+) -> Result<Request<Body>, StatusCode> {
+    let Some(encoding) = req.headers().get(header::CONTENT_ENCODING) else {
+        return Ok(req);
+    };
+    let output = || LimitedOutput::new(max_size.unwrap_or(DEFAULT_MAX_DECOMPRESSED_SIZE));
+    let coder = match encoding.to_str().map(|e| e.trim().to_ascii_lowercase()) {
+        Ok(e) if e == "identity" => None,
+        Ok(e) if e == "gzip" || e == "x-gzip" => {
+            Some(Coder::GzipDecoder(flate2::write::GzDecoder::new(output())))
+        }
+        Ok(e) if e == "deflate" => Some(Coder::ZlibDecoder(flate2::write::ZlibDecoder::new(
+            output(),
+        ))),
+        Ok(e) if e == "br" => Some(Coder::BrotliDecoder(Box::new(
+            brotli::DecompressorWriter::new(output(), BROTLI_BUFFER_SIZE),
+        ))),
+        _ => {
+            tracing::info!("Rejecting request with unsupported content encoding {encoding:?}");
+            return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+    };
+    let (mut parts, body) = req.into_parts();
+    parts.headers.remove(header::CONTENT_ENCODING);
+    let Some(coder) = coder else {
+        return Ok(Request::from_parts(parts, body));
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Ok(Request::from_parts(
+        parts,
+        CodingBody::new(body, coder).boxed_unsync(),
+    ))
+}
+
+/// A streaming encoder or decoder.
+enum Coder {
+    GzipEncoder(flate2::write::GzEncoder<Vec<u8>>),
+    BrotliEncoder(Box<brotli::CompressorWriter<Vec<u8>>>),
+    GzipDecoder(flate2::write::GzDecoder<LimitedOutput>),
+    ZlibDecoder(flate2::write::ZlibDecoder<LimitedOutput>),
+    BrotliDecoder(Box<brotli::DecompressorWriter<LimitedOutput>>),
+}
+
+impl Coder {
+    /// Feeds `data` through the coder and returns all output produced so far.
+    ///
+    /// Encoders are flushed after every chunk so that streamed responses are
+    /// delivered to the client as they are produced rather than held back
+    /// until the compressor's buffers fill. Decoders write their output in
+    /// bounded steps to a [`LimitedOutput`], which fails as soon as the
+    /// decompressed size exceeds its limit.
+    fn process(&mut self, data: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Self::GzipEncoder(e) => {
+                e.write_all(data)?;
+                e.flush()?;
+                e.get_mut()
+            }
+            Self::BrotliEncoder(e) => {
+                e.write_all(data)?;
+                e.flush()?;
+                e.get_mut()
+            }
+            Self::GzipDecoder(d) => {
+                d.write_all(data)?;
+                &mut d.get_mut().buf
+            }
+            Self::ZlibDecoder(d) => {
+                d.write_all(data)?;
+                &mut d.get_mut().buf
+            }
+            Self::BrotliDecoder(d) => {
+                d.write_all(data)?;
+                &mut d.get_mut().buf
+            }
+        };
+        Ok(mem::take(output).into())
+    }
+
+    /// Completes the stream and returns any remaining output.
+    fn finish(self) -> io::Result<Bytes> {
+        let output = match self {
+            Self::GzipEncoder(e) => e.finish()?,
+            Self::BrotliEncoder(e) => e.into_inner(),
+            Self::GzipDecoder(d) => d.finish()?.buf,
+            Self::ZlibDecoder(d) => d.finish()?.buf,
+            Self::BrotliDecoder(d) => {
+                d.into_inner()
+                    .map_err(|_| {
+                        io::Error::new(io::ErrorKind::UnexpectedEof, "truncated brotli stream")
+                    })?
+                    .buf
+            }
+        };
+        Ok(output.into())
+    }
+
+    fn is_decoder(&self) -> bool {
+        !matches!(self, Self::GzipEncoder(_) | Self::BrotliEncoder(_))
+    }
+}
+
+/// The output of a decoder, which may not grow beyond a limit.
+struct LimitedOutput {
+    buf: Vec<u8>,
+    written: u64,
+    limit: u64,
+}
+
+impl LimitedOutput {
+    fn new(limit: u64) -> Self {
+        Self {
+            buf: Vec::new(),
+            written: 0,
+            limit,
+        }
+    }
+}
+
+impl Write for LimitedOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.written + data.len() as u64;
+        if written > self.limit {
+            return Err(io::Error::other(OutputLimitExceeded {
+                limit: self.limit,
+                size: written,
+            }));
+        }
+        self.written = written;
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The error raised when a decompressed body would exceed its size limit.
+#[derive(Debug)]
+struct OutputLimitExceeded {
+    limit: u64,
+    size: u64,
+}
+
+impl std::fmt::Display for OutputLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "decompressed size exceeds limit of {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for OutputLimitExceeded {}
+
+/// A body which passes its data through a [`Coder`], forwarding any trailers
+/// once the coded stream is complete.
+struct CodingBody<B> {
+    body: B,
+    coder: Option<Coder>,
+    trailers: Option<HeaderMap>,
+}
+
+impl<B> CodingBody<B> {
+    fn new(body: B, coder: Coder) -> Self {
+        Self {
+            body,
+            coder: Some(coder),
+            trailers: None,
+        }
+    }
+
+    fn coding_error(&mut self, err: io::Error) -> ErrorCode {
+        let is_decoder = self.coder.as_ref().is_some_and(Coder::is_decoder);
+        self.coder = None;
+        self.trailers = None;
+        if let Some(exceeded) = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<OutputLimitExceeded>())
+        {
+            tracing::info!(
+                "Decompressed request body exceeded size limit of {} bytes",
+                exceeded.limit
+            );
+            return ErrorCode::HttpRequestBodySize(Some(exceeded.size));
+        }
+        if is_decoder {
+            tracing::info!("Failed to decompress request body: {err}");
+            ErrorCode::InternalError(Some(format!("invalid compressed request body: {err}")))
+        } else {
+            tracing::error!("Failed to compress response body: {err}");
+            ErrorCode::InternalError(Some(format!("failed to compress response body: {err}")))
+        }
+    }
+}
+
+impl<B> http_body::Body for CodingBody<B>
+where
+    B: http_body::Body<Data = Bytes, Error = ErrorCode> + Unpin,
+{
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            let Some(coder) = this.coder.as_mut() else {
+                return Poll::Ready(this.trailers.take().map(|t| Ok(Frame::trailers(t))));
+            };
+            let result = match ready!(Pin::new(&mut this.body).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => coder.process(&data),
+                    Err(frame) => {
+                        this.trailers = frame.into_trailers().ok();
+                        this.coder.take().unwrap().finish()
+                    }
+                },
+                Some(Err(err)) => {
+                    this.coder = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                None => this.coder.take().unwrap().finish(),
+            };
+            match result {
+                Ok(output) if output.is_empty() => continue,
+                Ok(output) => return Poll::Ready(Some(Ok(Frame::data(output)))),
+                Err(err) => return Poll::Ready(Some(Err(this.coding_error(err)))),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.coder.is_none() && self.trailers.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use spin_http::body;
+
+    use super::*;
+
+    fn headers(accept_encoding: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_str(accept_encoding).unwrap(),
+        );
+        headers
+    }
+
+    fn response(content_type: &str, body: &'static [u8]) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(body::full(Bytes::from_static(body)))
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiation_respects_quality() {
+        let get = Method::GET;
+        assert_eq!(
+            negotiate(&get, &headers("gzip, br")),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(
+            negotiate(&get, &headers("gzip, br;q=0.5")),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(negotiate(&get, &headers("*")), Some(Encoding::Brotli));
+        assert_eq!(negotiate(&get, &headers("br;q=0, gzip;q=0")), None);
+        assert_eq!(negotiate(&get, &headers("identity")), None);
+        assert_eq!(negotiate(&Method::HEAD, &headers("gzip")), None);
+    }
+
+    #[tokio::test]
+    async fn eligible_responses_are_compressed() {
+        let text = b"hello hello hello hello hello hello hello hello";
+        let config = CompressionConfig {
+            min_size: 16,
+            ..Default::default()
+        };
+        let res = compress_response(response("text/plain", text), &config, Encoding::Gzip);
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        assert!(res.headers().get(header::CONTENT_LENGTH).is_none());
+
+        let compressed = res.into_body().collect().await.unwrap().to_bytes();
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, text);
+    }
+
+    #[tokio::test]
+    async fn ineligible_responses_are_untouched() {
+        let config = CompressionConfig {
+            min_size: 16,
+            ..Default::default()
+        };
+        let small = compress_response(response("text/plain", b"tiny"), &config, Encoding::Gzip);
+        assert!(small.headers().get(header::CONTENT_ENCODING).is_none());
+
+        let image = compress_response(
+            response("image/png", b"not really a png but long enough"),
+            &config,
+            Encoding::Brotli,
+        );
+        assert!(image.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[tokio::test]
+    async fn streamed_responses_are_flushed_per_chunk() {
+        let chunks = futures::stream::iter(
+            [b"first chunk ".as_slice(), b"second chunk"]
+                .map(|chunk| Ok::<_, ErrorCode>(Frame::data(Bytes::from_static(chunk)))),
+        );
+        let res = Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .body(http_body_util::StreamBody::new(chunks).boxed_unsync())
+            .unwrap();
+        let mut body = compress_response(res, &Default::default(), Encoding::Brotli).into_body();
+
+        let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        let mut decoder = brotli::DecompressorWriter::new(Vec::new(), 4096);
+        decoder.write_all(&first).unwrap();
+        assert_eq!(decoder.get_ref(), b"first chunk ");
+
+        let rest = body.collect().await.unwrap().to_bytes();
+        decoder.write_all(&rest).unwrap();
+        assert_eq!(decoder.into_inner().unwrap(), b"first chunk second chunk");
+    }
+
+    #[tokio::test]
+    async fn requests_are_decompressed_within_limit() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(&[b'a'; 100]).unwrap();
+        let compressed = Bytes::from(encoder.finish().unwrap());
+
+        let req = || {
+            Request::builder()
+                .header(header::CONTENT_ENCODING, "gzip")
+                .body(body::full(compressed.clone()))
+                .unwrap()
+        };
+
+        let decompressed = decompress_request(req(), Some(100)).unwrap();
+        assert!(
+            decompressed
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .is_none()
+        );
+        let bytes = decompressed.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, [b'a'; 100].as_slice());
+
+        let err = decompress_request(req(), Some(50))
+            .unwrap()
+            .into_body()
+            .collect()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ErrorCode::HttpRequestBodySize(_)));
+
+        // A stream which decompresses to far more than the limit is rejected
+        // before it is fully decompressed.
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        for _ in 0..16 {
+            encoder.write_all(&[0; 1024 * 1024]).unwrap();
+        }
+        let bomb = Bytes::from(encoder.finish().unwrap());
+        let req = Request::builder()
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(body::full(bomb))
+            .unwrap();
+        let err = decompress_request(req, Some(1024 * 1024))
+            .unwrap()
+            .into_body()
+            .collect()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ErrorCode::HttpRequestBodySize(Some(size)) if size <= 2 * 1024 * 1024
+        ));
+
+        let unsupported = Request::builder()
+            .header(header::CONTENT_ENCODING, "zstd")
+            .body(body::empty())
+            .unwrap();
+        assert_eq!(
+            decompress_request(unsupported, None).unwrap_err(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use anyhow::Result;
 use http::Response;
 use opentelemetry_semantic_conventions::attribute as otel_attribute;
@@ -30,10 +32,14 @@ macro_rules! http_span {
 
 pub(crate) use http_span;
 
-/// Finish setting attributes on the HTTP span.
+/// The name of the request duration histogram, as seen by metrics exporters.
+const REQUEST_DURATION_METRIC: &str = "spin.http.server.request_duration";
+
+/// Finish setting attributes on the HTTP span and record the request duration.
 pub(crate) fn finalize_http_span(
     response: Result<Response<Body>>,
     method: String,
+    started: Instant,
 ) -> Result<Response<Body>> {
     let span = tracing::Span::current();
     match response {
@@ -44,6 +50,12 @@ pub(crate) fn finalize_http_span(
             );
 
             let matched_route = response.extensions().get::<MatchedRoute>();
+            record_request_duration(
+                started,
+                &method,
+                matched_route.map(|r| r.route.as_str()),
+                response.status().as_u16(),
+            );
             // Set otel.name and http.route
             if let Some(MatchedRoute { route }) = matched_route {
                 span.record(otel_attribute::HTTP_ROUTE, route);
@@ -62,6 +74,7 @@ pub(crate) fn finalize_http_span(
         }
         Err(err) => {
             instrument_error(&err);
+            record_request_duration(started, &method, None, 500);
             span.record(otel_attribute::HTTP_RESPONSE_STATUS_CODE, 500);
             span.record("otel.name", method);
             Err(err)
@@ -69,6 +82,43 @@ pub(crate) fn finalize_http_span(
     }
 }
 
+fn record_request_duration(started: Instant, method: &str, route: Option<&str>, status: u16) {
+    spin_telemetry::histogram!(
+        spin.http.server.request_duration = started.elapsed().as_secs_f64(),
+        http_request_method = metric_method(method),
+        http_route = route.unwrap_or(""),
+        http_response_status_code = status,
+        // According to the OpenTelemetry spec, instruments measuring durations should use "s" as the unit.
+        unit = "s"
+    );
+}
+
+/// Returns the request method to record in metrics. Non-standard methods are recorded as
+/// `_OTHER`, as the OpenTelemetry HTTP conventions require, so that clients can't create
+/// unbounded numbers of metric series.
+fn metric_method(method: &str) -> &str {
+    const KNOWN_METHODS: &[&str] = &[
+        "CONNECT", "DELETE", "GET", "HEAD", "OPTIONS", "PATCH", "POST", "PUT", "TRACE",
+    ];
+    if KNOWN_METHODS.contains(&method) {
+        method
+    } else {
+        "_OTHER"
+    }
+}
+
+/// Custom histogram bucket boundaries for the request duration metric, which is recorded in
+/// seconds rather than the milliseconds the OTel default boundaries are tuned for. Pass the
+/// result to `spin_telemetry::init`.
+pub fn metric_histogram_buckets() -> Vec<spin_telemetry::HistogramBuckets> {
+    vec![spin_telemetry::HistogramBuckets {
+        metric_name: REQUEST_DURATION_METRIC,
+        boundaries: vec![
+            0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0, 7.5, 10.0,
+        ],
+    }]
+}
+
 /// Marks the current span as errored.
 pub(crate) fn instrument_error(err: &anyhow::Error) {
     let span = tracing::Span::current();
@@ -98,3 +148,16 @@ impl MatchedRoute {
         resp
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_standard_methods_are_folded() {
+        assert_eq!(metric_method("GET"), "GET");
+        assert_eq!(metric_method("PATCH"), "PATCH");
+        assert_eq!(metric_method("get"), "_OTHER");
+        assert_eq!(metric_method("PURGE"), "_OTHER");
+    }
+}
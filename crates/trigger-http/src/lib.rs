@@ -1,13 +1,17 @@
 //! Implementation for the Spin HTTP engine.
 
+mod compression;
 mod headers;
 mod instrument;
+mod limits;
 mod middleware;
 mod outbound_http;
 mod server;
 mod spin;
+mod static_files;
 mod tls;
 mod wagi;
+mod warm_pool;
 mod wasi;
 mod wasip3;
 
@@ -30,10 +34,13 @@ use rand::{
 use serde::Deserialize;
 use spin_app::App;
 use spin_factors::RuntimeFactors;
-use spin_trigger::Trigger;
+use spin_http::config::RequestLimits;
+use spin_trigger::{AppReloads, Trigger, timeout::parse_duration};
 use wasmtime_wasi_http::p2::bindings::http::types::ErrorCode;
 
-pub use server::HttpServer;
+pub use instrument::metric_histogram_buckets;
+pub use limits::RequestLimitsConfig;
+pub use server::{HttpServer, HttpServerConfig};
 
 pub use tls::TlsConfig;
 
@@ -61,6 +68,11 @@ pub(crate) type TriggerApp<F> = spin_trigger::TriggerApp<HttpTrigger, F>;
 pub(crate) type TriggerInstanceBuilder<'a, F> =
     spin_trigger::TriggerInstanceBuilder<'a, HttpTrigger, F>;
 
+/// A [`spin_core::InstancePre`] of a component run by the HTTP trigger.
+pub(crate) type InstancePre<F> = spin_core::InstancePre<
+    spin_factors_executor::InstanceState<<F as RuntimeFactors>::InstanceState, ()>,
+>;
+
 #[derive(Args)]
 pub struct CliArgs {
     /// IP address and port to listen on
@@ -113,7 +125,10 @@ pub struct CliArgs {
 
     /// Request timeout to enforce.
     ///
-    /// As of this writing, this only affects WASIp3 components.
+    /// A component which doesn't produce a response in time is interrupted,
+    /// and the client receives a 504 Gateway Timeout. This applies to every
+    /// executor. WASIp3 instances also stop accepting requests which run past
+    /// it.
     ///
     /// A number with no suffix or with an `s` suffix is interpreted as seconds;
     /// other accepted suffixes include `ms` (milliseconds), `us` or `μs`
@@ -121,7 +136,7 @@ pub struct CliArgs {
     ///
     /// This may be specified either as a single time value or as a range,
     /// e.g. 1..8s.  If it's a range, a value will be selected from that range
-    /// at random for each new instance.
+    /// at random for each new instance or request.
     #[clap(long, value_parser = parse_duration_range)]
     pub request_timeout: Option<Range<Duration>>,
 
@@ -137,6 +152,63 @@ pub struct CliArgs {
     /// at random for each new instance.
     #[clap(long, default_value = "1s", value_parser = parse_duration_range)]
     pub idle_instance_timeout: Range<Duration>,
+
+    /// Number of instances of each component to instantiate ahead of requests.
+    ///
+    /// A request takes an instance from the component's pool if one is ready,
+    /// and the pool is replenished in the background. This removes
+    /// instantiation from the request path at the cost of holding the idle
+    /// instances in memory. Routes may override this with `warm_instances`
+    /// in the manifest. The default is 0, which disables the pools. Warm
+    /// instances count towards the limit on live instances, so the app fails
+    /// to start if they would use all of it.
+    #[clap(long, env = "SPIN_HTTP_WARM_INSTANCES", default_value_t = 0)]
+    pub warm_instances: usize,
+
+    /// Maximum size of an incoming request body, in bytes.
+    ///
+    /// Requests with a larger `Content-Length` are rejected with a 413 before
+    /// a component is instantiated; streamed bodies are cut off when they reach
+    /// the limit. Routes may override this with `request_limits` in the manifest.
+    #[clap(long, env = "SPIN_HTTP_MAX_REQUEST_BODY_SIZE")]
+    pub max_request_body_size: Option<u64>,
+
+    /// Maximum combined size of an incoming request's header names and values,
+    /// in bytes.
+    ///
+    /// Requests with larger headers are rejected with a 431. Routes may override
+    /// this with `request_limits` in the manifest.
+    #[clap(long, env = "SPIN_HTTP_MAX_REQUEST_HEADER_SIZE")]
+    pub max_request_header_size: Option<usize>,
+
+    /// Maximum number of headers in an incoming request.
+    ///
+    /// Requests with more headers are rejected with a 431. Routes may override
+    /// this with `request_limits` in the manifest.
+    #[clap(long, env = "SPIN_HTTP_MAX_REQUEST_HEADER_COUNT")]
+    pub max_request_header_count: Option<usize>,
+
+    /// Maximum rate at which an incoming request body is read, in bytes per
+    /// second.
+    ///
+    /// Clients uploading faster than this are slowed down by backpressure.
+    /// Routes may override this with `request_limits` in the manifest.
+    #[clap(long, env = "SPIN_HTTP_MAX_UPLOAD_RATE")]
+    pub max_upload_rate: Option<u64>,
+
+    /// Maximum time to wait for a client to send the request headers.
+    ///
+    /// This only applies to HTTP/1 connections. Accepts the same suffixes as
+    /// `--request-timeout`.
+    #[clap(long, value_parser = parse_duration)]
+    pub header_read_timeout: Option<Duration>,
+
+    /// Maximum time to wait for each chunk of an incoming request body before
+    /// aborting the request.
+    ///
+    /// Accepts the same suffixes as `--request-timeout`.
+    #[clap(long, value_parser = parse_duration)]
+    pub body_read_timeout: Option<Duration>,
 }
 
 impl CliArgs {
@@ -150,6 +222,19 @@ impl CliArgs {
             _ => unreachable!(),
         }
     }
+
+    fn request_limits_config(&self) -> RequestLimitsConfig {
+        RequestLimitsConfig {
+            default_limits: RequestLimits {
+                max_body_size: self.max_request_body_size,
+                max_header_size: self.max_request_header_size,
+                max_header_count: self.max_request_header_count,
+                max_upload_rate: self.max_upload_rate,
+            },
+            header_read_timeout: self.header_read_timeout,
+            body_read_timeout: self.body_read_timeout,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -165,6 +250,13 @@ impl<T> Range<T> {
             Self::Bounds(a, b) => Range::Bounds(fun(a), fun(b)),
         }
     }
+
+    /// Returns the largest value in the range.
+    fn upper(self) -> T {
+        match self {
+            Self::Value(v) | Self::Bounds(_, v) => v,
+        }
+    }
 }
 
 impl<T: SampleUniform + PartialOrd> SampleRange<T> for Range<T> {
@@ -202,31 +294,15 @@ fn parse_usize_range(s: &str) -> Result<Range<usize>, String> {
     parse_range(s)
 }
 
+/// A [`Duration`] parsed with [`parse_duration`], so that it can be used as
+/// the bounds of a [`Range`].
 struct ParsedDuration(Duration);
 
 impl FromStr for ParsedDuration {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let error = |e| {
-            format!("expected integer suffixed by `s`, `ms`, `us`, `μs`, or `ns`; got {s:?}; {e}")
-        };
-        Ok(Self(match s.parse() {
-            Ok(val) => Duration::from_secs(val),
-            Err(err) => {
-                if let Some(num) = s.strip_suffix("s") {
-                    Duration::from_secs(num.parse().map_err(error)?)
-                } else if let Some(num) = s.strip_suffix("ms") {
-                    Duration::from_millis(num.parse().map_err(error)?)
-                } else if let Some(num) = s.strip_suffix("us").or(s.strip_suffix("μs")) {
-                    Duration::from_micros(num.parse().map_err(error)?)
-                } else if let Some(num) = s.strip_suffix("ns") {
-                    Duration::from_nanos(num.parse().map_err(error)?)
-                } else {
-                    return Err(error(err));
-                }
-            }
-        }))
+        parse_duration(s).map(Self)
     }
 }
 
@@ -241,6 +317,7 @@ pub struct InstanceReuseConfig {
     request_timeout: Option<Range<Duration>>,
     request_deadline: Option<Duration>,
     idle_instance_timeout: Range<Duration>,
+    warm_instance_count: usize,
 }
 
 impl Default for InstanceReuseConfig {
@@ -253,6 +330,7 @@ impl Default for InstanceReuseConfig {
             request_timeout: DEFAULT_REQUEST_TIMEOUT,
             request_deadline: None,
             idle_instance_timeout: DEFAULT_IDLE_INSTANCE_TIMEOUT,
+            warm_instance_count: 0,
         }
     }
 }
@@ -272,8 +350,20 @@ impl InstanceReuseConfig {
             request_timeout: Some(Range::Value(timeout)),
             request_deadline: Some(timeout),
             idle_instance_timeout: DEFAULT_IDLE_INSTANCE_TIMEOUT,
+            warm_instance_count: 0,
         }
     }
+
+    /// Sets the number of instances of each component to instantiate ahead of
+    /// requests.
+    ///
+    /// Instances are taken from the pool when a request needs a new instance,
+    /// and the pool is replenished in the background. WASIp3 instances taken
+    /// from the pool are then reused as this configuration allows.
+    pub fn with_warm_instance_count(mut self, count: usize) -> Self {
+        self.warm_instance_count = count;
+        self
+    }
 }
 
 /// The Spin HTTP trigger.
@@ -285,9 +375,7 @@ pub struct HttpTrigger {
     listen_addr: SocketAddr,
     tls_config: Option<TlsConfig>,
     find_free_port: bool,
-    http1_max_buf_size: Option<usize>,
-    reuse_config: InstanceReuseConfig,
-    output_format: OutputFormat,
+    server_config: HttpServerConfig,
 }
 
 impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
@@ -298,8 +386,6 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
 
     fn new(cli_args: Self::CliArgs, app: &spin_app::App) -> anyhow::Result<Self> {
         let find_free_port = cli_args.find_free_port;
-        let http1_max_buf_size = cli_args.http1_max_buf_size;
-        let output_format = cli_args.format;
         let reuse_config = InstanceReuseConfig {
             max_instance_reuse_count: cli_args
                 .max_instance_reuse_count
@@ -312,6 +398,14 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
             request_timeout: cli_args.request_timeout,
             request_deadline: None,
             idle_instance_timeout: cli_args.idle_instance_timeout,
+            warm_instance_count: cli_args.warm_instances,
+        };
+
+        let server_config = HttpServerConfig {
+            http1_max_buf_size: cli_args.http1_max_buf_size,
+            reuse_config,
+            output_format: cli_args.format,
+            request_limits: cli_args.request_limits_config(),
         };
 
         Self::new(
@@ -319,9 +413,7 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
             cli_args.address,
             cli_args.into_tls_config(),
             find_free_port,
-            http1_max_buf_size,
-            reuse_config,
-            output_format,
+            server_config,
         )
     }
 
@@ -333,6 +425,26 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
         Ok(())
     }
 
+    async fn run_reloadable(
+        self,
+        trigger_app: TriggerApp<F>,
+        mut reloads: AppReloads<Self, F>,
+    ) -> anyhow::Result<()> {
+        let server = self.into_server(trigger_app)?;
+
+        let serve = server.clone().serve();
+        tokio::pin!(serve);
+        loop {
+            tokio::select! {
+                res = &mut serve => return res,
+                Some(reload) = reloads.recv() => {
+                    let res = server.reload(reload.trigger_app);
+                    _ = reload.applied.send(res);
+                }
+            }
+        }
+    }
+
     fn trigger_dependencies_composer() -> impl spin_factors_executor::TriggerDependenciesComposer {
         middleware::HttpMiddlewareComposer
     }
@@ -353,9 +465,7 @@ impl HttpTrigger {
         listen_addr: SocketAddr,
         tls_config: Option<TlsConfig>,
         find_free_port: bool,
-        http1_max_buf_size: Option<usize>,
-        reuse_config: InstanceReuseConfig,
-        output_format: OutputFormat,
+        server_config: HttpServerConfig,
     ) -> anyhow::Result<Self> {
         Self::validate_app(app)?;
 
@@ -363,9 +473,7 @@ impl HttpTrigger {
             listen_addr,
             tls_config,
             find_free_port,
-            http1_max_buf_size,
-            reuse_config,
-            output_format,
+            server_config,
         })
     }
 
@@ -378,23 +486,19 @@ impl HttpTrigger {
             listen_addr,
             tls_config,
             find_free_port,
-            http1_max_buf_size,
-            reuse_config,
-            output_format,
+            server_config,
         } = self;
         let server = Arc::new(HttpServer::new(
             listen_addr,
             tls_config,
             find_free_port,
             trigger_app,
-            http1_max_buf_size,
-            reuse_config,
-            output_format,
+            server_config,
         )?);
         Ok(server)
     }
 
-    fn validate_app(app: &App) -> anyhow::Result<()> {
+    pub(crate) fn validate_app(app: &App) -> anyhow::Result<()> {
         #[derive(Deserialize)]
         #[serde(deny_unknown_fields)]
         struct TriggerMetadata {
@@ -481,4 +585,18 @@ mod tests {
         assert!(matches!(config.request_timeout, Some(Range::Value(value)) if value == timeout));
         assert_eq!(config.request_deadline, Some(timeout));
     }
+
+    #[test]
+    fn duration_ranges_accept_every_suffix() {
+        assert!(matches!(
+            parse_duration_range("250ms").unwrap(),
+            Range::Value(value) if value == Duration::from_millis(250)
+        ));
+        assert!(matches!(
+            parse_duration_range("500us..2s").unwrap(),
+            Range::Bounds(start, end)
+                if start == Duration::from_micros(500) && end == Duration::from_secs(2)
+        ));
+        assert!(parse_duration_range("1..5m").is_err());
+    }
 }
@@ -0,0 +1,314 @@
+use std::{
+    pin::Pin,
+    task::{self, Context, Poll},
+    time::Duration,
+};
+
+use http::{HeaderMap, StatusCode};
+use http_body::{Frame, SizeHint};
+use http_body_util::BodyExt;
+use hyper::body::{Buf, Bytes};
+use pin_project_lite::pin_project;
+use spin_http::config::RequestLimits;
+use tokio::time::Instant;
+use wasmtime_wasi_http::p2::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// Limits and timeouts applied to incoming requests.
+#[derive(Clone, Debug, Default)]
+pub struct RequestLimitsConfig {
+    /// Limits applied to routes that don't override them in the manifest.
+    pub default_limits: RequestLimits,
+    /// The maximum time to wait for a client to send the request headers.
+    ///
+    /// This only applies to HTTP/1 connections.
+    pub header_read_timeout: Option<Duration>,
+    /// The maximum time to wait for each chunk of the request body.
+    pub body_read_timeout: Option<Duration>,
+}
+
+/// Checks the request headers against `limits`, returning the status code to
+/// reject the request with if they are exceeded.
+///
+/// A `Content-Length` larger than the maximum body size is rejected here so
+/// that the request can be refused before a component is instantiated. Bodies
+/// without a declared length are checked as they are streamed by [`LimitedBody`].
+pub(crate) fn check_request_headers(
+    headers: &HeaderMap,
+    limits: &RequestLimits,
+) -> Result<(), StatusCode> {
+    if let Some(max_count) = limits.max_header_count
+        && headers.len() > max_count
+    {
+        tracing::info!(
+            "Rejecting request with {} headers; limit is {max_count}",
+            headers.len()
+        );
+        return Err(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+    if let Some(max_size) = limits.max_header_size {
+        let size = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum::<usize>();
+        if size > max_size {
+            tracing::info!("Rejecting request with {size} bytes of headers; limit is {max_size}");
+            return Err(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+        }
+    }
+    if let Some(max_size) = limits.max_body_size {
+        let content_length = headers
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        if let Some(length) = content_length
+            && length > max_size
+        {
+            tracing::info!("Rejecting request with {length} byte body; limit is {max_size}");
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+    }
+    Ok(())
+}
+
+/// Wraps `body` in a [`LimitedBody`] if any body limits or timeouts apply.
+pub(crate) fn limit_body(
+    body: Body,
+    limits: &RequestLimits,
+    read_timeout: Option<Duration>,
+) -> Body {
+    if limits.max_body_size.is_none() && limits.max_upload_rate.is_none() && read_timeout.is_none()
+    {
+        return body;
+    }
+    LimitedBody::new(body, limits, read_timeout).boxed_unsync()
+}
+
+pin_project! {
+    /// A request body which enforces a maximum size, a maximum upload rate and
+    /// a timeout between chunks.
+    ///
+    /// The upload rate is enforced by delaying reads from the underlying
+    /// connection, which applies backpressure to the client.
+    pub(crate) struct LimitedBody<B> {
+        // Wrapping `body` in `Option` lets us drop it (and the underlying
+        // connection) as soon as a limit is exceeded.
+        body: Option<B>,
+        max_size: Option<u64>,
+        max_rate: Option<u64>,
+        read_timeout: Option<Duration>,
+        #[pin]
+        sleep: Option<tokio::time::Sleep>,
+        sleep_reason: SleepReason,
+        start: Instant,
+        byte_count: u64,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SleepReason {
+    Throttle,
+    ReadTimeout,
+}
+
+impl<B> LimitedBody<B> {
+    pub(crate) fn new(body: B, limits: &RequestLimits, read_timeout: Option<Duration>) -> Self {
+        Self {
+            body: Some(body),
+            max_size: limits.max_body_size,
+            max_rate: limits.max_upload_rate.filter(|rate| *rate > 0),
+            read_timeout,
+            sleep: None,
+            sleep_reason: SleepReason::ReadTimeout,
+            start: Instant::now(),
+            byte_count: 0,
+        }
+    }
+}
+
+impl<B: http_body::Body<Data = Bytes, Error = ErrorCode> + Unpin> http_body::Body
+    for LimitedBody<B>
+{
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut me = self.project();
+
+        // If we're ahead of the allowed upload rate, wait before reading more.
+        if let Some(rate) = *me.max_rate
+            && me.sleep.is_none()
+        {
+            let allowed_at =
+                *me.start + Duration::from_secs_f64(*me.byte_count as f64 / rate as f64);
+            if allowed_at > Instant::now() {
+                me.sleep
+                    .as_mut()
+                    .set(Some(tokio::time::sleep_until(allowed_at)));
+                *me.sleep_reason = SleepReason::Throttle;
+            }
+        }
+        if *me.sleep_reason == SleepReason::Throttle
+            && let Some(sleep) = me.sleep.as_mut().as_pin_mut()
+        {
+            task::ready!(sleep.poll(cx));
+            me.sleep.as_mut().set(None);
+            *me.sleep_reason = SleepReason::ReadTimeout;
+        }
+
+        let Some(body) = me.body.as_mut() else {
+            return Poll::Ready(None);
+        };
+        match Pin::new(body).poll_frame(cx) {
+            Poll::Ready(frame) => {
+                me.sleep.as_mut().set(None);
+                if let Some(Ok(frame)) = &frame
+                    && let Some(data) = frame.data_ref()
+                {
+                    *me.byte_count += data.remaining() as u64;
+                    if let Some(max_size) = *me.max_size
+                        && *me.byte_count > max_size
+                    {
+                        tracing::info!(
+                            "Request body exceeded size limit of {max_size} bytes; aborting"
+                        );
+                        *me.body = None;
+                        return Poll::Ready(Some(Err(ErrorCode::HttpRequestBodySize(Some(
+                            *me.byte_count,
+                        )))));
+                    }
+                }
+                Poll::Ready(frame)
+            }
+            Poll::Pending => {
+                let Some(timeout) = *me.read_timeout else {
+                    return Poll::Pending;
+                };
+                if me.sleep.is_none() {
+                    me.sleep.as_mut().set(Some(tokio::time::sleep(timeout)));
+                }
+                task::ready!(me.sleep.as_mut().as_pin_mut().unwrap().poll(cx));
+
+                tracing::info!("Timed out waiting for request body after {timeout:?}; aborting");
+                *me.body = None;
+                me.sleep.as_mut().set(None);
+                Poll::Ready(Some(Err(ErrorCode::ConnectionReadTimeout)))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.as_ref().is_none_or(|b| b.is_end_stream())
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body
+            .as_ref()
+            .map(|b| b.size_hint())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+    use spin_http::body;
+
+    use super::*;
+
+    fn limits(max_body_size: Option<u64>, max_upload_rate: Option<u64>) -> RequestLimits {
+        RequestLimits {
+            max_body_size,
+            max_upload_rate,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn header_limits_are_enforced() {
+        let mut headers = HeaderMap::new();
+        headers.insert("a", HeaderValue::from_static("1"));
+        headers.insert("b", HeaderValue::from_static("22"));
+
+        let count_limit = RequestLimits {
+            max_header_count: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_request_headers(&headers, &count_limit),
+            Err(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+        );
+
+        let size_limit = RequestLimits {
+            max_header_size: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(check_request_headers(&headers, &size_limit), Ok(()));
+        headers.insert("c", HeaderValue::from_static("3"));
+        assert_eq!(
+            check_request_headers(&headers, &size_limit),
+            Err(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+        );
+    }
+
+    #[test]
+    fn declared_content_length_over_limit_is_rejected() {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::CONTENT_LENGTH, HeaderValue::from_static("11"));
+        assert_eq!(
+            check_request_headers(&headers, &limits(Some(10), None)),
+            Err(StatusCode::PAYLOAD_TOO_LARGE)
+        );
+        assert_eq!(
+            check_request_headers(&headers, &limits(Some(11), None)),
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn streamed_body_over_limit_errors() {
+        let body = limit_body(
+            body::full(Bytes::from_static(b"hello world")),
+            &limits(Some(5), None),
+            None,
+        );
+        let err = body.collect().await.unwrap_err();
+        assert!(matches!(err, ErrorCode::HttpRequestBodySize(Some(11))));
+    }
+
+    #[tokio::test]
+    async fn body_within_limits_passes_through() {
+        let body = limit_body(
+            body::full(Bytes::from_static(b"hello")),
+            &limits(Some(5), Some(1024)),
+            Some(Duration::from_secs(1)),
+        );
+        let bytes = body.collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "hello");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn upload_rate_throttles_reads() {
+        let chunks = futures::stream::iter(
+            [b"aaaa", b"bbbb", b"cccc"]
+                .map(|chunk| Ok::<_, ErrorCode>(Frame::data(Bytes::from_static(chunk)))),
+        );
+        let body = limit_body(
+            http_body_util::StreamBody::new(chunks).boxed_unsync(),
+            &limits(None, Some(40)),
+            None,
+        );
+        let start = Instant::now();
+        let bytes = body.collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "aaaabbbbcccc");
+        // At 40 bytes/sec, the third chunk can't be read until 8 bytes / 40 = 200ms,
+        // and the end of the stream can't be read until 12 bytes / 40 = 300ms.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(400), "{elapsed:?}");
+    }
+}
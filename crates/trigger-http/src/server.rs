@@ -4,7 +4,7 @@ use std::{
     marker::PhantomData,
     net::SocketAddr,
     pin::Pin,
-    sync::{Arc, OnceLock},
+    sync::{Arc, OnceLock, RwLock, Weak},
     task::{Context, Poll},
     time::{Duration, Instant},
 };
@@ -20,7 +20,7 @@ use hyper::{
     service::service_fn,
 };
 use hyper_util::{
-    rt::{TokioExecutor, TokioIo},
+    rt::{TokioExecutor, TokioIo, TokioTimer},
     server::conn::auto::Builder,
 };
 use pin_project_lite::pin_project;
@@ -36,6 +36,7 @@ use spin_http::{
     routes::{RouteInfo, RouteMatch, Router},
     trigger::HandlerType,
 };
+use spin_trigger::timeout::{InvocationTimeout, run_with_timeout, set_deadline};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpListener,
@@ -52,26 +53,34 @@ use wasmtime_wasi_http::p2::body::HyperOutgoingBody;
 use wasmtime_wasi_http::p3::bindings::Service;
 
 use crate::{
-    Body, InstanceReuseConfig, NotFoundRouteKind, OutputFormat, TlsConfig, TriggerApp,
-    TriggerInstanceBuilder,
+    Body, InstancePre, InstanceReuseConfig, NotFoundRouteKind, OutputFormat, Range,
+    RequestLimitsConfig, TlsConfig, TriggerApp, TriggerInstanceBuilder,
+    compression::{compress_response, decompress_request, negotiate},
     headers::strip_forbidden_headers,
     instrument::{MatchedRoute, finalize_http_span, http_span, instrument_error},
+    limits::{check_request_headers, limit_body},
     outbound_http::OutboundHttpInterceptor,
     spin::SpinHttpExecutor,
+    static_files::StaticFileServer,
     wagi::WagiHttpExecutor,
+    warm_pool::{WarmInstance, WarmPool},
     wasi::WasiHttpExecutor,
     wasip3::Wasip3HttpExecutor,
 };
 
 pub const MAX_RETRIES: u16 = 10;
 
-pub(crate) fn set_request_deadline<T>(
-    store: &mut spin_core::Store<T>,
-    request_deadline: Option<Duration>,
-) {
-    if let Some(timeout) = request_deadline {
-        store.set_deadline(Instant::now() + timeout);
-    }
+/// Settings for how an [`HttpServer`] handles the requests it receives.
+#[derive(Clone, Default)]
+pub struct HttpServerConfig {
+    /// The maximum buffer size for an HTTP1 connection.
+    pub http1_max_buf_size: Option<usize>,
+    /// Instance reuse configuration, including request timeouts.
+    pub reuse_config: InstanceReuseConfig,
+    /// The output format for the server's startup information.
+    pub output_format: OutputFormat,
+    /// Limits and timeouts applied to incoming requests.
+    pub request_limits: RequestLimitsConfig,
 }
 
 /// An HTTP server which runs Spin apps.
@@ -92,16 +101,57 @@ pub struct HttpServer<F: RuntimeFactors> {
     find_free_port: bool,
     /// The output format for the server's startup information.
     output_format: OutputFormat,
-    /// Hard Wasmtime request deadline for direct HTTP executor paths.
-    request_deadline: Option<Duration>,
+    /// Limits and timeouts applied to incoming requests.
+    request_limits: RequestLimitsConfig,
+    /// Instance reuse configuration, applied to each app that is served.
+    reuse_config: InstanceReuseConfig,
+    /// The app being served. This is replaced when the app is reloaded.
+    served_app: RwLock<Arc<ServedApp<F>>>,
+}
+
+/// The app being served by an [`HttpServer`], and the routing derived from it.
+///
+/// A request uses the same `ServedApp` from start to finish, so requests
+/// which are in flight when the app is reloaded finish on the previous app.
+pub(crate) struct ServedApp<F: RuntimeFactors> {
     /// Request router.
     router: Router,
     /// The app being triggered.
     trigger_app: Arc<TriggerApp<F>>,
     // Component ID -> component trigger config
     component_trigger_configs: HashMap<spin_http::routes::TriggerLookupKey, HttpTriggerConfig>,
-    // Component ID -> handler type
-    component_handler_types: HashMap<String, HandlerType<HttpHandlerState<F>>>,
+    // Component ID -> handler, updated when a component is reloaded
+    component_handlers: Arc<ComponentHandlers<F>>,
+    // Trigger ID -> static file server, for `static` routes
+    static_file_servers: HashMap<spin_http::routes::TriggerLookupKey, StaticFileServer>,
+}
+
+/// The handlers of an app's HTTP components.
+struct ComponentHandlers<F: RuntimeFactors> {
+    // Weak, because the app (indirectly) owns this
+    trigger_app: Weak<TriggerApp<F>>,
+    app_id: String,
+    reuse_config: InstanceReuseConfig,
+    // Component ID -> executor
+    executors: HashMap<String, Option<HttpExecutorType>>,
+    // Component ID -> number of instances to make ahead of requests
+    warm_pool_sizes: HashMap<String, usize>,
+    // Component ID -> handler, for the components which are loaded
+    handlers: RwLock<HashMap<String, Arc<ComponentHandler<F>>>>,
+    // The server which warm pools are filled for, once it is serving
+    warm_pool_server: OnceLock<(Weak<HttpServer<F>>, Scheme)>,
+}
+
+/// A loaded component's [`InstancePre`] and everything derived from it.
+///
+/// These are replaced together when the component is reloaded, so a request
+/// never uses the handler of one version of a component with an instance of
+/// another.
+struct ComponentHandler<F: RuntimeFactors> {
+    instance_pre: InstancePre<F>,
+    handler_type: HandlerType<HttpHandlerState<F>>,
+    // Pool of instances of `instance_pre` made ahead of requests
+    warm_pool: Option<Arc<WarmPool<F>>>,
 }
 
 impl<F: RuntimeFactors> HttpServer<F> {
@@ -111,113 +161,49 @@ impl<F: RuntimeFactors> HttpServer<F> {
         tls_config: Option<TlsConfig>,
         find_free_port: bool,
         trigger_app: TriggerApp<F>,
-        http1_max_buf_size: Option<usize>,
-        reuse_config: InstanceReuseConfig,
-        output_format: OutputFormat,
+        config: HttpServerConfig,
     ) -> anyhow::Result<Self> {
-        // This needs to be a vec before building the router to handle duplicate routes
-        let component_trigger_configs = trigger_app
-            .app()
-            .trigger_configs::<HttpTriggerConfig>("http")?
-            .into_iter()
-            .map(|(trigger_id, config)| config.lookup_key(trigger_id).map(|k| (k, config)))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        // Build router
-        let component_routes = component_trigger_configs
-            .iter()
-            .map(|(key, config)| (key, &config.route));
-        let mut duplicate_routes = Vec::new();
-        let router = Router::build("/", component_routes, Some(&mut duplicate_routes))?;
-        if !duplicate_routes.is_empty() {
-            tracing::error!(
-                "The following component routes are duplicates and will never be used:"
-            );
-            for dup in &duplicate_routes {
-                tracing::error!(
-                    "  {}: {} (duplicate of {})",
-                    dup.replaced_id,
-                    dup.route(),
-                    dup.effective_id,
-                );
-            }
-        }
-        if router.contains_reserved_route() {
-            tracing::error!(
-                "Routes under {} are handled by the Spin runtime and will never be reached",
-                spin_http::WELL_KNOWN_PREFIX
-            );
-        }
-        tracing::trace!(
-            "Constructed router: {:?}",
-            router.routes().collect::<Vec<_>>()
-        );
-
-        // Now that router is built we can merge duplicate routes by component
-        let component_trigger_configs = HashMap::from_iter(component_trigger_configs);
-
-        let trigger_app = Arc::new(trigger_app);
-
-        let component_handler_types = component_trigger_configs
-            .iter()
-            .filter_map(|(key, trigger_config)| match key {
-                spin_http::routes::TriggerLookupKey::Component(component) => Some(
-                    Self::handler_type_for_component(
-                        &trigger_app,
-                        component,
-                        &trigger_config.executor,
-                        reuse_config,
-                    )
-                    .map(|ht| (component.clone(), ht)),
-                ),
-                spin_http::routes::TriggerLookupKey::Trigger(_) => None,
-            })
-            .collect::<anyhow::Result<_>>()?;
+        let HttpServerConfig {
+            http1_max_buf_size,
+            reuse_config,
+            output_format,
+            request_limits,
+        } = config;
+        let served_app = ServedApp::new(trigger_app, reuse_config)?;
         Ok(Self {
             listen_addr,
             local_addr: OnceLock::new(),
             tls_config,
             find_free_port,
-            router,
-            trigger_app,
             http1_max_buf_size,
-            component_trigger_configs,
-            component_handler_types,
             output_format,
-            request_deadline: reuse_config.request_deadline,
+            request_limits,
+            reuse_config,
+            served_app: RwLock::new(Arc::new(served_app)),
         })
     }
 
-    fn handler_type_for_component(
-        trigger_app: &Arc<TriggerApp<F>>,
-        component_id: &str,
-        executor: &Option<HttpExecutorType>,
-        reuse_config: InstanceReuseConfig,
-    ) -> anyhow::Result<HandlerType<HttpHandlerState<F>>> {
-        let pre = trigger_app.get_instance_pre(component_id)?;
-        let handler_type = match executor {
-            None | Some(HttpExecutorType::Http) => HandlerType::from_instance_pre(
-                pre,
-                HttpHandlerState {
-                    component_id: component_id.into(),
-                    reuse_config,
-                    server: Default::default(),
-                    self_scheme: Default::default(),
-                },
-            )?,
-            Some(HttpExecutorType::Wagi(wagi_config)) => {
-                anyhow::ensure!(
-                    wagi_config.entrypoint == "_start",
-                    "Wagi component '{component_id}' cannot use deprecated 'entrypoint' field"
-                );
-                HandlerType::Wagi(
-                    CommandIndices::new(pre)
-                        .map_err(anyhow::Error::from)
-                        .context("failed to find wasi command interface for wagi executor")?,
-                )
-            }
-        };
-        Ok(handler_type)
+    /// Replaces the app being served, for example after its manifest or
+    /// runtime config has changed.
+    ///
+    /// Requests which are already being handled finish on the previous app.
+    /// If the new app is invalid, the previous app continues to be served.
+    pub fn reload(self: &Arc<Self>, trigger_app: TriggerApp<F>) -> anyhow::Result<()> {
+        crate::HttpTrigger::validate_app(trigger_app.app())?;
+        let served_app = Arc::new(ServedApp::new(trigger_app, self.reuse_config)?);
+        *self.served_app.write().unwrap() = served_app.clone();
+        // Warm instances need the address the server is bound to, so if it
+        // isn't bound yet, `serve` starts the pools once it is.
+        if self.local_addr.get().is_some() {
+            served_app.start_warm_pools(self);
+        }
+        tracing::info!("Reloaded application");
+        Ok(())
+    }
+
+    /// Returns the app currently being served.
+    fn served_app(&self) -> Arc<ServedApp<F>> {
+        self.served_app.read().unwrap().clone()
     }
 
     /// Serve incoming requests over the provided [`TcpListener`].
@@ -235,6 +221,7 @@ impl<F: RuntimeFactors> HttpServer<F> {
         };
 
         let _ = self.local_addr.set(listener.local_addr()?);
+        self.served_app().start_warm_pools(&self);
 
         if let Some(tls_config) = self.tls_config.clone() {
             self.serve_https(listener, tls_config).await?;
@@ -328,13 +315,15 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     path,
                 )),
                 "info" => self.app_info(path),
+                "ready" => self.app_readiness(path),
                 _ => Self::not_found(NotFoundRouteKind::WellKnown),
             };
         }
 
-        match self.router.route(&path) {
+        let served_app = self.served_app();
+        match served_app.router.route(&path) {
             Ok(route_match) => {
-                self.handle_trigger_route(req, route_match, server_scheme, client_addr)
+                self.handle_route(&served_app, req, route_match, server_scheme, client_addr)
                     .await
             }
             Err(_) => Self::not_found(NotFoundRouteKind::Normal(path.to_string())),
@@ -344,13 +333,26 @@ impl<F: RuntimeFactors> HttpServer<F> {
     /// Handles a successful route match.
     pub async fn handle_trigger_route(
         self: &Arc<Self>,
+        req: Request<Body>,
+        route_match: RouteMatch<'_, '_>,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+    ) -> anyhow::Result<Response<Body>> {
+        let served_app = self.served_app();
+        self.handle_route(&served_app, req, route_match, server_scheme, client_addr)
+            .await
+    }
+
+    async fn handle_route(
+        self: &Arc<Self>,
+        served_app: &ServedApp<F>,
         mut req: Request<Body>,
         route_match: RouteMatch<'_, '_>,
         server_scheme: Scheme,
         client_addr: SocketAddr,
     ) -> anyhow::Result<Response<Body>> {
         set_req_uri(&mut req, server_scheme)?;
-        let app_id = self
+        let app_id = served_app
             .trigger_app
             .app()
             .get_metadata(APP_NAME_KEY)?
@@ -365,33 +367,74 @@ impl<F: RuntimeFactors> HttpServer<F> {
             component_id = lookup_key.to_string()
         );
 
-        let trigger_config = self
+        let trigger_config = served_app
             .component_trigger_configs
             .get(lookup_key)
             .with_context(|| format!("unknown routing destination '{lookup_key}'"))?;
 
-        match (&trigger_config.component, &trigger_config.static_response) {
-            (Some(component), None) => {
-                self.respond_wasm_component(
-                    req,
-                    route_match,
-                    client_addr,
-                    component,
-                    &trigger_config.executor,
-                )
-                .await
-            }
-            (None, Some(static_response)) => Self::respond_static_response(static_response),
-            // These error cases should have been ruled out by this point but belt and braces
-            (None, None) => Err(anyhow::anyhow!(
-                "Triggers must specify either component or static_response - neither is specified for {}",
-                route_match.raw_route()
-            )),
-            (Some(_), Some(_)) => Err(anyhow::anyhow!(
-                "Triggers must specify either component or static_response - both are specified for {}",
-                route_match.raw_route()
-            )),
+        // Enforce request limits before doing any work on the request, so that
+        // oversized requests are refused without instantiating a component.
+        let limits = match &trigger_config.request_limits {
+            Some(route_limits) => route_limits.or(&self.request_limits.default_limits),
+            None => self.request_limits.default_limits.clone(),
+        };
+        if let Err(status) = check_request_headers(req.headers(), &limits) {
+            return Self::limit_exceeded(status, route_match.raw_route());
         }
+        let mut req =
+            req.map(|body| limit_body(body, &limits, self.request_limits.body_read_timeout));
+
+        let compression = trigger_config.compression.as_ref();
+        if compression.is_some_and(|c| c.decompress_requests) {
+            req = match decompress_request(req, limits.max_body_size) {
+                Ok(req) => req,
+                Err(status) => {
+                    return Ok(MatchedRoute::with_response_extension(
+                        Response::builder().status(status).body(body::empty())?,
+                        route_match.raw_route(),
+                    ));
+                }
+            };
+        }
+        let encoding = compression.and_then(|_| negotiate(req.method(), req.headers()));
+
+        let res = if let Some(static_files) = served_app.static_file_servers.get(lookup_key) {
+            // Only the request's head is needed, and the body isn't `Sync`.
+            let (parts, _) = req.into_parts();
+            let res = static_files
+                .serve(&parts, &route_match.trailing_wildcard())
+                .await?;
+            MatchedRoute::with_response_extension(res, route_match.raw_route())
+        } else {
+            match (&trigger_config.component, &trigger_config.static_response) {
+                (Some(component), None) => {
+                    self.respond_wasm_component(
+                        served_app,
+                        req,
+                        route_match,
+                        client_addr,
+                        component,
+                        &trigger_config.executor,
+                    )
+                    .await?
+                }
+                (None, Some(static_response)) => Self::respond_static_response(static_response)?,
+                // These error cases should have been ruled out by this point but belt and braces
+                (None, None) => anyhow::bail!(
+                    "Triggers must specify either component or static_response - neither is specified for {}",
+                    route_match.raw_route()
+                ),
+                (Some(_), Some(_)) => anyhow::bail!(
+                    "Triggers must specify either component or static_response - both are specified for {}",
+                    route_match.raw_route()
+                ),
+            }
+        };
+
+        Ok(match (compression, encoding) {
+            (Some(config), Some(encoding)) => compress_response(res, config, encoding),
+            _ => res,
+        })
     }
 
     fn get_local_addr(&self) -> SocketAddr {
@@ -400,6 +443,7 @@ impl<F: RuntimeFactors> HttpServer<F> {
 
     async fn respond_wasm_component(
         self: &Arc<Self>,
+        served_app: &ServedApp<F>,
         req: Request<Body>,
         route_match: RouteMatch<'_, '_>,
         client_addr: SocketAddr,
@@ -407,46 +451,115 @@ impl<F: RuntimeFactors> HttpServer<F> {
         executor: &Option<HttpExecutorType>,
     ) -> anyhow::Result<Response<Body>> {
         // Prepare HTTP executor
-        let handler_type = self
-            .component_handler_types
+        // A lazily loaded component which hasn't been loaded yet is loaded now.
+        if !served_app.trigger_app.is_component_loaded(component_id) {
+            served_app
+                .trigger_app
+                .ensure_component_loaded(component_id)
+                .await?;
+        }
+        // Held for the whole request so that it is handled by one version of
+        // the component even if the component is reloaded meanwhile.
+        let handler = served_app
+            .component_handlers
             .get(component_id)
             .with_context(|| format!("unknown component ID {component_id:?}"))?;
+        let handler_type = &handler.handler_type;
+        let instance_pre = &handler.instance_pre;
         let executor = executor.as_ref().unwrap_or(&HttpExecutorType::Http);
-
-        let res = match executor {
-            HttpExecutorType::Http => match handler_type {
-                HandlerType::Spin => {
-                    SpinHttpExecutor
-                        .execute(self, &route_match, req, client_addr, component_id)
+        let trigger_app = &served_app.trigger_app;
+        let warm_pool = handler.warm_pool.as_deref();
+
+        let timeout = self.invocation_timeout();
+        let invocation = async {
+            match executor {
+                HttpExecutorType::Http => match handler_type {
+                    HandlerType::Spin => {
+                        SpinHttpExecutor {
+                            instance_pre,
+                            warm_pool,
+                            timeout,
+                        }
+                        .execute(
+                            self,
+                            trigger_app,
+                            &route_match,
+                            req,
+                            client_addr,
+                            component_id,
+                        )
                         .await
-                }
-                HandlerType::Wasi0_3(handler) => {
-                    Wasip3HttpExecutor(handler)
-                        .execute(self, &route_match, req, client_addr)
+                    }
+                    HandlerType::Wasi0_3(handler) => {
+                        Wasip3HttpExecutor { handler, timeout }
+                            .execute(
+                                self,
+                                trigger_app,
+                                &route_match,
+                                req,
+                                client_addr,
+                                component_id,
+                            )
+                            .await
+                    }
+                    HandlerType::Wasi0_2(_)
+                    | HandlerType::Wasi2023_11_10(_)
+                    | HandlerType::Wasi2023_10_18(_)
+                    | HandlerType::Wasi2026_03_15(_) => {
+                        WasiHttpExecutor {
+                            handler_type,
+                            instance_pre,
+                            warm_pool,
+                            timeout,
+                        }
+                        .execute(
+                            self,
+                            trigger_app,
+                            &route_match,
+                            req,
+                            client_addr,
+                            component_id,
+                        )
                         .await
-                }
-                HandlerType::Wasi0_2(_)
-                | HandlerType::Wasi2023_11_10(_)
-                | HandlerType::Wasi2023_10_18(_)
-                | HandlerType::Wasi2026_03_15(_) => {
-                    WasiHttpExecutor { handler_type }
-                        .execute(self, &route_match, req, client_addr, component_id)
+                    }
+                    HandlerType::Wagi(_) => unreachable!(),
+                },
+                HttpExecutorType::Wagi(wagi_config) => {
+                    let indices = match handler_type {
+                        HandlerType::Wagi(indices) => indices,
+                        _ => unreachable!(),
+                    };
+                    let executor = WagiHttpExecutor {
+                        wagi_config,
+                        indices,
+                        instance_pre,
+                        timeout,
+                    };
+                    executor
+                        .execute(
+                            self,
+                            trigger_app,
+                            &route_match,
+                            req,
+                            client_addr,
+                            component_id,
+                        )
                         .await
                 }
-                HandlerType::Wagi(_) => unreachable!(),
-            },
-            HttpExecutorType::Wagi(wagi_config) => {
-                let indices = match handler_type {
-                    HandlerType::Wagi(indices) => indices,
-                    _ => unreachable!(),
-                };
-                let executor = WagiHttpExecutor {
-                    wagi_config,
-                    indices,
-                };
-                executor
-                    .execute(self, &route_match, req, client_addr, component_id)
-                    .await
+            }
+        };
+        let res = match handler_type {
+            // A WASIp3 handler's worker enforces the time limit of each request it accepts.
+            HandlerType::Wasi0_3(_) => invocation.await,
+            _ => {
+                run_with_timeout(
+                    timeout,
+                    "http",
+                    trigger_app.app().id(),
+                    component_id,
+                    invocation,
+                )
+                .await
             }
         };
         match res {
@@ -454,6 +567,10 @@ impl<F: RuntimeFactors> HttpServer<F> {
                 res,
                 route_match.raw_route(),
             )),
+            Err(err) if err.is::<InvocationTimeout>() => {
+                instrument_error(&err);
+                Self::gateway_timeout(route_match.raw_route())
+            }
             Err(err) => {
                 tracing::error!("Error processing request: {err:?}");
                 instrument_error(&err);
@@ -462,12 +579,33 @@ impl<F: RuntimeFactors> HttpServer<F> {
         }
     }
 
-    pub(crate) fn trigger_instance_builder(
-        self: &'_ Arc<Self>,
+    /// Instantiates a component for a request, taking the instance from the
+    /// component's warm pool if one is ready.
+    pub(crate) async fn instantiate_component(
+        self: &Arc<Self>,
+        trigger_app: &TriggerApp<F>,
         component_id: &str,
+        instance_pre: &InstancePre<F>,
+        warm_pool: Option<&WarmPool<F>>,
         self_scheme: Option<&Scheme>,
-    ) -> anyhow::Result<TriggerInstanceBuilder<'_, F>> {
-        let mut instance_builder = self.trigger_app.prepare(component_id)?;
+    ) -> anyhow::Result<WarmInstance<F>> {
+        if let Some(instance) = warm_pool.and_then(WarmPool::take) {
+            return Ok(instance);
+        }
+        self.trigger_instance_builder(trigger_app, component_id, instance_pre.clone(), self_scheme)?
+            .instantiate(())
+            .await
+    }
+
+    pub(crate) fn trigger_instance_builder<'a>(
+        self: &Arc<Self>,
+        trigger_app: &'a TriggerApp<F>,
+        component_id: &str,
+        instance_pre: InstancePre<F>,
+        self_scheme: Option<&Scheme>,
+    ) -> anyhow::Result<TriggerInstanceBuilder<'a, F>> {
+        let mut instance_builder =
+            trigger_app.prepare_with_instance_pre(component_id, instance_pre)?;
 
         // Set up outbound HTTP request origin and service chaining
         // The outbound HTTP factor is required since both inbound and outbound wasi HTTP
@@ -507,7 +645,7 @@ impl<F: RuntimeFactors> HttpServer<F> {
 
     /// Returns spin status information.
     fn app_info(&self, route: String) -> anyhow::Result<Response<Body>> {
-        let info = AppInfo::new(self.trigger_app.app());
+        let info = AppInfo::new(self.served_app().trigger_app.app());
         let body = serde_json::to_vec_pretty(&info)?;
         Ok(MatchedRoute::with_response_extension(
             Response::builder()
@@ -517,6 +655,22 @@ impl<F: RuntimeFactors> HttpServer<F> {
         ))
     }
 
+    /// Returns whether all of the app's components have been loaded. Only
+    /// a trigger started with `--lazy-load-components` is ever not ready.
+    fn app_readiness(&self, route: String) -> anyhow::Result<Response<Body>> {
+        let (status, body) = if self.served_app().trigger_app.deferred_components_loaded() {
+            (StatusCode::OK, "ready")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "loading")
+        };
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .status(status)
+                .body(body::full(Bytes::from_static(body.as_bytes())))?,
+            route,
+        ))
+    }
+
     /// Creates an HTTP 500 response.
     fn internal_error(
         body: Option<&str>,
@@ -535,6 +689,31 @@ impl<F: RuntimeFactors> HttpServer<F> {
         ))
     }
 
+    /// Creates an HTTP 504 response, for a request which the component didn't
+    /// handle in time.
+    fn gateway_timeout(route: impl Into<String>) -> anyhow::Result<Response<Body>> {
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .status(StatusCode::GATEWAY_TIMEOUT)
+                .body(body::empty())?,
+            route,
+        ))
+    }
+
+    /// Creates a response for a request that exceeded a request limit.
+    fn limit_exceeded(
+        status: StatusCode,
+        route: impl Into<String>,
+    ) -> anyhow::Result<Response<Body>> {
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .status(status)
+                .header(http::header::CONNECTION, "close")
+                .body(body::empty())?,
+            route,
+        ))
+    }
+
     /// Creates an HTTP 404 response.
     fn not_found(kind: NotFoundRouteKind) -> anyhow::Result<Response<Body>> {
         use std::sync::atomic::{AtomicBool, Ordering};
@@ -565,6 +744,13 @@ impl<F: RuntimeFactors> HttpServer<F> {
                 server_builder.http1().max_buf_size(http1_max_buf_size);
             }
 
+            if let Some(header_read_timeout) = self.request_limits.header_read_timeout {
+                server_builder
+                    .http1()
+                    .timer(TokioTimer::new())
+                    .header_read_timeout(header_read_timeout);
+            }
+
             if let Err(err) = server_builder
                 .serve_connection(
                     TokioIo::new(stream),
@@ -591,6 +777,7 @@ impl<F: RuntimeFactors> HttpServer<F> {
     ) -> anyhow::Result<Response<HyperOutgoingBody>> {
         let span = http_span!(request, client_addr);
         let method = request.method().to_string();
+        let started = Instant::now();
         async {
             let result = self
                 .handle(
@@ -602,40 +789,25 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     client_addr,
                 )
                 .await;
-            finalize_http_span(result, method)
+            finalize_http_span(result, method, started)
         }
         .instrument(span)
         .await
     }
 
-    fn get_description_for_route(
-        &self,
-        key: &spin_http::routes::TriggerLookupKey,
-    ) -> anyhow::Result<Option<String>> {
-        if let spin_http::routes::TriggerLookupKey::Component(component_id) = key {
-            self.trigger_app
-                .app()
-                .get_component(component_id)
-                .and_then(|c| c.get_metadata(APP_DESCRIPTION_KEY).transpose())
-                .transpose()
-                .map_err(Into::into)
-        } else {
-            Ok(None)
-        }
-    }
-
     fn print_startup_msgs(&self, scheme: &str, listener: &TcpListener) -> anyhow::Result<()> {
         let local_addr = listener.local_addr()?;
         let base_url = format!("{scheme}://{local_addr:?}");
         tracing::info!("Serving {base_url}");
 
+        let served_app = self.served_app();
         match self.output_format {
             OutputFormat::Plain => {
                 terminal::step!("\nServing", "{base_url}");
                 println!("Available Routes:");
-                for (route, key) in self.router.routes() {
+                for (route, key) in served_app.router.routes() {
                     println!("  {key}: {base_url}{route}");
-                    if let Some(description) = self.get_description_for_route(key)? {
+                    if let Some(description) = served_app.get_description_for_route(key)? {
                         println!("    {description}");
                     }
                 }
@@ -656,12 +828,12 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     description: Option<String>,
                 }
                 let mut routes = Vec::new();
-                for (route, key) in self.router.routes() {
+                for (route, key) in served_app.router.routes() {
                     routes.push(RouteEntry {
                         id: key.to_string(),
                         route: route.path().to_string(),
                         wildcard: route.is_wildcard(),
-                        description: self.get_description_for_route(key)?,
+                        description: served_app.get_description_for_route(key)?,
                     });
                 }
 
@@ -672,8 +844,289 @@ impl<F: RuntimeFactors> HttpServer<F> {
         Ok(())
     }
 
-    pub(crate) fn request_deadline(&self) -> Option<Duration> {
-        self.request_deadline
+    /// Returns the time limit for a request to a component, if there is one.
+    ///
+    /// If the request timeout is a range, a value is selected from it at
+    /// random for each request.
+    fn invocation_timeout(&self) -> Option<Duration> {
+        self.reuse_config.request_deadline.or_else(|| {
+            self.reuse_config
+                .request_timeout
+                .map(|range| rand::rng().random_range(range))
+        })
+    }
+}
+
+impl<F: RuntimeFactors> ServedApp<F> {
+    fn new(trigger_app: TriggerApp<F>, reuse_config: InstanceReuseConfig) -> anyhow::Result<Self> {
+        // This needs to be a vec before building the router to handle duplicate routes
+        let component_trigger_configs = trigger_app
+            .app()
+            .trigger_configs::<HttpTriggerConfig>("http")?
+            .into_iter()
+            .map(|(trigger_id, config)| config.lookup_key(trigger_id).map(|k| (k, config)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Build router
+        let component_routes = component_trigger_configs
+            .iter()
+            .map(|(key, config)| (key, &config.route));
+        let mut duplicate_routes = Vec::new();
+        let router = Router::build("/", component_routes, Some(&mut duplicate_routes))?;
+        if !duplicate_routes.is_empty() {
+            tracing::error!(
+                "The following component routes are duplicates and will never be used:"
+            );
+            for dup in &duplicate_routes {
+                tracing::error!(
+                    "  {}: {} (duplicate of {})",
+                    dup.replaced_id,
+                    dup.route(),
+                    dup.effective_id,
+                );
+            }
+        }
+        if router.contains_reserved_route() {
+            tracing::error!(
+                "Routes under {} are handled by the Spin runtime and will never be reached",
+                spin_http::WELL_KNOWN_PREFIX
+            );
+        }
+        tracing::trace!(
+            "Constructed router: {:?}",
+            router.routes().collect::<Vec<_>>()
+        );
+
+        // Now that router is built we can merge duplicate routes by component
+        let component_trigger_configs = HashMap::from_iter(component_trigger_configs);
+
+        let static_file_servers = component_trigger_configs
+            .iter()
+            .filter_map(|(key, trigger_config)| {
+                let static_files = trigger_config.route.static_files()?;
+                Some(
+                    StaticFileServer::new(static_files, trigger_app.app())
+                        .with_context(|| format!("invalid static route for trigger '{key}'"))
+                        .map(|server| (key.clone(), server)),
+                )
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let trigger_app = Arc::new(trigger_app);
+
+        // Component ID -> executor
+        let executors: HashMap<String, Option<HttpExecutorType>> = component_trigger_configs
+            .iter()
+            .filter_map(|(key, trigger_config)| match key {
+                spin_http::routes::TriggerLookupKey::Component(component) => {
+                    Some((component.clone(), trigger_config.executor.clone()))
+                }
+                spin_http::routes::TriggerLookupKey::Trigger(_) => None,
+            })
+            .collect();
+
+        let app_id = trigger_app
+            .app()
+            .get_metadata(APP_NAME_KEY)?
+            .unwrap_or_else(|| "<unnamed>".into());
+
+        // Component ID -> warm pool size. A component which is routed to by
+        // several triggers gets the largest pool that any of them asks for.
+        let mut warm_pool_sizes: HashMap<String, usize> = HashMap::new();
+        for (key, trigger_config) in &component_trigger_configs {
+            let spin_http::routes::TriggerLookupKey::Component(component) = key else {
+                continue;
+            };
+            // Wagi instances are set up for a specific request.
+            if matches!(trigger_config.executor, Some(HttpExecutorType::Wagi(_))) {
+                continue;
+            }
+            let size = trigger_config
+                .warm_instances
+                .unwrap_or(reuse_config.warm_instance_count);
+            let max_size = warm_pool_sizes.entry(component.clone()).or_default();
+            *max_size = (*max_size).max(size);
+        }
+        warm_pool_sizes.retain(|_, size| *size > 0);
+
+        // Warm instances count against the pooling allocator's limit on live
+        // instances, so they mustn't leave none for requests to use.
+        let warm_instances = warm_pool_sizes.values().sum::<usize>();
+        if let Some(limit) = trigger_app.engine().instance_limit()
+            && warm_instances >= limit as usize
+        {
+            bail!(
+                "the app's warm pools hold {warm_instances} instances, but only {limit} \
+                 instances can be live at once (set by SPIN_WASMTIME_INSTANCE_COUNT or \
+                 SPIN_MAX_INSTANCE_COUNT); reduce --warm-instances or the routes' \
+                 `warm_instances`"
+            );
+        }
+
+        let component_handlers = Arc::new(ComponentHandlers {
+            trigger_app: Arc::downgrade(&trigger_app),
+            app_id,
+            reuse_config,
+            executors,
+            warm_pool_sizes,
+            handlers: Default::default(),
+            warm_pool_server: OnceLock::new(),
+        });
+
+        // A reloaded component may export a different handler, or the same
+        // handler at different indices. This also catches components which
+        // are loaded lazily, so it's registered before looking at which
+        // components are already loaded.
+        let reloaded_handlers = component_handlers.clone();
+        trigger_app.on_component_reload(move |component_id, pre| {
+            let handler = reloaded_handlers.build(component_id, pre)?;
+            let reloaded_handlers = reloaded_handlers.clone();
+            let component_id = component_id.to_owned();
+            Ok(move || {
+                if let Some(handler) = handler {
+                    reloaded_handlers.insert(component_id, handler);
+                }
+            })
+        });
+
+        for component in component_handlers.executors.keys() {
+            if !trigger_app.is_component_loaded(component) {
+                continue;
+            }
+            let pre = trigger_app.get_instance_pre(component)?;
+            if let Some(handler) = component_handlers.build(component, &pre)? {
+                component_handlers.insert(component.clone(), handler);
+            }
+        }
+        Ok(Self {
+            router,
+            trigger_app,
+            component_trigger_configs,
+            component_handlers,
+            static_file_servers,
+        })
+    }
+
+    /// Starts filling the app's warm pools with instances made for `server`.
+    fn start_warm_pools(&self, server: &Arc<HttpServer<F>>) {
+        self.component_handlers.start_warm_pools(server);
+    }
+
+    fn get_description_for_route(
+        &self,
+        key: &spin_http::routes::TriggerLookupKey,
+    ) -> anyhow::Result<Option<String>> {
+        if let spin_http::routes::TriggerLookupKey::Component(component_id) = key {
+            self.trigger_app
+                .app()
+                .get_component(component_id)
+                .and_then(|c| c.get_metadata(APP_DESCRIPTION_KEY).transpose())
+                .transpose()
+                .map_err(Into::into)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<F: RuntimeFactors> ComponentHandlers<F> {
+    /// Returns the handler of a loaded component.
+    fn get(&self, component_id: &str) -> Option<Arc<ComponentHandler<F>>> {
+        self.handlers.read().unwrap().get(component_id).cloned()
+    }
+
+    /// Makes the handler for an [`InstancePre`] of a component, or returns
+    /// `None` if the component isn't run by this trigger.
+    fn build(
+        &self,
+        component_id: &str,
+        pre: &InstancePre<F>,
+    ) -> anyhow::Result<Option<ComponentHandler<F>>> {
+        let Some(executor) = self.executors.get(component_id) else {
+            return Ok(None);
+        };
+        let warm_pool = self.warm_pool_sizes.get(component_id).map(|size| {
+            Arc::new(WarmPool::new(
+                self.app_id.clone(),
+                component_id.to_owned(),
+                pre.clone(),
+                *size,
+            ))
+        });
+        let handler_type = match executor {
+            None | Some(HttpExecutorType::Http) => HandlerType::from_instance_pre(
+                pre,
+                HttpHandlerState {
+                    component_id: component_id.into(),
+                    trigger_app: self.trigger_app.clone(),
+                    instance_pre: pre.clone(),
+                    reuse_config: self.reuse_config,
+                    warm_pool: warm_pool.clone(),
+                    server: Default::default(),
+                    self_scheme: Default::default(),
+                },
+            )?,
+            Some(HttpExecutorType::Wagi(wagi_config)) => {
+                anyhow::ensure!(
+                    wagi_config.entrypoint == "_start",
+                    "Wagi component '{component_id}' cannot use deprecated 'entrypoint' field"
+                );
+                HandlerType::Wagi(
+                    CommandIndices::new(pre)
+                        .map_err(anyhow::Error::from)
+                        .context("failed to find wasi command interface for wagi executor")?,
+                )
+            }
+        };
+        Ok(Some(ComponentHandler {
+            instance_pre: pre.clone(),
+            handler_type,
+            warm_pool,
+        }))
+    }
+
+    /// Puts a component's handler in place, replacing any previous handler
+    /// along with its warm pool.
+    fn insert(&self, component_id: String, handler: ComponentHandler<F>) {
+        let handler = Arc::new(handler);
+        self.handlers
+            .write()
+            .unwrap()
+            .insert(component_id, handler.clone());
+        let server = self
+            .warm_pool_server
+            .get()
+            .and_then(|(server, scheme)| Some((server.upgrade()?, scheme)));
+        if let Some((server, scheme)) = server {
+            self.start_warm_pool(&handler, &server, scheme);
+        }
+    }
+
+    /// Starts filling the warm pools with instances made for `server`,
+    /// including the pools of components which are loaded later.
+    fn start_warm_pools(&self, server: &Arc<HttpServer<F>>) {
+        let self_scheme = if server.tls_config.is_some() {
+            Scheme::HTTPS
+        } else {
+            Scheme::HTTP
+        };
+        let (_, self_scheme) = self
+            .warm_pool_server
+            .get_or_init(|| (Arc::downgrade(server), self_scheme));
+        for handler in self.handlers.read().unwrap().values() {
+            self.start_warm_pool(handler, server, self_scheme);
+        }
+    }
+
+    fn start_warm_pool(
+        &self,
+        handler: &ComponentHandler<F>,
+        server: &Arc<HttpServer<F>>,
+        self_scheme: &Scheme,
+    ) {
+        if let Some(pool) = &handler.warm_pool {
+            pool.start(server, self.trigger_app.clone(), self_scheme.clone());
+        }
     }
 }
 
@@ -758,7 +1211,6 @@ impl WorkerExpiration for HttpWorkerExpiration {
 }
 
 pub(crate) struct HttpWorkerState<F: RuntimeFactors> {
-    request_timeout: Duration,
     max_instance_reuse_count: usize,
     max_instance_concurrent_reuse_count: usize,
     _phantom: PhantomData<F>,
@@ -766,7 +1218,8 @@ pub(crate) struct HttpWorkerState<F: RuntimeFactors> {
 
 impl<F: RuntimeFactors> WorkerState for HttpWorkerState<F> {
     type StoreData = InstanceState<F::InstanceState, ()>;
-    type RequestId = ();
+    /// The request's time limit, if it has one.
+    type RequestId = Option<Duration>;
 
     fn should_accept_request(&self, concurrent_count: usize, total_count: usize) -> ShouldAccept {
         if total_count >= self.max_instance_reuse_count {
@@ -781,10 +1234,13 @@ impl<F: RuntimeFactors> WorkerState for HttpWorkerState<F> {
     fn on_request_start(
         &self,
         _: StoreContextMut<'_, Self::StoreData>,
-        _: Self::RequestId,
+        timeout: Self::RequestId,
         _: GuestTaskId,
     ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
-        Box::pin(tokio::time::sleep(self.request_timeout))
+        match timeout {
+            Some(timeout) => Box::pin(tokio::time::sleep(timeout)),
+            None => Box::pin(std::future::pending()),
+        }
     }
 
     fn drop(&self, store: Store<Self::StoreData>, result: Result<(), wasmtime::Error>) {
@@ -798,7 +1254,12 @@ impl<F: RuntimeFactors> WorkerState for HttpWorkerState<F> {
 
 pub(crate) struct HttpHandlerState<F: RuntimeFactors> {
     component_id: String,
+    // Weak, because the app (indirectly) owns this state
+    trigger_app: Weak<TriggerApp<F>>,
+    // The version of the component which this handler was made for
+    instance_pre: InstancePre<F>,
     reuse_config: InstanceReuseConfig,
+    warm_pool: Option<Arc<WarmPool<F>>>,
     server: OnceLock<Arc<HttpServer<F>>>,
     self_scheme: OnceLock<Scheme>,
 }
@@ -821,25 +1282,36 @@ impl<F: RuntimeFactors> HandlerState for HttpHandlerState<F> {
         &self,
     ) -> wasmtime::Result<Instance<Self::StoreData, Self::WorkerExpiration, Self::WorkerState>>
     {
+        let trigger_app = self
+            .trigger_app
+            .upgrade()
+            .context("application has been unloaded")
+            .to_wasmtime_result()?;
         let (instance, mut store) = self
             .server
             .get()
             .expect("server should have been set")
-            .trigger_instance_builder(&self.component_id, self.self_scheme.get())
-            .to_wasmtime_result()?
-            .instantiate(())
+            .instantiate_component(
+                &trigger_app,
+                &self.component_id,
+                &self.instance_pre,
+                self.warm_pool.as_deref(),
+                self.self_scheme.get(),
+            )
             .await
             .to_wasmtime_result()?;
-        set_request_deadline(&mut store, self.reuse_config.request_deadline);
+        set_deadline(&mut store, self.reuse_config.request_deadline);
 
         let mut store = store.into_inner();
 
         let proxy = Proxy::P3(Service::new(&mut store, &instance).unwrap());
 
+        // The longest time limit a request may be given, so that the instance
+        // doesn't expire while a request is still within its own time limit.
         let request_timeout = self
             .reuse_config
-            .request_timeout
-            .map(|range| rand::rng().random_range(range))
+            .request_deadline
+            .or_else(|| self.reuse_config.request_timeout.map(Range::upper))
             .unwrap_or(Duration::MAX);
 
         Ok(Instance {
@@ -857,7 +1329,6 @@ impl<F: RuntimeFactors> HandlerState for HttpHandlerState<F> {
                 sleep: tokio::time::sleep(Duration::MAX),
             },
             state: HttpWorkerState {
-                request_timeout,
                 max_instance_reuse_count: rand::rng()
                     .random_range(self.reuse_config.max_instance_reuse_count),
                 max_instance_concurrent_reuse_count: rand::rng()
@@ -867,3 +1338,418 @@ impl<F: RuntimeFactors> HandlerState for HttpHandlerState<F> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_outbound_networking::OutboundNetworkingFactor;
+    use spin_factor_variables::VariablesFactor;
+    use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
+    use spin_factors_executor::{ComponentLoader, FactorsExecutor, TriggerDependenciesComposer};
+    use wasmtime::component::Component;
+
+    use super::*;
+
+    #[derive(RuntimeFactors)]
+    struct TestFactors {
+        wasi: WasiFactor,
+        variables: VariablesFactor,
+        networking: OutboundNetworkingFactor,
+        http: OutboundHttpFactor,
+    }
+
+    /// A component which is detected as handling Spin HTTP requests.
+    const SPIN_HANDLER: &str =
+        r#"(component (instance $h) (export "fermyon:spin/inbound-http" (instance $h)))"#;
+
+    /// A Spin HTTP handler module which never returns.
+    const LOOPING_SPIN_MODULE: &str = r#"
+        (module
+          (memory (export "memory") 4)
+          (global $next (mut i32) (i32.const 1024))
+          ;; A bump allocator, which is enough for the adapter's state.
+          (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr
+              (i32.and
+                (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+                (i32.sub (i32.const 0) (local.get 2))))
+            (global.set $next (i32.add (local.get $ptr) (local.get 3)))
+            (local.get $ptr))
+          (func (export "canonical_abi_free") (param i32 i32 i32))
+          (func (export "handle-http-request")
+            (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)
+            (loop $forever (br $forever))
+            unreachable))
+    "#;
+
+    /// A Wagi command module which never exits.
+    const LOOPING_WAGI_MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "_start")
+            (loop $forever (br $forever))))
+    "#;
+
+    /// Loads every component from the same Wasm or WAT.
+    struct TestComponentLoader(Vec<u8>);
+
+    #[spin_core::async_trait]
+    impl ComponentLoader<TestFactors, ()> for TestComponentLoader {
+        async fn load_component(
+            &self,
+            engine: &wasmtime::Engine,
+            _component: &spin_app::AppComponent,
+            _trigger_dependencies_composer: &impl TriggerDependenciesComposer,
+        ) -> anyhow::Result<Component> {
+            Ok(Component::new(engine, &self.0)?)
+        }
+    }
+
+    async fn static_app(route: &str, body: &str) -> anyhow::Result<spin_app::locked::LockedApp> {
+        let manifest = toml::from_str(&format!(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "reload-test"
+            [[trigger.http]]
+            route = "{route}"
+            static_response = {{ body = "{body}" }}
+            "#
+        ))?;
+        spin_factors_test::build_locked_app(&manifest).await
+    }
+
+    async fn trigger_app(
+        executor: &Arc<FactorsExecutor<TestFactors, ()>>,
+        locked: spin_app::locked::LockedApp,
+    ) -> anyhow::Result<TriggerApp<TestFactors>> {
+        let app = spin_app::App::new("reload-test", locked);
+        executor
+            .clone()
+            .configure_app(app, Default::default())
+            .await
+    }
+
+    async fn test_server() -> anyhow::Result<(
+        Arc<FactorsExecutor<TestFactors, ()>>,
+        Arc<HttpServer<TestFactors>>,
+    )> {
+        let executor = executor()?;
+        let locked = static_app("/old", "old").await?;
+        let server = HttpServer::new(
+            (std::net::Ipv4Addr::LOCALHOST, 0).into(),
+            None,
+            false,
+            trigger_app(&executor, locked).await?,
+            Default::default(),
+        )?;
+        Ok((executor, Arc::new(server)))
+    }
+
+    fn executor() -> anyhow::Result<Arc<FactorsExecutor<TestFactors, ()>>> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+            variables: VariablesFactor::default(),
+            networking: OutboundNetworkingFactor::new(),
+            http: OutboundHttpFactor::default(),
+        };
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        Ok(Arc::new(FactorsExecutor::new(engine_builder, factors)?))
+    }
+
+    async fn wait_until_ready(pool: &WarmPool<TestFactors>, count: usize) -> anyhow::Result<()> {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while pool.ready_count() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .with_context(|| format!("warm pool didn't fill to {count} instances"))
+    }
+
+    /// Serves an app whose `hello` component is routed to from `/hello`, with
+    /// any further settings for the route given in `trigger`.
+    async fn component_server(
+        executor: &Arc<FactorsExecutor<TestFactors, ()>>,
+        trigger: &str,
+        loader: &TestComponentLoader,
+        reuse_config: InstanceReuseConfig,
+    ) -> anyhow::Result<Arc<HttpServer<TestFactors>>> {
+        let manifest = toml::from_str(&format!(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "component-test"
+            [[trigger.http]]
+            route = "/hello"
+            component = "hello"
+            {trigger}
+            [component.hello]
+            source = "hello.wasm"
+            "#
+        ))?;
+        let locked = spin_factors_test::build_locked_app(&manifest).await?;
+        let app = spin_app::App::new("component-test", locked);
+        let trigger_app = executor
+            .clone()
+            .load_app(app, Default::default(), loader, Some("http"), ())
+            .await?;
+        Ok(Arc::new(HttpServer::new(
+            (std::net::Ipv4Addr::LOCALHOST, 0).into(),
+            None,
+            false,
+            trigger_app,
+            HttpServerConfig {
+                reuse_config,
+                ..Default::default()
+            },
+        )?))
+    }
+
+    /// Returns the status of a request to a component made from `module`
+    /// which runs past its deadline.
+    async fn looping_component_status(module: &str, trigger: &str) -> anyhow::Result<StatusCode> {
+        let component = spin_componentize::componentize(&wat::parse_str(module)?)?;
+        let server = component_server(
+            &executor()?,
+            trigger,
+            &TestComponentLoader(component),
+            InstanceReuseConfig::single_use_with_request_deadline(Duration::from_millis(100)),
+        )
+        .await?;
+        Ok(get(&server, "/hello").await?.0)
+    }
+
+    async fn get(
+        server: &Arc<HttpServer<TestFactors>>,
+        path: &str,
+    ) -> anyhow::Result<(StatusCode, Bytes)> {
+        let req = Request::get(format!("http://localhost{path}")).body(body::empty())?;
+        let res = server
+            .handle(req, Scheme::HTTP, ([127, 0, 0, 1], 12345).into())
+            .await?;
+        let status = res.status();
+        Ok((status, res.into_body().collect().await?.to_bytes()))
+    }
+
+    #[tokio::test]
+    async fn reload_swaps_the_served_app() -> anyhow::Result<()> {
+        let (executor, server) = test_server().await?;
+        assert_eq!(get(&server, "/old").await?, (StatusCode::OK, "old".into()));
+
+        let locked = static_app("/new", "new").await?;
+        server.reload(trigger_app(&executor, locked).await?)?;
+
+        assert_eq!(get(&server, "/new").await?, (StatusCode::OK, "new".into()));
+        assert_eq!(get(&server, "/old").await?.0, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_app_is_not_reloaded() -> anyhow::Result<()> {
+        let (executor, server) = test_server().await?;
+
+        let mut locked = static_app("/new", "new").await?;
+        locked.metadata.insert(
+            "triggers".into(),
+            serde_json::json!({ "http": { "base": "/api" } }),
+        );
+        let err = server
+            .reload(trigger_app(&executor, locked).await?)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("deprecated trigger 'base'"),
+            "{err}"
+        );
+
+        assert_eq!(get(&server, "/old").await?, (StatusCode::OK, "old".into()));
+        assert_eq!(get(&server, "/new").await?.0, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn in_flight_requests_finish_on_the_previous_app() -> anyhow::Result<()> {
+        let (executor, server) = test_server().await?;
+
+        // A request which was routed before the reload...
+        let served_app = server.served_app();
+        let route_match = served_app.router.route("/old")?;
+
+        let locked = static_app("/new", "new").await?;
+        server.reload(trigger_app(&executor, locked).await?)?;
+
+        // ...is still handled by the app it was routed by.
+        let req = Request::get("http://localhost/old").body(body::empty())?;
+        let res = server
+            .handle_route(
+                &served_app,
+                req,
+                route_match,
+                Scheme::HTTP,
+                ([127, 0, 0, 1], 12345).into(),
+            )
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.into_body().collect().await?.to_bytes(), "old");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn static_routes_serve_files_from_directory() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("index.html"), "<h1>home</h1>")?;
+        std::fs::write(dir.path().join("site.css"), "h1 {}")?;
+        let directory = toml::Value::from(dir.path().to_str().unwrap());
+        let manifest = toml::from_str(&format!(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "static-test"
+            [[trigger.http]]
+            route = {{ static = "/assets/...", directory = {directory} }}
+            "#
+        ))?;
+        let locked = spin_factors_test::build_locked_app(&manifest).await?;
+        let executor = executor()?;
+        let server = Arc::new(HttpServer::new(
+            (std::net::Ipv4Addr::LOCALHOST, 0).into(),
+            None,
+            false,
+            trigger_app(&executor, locked).await?,
+            Default::default(),
+        )?);
+
+        assert_eq!(
+            get(&server, "/assets/site.css").await?,
+            (StatusCode::OK, "h1 {}".into())
+        );
+        assert_eq!(
+            get(&server, "/assets/").await?,
+            (StatusCode::OK, "<h1>home</h1>".into())
+        );
+        assert_eq!(
+            get(&server, "/assets/missing.js").await?.0,
+            StatusCode::NOT_FOUND
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn static_routes_resolve_relative_directories_against_the_manifest() -> anyhow::Result<()>
+    {
+        let manifest = toml::toml! {
+            spin_manifest_version = 2
+            [application]
+            name = "static-test"
+            [[trigger.http]]
+            route = { static = "/assets/...", directory = "dist" }
+        };
+        let locked = spin_factors_test::build_locked_app(&manifest).await?;
+        let origin = locked.metadata["origin"].as_str().unwrap();
+        let app_dir = spin_common::url::parse_file_url(origin)?
+            .parent()
+            .unwrap()
+            .to_owned();
+        std::fs::create_dir_all(app_dir.join("dist"))?;
+        std::fs::write(app_dir.join("dist/site.css"), "h1 {}")?;
+
+        let executor = executor()?;
+        let result = async {
+            let server = Arc::new(HttpServer::new(
+                (std::net::Ipv4Addr::LOCALHOST, 0).into(),
+                None,
+                false,
+                trigger_app(&executor, locked).await?,
+                Default::default(),
+            )?);
+            get(&server, "/assets/site.css").await
+        }
+        .await;
+        std::fs::remove_dir_all(&app_dir)?;
+
+        assert_eq!(result?, (StatusCode::OK, "h1 {}".into()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn warm_pools_are_filled_taken_from_and_replaced_on_reload() -> anyhow::Result<()> {
+        let loader = TestComponentLoader(SPIN_HANDLER.into());
+        let server = component_server(
+            &executor()?,
+            "warm_instances = 2",
+            &loader,
+            Default::default(),
+        )
+        .await?;
+        let served_app = server.served_app();
+        let handler = served_app.component_handlers.get("hello").unwrap();
+        let pool = handler.warm_pool.clone().unwrap();
+
+        // Until the server starts the pools, requests miss and instantiate
+        // the component themselves.
+        server
+            .instantiate_component(
+                &served_app.trigger_app,
+                "hello",
+                &handler.instance_pre,
+                Some(&pool),
+                None,
+            )
+            .await?;
+        assert_eq!(pool.ready_count(), 0);
+
+        // Once started, the pool is filled in the background...
+        served_app.start_warm_pools(&server);
+        wait_until_ready(&pool, 2).await?;
+
+        // ...requests take instances from it...
+        server
+            .instantiate_component(
+                &served_app.trigger_app,
+                "hello",
+                &handler.instance_pre,
+                Some(&pool),
+                None,
+            )
+            .await?;
+        assert_eq!(pool.ready_count(), 1);
+
+        // ...and those taken are replaced.
+        wait_until_ready(&pool, 2).await?;
+
+        // Reloading the component replaces its pool with one of instances of
+        // the reloaded component, which is filled in turn.
+        let reloaded = served_app
+            .trigger_app
+            .component_reloader()
+            .reload_component("hello", &loader, &())
+            .await?;
+        assert!(reloaded);
+        let reloaded_pool = served_app
+            .component_handlers
+            .get("hello")
+            .unwrap()
+            .warm_pool
+            .clone()
+            .unwrap();
+        assert!(!Arc::ptr_eq(&pool, &reloaded_pool));
+        wait_until_ready(&reloaded_pool, 2).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn spin_handler_which_runs_too_long_times_out() -> anyhow::Result<()> {
+        let status = looping_component_status(LOOPING_SPIN_MODULE, "").await?;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wagi_handler_which_runs_too_long_times_out() -> anyhow::Result<()> {
+        let status =
+            looping_component_status(LOOPING_WAGI_MODULE, r#"executor = { type = "wagi" }"#)
+                .await?;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use http_body_util::BodyExt;
@@ -6,24 +6,29 @@ use hyper::{Request, Response};
 use spin_factors::RuntimeFactors;
 use spin_http::body;
 use spin_http::routes::RouteMatch;
+use spin_trigger::timeout::set_deadline;
 use spin_world::v1::http_types;
 use tracing::{Level, instrument};
 
 use crate::{
-    Body, HttpServer,
+    Body, HttpServer, InstancePre, TriggerApp,
     headers::{append_headers, prepare_request_headers},
-    server::set_request_deadline,
+    warm_pool::WarmPool,
 };
 
 /// An [`HttpExecutor`] that uses the `fermyon:spin/inbound-http` interface.
-#[derive(Clone)]
-pub struct SpinHttpExecutor;
+pub struct SpinHttpExecutor<'a, F: RuntimeFactors> {
+    pub instance_pre: &'a InstancePre<F>,
+    pub warm_pool: Option<&'a WarmPool<F>>,
+    pub timeout: Option<Duration>,
+}
 
-impl SpinHttpExecutor {
+impl<F: RuntimeFactors> SpinHttpExecutor<'_, F> {
     #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.lookup_key().to_string())))]
-    pub async fn execute<F: RuntimeFactors>(
+    pub async fn execute(
         &self,
         server: &Arc<HttpServer<F>>,
+        trigger_app: &TriggerApp<F>,
         route_match: &RouteMatch<'_, '_>,
         req: Request<Body>,
         client_addr: SocketAddr,
@@ -32,10 +37,15 @@ impl SpinHttpExecutor {
         tracing::trace!("Executing request using the Spin executor for component {component_id}");
 
         let (instance, mut store) = server
-            .trigger_instance_builder(component_id, req.uri().scheme())?
-            .instantiate(())
+            .instantiate_component(
+                trigger_app,
+                component_id,
+                self.instance_pre,
+                self.warm_pool,
+                req.uri().scheme(),
+            )
             .await?;
-        set_request_deadline(&mut store, server.request_deadline());
+        set_deadline(&mut store, self.timeout);
 
         let headers = prepare_request_headers(&req, route_match, client_addr)?;
         // Expects here are safe since we have already checked that this
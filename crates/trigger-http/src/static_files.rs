@@ -0,0 +1,656 @@
+//! Serving static files directly from the host for `static` routes.
+
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, bail};
+use futures::TryStreamExt;
+use http::{HeaderMap, HeaderValue, Method, Response, StatusCode, header, request};
+use http_body::Frame;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Bytes;
+use spin_app::{App, MetadataKey};
+use spin_common::url::parse_file_url;
+use spin_http::{body, routes::HttpStaticRoute};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use wasmtime_wasi_http::p2::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// The locked app metadata key holding the URL the app was loaded from.
+const ORIGIN_KEY: MetadataKey = MetadataKey::new("origin");
+
+/// The size of chunks read from files when streaming them to the client.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Precompressed file variants, in order of preference.
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// Serves the files for a single `static` route.
+pub(crate) struct StaticFileServer {
+    /// The directories to serve, most specific mount path first.
+    roots: Vec<StaticRoot>,
+    index: Vec<String>,
+    fallback: Option<String>,
+    compress: bool,
+    cache_control: Option<HeaderValue>,
+}
+
+/// A host directory served under a path relative to the route.
+#[derive(Debug)]
+struct StaticRoot {
+    /// The path segments under which the directory is served.
+    mount: Vec<String>,
+    /// The canonicalized host directory.
+    dir: PathBuf,
+}
+
+/// A file resolved from a request path.
+struct ResolvedFile {
+    path: PathBuf,
+    metadata: std::fs::Metadata,
+}
+
+enum Resolution {
+    File(ResolvedFile),
+    /// The path names a directory and must be requested with a trailing slash
+    /// so that relative links in its index file resolve correctly.
+    AddTrailingSlash,
+    NotFound,
+}
+
+impl StaticFileServer {
+    /// Creates a server for the given route configuration.
+    pub(crate) fn new(config: &HttpStaticRoute, app: &App) -> anyhow::Result<Self> {
+        let roots = match (&config.directory, &config.component_files) {
+            (Some(directory), None) => {
+                let dir = Self::resolve_directory(directory, app)?;
+                vec![StaticRoot::new(vec![], &dir)?]
+            }
+            (None, Some(component_id)) => {
+                let component = app.get_component(component_id).with_context(|| {
+                    format!(
+                        "static route component_files refers to unknown component '{component_id}'"
+                    )
+                })?;
+                let mut roots = component
+                    .files()
+                    .map(|mount| {
+                        let source = mount.content.source.as_deref().with_context(|| {
+                            format!("Missing 'source' on files mount {mount:?}")
+                        })?;
+                        let mount_path = mount.path.to_string_lossy();
+                        let segments = path_segments(&mount_path)
+                            .context("invalid files mount path")?
+                            .into_iter()
+                            .map(str::to_owned)
+                            .collect();
+                        StaticRoot::new(segments, &parse_file_url(source)?)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                roots.sort_by_key(|root| std::cmp::Reverse(root.mount.len()));
+                roots
+            }
+            _ => bail!("static routes must specify exactly one of directory or component_files"),
+        };
+        let cache_control = config
+            .cache_control
+            .as_deref()
+            .map(HeaderValue::from_str)
+            .transpose()
+            .context("invalid static route cache_control")?;
+        Ok(Self {
+            roots,
+            index: config.index.clone(),
+            fallback: config.fallback.clone(),
+            compress: config.compress,
+            cache_control,
+        })
+    }
+
+    /// Resolves `directory` against the directory containing the manifest if it is relative.
+    fn resolve_directory(directory: &str, app: &App) -> anyhow::Result<PathBuf> {
+        let directory = Path::new(directory);
+        if directory.is_absolute() {
+            return Ok(directory.to_owned());
+        }
+        let origin = app
+            .get_metadata(ORIGIN_KEY)?
+            .filter(|origin| origin.starts_with("file:"))
+            .with_context(|| {
+                format!(
+                    "static directory {directory:?} is relative, but the app was not loaded from a local manifest"
+                )
+            })?;
+        let manifest_path = parse_file_url(&origin)?;
+        let app_dir = manifest_path.parent().unwrap_or(Path::new("."));
+        Ok(app_dir.join(directory))
+    }
+
+    /// Responds to the request with head `req`, where `path` is the part of
+    /// the request path matched by the route's trailing wildcard.
+    pub(crate) async fn serve(
+        &self,
+        req: &request::Parts,
+        path: &str,
+    ) -> anyhow::Result<Response<Body>> {
+        if req.method != Method::GET && req.method != Method::HEAD {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "GET, HEAD")
+                .body(body::empty())?);
+        }
+
+        let file = match self.resolve(path).await {
+            Resolution::File(file) => file,
+            Resolution::AddTrailingSlash => {
+                let mut location = format!("{}/", req.uri.path());
+                if let Some(query) = req.uri.query() {
+                    location = format!("{location}?{query}");
+                }
+                return Ok(Response::builder()
+                    .status(StatusCode::PERMANENT_REDIRECT)
+                    .header(header::LOCATION, location)
+                    .body(body::empty())?);
+            }
+            Resolution::NotFound => match self.resolve_fallback().await {
+                Some(file) => file,
+                None => {
+                    return Ok(Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(body::empty())?);
+                }
+            },
+        };
+
+        self.respond_file(req, file).await
+    }
+
+    async fn resolve(&self, path: &str) -> Resolution {
+        let decoded = percent_encoding::percent_decode_str(path).decode_utf8_lossy();
+        let Some(segments) = path_segments(&decoded) else {
+            return Resolution::NotFound;
+        };
+        let Some(candidate) = self.find_in_roots(&segments).await else {
+            return Resolution::NotFound;
+        };
+        if candidate.metadata.is_file() {
+            return Resolution::File(candidate);
+        }
+        for index in &self.index {
+            let index_file = self.checked_file(&candidate.path.join(index)).await;
+            let Some(file) = index_file.filter(|file| file.metadata.is_file()) else {
+                continue;
+            };
+            if !decoded.ends_with('/') {
+                return Resolution::AddTrailingSlash;
+            }
+            return Resolution::File(file);
+        }
+        Resolution::NotFound
+    }
+
+    async fn resolve_fallback(&self) -> Option<ResolvedFile> {
+        let fallback = self.fallback.as_deref()?;
+        let segments = path_segments(fallback)?;
+        self.find_in_roots(&segments)
+            .await
+            .filter(|file| file.metadata.is_file())
+    }
+
+    /// Finds the file or directory at `segments` in the most specific root that has it.
+    async fn find_in_roots(&self, segments: &[&str]) -> Option<ResolvedFile> {
+        for root in &self.roots {
+            let Some(rest) = strip_mount(segments, &root.mount) else {
+                continue;
+            };
+            let path = rest.iter().fold(root.dir.clone(), |path, s| path.join(s));
+            if let Some(file) = self.checked_file(&path).await {
+                return Some(file);
+            }
+        }
+        None
+    }
+
+    /// Returns the file at `path` if it exists and does not escape the served
+    /// directories via symlinks.
+    async fn checked_file(&self, path: &Path) -> Option<ResolvedFile> {
+        let path = tokio::fs::canonicalize(path).await.ok()?;
+        if !self.roots.iter().any(|root| path.starts_with(&root.dir)) {
+            tracing::warn!("Refusing to serve {path:?} outside of static root");
+            return None;
+        }
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        Some(ResolvedFile { path, metadata })
+    }
+
+    async fn respond_file(
+        &self,
+        req: &request::Parts,
+        file: ResolvedFile,
+    ) -> anyhow::Result<Response<Body>> {
+        let content_type = content_type(&file.path);
+
+        // Range requests are served from the uncompressed file so that
+        // offsets are meaningful regardless of the client's encodings.
+        let range_header = req.headers.get(header::RANGE);
+        let (file, encoding) = if self.compress && range_header.is_none() {
+            self.precompressed_variant(&req.headers, file).await
+        } else {
+            (file, None)
+        };
+
+        let etag = etag(&file.metadata, encoding);
+        let mut response = Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::ETAG, &etag)
+            .header(header::ACCEPT_RANGES, "bytes");
+        if let Some(cache_control) = &self.cache_control {
+            response = response.header(header::CACHE_CONTROL, cache_control);
+        }
+        if self.compress {
+            response = response.header(header::VARY, "accept-encoding");
+        }
+        if let Some(encoding) = encoding {
+            response = response.header(header::CONTENT_ENCODING, encoding);
+        }
+
+        if let Some(if_none_match) = req.headers.get(header::IF_NONE_MATCH)
+            && etag_matches(if_none_match, &etag)
+        {
+            return Ok(response
+                .status(StatusCode::NOT_MODIFIED)
+                .body(body::empty())?);
+        }
+
+        let len = file.metadata.len();
+        let if_range_matches = req
+            .headers
+            .get(header::IF_RANGE)
+            .is_none_or(|if_range| if_range.as_bytes() == etag.as_bytes());
+        let range = match range_header.filter(|_| if_range_matches) {
+            Some(range) => parse_range(range, len),
+            None => None,
+        };
+        let (start, end) = match range {
+            Some(Ok((start, end))) => {
+                response = response
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"));
+                (start, end + 1)
+            }
+            Some(Err(())) => {
+                return Ok(response
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                    .body(body::empty())?);
+            }
+            None => (0, len),
+        };
+
+        response = response.header(header::CONTENT_LENGTH, end - start);
+        if req.method == Method::HEAD {
+            return Ok(response.body(body::empty())?);
+        }
+        let mut handle = tokio::fs::File::open(&file.path)
+            .await
+            .with_context(|| format!("failed to open static file {:?}", file.path))?;
+        if start > 0 {
+            handle.seek(SeekFrom::Start(start)).await?;
+        }
+        Ok(response.body(file_body(handle, end - start))?)
+    }
+
+    /// Returns a precompressed variant of `file` which the client accepts, if any.
+    async fn precompressed_variant(
+        &self,
+        headers: &HeaderMap,
+        file: ResolvedFile,
+    ) -> (ResolvedFile, Option<&'static str>) {
+        for (encoding, extension) in ENCODINGS {
+            if !accepts_encoding(headers, encoding) {
+                continue;
+            }
+            let mut variant = file.path.clone().into_os_string();
+            variant.push(".");
+            variant.push(extension);
+            if let Some(variant) = self.checked_file(Path::new(&variant)).await
+                && variant.metadata.is_file()
+            {
+                return (variant, Some(encoding));
+            }
+        }
+        (file, None)
+    }
+}
+
+impl StaticRoot {
+    fn new(mount: Vec<String>, dir: &Path) -> anyhow::Result<Self> {
+        let dir = dir
+            .canonicalize()
+            .with_context(|| format!("static file directory {dir:?} does not exist"))?;
+        anyhow::ensure!(
+            dir.is_dir(),
+            "static file source {dir:?} is not a directory"
+        );
+        Ok(Self { mount, dir })
+    }
+}
+
+/// Splits a request path into segments, returning `None` if it tries to
+/// traverse outside of the served directory.
+fn path_segments(path: &str) -> Option<Vec<&str>> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            s if s.contains('\\') || s.contains('\0') => return None,
+            s => segments.push(s),
+        }
+    }
+    Some(segments)
+}
+
+/// Returns the segments remaining after `mount` if `segments` is under it.
+fn strip_mount<'a, 'b>(segments: &'a [&'b str], mount: &[String]) -> Option<&'a [&'b str]> {
+    if segments.len() < mount.len() || !segments.iter().zip(mount).all(|(s, m)| s == m) {
+        return None;
+    }
+    Some(&segments[mount.len()..])
+}
+
+fn etag(metadata: &std::fs::Metadata, encoding: Option<&str>) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+        .as_nanos();
+    match encoding {
+        Some(encoding) => format!("\"{:x}-{modified:x}-{encoding}\"", metadata.len()),
+        None => format!("\"{:x}-{modified:x}\"", metadata.len()),
+    }
+}
+
+/// Checks an `If-None-Match` header against `etag`, using weak comparison.
+fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let rejected = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            name.eq_ignore_ascii_case(encoding) && !rejected
+        })
+}
+
+/// Parses a single byte range, returning the inclusive start and end offsets.
+///
+/// Returns `None` if the header should be ignored (it is malformed or requests
+/// multiple ranges) and `Some(Err(()))` if the range cannot be satisfied.
+fn parse_range(range: &HeaderValue, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = range.to_str().ok()?.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            if suffix == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let start = start.parse().ok()?;
+            let end: u64 = end.parse().ok()?;
+            if end < start {
+                return None;
+            }
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end)))
+}
+
+/// Streams `len` bytes from `file` in chunks.
+fn file_body(file: tokio::fs::File, len: u64) -> Body {
+    let stream = futures::stream::try_unfold((file, len), |(mut file, remaining)| async move {
+        if remaining == 0 {
+            return Ok(None);
+        }
+        let mut buf = vec![0; remaining.min(CHUNK_SIZE) as usize];
+        let read = file.read(&mut buf).await?;
+        if read == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        }
+        buf.truncate(read);
+        Ok(Some((
+            Frame::data(Bytes::from(buf)),
+            (file, remaining - read as u64),
+        )))
+    });
+    StreamBody::new(stream.map_err(|err| ErrorCode::InternalError(Some(err.to_string()))))
+        .boxed_unsync()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(dir: &Path, fallback: Option<&str>) -> StaticFileServer {
+        StaticFileServer {
+            roots: vec![StaticRoot::new(vec![], dir).unwrap()],
+            index: vec!["index.html".into()],
+            fallback: fallback.map(Into::into),
+            compress: true,
+            cache_control: None,
+        }
+    }
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> request::Parts {
+        let mut builder = http::Request::get(path);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    async fn body_string(response: Response<Body>) -> String {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "home").unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/index.html"), "docs").unwrap();
+        std::fs::write(dir.path().join("app.js"), "0123456789").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), "gzipped").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn serves_files_and_index() {
+        let dir = fixture();
+        let server = server(dir.path(), None);
+
+        let res = server
+            .serve(&request("/app.js", &[]), "/app.js")
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(body_string(res).await, "0123456789");
+
+        let res = server.serve(&request("/", &[]), "/").await.unwrap();
+        assert_eq!(body_string(res).await, "home");
+
+        let res = server.serve(&request("/docs", &[]), "/docs").await.unwrap();
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers()[header::LOCATION], "/docs/");
+
+        let res = server
+            .serve(&request("/docs/", &[]), "/docs/")
+            .await
+            .unwrap();
+        assert_eq!(body_string(res).await, "docs");
+    }
+
+    #[tokio::test]
+    async fn traversal_and_missing_files_are_not_found() {
+        let dir = fixture();
+        let server = server(dir.path(), None);
+        for path in ["/../secret", "/%2e%2e/secret", "/missing.txt"] {
+            let res = server.serve(&request("/", &[]), path).await.unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_files_use_fallback() {
+        let dir = fixture();
+        let server = server(dir.path(), Some("index.html"));
+        let res = server
+            .serve(&request("/app/settings", &[]), "/app/settings")
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_string(res).await, "home");
+    }
+
+    #[tokio::test]
+    async fn etag_revalidation_returns_not_modified() {
+        let dir = fixture();
+        let server = server(dir.path(), None);
+        let res = server
+            .serve(&request("/app.js", &[]), "/app.js")
+            .await
+            .unwrap();
+        let etag = res.headers()[header::ETAG].to_str().unwrap().to_owned();
+
+        let res = server
+            .serve(
+                &request("/app.js", &[("if-none-match", etag.as_str())]),
+                "/app.js",
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn ranges_are_served() {
+        let dir = fixture();
+        let server = server(dir.path(), None);
+        let res = server
+            .serve(&request("/app.js", &[("range", "bytes=2-4")]), "/app.js")
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(body_string(res).await, "234");
+
+        let res = server
+            .serve(&request("/app.js", &[("range", "bytes=-3")]), "/app.js")
+            .await
+            .unwrap();
+        assert_eq!(body_string(res).await, "789");
+
+        let res = server
+            .serve(&request("/app.js", &[("range", "bytes=10-")]), "/app.js")
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+
+    #[tokio::test]
+    async fn precompressed_variants_are_negotiated() {
+        let dir = fixture();
+        let server = server(dir.path(), None);
+        let res = server
+            .serve(
+                &request("/app.js", &[("accept-encoding", "br;q=0, gzip")]),
+                "/app.js",
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(body_string(res).await, "gzipped");
+
+        let res = server
+            .serve(
+                &request("/app.js", &[("accept-encoding", "gzip;q=0")]),
+                "/app.js",
+            )
+            .await
+            .unwrap();
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io::Cursor, net::SocketAddr, sync::Arc};
+use std::{io::Cursor, net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, ensure};
 use http_body_util::BodyExt;
@@ -6,23 +6,27 @@ use hyper::{Request, Response};
 use spin_factor_wasi::WasiFactor;
 use spin_factors::RuntimeFactors;
 use spin_http::{config::WagiTriggerConfig, routes::RouteMatch, wagi};
+use spin_trigger::timeout::set_deadline;
 use tracing::{Level, instrument};
 use wasmtime_wasi::p2::bindings::CommandIndices;
 use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
 use wasmtime_wasi_http::p2::body::HyperIncomingBody as Body;
 
-use crate::{HttpServer, headers::compute_default_headers, server::set_request_deadline};
+use crate::{HttpServer, InstancePre, TriggerApp, headers::compute_default_headers};
 
-pub struct WagiHttpExecutor<'a> {
+pub struct WagiHttpExecutor<'a, F: RuntimeFactors> {
     pub wagi_config: &'a WagiTriggerConfig,
     pub indices: &'a CommandIndices,
+    pub instance_pre: &'a InstancePre<F>,
+    pub timeout: Option<Duration>,
 }
 
-impl WagiHttpExecutor<'_> {
+impl<F: RuntimeFactors> WagiHttpExecutor<'_, F> {
     #[instrument(name = "spin_trigger_http.execute_wagi", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wagi_component {}", route_match.lookup_key().to_string())))]
-    pub async fn execute<F: RuntimeFactors>(
+    pub async fn execute(
         &self,
         server: &Arc<HttpServer<F>>,
+        trigger_app: &TriggerApp<F>,
         route_match: &RouteMatch<'_, '_>,
         req: Request<Body>,
         client_addr: SocketAddr,
@@ -72,8 +76,12 @@ impl WagiHttpExecutor<'_> {
 
         let stdout = MemoryOutputPipe::new(usize::MAX);
 
-        let mut instance_builder =
-            server.trigger_instance_builder(component_id, parts.uri.scheme())?;
+        let mut instance_builder = server.trigger_instance_builder(
+            trigger_app,
+            component_id,
+            self.instance_pre.clone(),
+            parts.uri.scheme(),
+        )?;
         let wasi_builder = instance_builder
             .factor_builder::<WasiFactor>()
             .context("The wagi HTTP trigger was configured without the required wasi support")?;
@@ -85,7 +93,7 @@ impl WagiHttpExecutor<'_> {
         wasi_builder.stdout(stdout.clone());
 
         let (instance, mut store) = instance_builder.instantiate(()).await?;
-        set_request_deadline(&mut store, server.request_deadline());
+        set_deadline(&mut store, self.timeout);
 
         let command = self.indices.load(&mut store, &instance)?;
 
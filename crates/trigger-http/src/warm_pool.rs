@@ -0,0 +1,203 @@
+//! Pools of component instances which are instantiated ahead of requests.
+//!
+//! Each component with a warm pool has a background task which keeps the
+//! pool topped up. A request which needs a new instance takes one from the
+//! pool if one is ready (a hit) and instantiates the component itself
+//! otherwise (a miss). Either way, the task is woken to replace it.
+//!
+//! A pool holds instances of a single [`InstancePre`], so when a component is
+//! reloaded its pool is replaced rather than refilled.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, Weak},
+};
+
+use http::uri::Scheme;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::InstanceState;
+use tokio::sync::mpsc;
+
+use crate::{HttpServer, InstancePre, TriggerApp};
+
+/// An instance of a component, with the store it was instantiated in.
+pub(crate) type WarmInstance<F> = (
+    spin_core::Instance,
+    spin_core::Store<InstanceState<<F as RuntimeFactors>::InstanceState, ()>>,
+);
+
+/// A pool of instances of a single component.
+pub(crate) struct WarmPool<F: RuntimeFactors> {
+    app_id: String,
+    component_id: String,
+    instance_pre: InstancePre<F>,
+    slots: Mutex<Slots<WarmInstance<F>>>,
+    refill_tx: mpsc::UnboundedSender<()>,
+    // Taken by the task which replenishes the pool when it is started.
+    refill_rx: Mutex<Option<mpsc::UnboundedReceiver<()>>>,
+}
+
+impl<F: RuntimeFactors> WarmPool<F> {
+    pub fn new(
+        app_id: String,
+        component_id: String,
+        instance_pre: InstancePre<F>,
+        size: usize,
+    ) -> Self {
+        let (refill_tx, refill_rx) = mpsc::unbounded_channel();
+        Self {
+            app_id,
+            component_id,
+            instance_pre,
+            slots: Mutex::new(Slots::new(size)),
+            refill_tx,
+            refill_rx: Mutex::new(Some(refill_rx)),
+        }
+    }
+
+    /// Takes an instance from the pool, if one is ready.
+    pub fn take(&self) -> Option<WarmInstance<F>> {
+        let instance = self.slots.lock().unwrap().take();
+        if instance.is_some() {
+            spin_telemetry::metrics::monotonic_counter!(
+                spin.warm_pool_hits = 1,
+                app_id = self.app_id.clone(),
+                component_id = self.component_id.clone()
+            );
+        } else {
+            spin_telemetry::metrics::monotonic_counter!(
+                spin.warm_pool_misses = 1,
+                app_id = self.app_id.clone(),
+                component_id = self.component_id.clone()
+            );
+        }
+        _ = self.refill_tx.send(());
+        instance
+    }
+
+    /// Returns the number of instances which are ready to be taken.
+    #[cfg(test)]
+    pub fn ready_count(&self) -> usize {
+        self.slots.lock().unwrap().instances.len()
+    }
+
+    /// Starts filling the pool in the background with instances made for
+    /// `server`. Does nothing if the pool has already been started.
+    ///
+    /// The task stops when the pool or the app is dropped.
+    pub fn start(
+        self: &Arc<Self>,
+        server: &Arc<HttpServer<F>>,
+        trigger_app: Weak<TriggerApp<F>>,
+        self_scheme: Scheme,
+    ) {
+        let Some(mut refill_rx) = self.refill_rx.lock().unwrap().take() else {
+            return;
+        };
+        let pool = Arc::downgrade(self);
+        let server = server.clone();
+        tokio::spawn(async move {
+            loop {
+                let (Some(pool), Some(trigger_app)) = (pool.upgrade(), trigger_app.upgrade())
+                else {
+                    return;
+                };
+                pool.fill(&server, &trigger_app, &self_scheme).await;
+                drop((pool, trigger_app));
+
+                if refill_rx.recv().await.is_none() {
+                    return;
+                }
+                // Requests which arrived while the pool was being filled are
+                // covered by the next fill.
+                while refill_rx.try_recv().is_ok() {}
+            }
+        });
+    }
+
+    async fn fill(
+        &self,
+        server: &Arc<HttpServer<F>>,
+        trigger_app: &TriggerApp<F>,
+        scheme: &Scheme,
+    ) {
+        while self.slots.lock().unwrap().has_vacancy() {
+            match self.instantiate(server, trigger_app, scheme).await {
+                Ok(instance) => self.slots.lock().unwrap().put(instance),
+                Err(err) => {
+                    // Trying again straight away would most likely fail the
+                    // same way, so wait until the pool is next used.
+                    tracing::warn!(
+                        "Failed to instantiate component '{}' for its warm pool: {err:?}",
+                        self.component_id
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn instantiate(
+        &self,
+        server: &Arc<HttpServer<F>>,
+        trigger_app: &TriggerApp<F>,
+        scheme: &Scheme,
+    ) -> anyhow::Result<WarmInstance<F>> {
+        server
+            .trigger_instance_builder(
+                trigger_app,
+                &self.component_id,
+                self.instance_pre.clone(),
+                Some(scheme),
+            )?
+            .instantiate(())
+            .await
+    }
+}
+
+/// The instances in a pool.
+struct Slots<T> {
+    size: usize,
+    instances: VecDeque<T>,
+}
+
+impl<T> Slots<T> {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            instances: VecDeque::with_capacity(size),
+        }
+    }
+
+    fn take(&mut self) -> Option<T> {
+        self.instances.pop_front()
+    }
+
+    fn has_vacancy(&self) -> bool {
+        self.instances.len() < self.size
+    }
+
+    fn put(&mut self, instance: T) {
+        if self.has_vacancy() {
+            self.instances.push_back(instance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_fill_to_size() {
+        let mut slots = Slots::new(2);
+        for instance in 0..3 {
+            slots.put(instance);
+        }
+        assert!(!slots.has_vacancy());
+        assert_eq!(slots.take(), Some(0));
+        assert_eq!(slots.take(), Some(1));
+        assert_eq!(slots.take(), None);
+        assert!(slots.has_vacancy());
+    }
+}
@@ -2,6 +2,7 @@ use std::future;
 use std::io::IsTerminal;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow};
 use futures::TryFutureExt;
@@ -16,6 +17,7 @@ use spin_factors::{RuntimeFactors, RuntimeFactorsInstanceState};
 use spin_factors_executor::InstanceState;
 use spin_http::routes::RouteMatch;
 use spin_http::trigger::HandlerType;
+use spin_trigger::timeout::set_deadline;
 use tokio::{sync::oneshot, task};
 use tracing::{Instrument, Level, instrument};
 use wasmtime::AsContextMut;
@@ -24,9 +26,9 @@ use wasmtime_wasi_http::p2::bindings::http::types::Scheme;
 use wasmtime_wasi_http::p2::{bindings::Proxy, body::HyperIncomingBody as Body};
 use wasmtime_wasi_http::p3;
 
-use crate::HttpServer;
 use crate::headers::prepare_request_headers;
-use crate::server::set_request_deadline;
+use crate::warm_pool::WarmPool;
+use crate::{HttpServer, InstancePre, TriggerApp};
 
 pub(super) fn prepare_request(
     route_match: &RouteMatch<'_, '_>,
@@ -57,15 +59,19 @@ pub(super) fn prepare_request(
 }
 
 /// An [`HttpExecutor`] that uses the `wasi:http/incoming-handler` interface.
-pub struct WasiHttpExecutor<'a, S: HandlerState> {
+pub struct WasiHttpExecutor<'a, S: HandlerState, F: RuntimeFactors> {
     pub handler_type: &'a HandlerType<S>,
+    pub instance_pre: &'a InstancePre<F>,
+    pub warm_pool: Option<&'a WarmPool<F>>,
+    pub timeout: Option<Duration>,
 }
 
-impl<S: HandlerState> WasiHttpExecutor<'_, S> {
+impl<S: HandlerState, F: RuntimeFactors> WasiHttpExecutor<'_, S, F> {
     #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.lookup_key().to_string())))]
-    pub async fn execute<F: RuntimeFactors>(
+    pub async fn execute(
         &self,
         server: &Arc<HttpServer<F>>,
+        trigger_app: &TriggerApp<F>,
         route_match: &RouteMatch<'_, '_>,
         mut req: Request<Body>,
         client_addr: SocketAddr,
@@ -74,10 +80,15 @@ impl<S: HandlerState> WasiHttpExecutor<'_, S> {
         prepare_request(route_match, &mut req, client_addr)?;
 
         let (instance, mut store) = server
-            .trigger_instance_builder(component_id, req.uri().scheme())?
-            .instantiate(())
+            .instantiate_component(
+                trigger_app,
+                component_id,
+                self.instance_pre,
+                self.warm_pool,
+                req.uri().scheme(),
+            )
             .await?;
-        set_request_deadline(&mut store, server.request_deadline());
+        set_deadline(&mut store, self.timeout);
 
         enum Handler {
             Latest(Proxy),
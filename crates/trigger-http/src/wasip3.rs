@@ -1,46 +1,65 @@
-use crate::{HttpServer, server::HttpHandlerState};
+use crate::{HttpServer, TriggerApp, server::HttpHandlerState};
 use anyhow::Result;
 use http_body_util::BodyExt;
+use spin_core::Trap;
 use spin_factors::RuntimeFactors;
 use spin_http::routes::RouteMatch;
-use std::{net::SocketAddr, sync::Arc};
+use spin_trigger::timeout::report_timeout;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tracing::{Level, instrument};
 use wasmtime_wasi_http::{
-    handler::{ErrorCode, ProxyHandler},
+    handler::{ErrorCode, ExpirationError, ProxyHandler},
     p2::{bindings::http::types as p2_types, body::HyperIncomingBody as Body},
     p3::bindings::http::types as p3_types,
 };
 
 /// An [`HttpExecutor`] that uses the `wasi:http@0.3.*/handler` interface.
-pub(super) struct Wasip3HttpExecutor<'a, F: RuntimeFactors>(
-    pub(super) &'a ProxyHandler<HttpHandlerState<F>>,
-);
+///
+/// The request's time limit is enforced by the worker which handles it, rather
+/// than by [`spin_trigger::timeout::run_with_timeout`].
+pub(super) struct Wasip3HttpExecutor<'a, F: RuntimeFactors> {
+    pub(super) handler: &'a ProxyHandler<HttpHandlerState<F>>,
+    pub(super) timeout: Option<Duration>,
+}
 
 impl<F: RuntimeFactors> Wasip3HttpExecutor<'_, F> {
     #[instrument(name = "spin_trigger_http.execute_wasm", skip_all, err(level = Level::INFO), fields(otel.name = format!("execute_wasm_component {}", route_match.lookup_key().to_string())))]
     pub async fn execute(
         &self,
         server: &Arc<HttpServer<F>>,
+        trigger_app: &TriggerApp<F>,
         route_match: &RouteMatch<'_, '_>,
         mut req: http::Request<Body>,
         client_addr: SocketAddr,
+        component_id: &str,
     ) -> Result<http::Response<Body>> {
-        self.0.state().init_once(server, req.uri());
+        self.handler.state().init_once(server, req.uri());
         super::wasi::prepare_request(route_match, &mut req, client_addr)?;
 
-        Ok(self
-            .0
+        let result = self
+            .handler
             .handle(
-                (),
+                self.timeout,
                 req.map(|body| body.map_err(ErrorCode::from).boxed_unsync()),
             )
-            .await?
-            .map(|body| {
-                body.map_err(|e| match e.downcast::<p3_types::ErrorCode>() {
-                    Ok(e) => e.into(),
-                    Err(e) => p2_types::ErrorCode::InternalError(Some(e.to_string())),
-                })
-                .boxed_unsync()
-            }))
+            .await;
+        let response = match (result, self.timeout) {
+            (Err(err), Some(timeout))
+                if err.is::<ExpirationError>()
+                    || err.downcast_ref::<Trap>() == Some(&Trap::Interrupt) =>
+            {
+                let app_id = trigger_app.app().id();
+                return Err(report_timeout(timeout, "http", app_id, component_id).into());
+            }
+            (result, _) => result?,
+        };
+
+        Ok(response.map(|body| {
+            body.map_err(|e| match e.downcast::<p3_types::ErrorCode>() {
+                Ok(e) => e.into(),
+                Err(e) => p2_types::ErrorCode::InternalError(Some(e.to_string())),
+            })
+            .boxed_unsync()
+        }))
     }
 }
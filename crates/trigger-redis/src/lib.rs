@@ -1,17 +1,35 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::Context;
+use clap::Args;
 use futures::{StreamExt, TryFutureExt};
 use redis::{Client, Msg};
 use serde::Deserialize;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
-use spin_trigger::{App, Trigger, TriggerApp, cli::NoCliArgs};
+use spin_trigger::{
+    App, Trigger, TriggerApp,
+    timeout::{parse_duration, run_with_timeout, set_deadline},
+};
 use spin_world::exports::fermyon::spin::inbound_redis as v1;
 use spin_world::exports::spin::redis::inbound_redis as v3;
 use tracing::{Level, instrument};
 
-pub struct RedisTrigger;
+pub struct RedisTrigger {
+    message_timeout: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct CliArgs {
+    /// Maximum time a component may take to handle a message.
+    ///
+    /// A component which runs longer is interrupted, and the message is
+    /// reported as failed. A number with no suffix or with an `s` suffix is
+    /// interpreted as seconds; other accepted suffixes include `ms`
+    /// (milliseconds), `us` or `μs` (microseconds), and `ns` (nanoseconds).
+    #[clap(long, env = "SPIN_REDIS_MESSAGE_TIMEOUT", value_parser = parse_duration)]
+    pub message_timeout: Option<Duration>,
+}
 
 /// Redis trigger metadata.
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -35,12 +53,14 @@ struct TriggerConfig {
 impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
     const TYPE: &'static str = "redis";
 
-    type CliArgs = NoCliArgs;
+    type CliArgs = CliArgs;
 
     type InstanceState = ();
 
-    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
-        Ok(Self)
+    fn new(cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self {
+            message_timeout: cli_args.message_timeout,
+        })
     }
 
     async fn run(self, trigger_app: spin_trigger::TriggerApp<Self, F>) -> anyhow::Result<()> {
@@ -105,7 +125,12 @@ impl<F: RuntimeFactors> Trigger<F> for RedisTrigger {
         let trigger_app = Arc::new(trigger_app);
         let mut subscriber_tasks = Vec::new();
         for (address, channel_components) in server_channel_components {
-            let subscriber = Subscriber::new(address, trigger_app.clone(), channel_components)?;
+            let subscriber = Subscriber::new(
+                address,
+                trigger_app.clone(),
+                channel_components,
+                self.message_timeout,
+            )?;
             let task = tokio::spawn(subscriber.run_listener());
             subscriber_tasks.push(task);
         }
@@ -124,6 +149,7 @@ struct Subscriber<F: RuntimeFactors> {
     client: Client,
     trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
     channel_components: ChannelComponents,
+    message_timeout: Option<Duration>,
 }
 
 impl<F: RuntimeFactors> Subscriber<F> {
@@ -131,12 +157,14 @@ impl<F: RuntimeFactors> Subscriber<F> {
         address: String,
         trigger_app: Arc<TriggerApp<RedisTrigger, F>>,
         channel_components: ChannelComponents,
+        message_timeout: Option<Duration>,
     ) -> anyhow::Result<Self> {
         let client = Client::open(address)?;
         Ok(Self {
             client,
             trigger_app,
             channel_components,
+            message_timeout,
         })
     }
 
@@ -187,7 +215,7 @@ impl<F: RuntimeFactors> Subscriber<F> {
 
         let dispatch_futures = component_ids.iter().map(|component_id| {
             tracing::trace!("Executing Redis component {component_id}");
-            self.dispatch_handler(&msg, component_id)
+            self.dispatch_handler(msg.get_payload_bytes(), component_id)
                 .inspect_err(move |err| {
                     tracing::info!("Component {component_id} handler failed: {err}");
                 })
@@ -197,7 +225,7 @@ impl<F: RuntimeFactors> Subscriber<F> {
         Ok(())
     }
 
-    async fn dispatch_handler(&self, msg: &Msg, component_id: &str) -> anyhow::Result<()> {
+    async fn dispatch_handler(&self, payload: &[u8], component_id: &str) -> anyhow::Result<()> {
         spin_telemetry::metrics::monotonic_counter!(
             spin.request_count = 1,
             trigger_type = "redis",
@@ -205,11 +233,28 @@ impl<F: RuntimeFactors> Subscriber<F> {
             component_id = component_id
         );
 
+        // Loads the component now if it's being loaded lazily.
+        self.trigger_app
+            .ensure_component_loaded(component_id)
+            .await?;
+
+        run_with_timeout(
+            self.message_timeout,
+            "redis",
+            self.trigger_app.app().id(),
+            component_id,
+            self.invoke_handler(payload, component_id),
+        )
+        .await
+    }
+
+    async fn invoke_handler(&self, payload: &[u8], component_id: &str) -> anyhow::Result<()> {
         let (instance, mut store) = self
             .trigger_app
             .prepare(component_id)?
             .instantiate(())
             .await?;
+        set_deadline(&mut store, self.message_timeout);
 
         let pre = instance.instance_pre(&store);
 
@@ -217,8 +262,7 @@ impl<F: RuntimeFactors> Subscriber<F> {
             HandlerType::V1(guest_indices) => {
                 let guest = guest_indices.load(&mut store, &instance)?;
 
-                let payload = msg.get_payload_bytes().to_vec();
-
+                let payload = payload.to_vec();
                 guest
                     .call_handle_message(&mut store, &payload)
                     .await?
@@ -227,7 +271,7 @@ impl<F: RuntimeFactors> Subscriber<F> {
             HandlerType::V3(guest_indices) => {
                 let guest = guest_indices.load(&mut store, &instance)?;
 
-                let payload = msg.get_payload_bytes().to_vec();
+                let payload = payload.to_vec();
                 let res = std::pin::pin!(store.as_mut().run_concurrent(async |accessor| {
                     guest.call_handle_message(accessor, payload).await
                 }))
@@ -269,3 +313,92 @@ impl HandlerType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
+    use spin_factors::wasmtime::{self, component::Component};
+    use spin_factors_executor::{ComponentLoader, FactorsExecutor, TriggerDependenciesComposer};
+    use spin_factors_test::TestEnvironment;
+    use spin_trigger::timeout::InvocationTimeout;
+
+    use super::*;
+
+    #[derive(RuntimeFactors)]
+    struct TestFactors {
+        wasi: WasiFactor,
+    }
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[clap(flatten)]
+        args: CliArgs,
+    }
+
+    /// Loads every component as a Redis handler which never returns.
+    struct LoopingHandlerLoader;
+
+    #[spin_world::async_trait]
+    impl ComponentLoader<TestFactors, ()> for LoopingHandlerLoader {
+        async fn load_component(
+            &self,
+            engine: &wasmtime::Engine,
+            _component: &spin_factors::AppComponent,
+            _trigger_dependencies_composer: &impl TriggerDependenciesComposer,
+        ) -> anyhow::Result<Component> {
+            let module = wat::parse_str(
+                r#"
+                (module
+                  (memory (export "memory") 1)
+                  (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32)
+                    i32.const 0)
+                  (func (export "canonical_abi_free") (param i32 i32 i32))
+                  (func (export "handle-redis-message") (param i32 i32) (result i32)
+                    (loop $forever (br $forever))
+                    unreachable))
+                "#,
+            )?;
+            Ok(Component::new(
+                engine,
+                spin_componentize::componentize(&module)?,
+            )?)
+        }
+    }
+
+    #[tokio::test]
+    async fn message_timeout_interrupts_handler() -> anyhow::Result<()> {
+        let cli = TestCli::try_parse_from(["redis", "--message-timeout", "100ms"])?;
+        assert_eq!(cli.args.message_timeout, Some(Duration::from_millis(100)));
+
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors);
+        let locked = env.build_locked_app().await?;
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let trigger_app = executor
+            .load_app(
+                App::new("test-app", locked),
+                Default::default(),
+                &LoopingHandlerLoader,
+                None,
+                (),
+            )
+            .await?;
+
+        let subscriber = Subscriber::new(
+            "redis://localhost:6379".into(),
+            Arc::new(trigger_app),
+            Default::default(),
+            cli.args.message_timeout,
+        )?;
+        let err = subscriber
+            .dispatch_handler(b"hello", "empty")
+            .await
+            .unwrap_err();
+        assert!(err.is::<InvocationTimeout>(), "{err:?}");
+        Ok(())
+    }
+}
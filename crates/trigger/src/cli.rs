@@ -1,12 +1,18 @@
+mod allowed_hosts_audit;
+mod app_reload;
 mod initial_kv_setter;
 mod launch_metadata;
+mod log_file;
 mod max_instance_memory;
+mod reload;
 mod sqlite_statements;
 mod stdio;
 mod summary;
 mod variable;
 
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::{future::Future, sync::Arc};
 
 use anyhow::{Context, Result};
@@ -14,21 +20,25 @@ use anyhow::{Context, Result};
 use clap::ValueEnum;
 use clap::{Args, CommandFactory, Parser};
 use spin_app::App;
+use spin_common::paths::parent_dir;
 use spin_common::sloth;
 use spin_common::ui::quoted_path;
 use spin_common::url::parse_file_url;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{ComponentLoader, FactorsExecutor};
 
+use crate::precompile::{PrecompileOptions, PrecompiledComponent};
 use crate::{Trigger, TriggerApp, loader::ComponentLoader as ComponentLoaderImpl};
+pub use allowed_hosts_audit::AllowedHostsAuditHook;
 pub use initial_kv_setter::InitialKvSetterHook;
 pub use launch_metadata::LaunchMetadata;
+pub use log_file::{ComponentLogConfig, ComponentLogFormat};
 pub use max_instance_memory::MaxInstanceMemoryHook;
 pub use sqlite_statements::SqlStatementExecutorHook;
 use stdio::FollowComponents;
 pub use stdio::StdioLoggingExecutorHooks;
 pub use summary::{KeyValueDefaultStoreSummaryHook, SqliteDefaultStoreSummaryHook};
-pub use variable::VariablesValidatorHook;
+pub use variable::{VariablesRefreshHook, VariablesValidatorHook};
 
 pub const APP_LOG_DIR: &str = "APP_LOG_DIR";
 pub const SPIN_TRUNCATE_LOGS: &str = "SPIN_TRUNCATE_LOGS";
@@ -41,6 +51,11 @@ pub const RUNTIME_CONFIG_FILE: &str = "RUNTIME_CONFIG_FILE";
 pub const SPIN_LOCKED_URL: &str = "SPIN_LOCKED_URL";
 pub const SPIN_LOCAL_APP_DIR: &str = "SPIN_LOCAL_APP_DIR";
 pub const SPIN_WORKING_DIR: &str = "SPIN_WORKING_DIR";
+pub const SPIN_APP_RELOAD_ADDRESS: &str = "SPIN_APP_RELOAD_ADDRESS";
+pub const SPIN_APP_RELOAD_TOKEN: &str = "SPIN_APP_RELOAD_TOKEN";
+
+// Set by `spin watch`
+pub const SPIN_RELOAD_ADDRESS: &str = "SPIN_RELOAD_ADDRESS";
 
 /// A command that runs a TriggerExecutor.
 #[derive(Parser, Debug)]
@@ -67,6 +82,24 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     )]
     pub truncate_logs: bool,
 
+    /// Format of the component log files. Defaults to the runtime config
+    /// `[component_logs]` setting, or `text`.
+    #[clap(long = "log-format", value_enum)]
+    pub log_format: Option<ComponentLogFormat>,
+
+    /// Rotate component log files once they would exceed this size, in
+    /// megabytes.
+    #[clap(long = "log-max-size-mb")]
+    pub log_max_size_mb: Option<u64>,
+
+    /// Rotate component log files once they are this many hours old.
+    #[clap(long = "log-max-age-hours")]
+    pub log_max_age_hours: Option<u64>,
+
+    /// How many rotated files to keep for each component log. Defaults to 5.
+    #[clap(long = "log-max-files")]
+    pub log_max_files: Option<usize>,
+
     /// Disable Wasmtime cache.
     #[clap(
         name = DISABLE_WASMTIME_CACHE,
@@ -94,6 +127,21 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     #[clap(long = "debug-info")]
     pub debug_info: bool,
 
+    /// Start the trigger before the app's components are compiled. They are
+    /// compiled in the background, and a component which is invoked before
+    /// then is compiled straight away. Until all are compiled, the HTTP
+    /// trigger's `/.well-known/spin/ready` endpoint reports that the app is
+    /// not ready.
+    #[clap(long = "lazy-load-components")]
+    pub lazy_load_components: bool,
+
+    /// Load components from the precompiled artifacts recorded by `spin
+    /// compile`, if they are compatible with this host, instead of compiling
+    /// them. Precompiled artifacts are native code which bypasses the Wasm
+    /// sandbox's checks: only use this with apps that you trust.
+    #[clap(long = "allow-precompiled")]
+    pub allow_precompiled: bool,
+
     /// Print output to stdout/stderr only for given component(s)
     #[clap(
         name = FOLLOW_LOG_OPT,
@@ -147,6 +195,15 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     pub precompose_only: bool,
     #[clap(long = "precompose-component-id", hide = true)]
     pub precompose_component_id: Option<String>,
+
+    #[clap(long = "precompile-only", hide = true)]
+    pub precompile_only: bool,
+    #[clap(long = "precompile-dir", hide = true)]
+    pub precompile_dir: Option<PathBuf>,
+    #[clap(long = "precompile-target", hide = true)]
+    pub precompile_target: Option<String>,
+    #[clap(long = "precompile-cpu-feature", hide = true)]
+    pub precompile_cpu_features: Vec<String>,
 }
 
 #[cfg(feature = "experimental-wasm-features")]
@@ -175,6 +232,8 @@ pub struct FactorsConfig {
     pub log_dir: UserProvidedPath,
     /// If set, Spin truncates the log files before starting the application.
     pub truncate_logs: bool,
+    /// Component log format and rotation set on the command line.
+    pub component_logs: ComponentLogConfig,
 }
 
 /// An empty implementation of clap::Args to be used as TriggerExecutor::RunConfig
@@ -184,7 +243,10 @@ pub struct NoCliArgs;
 
 impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T, B> {
     /// Create a new TriggerExecutorBuilder from this TriggerExecutorCommand.
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self) -> Result<()>
+    where
+        T: 'static,
+    {
         spin_tls::install_default_crypto_provider();
         // Handle --help-args-only
         if self.help_args_only {
@@ -207,18 +269,27 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
         let working_dir = std::env::var(SPIN_WORKING_DIR).context(SPIN_WORKING_DIR)?;
         let locked_url = std::env::var(SPIN_LOCKED_URL).context(SPIN_LOCKED_URL)?;
         let local_app_dir = std::env::var(SPIN_LOCAL_APP_DIR).ok();
+        let reload_address = std::env::var(SPIN_RELOAD_ADDRESS)
+            .ok()
+            .map(|address| address.parse::<SocketAddr>())
+            .transpose()
+            .context(SPIN_RELOAD_ADDRESS)?;
+        let app_reload_address = std::env::var(SPIN_APP_RELOAD_ADDRESS)
+            .ok()
+            .map(|address| address.parse::<SocketAddr>())
+            .transpose()
+            .context(SPIN_APP_RELOAD_ADDRESS)?;
+        let app_reload = app_reload_address
+            .map(|address| {
+                let token = std::env::var(SPIN_APP_RELOAD_TOKEN).context(SPIN_APP_RELOAD_TOKEN)?;
+                anyhow::Ok((address, token))
+            })
+            .transpose()?;
 
         let follow_components = self.follow_components();
 
         // Load App
-        let app = {
-            let path = parse_file_url(&locked_url)?;
-            let contents = std::fs::read(&path)
-                .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
-            let locked =
-                serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
-            App::new(locked_url, locked)
-        };
+        let app = load_locked_app(&locked_url)?;
 
         // Handle --precompose-only
         if self.precompose_only {
@@ -246,12 +317,7 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
         }
 
         // Validate required host features
-        if let Err(unmet) = app.ensure_needs_only(T::TYPE, &T::supported_host_requirements()) {
-            anyhow::bail!(
-                "This application requires the following features that are not available in this version of the '{}' trigger: {unmet}",
-                T::TYPE
-            );
-        }
+        T::ensure_host_requirements(&app)?;
 
         let trigger = T::new(self.trigger_args, &app)?;
 
@@ -288,6 +354,30 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
             }
         }
 
+        // Handle --precompile-only
+        if self.precompile_only {
+            let Some(precompile_dir) = self.precompile_dir.as_ref() else {
+                anyhow::bail!("got --precompile-only but no --precompile-dir");
+            };
+            let options = PrecompileOptions {
+                target: self.precompile_target.clone(),
+                cpu_features: self.precompile_cpu_features.clone(),
+            };
+            let precompiled = builder.precompile(&app, precompile_dir, &options).await?;
+            serde_json::to_writer(std::io::stdout(), &precompiled)
+                .context("failed to write precompiled components to stdout")?;
+            return Ok(());
+        }
+
+        // Only the process which runs the triggers serves the admin server,
+        // so that it reports their metrics. An app whose triggers run in
+        // several processes (such as a plugin trigger alongside the built-in
+        // ones) has them all contend for the address: the first to bind it
+        // serves its metrics, and the others carry on without a server.
+        if let Err(err) = spin_telemetry::admin::start() {
+            tracing::warn!("The admin server is not running in this trigger process: {err:#}");
+        }
+
         let state_dir = match &self.state_dir {
             // Make sure `--state-dir=""` unsets the state dir
             Some(s) if s.is_empty() => UserProvidedPath::Unset,
@@ -308,16 +398,121 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
             follow_components,
             log_dir,
             truncate_logs: self.truncate_logs,
+            component_logs: ComponentLogConfig {
+                format: self.log_format,
+                max_size_mb: self.log_max_size_mb,
+                max_age_hours: self.log_max_age_hours,
+                max_files: self.log_max_files,
+            },
         };
 
-        let loader = ComponentLoaderImpl::new();
-        let run_fut = builder
-            .run(app, common_options, self.builder_args, &loader)
+        let mut loader = ComponentLoaderImpl::new();
+        if self.allow_precompiled {
+            let lock_file_dir = parent_dir(parse_file_url(&locked_url)?)?;
+            // SAFETY: the user has asserted that they trust the app, which
+            // is what `--allow-precompiled` is documented to require.
+            unsafe {
+                loader.enable_loading_precompiled_artifacts(lock_file_dir);
+            }
+        }
+        let loader = Arc::new(loader);
+        let configured_app = builder
+            .configure_app(app, &common_options, &self.builder_args)
             .await?;
+        let deferred_app = if self.lazy_load_components {
+            T::defer_components(&configured_app, &loader);
+            Some(configured_app.clone())
+        } else {
+            let _sloth_guard = warn_if_wasm_build_slothful();
+            T::load_components(&configured_app, loader.as_ref()).await?;
+            None
+        };
+        let executor = configured_app.executor().clone();
+        // Follows the app as it is reloaded, so that rebuilt components are
+        // reloaded into the app which is running.
+        let (component_reloader, current_reloader) =
+            tokio::sync::watch::channel(configured_app.component_reloader());
+        let (app_reloads, reload_receiver) = tokio::sync::mpsc::channel(1);
+        let run_fut = builder
+            .trigger
+            .run_reloadable(configured_app, reload_receiver);
+
+        // Under `spin up --hot-reload`, reload the whole app when asked to.
+        let run_fut = async {
+            let Some((address, token)) = &app_reload else {
+                drop(app_reloads);
+                return run_fut.await;
+            };
+            let reloads = app_reload::serve_app_reload_requests::<T, B>(
+                *address,
+                token,
+                app_reloads,
+                &component_reloader,
+                executor.clone(),
+                &locked_url,
+                &common_options,
+                &self.builder_args,
+                loader.as_ref(),
+            );
+            tokio::pin!(run_fut);
+            tokio::select! {
+                result = &mut run_fut => return result,
+                result = reloads => {
+                    if let Err(err) = result {
+                        tracing::warn!("Application reloading is unavailable: {err:#}");
+                    }
+                }
+            }
+            run_fut.await
+        };
+
+        // Under `spin watch`, reload rebuilt components in place while the
+        // trigger keeps running.
+        let run_fut = async {
+            let Some(address) = reload_address else {
+                return run_fut.await;
+            };
+            let composer = T::trigger_dependencies_composer();
+            let reloads = reload::serve_reload_requests(
+                address,
+                current_reloader,
+                loader.as_ref(),
+                &composer,
+            );
+            tokio::pin!(run_fut);
+            tokio::select! {
+                result = &mut run_fut => return result,
+                result = reloads => {
+                    if let Err(err) = result {
+                        tracing::warn!("Component reloading is unavailable: {err:#}");
+                    }
+                }
+            }
+            run_fut.await
+        };
+
+        // With --lazy-load-components, compile the components in the
+        // background. If one fails to compile, stop, as if it had failed
+        // before the trigger started.
+        let run_fut = async {
+            let Some(deferred_app) = deferred_app else {
+                return run_fut.await;
+            };
+            tokio::pin!(run_fut);
+            tokio::select! {
+                result = &mut run_fut => return result,
+                result = deferred_app.load_deferred_components() => {
+                    result?;
+                    tracing::info!("All components loaded");
+                }
+            }
+            drop(deferred_app);
+            run_fut.await
+        };
 
         let (abortable, abort_handle) = futures::future::abortable(run_fut);
         ctrlc::set_handler(move || abort_handle.abort())?;
-        match abortable.await {
+        let result = match abortable.await {
             Ok(Ok(())) => {
                 tracing::info!("Trigger executor shut down: exiting");
                 Ok(())
@@ -330,7 +525,11 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
                 tracing::info!("User requested shutdown: exiting");
                 Ok(())
             }
+        };
+        if let Err(err) = executor.shutdown() {
+            tracing::error!(%err, "Trigger executor shutdown hooks failed");
         }
+        result
     }
 
     fn follow_components(&self) -> FollowComponents {
@@ -345,6 +544,14 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
     }
 }
 
+fn load_locked_app(locked_url: &str) -> Result<App> {
+    let path = parse_file_url(locked_url)?;
+    let contents = std::fs::read(&path)
+        .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
+    let locked = serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
+    Ok(App::new(locked_url, locked))
+}
+
 const SLOTH_WARNING_DELAY_MILLIS: u64 = 1250;
 
 fn warn_if_wasm_build_slothful() -> sloth::SlothGuard {
@@ -396,6 +603,42 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
         common_options: FactorsConfig,
         options: B::CliArgs,
         loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
+    ) -> anyhow::Result<TriggerApp<T, B::Factors>> {
+        let configured_app = self.configure_app(app, &common_options, &options).await?;
+        {
+            let _sloth_guard = warn_if_wasm_build_slothful();
+            T::load_components(&configured_app, loader).await?;
+        }
+        Ok(configured_app)
+    }
+
+    /// Compiles the components which the trigger runs, with the same engine
+    /// configuration as [`TriggerAppBuilder::build`] would use, and writes
+    /// them to `output_dir`.
+    pub(crate) async fn precompile(
+        &mut self,
+        app: &App,
+        output_dir: &Path,
+        options: &PrecompileOptions,
+    ) -> anyhow::Result<BTreeMap<String, PrecompiledComponent>> {
+        self.trigger.update_core_config(&mut self.engine_config)?;
+        crate::precompile::precompile_components::<T, B::Factors>(
+            &mut self.engine_config,
+            app,
+            output_dir,
+            options,
+        )
+        .await
+    }
+
+    /// Like [`TriggerAppBuilder::build`], but doesn't load the app's
+    /// components, and leaves the options with the caller so that they can
+    /// be used again to reload the app.
+    pub(crate) async fn configure_app(
+        &mut self,
+        app: App,
+        common_options: &FactorsConfig,
+        options: &B::CliArgs,
     ) -> anyhow::Result<TriggerApp<T, B::Factors>> {
         let mut core_engine_builder = {
             self.trigger.update_core_config(&mut self.engine_config)?;
@@ -404,26 +647,13 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
         };
         self.trigger.add_to_linker(core_engine_builder.linker())?;
 
-        let (factors, runtime_config) = B::build(&common_options, &options)?;
+        let (factors, runtime_config) = B::build(common_options, options)?;
 
         let mut executor = FactorsExecutor::new(core_engine_builder, factors)?;
-        B::configure_app(&mut executor, &runtime_config, &common_options, &options)?;
+        B::configure_app(&mut executor, &runtime_config, common_options, options)?;
         let executor = Arc::new(executor);
 
-        let configured_app = {
-            let _sloth_guard = warn_if_wasm_build_slothful();
-            executor
-                .load_app(
-                    app,
-                    runtime_config.into(),
-                    loader,
-                    Some(T::TYPE),
-                    T::trigger_dependencies_composer(),
-                )
-                .await?
-        };
-
-        Ok(configured_app)
+        executor.configure_app(app, runtime_config.into()).await
     }
 
     /// Run the [`TriggerApp`] with the given [`App`] and options.
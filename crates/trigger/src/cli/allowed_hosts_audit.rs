@@ -0,0 +1,168 @@
+use std::fs::OpenOptions;
+use std::io::{Read as _, Seek as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context as _;
+use spin_common::ui::quoted_path;
+use spin_core::async_trait;
+use spin_factor_outbound_networking::{OutboundNetworkingFactor, config::audit::AllowedHostsAudit};
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::ExecutorHooks;
+
+/// An [`ExecutorHooks`] that reports the outbound destinations recorded in
+/// allowed hosts audit mode when the trigger shuts down.
+pub struct AllowedHostsAuditHook {
+    /// Where to write the audited allowed hosts as TOML. If `None`, they are
+    /// printed to stderr.
+    output: Option<PathBuf>,
+    /// The audit of each configured app. An app which is reloaded is
+    /// configured again, with a new audit.
+    audits: Mutex<Vec<Arc<AllowedHostsAudit>>>,
+}
+
+impl AllowedHostsAuditHook {
+    pub fn new(output: Option<PathBuf>) -> Self {
+        Self {
+            output,
+            audits: Default::default(),
+        }
+    }
+
+    /// Merges the audits of every configured app, or returns `None` if no app
+    /// has been configured in audit mode.
+    fn merged_audit(&self) -> Option<AllowedHostsAudit> {
+        let audits = self.audits.lock().unwrap();
+        if audits.is_empty() {
+            return None;
+        }
+        let merged = AllowedHostsAudit::new();
+        for audit in audits.iter() {
+            merged.merge(audit);
+        }
+        Some(merged)
+    }
+}
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for AllowedHostsAuditHook {
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        let Some(audit) = configured_app
+            .app_state::<OutboundNetworkingFactor>()
+            .ok()
+            .and_then(|state| state.allowed_hosts_audit())
+        else {
+            return Ok(());
+        };
+        self.audits.lock().unwrap().push(audit);
+        Ok(())
+    }
+
+    fn shutdown(&self) -> anyhow::Result<()> {
+        let Some(audit) = self.merged_audit() else {
+            return Ok(());
+        };
+        if audit.is_empty() {
+            eprintln!("Allowed hosts audit: no outbound network destinations were attempted.");
+            return Ok(());
+        }
+        match &self.output {
+            Some(path) => {
+                merge_into_file(&audit, path).with_context(|| {
+                    format!(
+                        "failed to write allowed hosts audit to {}",
+                        quoted_path(path)
+                    )
+                })?;
+                eprintln!(
+                    "Allowed hosts audit: wrote the allowed_outbound_hosts needed by this app to {}",
+                    quoted_path(path)
+                );
+            }
+            None => {
+                let toml = audit.to_toml()?;
+                eprintln!(
+                    "Allowed hosts audit: this app needs the following allowed_outbound_hosts:\n"
+                );
+                eprintln!("{toml}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the audit to `path`, keeping the destinations already recorded
+/// there.
+///
+/// Each trigger of an app runs in its own process and writes its audit to
+/// the same file when it shuts down, so the file is locked while it's updated.
+/// `spin up` empties the file when it starts, so only the destinations of
+/// the current run are kept.
+fn merge_into_file(audit: &AllowedHostsAudit, path: &Path) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    file.lock()?;
+    let mut existing = String::new();
+    file.read_to_string(&mut existing)?;
+    if !existing.trim().is_empty() {
+        audit
+            .merge_toml(&existing)
+            .context("the existing file isn't an allowed hosts audit")?;
+    }
+    let toml = audit.to_toml()?;
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(toml.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audits_of_several_triggers_are_merged() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit.toml");
+
+        let http = AllowedHostsAudit::new();
+        http.record("web", "https", "api.example.com");
+        merge_into_file(&http, &path)?;
+
+        let redis = AllowedHostsAudit::new();
+        redis.record("worker", "redis", "cache:6379");
+        merge_into_file(&redis, &path)?;
+
+        let merged = AllowedHostsAudit::new();
+        merged.merge_toml(&std::fs::read_to_string(&path)?)?;
+        let hosts = merged.allowed_outbound_hosts();
+        assert_eq!(hosts["web"], ["https://api.example.com"]);
+        assert_eq!(hosts["worker"], ["redis://cache:6379"]);
+        Ok(())
+    }
+
+    #[test]
+    fn audits_from_before_and_after_a_reload_are_merged() {
+        let hook = AllowedHostsAuditHook::new(None);
+        assert!(hook.merged_audit().is_none());
+
+        for host in ["before.example.com", "after.example.com"] {
+            let audit = Arc::new(AllowedHostsAudit::new());
+            audit.record("web", "https", host);
+            hook.audits.lock().unwrap().push(audit);
+        }
+
+        let hosts = hook.merged_audit().unwrap().allowed_outbound_hosts();
+        assert_eq!(
+            hosts["web"],
+            ["https://after.example.com", "https://before.example.com"]
+        );
+    }
+}
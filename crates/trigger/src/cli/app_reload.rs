@@ -0,0 +1,291 @@
+use std::{future::Future, net::SocketAddr, sync::Arc};
+
+use anyhow::Context;
+use spin_factors_executor::{ComponentLoader, ComponentReloader, FactorsExecutor};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::{mpsc, oneshot, watch},
+};
+
+use super::{FactorsConfig, RuntimeFactorsBuilder};
+use crate::{AppReload, Trigger, TriggerApp};
+
+/// Serves app reload requests from `spin up --hot-reload` until it closes
+/// the connection.
+///
+/// The connection is authenticated by sending `token` first. `spin up`
+/// rewrites the lock file at `locked_url`, then sends a `reload`
+/// line. The runtime config file is re-read too. Once the trigger is running
+/// the reloaded app, `ok` is sent back, and `component_reloader` is pointed at
+/// the reloaded app. If the reloaded app can't be loaded, or the trigger
+/// rejects it, `error <message>` is sent back and the trigger keeps running
+/// the app it already has.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn serve_app_reload_requests<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder>(
+    address: SocketAddr,
+    token: &str,
+    reloads: mpsc::Sender<AppReload<T, B::Factors>>,
+    component_reloader: &watch::Sender<ComponentReloader<B::Factors, T::InstanceState>>,
+    executor: Arc<FactorsExecutor<B::Factors, T::InstanceState>>,
+    locked_url: &str,
+    common_options: &FactorsConfig,
+    options: &B::CliArgs,
+    loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
+) -> anyhow::Result<()> {
+    let reloads = &reloads;
+    let executor = &executor;
+    serve_requests(address, token, move || async move {
+        let trigger_app = load_app::<T, B>(
+            executor.clone(),
+            locked_url,
+            common_options,
+            options,
+            loader,
+        )
+        .await?;
+        let reloader = trigger_app.component_reloader();
+        apply_reload(reloads, trigger_app.clone()).await?;
+        trigger_app.commit_reload();
+        component_reloader.send_replace(reloader);
+        Ok(())
+    })
+    .await
+}
+
+/// Connects to `spin up` at `address`, sends `token`, and calls `reload` for
+/// each request.
+async fn serve_requests<Fut: Future<Output = anyhow::Result<()>>>(
+    address: SocketAddr,
+    token: &str,
+    reload: impl Fn() -> Fut,
+) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(address)
+        .await
+        .with_context(|| format!("failed to connect to app reload channel at {address}"))?;
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(format!("{token}\n").as_bytes()).await?;
+    let mut requests = BufReader::new(reader).lines();
+
+    while let Some(request) = requests.next_line().await? {
+        let result = match request.as_str() {
+            "reload" => {
+                tracing::info!("Reloading application");
+                reload().await
+            }
+            _ => Err(anyhow::anyhow!("unknown request {request:?}")),
+        };
+        let response = match result {
+            Ok(()) => {
+                tracing::info!("Application reloaded");
+                "ok".to_owned()
+            }
+            Err(err) => {
+                tracing::error!("Failed to reload application, keeping the running one: {err:?}");
+                format!("error {}", format!("{err:#}").replace('\n', " "))
+            }
+        };
+        writer.write_all(format!("{response}\n").as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// Hands the reloaded app to the trigger, and waits for it to be swapped in.
+async fn apply_reload<T: Trigger<F>, F: spin_factors::RuntimeFactors>(
+    reloads: &mpsc::Sender<AppReload<T, F>>,
+    trigger_app: TriggerApp<T, F>,
+) -> anyhow::Result<()> {
+    let (applied, outcome) = oneshot::channel();
+    let reload = AppReload {
+        trigger_app,
+        applied,
+    };
+    if reloads.send(reload).await.is_err() {
+        anyhow::bail!(
+            "the {} trigger does not support reloading",
+            T::display_name()
+        );
+    }
+    outcome.await.with_context(|| {
+        format!(
+            "the {} trigger stopped accepting reloads",
+            T::display_name()
+        )
+    })?
+}
+
+async fn load_app<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder>(
+    executor: Arc<FactorsExecutor<B::Factors, T::InstanceState>>,
+    locked_url: &str,
+    common_options: &FactorsConfig,
+    options: &B::CliArgs,
+    loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
+) -> anyhow::Result<TriggerApp<T, B::Factors>> {
+    let app = super::load_locked_app(locked_url)?;
+    T::ensure_host_requirements(&app)?;
+    // The factors themselves are kept, so only the runtime config is used.
+    let (_, runtime_config) = B::build(common_options, options)?;
+    let trigger_app = executor.reconfigure_app(app, runtime_config.into()).await?;
+    T::load_components(&trigger_app, loader).await?;
+    Ok(trigger_app)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use spin_app::App;
+    use spin_factor_key_value::{KeyValueFactor, runtime_config::spin::MakeKeyValueStore};
+    use spin_factor_sqlite::SqliteFactor;
+    use spin_factors::RuntimeFactors;
+    use spin_factors_test::TestEnvironment;
+    use spin_key_value_spin::{SpinKeyValueRuntimeConfig, SpinKeyValueStore};
+    use spin_sqlite_inproc::{InProcConnection, InProcDatabaseLocation};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::cli::{InitialKvSetterHook, SqlStatementExecutorHook};
+
+    /// Plays the part of `spin up`, sending `requests` and returning the
+    /// responses.
+    async fn send_requests(
+        reload: impl Fn() -> anyhow::Result<()>,
+        requests: &[&str],
+    ) -> anyhow::Result<Vec<String>> {
+        let listener = TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+        let address = listener.local_addr()?;
+        let serve = serve_requests(address, "secret", || std::future::ready(reload()));
+        let spin_up = async {
+            let (stream, _) = listener.accept().await?;
+            let (reader, mut writer) = stream.into_split();
+            let mut responses = BufReader::new(reader).lines();
+            let token = responses.next_line().await?;
+            anyhow::ensure!(token.as_deref() == Some("secret"), "got token {token:?}");
+            let mut received = vec![];
+            for request in requests {
+                writer.write_all(format!("{request}\n").as_bytes()).await?;
+                received.push(responses.next_line().await?.unwrap_or_default());
+            }
+            anyhow::Ok(received)
+        };
+        let (served, responses) = tokio::join!(serve, spin_up);
+        served?;
+        responses
+    }
+
+    #[tokio::test]
+    async fn successful_reloads_are_acknowledged() -> anyhow::Result<()> {
+        let reloads = AtomicUsize::new(0);
+        let responses = send_requests(
+            || {
+                reloads.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            },
+            &["reload", "reload"],
+        )
+        .await?;
+        assert_eq!(responses, ["ok", "ok"]);
+        assert_eq!(reloads.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_reloads_are_reported() -> anyhow::Result<()> {
+        let responses = send_requests(
+            || Err(anyhow::anyhow!("invalid app").context("failed to load app")),
+            &["reload", "restart"],
+        )
+        .await?;
+        assert_eq!(
+            responses,
+            [
+                "error failed to load app: invalid app",
+                r#"error unknown request "restart""#
+            ]
+        );
+        Ok(())
+    }
+
+    #[derive(RuntimeFactors)]
+    struct StateFactors {
+        key_value: KeyValueFactor,
+        sqlite: SqliteFactor,
+    }
+
+    /// Configures the factors to keep their state in `dir`, so that it is
+    /// shared by each configuration of the app.
+    fn state_runtime_config(dir: &std::path::Path) -> anyhow::Result<StateFactorsRuntimeConfig> {
+        let mut key_value = spin_factor_key_value::RuntimeConfig::default();
+        let store = SpinKeyValueStore::new(None)
+            .make_store(SpinKeyValueRuntimeConfig::new(Some(dir.join("kv.db"))))?;
+        key_value.add_store_manager("default".into(), Arc::new(store));
+
+        let sqlite_path = dir.join("sqlite.db");
+        let connect = move || -> anyhow::Result<Arc<dyn spin_factor_sqlite::Connection>> {
+            let location = InProcDatabaseLocation::Path(sqlite_path.clone());
+            Ok(Arc::new(InProcConnection::new(location, false)?))
+        };
+        let sqlite = spin_factor_sqlite::RuntimeConfig {
+            connection_creators: [("default".into(), Arc::new(connect) as _)].into(),
+        };
+
+        Ok(StateFactorsRuntimeConfig {
+            key_value: Some(key_value),
+            sqlite: Some(sqlite),
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reloading_does_not_repeat_initial_sqlite_statements_or_key_values()
+    -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = TestEnvironment::new(StateFactors {
+            key_value: KeyValueFactor::new(),
+            sqlite: SqliteFactor::new(),
+        });
+        let locked = env.build_locked_app().await?;
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let mut executor = FactorsExecutor::<_, ()>::new(engine_builder, env.factors)?;
+        // As given by `--sqlite` and `--key-value`. Creating the table again
+        // would fail.
+        executor.add_hooks(SqlStatementExecutorHook::new(vec![
+            "CREATE TABLE greetings (text TEXT)".into(),
+        ]));
+        executor.add_hooks(InitialKvSetterHook::new(vec![(
+            "greeting".into(),
+            "hello".into(),
+        )]));
+        let executor = Arc::new(executor);
+
+        let app = executor
+            .clone()
+            .configure_app(
+                App::new("test-app", locked.clone()),
+                state_runtime_config(dir.path())?,
+            )
+            .await?;
+        let store = app
+            .configured_app()
+            .app_state::<KeyValueFactor>()?
+            .get_store("default")
+            .await
+            .unwrap();
+        assert_eq!(store.get("greeting", 1024).await?.unwrap(), b"hello");
+        store.set("greeting", b"changed by the app").await?;
+
+        let reloaded = executor
+            .reconfigure_app(
+                App::new("test-app", locked),
+                state_runtime_config(dir.path())?,
+            )
+            .await?;
+        reloaded.commit_reload();
+        assert_eq!(
+            store.get("greeting", 1024).await?.unwrap(),
+            b"changed by the app"
+        );
+        Ok(())
+    }
+}
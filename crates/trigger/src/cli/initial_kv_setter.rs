@@ -42,4 +42,13 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for InitialKvSetterHook {
 
         Ok(())
     }
+
+    async fn reconfigure_app(
+        &self,
+        _configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        // The pairs were set when the app started; setting them again would
+        // overwrite any changes the app has made since.
+        Ok(())
+    }
 }
@@ -0,0 +1,228 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How many rotated files are kept per log if rotation is enabled but no
+/// retention limit is configured.
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// The format of component log files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentLogFormat {
+    /// Component output is written as is.
+    #[default]
+    Text,
+    /// Each line of component output is written as a JSON object, with the
+    /// time, component ID, stream and (if available) trace ID.
+    Json,
+}
+
+/// How component log files are written and rotated.
+///
+/// Unset fields fall back to the runtime config, and then to the defaults:
+/// text format and no rotation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentLogConfig {
+    /// The format of log files.
+    pub format: Option<ComponentLogFormat>,
+    /// Rotate a log file once it would exceed this many megabytes.
+    pub max_size_mb: Option<u64>,
+    /// Rotate a log file once it is this many hours old.
+    pub max_age_hours: Option<u64>,
+    /// How many rotated files to keep per log.
+    pub max_files: Option<usize>,
+}
+
+impl ComponentLogConfig {
+    /// Fills unset fields from `fallback`.
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            format: self.format.or(fallback.format),
+            max_size_mb: self.max_size_mb.or(fallback.max_size_mb),
+            max_age_hours: self.max_age_hours.or(fallback.max_age_hours),
+            max_files: self.max_files.or(fallback.max_files),
+        }
+    }
+
+    pub(crate) fn format(&self) -> ComponentLogFormat {
+        self.format.unwrap_or_default()
+    }
+
+    fn max_size(&self) -> Option<u64> {
+        self.max_size_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
+    fn max_age(&self) -> Option<Duration> {
+        self.max_age_hours
+            .map(|hours| Duration::from_secs(hours.saturating_mul(60 * 60)))
+    }
+
+    fn max_files(&self) -> usize {
+        self.max_files.unwrap_or(DEFAULT_MAX_FILES)
+    }
+}
+
+/// A line of component output in [`ComponentLogFormat::Json`].
+#[derive(Serialize)]
+pub(crate) struct JsonLogRecord<'a> {
+    pub timestamp: String,
+    pub component_id: &'a str,
+    pub stream: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    pub message: &'a str,
+}
+
+/// A log file which is rotated by size and age.
+///
+/// Rotated files are named after the log file with a numeric suffix, `.1`
+/// being the most recent.
+pub(crate) struct LogFile {
+    path: PathBuf,
+    config: ComponentLogConfig,
+    file: File,
+    size: u64,
+    opened: SystemTime,
+}
+
+impl LogFile {
+    pub fn open(path: &Path, config: ComponentLogConfig) -> std::io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        Ok(Self {
+            path: path.to_owned(),
+            config,
+            size: metadata.len(),
+            opened: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            file,
+        })
+    }
+
+    /// Writes the whole of `buf`, first rotating the file if needed.
+    pub fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if self.should_rotate(buf.len() as u64) {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+
+    fn should_rotate(&self, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self
+            .config
+            .max_size()
+            .is_some_and(|max| self.size + incoming > max);
+        let too_old = self
+            .config
+            .max_age()
+            .is_some_and(|max| self.opened.elapsed().is_ok_and(|age| age >= max));
+        too_big || too_old
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let max_files = self.config.max_files();
+        if max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            remove_if_exists(&rotated_path(&self.path, max_files))?;
+            for index in (1..max_files).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    std::fs::rename(from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = File::options().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.opened = SystemTime::now();
+        Ok(())
+    }
+}
+
+/// Returns whether `name` is a rotated copy of the log file `log_name`.
+pub(crate) fn is_rotated_log(name: &str, log_name: &str) -> bool {
+    name.strip_prefix(log_name)
+        .and_then(|suffix| suffix.strip_prefix('.'))
+        .is_some_and(|index| index.parse::<usize>().is_ok())
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    rotated.into()
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_files_rotate_by_size() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("component_stdout.txt");
+        let config = ComponentLogConfig {
+            max_size_mb: Some(1),
+            max_files: Some(2),
+            ..Default::default()
+        };
+        let mut log = LogFile::open(&path, config)?;
+        let chunk = vec![b'x'; 600 * 1024];
+        for _ in 0..4 {
+            log.write_all(&chunk)?;
+        }
+
+        let len = |index| std::fs::metadata(rotated_path(&path, index)).map(|m| m.len());
+        assert_eq!(std::fs::metadata(&path)?.len(), chunk.len() as u64);
+        assert_eq!(len(1)?, chunk.len() as u64);
+        assert_eq!(len(2)?, chunk.len() as u64);
+        assert!(len(3).is_err(), "retention limit should apply");
+        Ok(())
+    }
+
+    #[test]
+    fn rotated_logs_are_recognized() {
+        let log_name = "component_stdout.txt";
+        assert!(is_rotated_log("component_stdout.txt.3", log_name));
+        assert!(!is_rotated_log("component_stdout.txt", log_name));
+        assert!(!is_rotated_log("component_stdout.txt.bak", log_name));
+    }
+
+    #[test]
+    fn cli_config_overrides_runtime_config() {
+        let cli = ComponentLogConfig {
+            format: Some(ComponentLogFormat::Json),
+            ..Default::default()
+        };
+        let runtime_config = ComponentLogConfig {
+            format: Some(ComponentLogFormat::Text),
+            max_files: Some(3),
+            ..Default::default()
+        };
+        let merged = cli.or(runtime_config);
+        assert_eq!(merged.format(), ComponentLogFormat::Json);
+        assert_eq!(merged.max_files, Some(3));
+    }
+}
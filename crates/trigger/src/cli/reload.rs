@@ -0,0 +1,56 @@
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ComponentLoader, ComponentReloader, TriggerDependenciesComposer};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::watch,
+};
+
+/// Serves component reload requests from `spin watch` until it closes the
+/// connection.
+///
+/// Each request is a line containing the ID of a component which has been
+/// rebuilt. Once the component has been reloaded, `ok` is sent back; if it
+/// can't be reloaded, `error <message>` is sent back, and `spin watch` falls
+/// back to restarting the application. Components which this trigger doesn't
+/// run are acknowledged with `ok`.
+///
+/// Components are reloaded with the latest `reloader`, which changes when the
+/// whole app is reloaded.
+pub(crate) async fn serve_reload_requests<F: RuntimeFactors, U: Send + 'static>(
+    address: SocketAddr,
+    reloader: watch::Receiver<ComponentReloader<F, U>>,
+    loader: &impl ComponentLoader<F, U>,
+    trigger_dependencies_composer: &impl TriggerDependenciesComposer,
+) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(address)
+        .await
+        .with_context(|| format!("failed to connect to reload channel at {address}"))?;
+    let (reader, mut writer) = stream.into_split();
+    let mut requests = BufReader::new(reader).lines();
+
+    while let Some(component_id) = requests.next_line().await? {
+        let reloader = reloader.borrow().clone();
+        let response = match reloader
+            .reload_component(&component_id, loader, trigger_dependencies_composer)
+            .await
+        {
+            Ok(reloaded) => {
+                if reloaded {
+                    tracing::info!("Reloaded component {component_id:?}");
+                }
+                "ok".to_owned()
+            }
+            Err(err) => {
+                tracing::error!("Failed to reload component {component_id:?}: {err:?}");
+                format!("error {}", format!("{err:#}").replace('\n', " "))
+            }
+        };
+        writer.write_all(format!("{response}\n").as_bytes()).await?;
+    }
+
+    Ok(())
+}
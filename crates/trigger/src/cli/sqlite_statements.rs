@@ -84,6 +84,15 @@ where
         self.execute(sqlite).await?;
         Ok(())
     }
+
+    async fn reconfigure_app(
+        &self,
+        _configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        // The statements have already run against the app's databases, and
+        // running them again may fail or duplicate data.
+        Ok(())
+    }
 }
 
 /// Parses a @{file:label} sqlite statement
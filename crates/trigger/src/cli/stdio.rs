@@ -1,7 +1,9 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, mpsc},
     task::Poll,
+    thread::JoinHandle,
 };
 
 use anyhow::{Context, Result};
@@ -10,11 +12,19 @@ use spin_core::async_trait;
 use spin_factor_wasi::WasiFactor;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::ExecutorHooks;
+use spin_telemetry::redaction::redact_bytes;
 use tokio::io::AsyncWrite;
 
+use super::log_file::{
+    ComponentLogConfig, ComponentLogFormat, JsonLogRecord, LogFile, is_rotated_log,
+};
+
 pub const STDOUT_LOG_FILE_SUFFIX: &str = "stdout";
 pub const STDERR_LOG_FILE_SUFFIX: &str = "stderr";
 
+/// JSON Lines output is buffered until a newline, up to this many bytes.
+const MAX_BUFFERED_LINE: usize = 64 * 1024;
+
 /// Which components should have their logs followed on stdout/stderr.
 #[derive(Clone, Debug, Default)]
 pub enum FollowComponents {
@@ -43,6 +53,10 @@ pub struct StdioLoggingExecutorHooks {
     follow_components: FollowComponents,
     log_dir: Option<PathBuf>,
     truncate_log: bool,
+    log_config: ComponentLogConfig,
+    /// Open log files, shared by all instances of a component so that
+    /// rotation applies to all of their output.
+    log_files: Mutex<HashMap<PathBuf, Arc<LogWriter>>>,
 }
 
 impl StdioLoggingExecutorHooks {
@@ -50,18 +64,21 @@ impl StdioLoggingExecutorHooks {
         follow_components: FollowComponents,
         log_dir: Option<PathBuf>,
         truncate_log: bool,
+        log_config: ComponentLogConfig,
     ) -> Self {
         Self {
             follow_components,
             log_dir,
             truncate_log,
+            log_config,
+            log_files: Default::default(),
         }
     }
 
     fn component_stdio_writer(
         &self,
         component_id: &str,
-        log_suffix: &str,
+        log_suffix: &'static str,
         log_dir: Option<&Path>,
     ) -> Result<ComponentStdioWriter> {
         let sanitized_component_id = sanitize_filename::sanitize(component_id);
@@ -71,10 +88,45 @@ impl StdioLoggingExecutorHooks {
 
         let follow = self.follow_components.should_follow(component_id);
         match log_path {
-            Some(log_path) => ComponentStdioWriter::new_forward(component_id, log_path, follow)
-                .with_context(|| format!("Failed to open log file {}", quoted_path(log_path))),
-            None => ComponentStdioWriter::new_inherit(component_id),
+            Some(log_path) => {
+                let file = self.log_file(log_path).with_context(|| {
+                    format!("Failed to open log file {}", quoted_path(log_path))
+                })?;
+                Ok(ComponentStdioWriter::new_forward(
+                    component_id,
+                    log_suffix,
+                    file,
+                    self.log_config.format(),
+                    follow,
+                ))
+            }
+            None => Ok(ComponentStdioWriter::new_inherit(component_id, log_suffix)),
+        }
+    }
+
+    fn log_file(&self, path: &Path) -> std::io::Result<Arc<LogWriter>> {
+        let mut log_files = self.log_files.lock().unwrap();
+        if let Some(file) = log_files.get(path) {
+            return Ok(file.clone());
         }
+        let file = Arc::new(LogWriter::open(path, self.log_config.clone())?);
+        log_files.insert(path.to_owned(), file.clone());
+        Ok(file)
+    }
+
+    /// Stops writing the log files of components which are no longer in the
+    /// app, e.g. after it is reloaded.
+    fn prune_log_files(&self, app: &spin_app::App) {
+        let component_ids = app
+            .components()
+            .map(|c| sanitize_filename::sanitize(c.id()))
+            .collect::<HashSet<_>>();
+        self.log_files.lock().unwrap().retain(|path, _| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.rsplit_once('_'))
+                .is_some_and(|(id, _)| component_ids.contains(id))
+        });
     }
 
     fn validate_follows(&self, app: &spin_app::App) -> anyhow::Result<()> {
@@ -98,7 +150,16 @@ impl StdioLoggingExecutorHooks {
         }
     }
 
+    fn create_log_dir(log_dir: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(log_dir)
+            .with_context(|| format!("Failed to create log dir {}", quoted_path(log_dir)))
+    }
+
     fn truncate_log_files(log_dir: &Path) {
+        let is_log = |name: &str| {
+            name.ends_with(&format!("{STDOUT_LOG_FILE_SUFFIX}.txt"))
+                || name.ends_with(&format!("{STDERR_LOG_FILE_SUFFIX}.txt"))
+        };
         if let Ok(entries) = log_dir.read_dir() {
             for entry in entries.flatten() {
                 let path = entry.path();
@@ -106,10 +167,13 @@ impl StdioLoggingExecutorHooks {
                     continue;
                 };
 
-                if name.ends_with(&format!("{STDOUT_LOG_FILE_SUFFIX}.txt"))
-                    || name.ends_with(&format!("{STDERR_LOG_FILE_SUFFIX}.txt"))
-                {
+                if is_log(name) {
                     _ = std::fs::File::create(path)
+                } else if let Some((log_name, _)) = name.rsplit_once('.')
+                    && is_log(log_name)
+                    && is_rotated_log(name, log_name)
+                {
+                    _ = std::fs::remove_file(path)
                 }
             }
         }
@@ -125,9 +189,7 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         self.validate_follows(configured_app.app())?;
 
         if let Some(dir) = &self.log_dir {
-            // Ensure log dir exists if set
-            std::fs::create_dir_all(dir)
-                .with_context(|| format!("Failed to create log dir {}", quoted_path(dir)))?;
+            Self::create_log_dir(dir)?;
 
             if self.truncate_log {
                 Self::truncate_log_files(dir);
@@ -138,6 +200,23 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         Ok(())
     }
 
+    async fn reconfigure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        // The logs of the running app are kept, and are appended to by the
+        // reloaded app.
+        self.validate_follows(configured_app.app())?;
+        if let Some(dir) = &self.log_dir {
+            Self::create_log_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    fn app_reloaded(&self, configured_app: &spin_factors::ConfiguredApp<F>) {
+        self.prune_log_files(configured_app.app());
+    }
+
     fn prepare_instance(
         &self,
         builder: &mut spin_factors_executor::FactorsInstanceBuilder<F, U>,
@@ -158,12 +237,88 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         )?);
         Ok(())
     }
+
+    fn shutdown(&self) -> anyhow::Result<()> {
+        // Wait for pending output to be written before the process exits.
+        let log_files = std::mem::take(&mut *self.log_files.lock().unwrap());
+        for file in log_files.into_values().filter_map(Arc::into_inner) {
+            file.close();
+        }
+        Ok(())
+    }
+}
+
+/// Writes to a log file on a dedicated thread, so that component output
+/// never blocks the async runtime on file I/O or rotation.
+struct LogWriter {
+    sender: mpsc::Sender<LogCommand>,
+    thread: JoinHandle<()>,
+}
+
+enum LogCommand {
+    /// Write a record to the file and, if following, its text to stderr.
+    Write {
+        record: Vec<u8>,
+        echo: Option<Vec<u8>>,
+    },
+    Flush,
+}
+
+impl LogWriter {
+    fn open(path: &Path, config: ComponentLogConfig) -> std::io::Result<Self> {
+        let mut file = LogFile::open(path, config)?;
+        let path = path.to_owned();
+        let (sender, receiver) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("component-log-writer".into())
+            .spawn(move || {
+                for command in receiver {
+                    if let Err(err) = Self::run(&mut file, command) {
+                        tracing::warn!("Failed to write log file {}: {err}", quoted_path(&path));
+                    }
+                }
+                _ = file.flush();
+            })?;
+        Ok(Self { sender, thread })
+    }
+
+    fn run(file: &mut LogFile, command: LogCommand) -> std::io::Result<()> {
+        use std::io::Write;
+        match command {
+            LogCommand::Write { record, echo } => {
+                // Whole writes keep records and redaction markers intact.
+                file.write_all(&record)?;
+                if let Some(echo) = echo {
+                    std::io::stderr().write_all(&echo)?;
+                }
+            }
+            LogCommand::Flush => {
+                file.flush()?;
+                std::io::stderr().flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&self, command: LogCommand) -> std::io::Result<()> {
+        self.sender
+            .send(command)
+            .map_err(|_| std::io::Error::other("component log writer has stopped"))
+    }
+
+    /// Stops the writer once it has written all pending output.
+    fn close(self) {
+        drop(self.sender);
+        _ = self.thread.join();
+    }
 }
 
 /// ComponentStdioWriter forwards output to a log file, (optionally) stderr, and (optionally) to a
-/// tracing compatibility layer.
+/// tracing compatibility layer. Secret variable values are redacted from all of them.
 pub struct ComponentStdioWriter {
     component_id: String,
+    /// The name of the stream, `stdout` or `stderr`.
+    stream: &'static str,
     inner: ComponentStdioWriterInner,
 }
 
@@ -172,193 +327,168 @@ enum ComponentStdioWriterInner {
     Inherit,
     /// Forward stdout/stderr to a file in addition to the inherited stdout/stderr.
     Forward {
-        sync_file: std::fs::File,
-        async_file: tokio::fs::File,
-        state: ComponentStdioWriterState,
+        file: Arc<LogWriter>,
+        format: ComponentLogFormat,
         follow: bool,
+        /// JSON Lines output not yet ended by a newline.
+        partial_line: Vec<u8>,
     },
 }
 
-#[derive(Debug)]
-enum ComponentStdioWriterState {
-    File,
-    Follow(std::ops::Range<usize>),
-}
-
 impl ComponentStdioWriter {
-    fn new_forward(component_id: &str, log_path: &Path, follow: bool) -> anyhow::Result<Self> {
-        let sync_file = std::fs::File::options()
-            .create(true)
-            .append(true)
-            .open(log_path)?;
-
-        let async_file = sync_file
-            .try_clone()
-            .context("could not get async file handle")?
-            .into();
-
-        Ok(Self {
+    fn new_forward(
+        component_id: &str,
+        stream: &'static str,
+        file: Arc<LogWriter>,
+        format: ComponentLogFormat,
+        follow: bool,
+    ) -> Self {
+        Self {
             component_id: component_id.to_string(),
+            stream,
             inner: ComponentStdioWriterInner::Forward {
-                sync_file,
-                async_file,
-                state: ComponentStdioWriterState::File,
+                file,
+                format,
                 follow,
+                partial_line: Vec::new(),
             },
-        })
+        }
     }
 
-    fn new_inherit(component_id: &str) -> anyhow::Result<Self> {
-        Ok(Self {
+    fn new_inherit(component_id: &str, stream: &'static str) -> Self {
+        Self {
             component_id: component_id.to_string(),
+            stream,
             inner: ComponentStdioWriterInner::Inherit,
-        })
+        }
+    }
+
+    /// Formats output as JSON Lines, one record per line of output.
+    fn json_lines(component_id: &str, stream: &str, buf: &[u8]) -> std::io::Result<Vec<u8>> {
+        let text = String::from_utf8_lossy(buf);
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let trace_id = spin_telemetry::traces::current_trace_id();
+        let mut out = Vec::with_capacity(buf.len() * 2);
+        for line in text.strip_suffix('\n').unwrap_or(&text).split('\n') {
+            let record = JsonLogRecord {
+                timestamp: timestamp.clone(),
+                component_id,
+                stream,
+                trace_id: trace_id.clone(),
+                message: line.strip_suffix('\r').unwrap_or(line),
+            };
+            serde_json::to_writer(&mut out, &record)?;
+            out.push(b'\n');
+        }
+        Ok(out)
+    }
+
+    /// Sends output to the log file, formatted as configured.
+    ///
+    /// JSON Lines records are only written for complete lines, unless `all`
+    /// is set or the buffered line is too long.
+    fn forward(&mut self, buf: &[u8], all: bool) -> std::io::Result<()> {
+        let ComponentStdioWriterInner::Forward {
+            file,
+            format,
+            follow,
+            partial_line,
+        } = &mut self.inner
+        else {
+            return Ok(());
+        };
+        let (record, echo) = match format {
+            ComponentLogFormat::Text => {
+                if buf.is_empty() {
+                    return Ok(());
+                }
+                let redacted = redact_bytes(buf).into_owned();
+                let echo = follow.then(|| redacted.clone());
+                (redacted, echo)
+            }
+            ComponentLogFormat::Json => {
+                partial_line.extend_from_slice(buf);
+                let end = if all || partial_line.len() > MAX_BUFFERED_LINE {
+                    partial_line.len()
+                } else {
+                    match partial_line.iter().rposition(|&b| b == b'\n') {
+                        Some(newline) => newline + 1,
+                        None => return Ok(()),
+                    }
+                };
+                let lines = partial_line.drain(..end).collect::<Vec<_>>();
+                if lines.is_empty() {
+                    return Ok(());
+                }
+                let redacted = redact_bytes(&lines).into_owned();
+                let record = Self::json_lines(&self.component_id, self.stream, &redacted)?;
+                (record, follow.then_some(redacted))
+            }
+        };
+        file.send(LogCommand::Write { record, echo })
     }
 }
 
+// Output is handed to the log writer thread, so writes never block.
 impl AsyncWrite for ComponentStdioWriter {
     fn poll_write(
         self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
+        _cx: &mut std::task::Context<'_>,
         buf: &[u8],
     ) -> Poll<std::result::Result<usize, std::io::Error>> {
-        let this = self.get_mut();
-
-        loop {
-            match &mut this.inner {
-                ComponentStdioWriterInner::Inherit => {
-                    let written = futures::ready!(
-                        std::pin::Pin::new(&mut tokio::io::stderr()).poll_write(cx, buf)
-                    );
-                    let written = match written {
-                        Ok(w) => w,
-                        Err(e) => return Poll::Ready(Err(e)),
-                    };
-                    return Poll::Ready(Ok(written));
-                }
-                ComponentStdioWriterInner::Forward {
-                    async_file,
-                    state,
-                    follow,
-                    ..
-                } => match &state {
-                    ComponentStdioWriterState::File => {
-                        let written =
-                            futures::ready!(std::pin::Pin::new(async_file).poll_write(cx, buf));
-                        let written = match written {
-                            Ok(w) => w,
-                            Err(e) => return Poll::Ready(Err(e)),
-                        };
-                        if *follow {
-                            *state = ComponentStdioWriterState::Follow(0..written);
-                        } else {
-                            return Poll::Ready(Ok(written));
-                        }
-                    }
-                    ComponentStdioWriterState::Follow(range) => {
-                        let written = futures::ready!(
-                            std::pin::Pin::new(&mut tokio::io::stderr())
-                                .poll_write(cx, &buf[range.clone()])
-                        );
-                        let written = match written {
-                            Ok(w) => w,
-                            Err(e) => return Poll::Ready(Err(e)),
-                        };
-                        if range.start + written >= range.end {
-                            let end = range.end;
-                            *state = ComponentStdioWriterState::File;
-                            return Poll::Ready(Ok(end));
-                        } else {
-                            *state = ComponentStdioWriterState::Follow(
-                                (range.start + written)..range.end,
-                            );
-                        };
-                    }
-                },
-            }
-        }
+        Poll::Ready(std::io::Write::write(self.get_mut(), buf))
     }
 
     fn poll_flush(
         self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
+        _cx: &mut std::task::Context<'_>,
     ) -> Poll<std::result::Result<(), std::io::Error>> {
-        let this = self.get_mut();
-
-        match &mut this.inner {
-            ComponentStdioWriterInner::Inherit => {
-                std::pin::Pin::new(&mut tokio::io::stderr()).poll_flush(cx)
-            }
-            ComponentStdioWriterInner::Forward {
-                async_file, state, ..
-            } => match state {
-                ComponentStdioWriterState::File => std::pin::Pin::new(async_file).poll_flush(cx),
-                ComponentStdioWriterState::Follow(_) => {
-                    std::pin::Pin::new(&mut tokio::io::stderr()).poll_flush(cx)
-                }
-            },
-        }
+        Poll::Ready(std::io::Write::flush(self.get_mut()))
     }
 
     fn poll_shutdown(
         self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
+        _cx: &mut std::task::Context<'_>,
     ) -> Poll<std::result::Result<(), std::io::Error>> {
         let this = self.get_mut();
-
-        match &mut this.inner {
-            ComponentStdioWriterInner::Inherit => {
-                std::pin::Pin::new(&mut tokio::io::stderr()).poll_flush(cx)
-            }
-            ComponentStdioWriterInner::Forward {
-                async_file, state, ..
-            } => match state {
-                ComponentStdioWriterState::File => std::pin::Pin::new(async_file).poll_shutdown(cx),
-                ComponentStdioWriterState::Follow(_) => {
-                    std::pin::Pin::new(&mut tokio::io::stderr()).poll_flush(cx)
-                }
-            },
-        }
+        Poll::Ready(
+            this.forward(&[], true)
+                .and_then(|()| std::io::Write::flush(this)),
+        )
     }
 }
 
 impl std::io::Write for ComponentStdioWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
         spin_telemetry::logs::handle_app_log(buf, &self.component_id);
 
-        match &mut self.inner {
-            ComponentStdioWriterInner::Inherit => {
-                std::io::stderr().write_all(buf)?;
-                Ok(buf.len())
-            }
-            ComponentStdioWriterInner::Forward {
-                sync_file, follow, ..
-            } => {
-                let written = sync_file.write(buf)?;
-                if *follow {
-                    std::io::stderr().write_all(&buf[..written])?;
-                }
-                Ok(written)
-            }
+        if let ComponentStdioWriterInner::Inherit = self.inner {
+            std::io::stderr().write_all(&redact_bytes(buf))?;
+        } else {
+            self.forward(buf, false)?;
         }
+        Ok(buf.len())
     }
 
+    // Flushing does not end a partial JSON Lines record, as WASI flushes
+    // after every write.
     fn flush(&mut self) -> std::io::Result<()> {
-        match &mut self.inner {
+        match &self.inner {
             ComponentStdioWriterInner::Inherit => std::io::stderr().flush(),
-            ComponentStdioWriterInner::Forward {
-                sync_file, follow, ..
-            } => {
-                sync_file.flush()?;
-                if *follow {
-                    std::io::stderr().flush()?;
-                }
-                Ok(())
-            }
+            ComponentStdioWriterInner::Forward { file, .. } => file.send(LogCommand::Flush),
         }
     }
 }
 
+impl Drop for ComponentStdioWriter {
+    fn drop(&mut self) {
+        _ = self.forward(&[], true);
+    }
+}
+
 fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> String {
     items
         .into_iter()
@@ -366,3 +496,81 @@ fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> Stri
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn json_lines_are_buffered_until_newline() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("component_stdout.txt");
+        let config = ComponentLogConfig {
+            format: Some(ComponentLogFormat::Json),
+            ..Default::default()
+        };
+        let file = Arc::new(LogWriter::open(&path, config)?);
+        let mut writer = ComponentStdioWriter::new_forward(
+            "component",
+            STDOUT_LOG_FILE_SUFFIX,
+            file.clone(),
+            ComponentLogFormat::Json,
+            false,
+        );
+        writer.write_all(b"hel")?;
+        writer.flush()?;
+        writer.write_all(b"lo\nwor")?;
+        drop(writer);
+        Arc::into_inner(file).unwrap().close();
+
+        let messages = std::fs::read_to_string(&path)?
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line).unwrap();
+                record["message"].as_str().unwrap().to_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["hello", "wor"]);
+        Ok(())
+    }
+
+    #[test]
+    fn log_files_of_removed_components_are_closed() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let hooks = StdioLoggingExecutorHooks::new(
+            FollowComponents::None,
+            Some(dir.path().to_owned()),
+            false,
+            Default::default(),
+        );
+        for id in ["kept", "removed"] {
+            hooks.component_stdio_writer(id, STDOUT_LOG_FILE_SUFFIX, Some(dir.path()))?;
+        }
+
+        let locked = spin_app::locked::LockedApp::from_json(
+            serde_json::json!({
+                "spin_lock_version": 1,
+                "triggers": [],
+                "components": [{
+                    "id": "kept",
+                    "source": { "content_type": "application/wasm" },
+                }],
+            })
+            .to_string()
+            .as_bytes(),
+        )?;
+        hooks.prune_log_files(&spin_app::App::new("app", locked));
+
+        let open = hooks
+            .log_files
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(open, [dir.path().join("kept_stdout.txt")]);
+        Ok(())
+    }
+}
@@ -25,6 +25,11 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for KeyValueDefaultStoreSummaryHo
         }
         Ok(())
     }
+
+    async fn reconfigure_app(&self, _: &spin_factors::ConfiguredApp<F>) -> anyhow::Result<()> {
+        // The summary was printed when the app started.
+        Ok(())
+    }
 }
 
 /// An [`ExecutorHooks`] that prints information about the default KV store.
@@ -53,4 +58,9 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for SqliteDefaultStoreSummaryHook
         }
         Ok(())
     }
+
+    async fn reconfigure_app(&self, _: &spin_factors::ConfiguredApp<F>) -> anyhow::Result<()> {
+        // The summary was printed when the app started.
+        Ok(())
+    }
 }
@@ -1,6 +1,9 @@
-use spin_factor_variables::VariablesFactor;
+use std::sync::{Arc, Mutex};
+
+use spin_factor_variables::{DynamicVariables, VariablesFactor};
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::ExecutorHooks;
+use tokio::task::JoinHandle;
 
 /// An executor hook that prepares the variables factor before runtime execution.
 pub struct VariablesValidatorHook;
@@ -19,3 +22,201 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for VariablesValidatorHook {
         Ok(())
     }
 }
+
+/// The admin server path which re-resolves dynamic variables when it
+/// receives a `POST` request.
+pub const VARIABLES_REFRESH_ADMIN_PATH: &str = "/variables/refresh";
+
+/// An executor hook that resolves dynamic variables before runtime execution
+/// and re-resolves them as configured by the `variables_refresh` runtime config.
+///
+/// Variables are also re-resolved by a `POST` request to
+/// [`VARIABLES_REFRESH_ADMIN_PATH`] on the admin server, if it is enabled.
+#[derive(Default)]
+pub struct VariablesRefreshHook {
+    // Refreshes the variables of the running app.
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl VariablesRefreshHook {
+    /// Stops refreshing the variables of the previously configured app, and
+    /// runs `task` in its place.
+    fn replace_task(&self, task: Option<JoinHandle<()>>) {
+        let previous = std::mem::replace(&mut *self.task.lock().unwrap(), task);
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+    }
+
+    /// Refreshes `dynamic` in place of the variables of any previously
+    /// configured app, or stops refreshing if `dynamic` is `None`.
+    fn refresh_app_variables(&self, dynamic: Option<Arc<DynamicVariables>>) {
+        let Some(dynamic) = dynamic else {
+            self.replace_task(None);
+            spin_telemetry::admin::unregister_action(VARIABLES_REFRESH_ADMIN_PATH);
+            return;
+        };
+        self.replace_task(Some(tokio::spawn(refresh_variables(dynamic.clone()))));
+        spin_telemetry::admin::register_action(
+            VARIABLES_REFRESH_ADMIN_PATH,
+            Arc::new(move || {
+                let dynamic = dynamic.clone();
+                Box::pin(async move {
+                    tracing::info!("Received admin request; refreshing variables");
+                    let changed = dynamic.refresh().await?;
+                    // Only names are reported: values may be secrets.
+                    Ok(format!("Changed variables: [{}]\n", changed.join(", ")))
+                })
+            }),
+        );
+    }
+}
+
+/// Returns the configured app's dynamic variables, if it has any.
+fn dynamic_variables<F: RuntimeFactors>(
+    configured_app: &spin_factors::ConfiguredApp<F>,
+) -> anyhow::Result<Option<Arc<DynamicVariables>>> {
+    Ok(configured_app
+        .app_state::<VariablesFactor>()?
+        .dynamic_variables()
+        .cloned())
+}
+
+#[spin_core::async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for VariablesRefreshHook {
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        let dynamic = dynamic_variables(configured_app)?;
+        if let Some(dynamic) = &dynamic {
+            // The initial resolution must succeed, as it would without refresh.
+            dynamic.refresh().await?;
+        }
+        self.refresh_app_variables(dynamic);
+        Ok(())
+    }
+
+    async fn reconfigure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        // The reload may still be rejected, so the running app's variables
+        // are refreshed until it is replaced.
+        if let Some(dynamic) = dynamic_variables(configured_app)? {
+            dynamic.refresh().await?;
+        }
+        Ok(())
+    }
+
+    fn app_reloaded(&self, configured_app: &spin_factors::ConfiguredApp<F>) {
+        self.refresh_app_variables(dynamic_variables(configured_app).ok().flatten());
+    }
+
+    fn shutdown(&self) -> anyhow::Result<()> {
+        self.refresh_app_variables(None);
+        Ok(())
+    }
+}
+
+/// Re-resolves `dynamic` whenever its refresh schedule or signal fires.
+async fn refresh_variables(dynamic: Arc<DynamicVariables>) {
+    let config = dynamic.config().clone();
+    let mut interval = config.interval.map(|period| {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    });
+    let mut signal = if config.on_signal {
+        refresh_signal()
+    } else {
+        None
+    };
+
+    loop {
+        let signalled = tokio::select! {
+            _ = next_tick(&mut interval) => None,
+            received = next_signal(&mut signal) => Some(received),
+        };
+        match signalled {
+            Some(Some(())) => tracing::info!("Received SIGUSR1; refreshing variables"),
+            Some(None) => {
+                // The signal stream has closed; stop listening to it.
+                signal = None;
+                continue;
+            }
+            None => {}
+        }
+        // Failures are recorded by `refresh`; the previous values remain in use.
+        _ = dynamic.refresh().await;
+    }
+}
+
+async fn next_tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => _ = interval.tick().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn next_signal(signal: &mut Option<RefreshSignal>) -> Option<()> {
+    match signal {
+        Some(signal) => signal.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+// SIGHUP is used to reload the whole app (see `app_reload`), so variables are
+// refreshed on a different signal.
+#[cfg(unix)]
+type RefreshSignal = tokio::signal::unix::Signal;
+
+#[cfg(unix)]
+fn refresh_signal() -> Option<RefreshSignal> {
+    use tokio::signal::unix::{SignalKind, signal};
+    signal(SignalKind::user_defined1())
+        .inspect_err(|err| {
+            tracing::warn!("Unable to listen for SIGUSR1 to refresh variables: {err}")
+        })
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn refresh_signal() -> Option<RefreshSignal> {
+    tracing::warn!("Refreshing variables on a signal is only supported on Unix");
+    None
+}
+
+#[cfg(not(unix))]
+struct RefreshSignal;
+
+#[cfg(not(unix))]
+impl RefreshSignal {
+    async fn recv(&mut self) -> Option<()> {
+        std::future::pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replaced_refresh_task_is_stopped() {
+        let hook = VariablesRefreshHook::default();
+        let first = tokio::spawn(std::future::pending());
+        let first_abort = first.abort_handle();
+        hook.replace_task(Some(first));
+
+        let second = tokio::spawn(std::future::pending());
+        let second_abort = second.abort_handle();
+        hook.replace_task(Some(second));
+        tokio::task::yield_now().await;
+        assert!(first_abort.is_finished());
+        assert!(!second_abort.is_finished());
+
+        hook.replace_task(None);
+        tokio::task::yield_now().await;
+        assert!(second_abort.is_finished());
+    }
+}
@@ -1,14 +1,18 @@
 pub mod cli;
 pub mod loader;
+mod multi;
+pub mod precompile;
+pub mod timeout;
 
 use heck::ToTitleCase;
-use std::future::Future;
+use std::{future::Future, sync::Arc};
 
 use clap::Args;
 use spin_core::Linker;
 use spin_factors::RuntimeFactors;
-use spin_factors_executor::{FactorsExecutorApp, FactorsInstanceBuilder};
+use spin_factors_executor::{ComponentLoader, FactorsExecutorApp, FactorsInstanceBuilder};
 
+pub use multi::{MultiTrigger, MultiTriggerCliArgs};
 pub use spin_app::App;
 
 /// Type alias for a [`spin_factors_executor::FactorsExecutorApp`] specialized to a [`Trigger`].
@@ -27,6 +31,18 @@ type TriggerInstanceState<T, F> = spin_factors_executor::InstanceState<
     <T as Trigger<F>>::InstanceState,
 >;
 
+/// A request to replace the app run by a trigger with a reloaded one.
+pub struct AppReload<T: Trigger<F>, F: RuntimeFactors> {
+    /// The reloaded app.
+    pub trigger_app: TriggerApp<T, F>,
+    /// Receives the outcome of the reload. If the reload fails, the trigger
+    /// keeps running the previous app.
+    pub applied: tokio::sync::oneshot::Sender<anyhow::Result<()>>,
+}
+
+/// A stream of [`AppReload`] requests, passed to [`Trigger::run_reloadable`].
+pub type AppReloads<T, F> = tokio::sync::mpsc::Receiver<AppReload<T, F>>;
+
 /// A trigger for a Spin runtime.
 pub trait Trigger<F: RuntimeFactors>: Sized + Send {
     /// A unique identifier for this trigger.
@@ -38,6 +54,13 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
     /// The instance state for this trigger.
     type InstanceState: Send + 'static;
 
+    /// The types of app trigger which this trigger runs. Defaults to
+    /// [`Self::TYPE`]; a [`MultiTrigger`] runs the types of each of the
+    /// triggers it combines.
+    fn trigger_types() -> Vec<&'static str> {
+        vec![Self::TYPE]
+    }
+
     /// Constructs a new trigger.
     fn new(cli_args: Self::CliArgs, app: &App) -> anyhow::Result<Self>;
 
@@ -66,12 +89,77 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
         Ok(())
     }
 
+    /// Returns the IDs of the app's components which this trigger runs.
+    fn component_ids(app: &App) -> Vec<String> {
+        let mut component_ids = Vec::new();
+        for trigger_type in Self::trigger_types() {
+            for trigger in app.triggers_with_type(trigger_type) {
+                if let Ok(component) = trigger.component()
+                    && !component_ids.iter().any(|id| id == component.id())
+                {
+                    component_ids.push(component.id().to_owned());
+                }
+            }
+        }
+        component_ids
+    }
+
+    /// Load the components which this trigger runs into `trigger_app`.
+    fn load_components(
+        trigger_app: &TriggerApp<Self, F>,
+        loader: &impl ComponentLoader<F, Self::InstanceState>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        async move {
+            for trigger_type in Self::trigger_types() {
+                trigger_app
+                    .load_components(
+                        loader,
+                        Some(trigger_type),
+                        Self::trigger_dependencies_composer(),
+                    )
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Defer loading the components which this trigger runs until they're
+    /// first used; see [`FactorsExecutorApp::defer_components`].
+    fn defer_components(
+        trigger_app: &TriggerApp<Self, F>,
+        loader: &Arc<impl ComponentLoader<F, Self::InstanceState> + Send + 'static>,
+    ) where
+        Self: 'static,
+    {
+        for trigger_type in Self::trigger_types() {
+            trigger_app.defer_components(
+                loader.clone(),
+                Some(trigger_type),
+                Self::trigger_dependencies_composer(),
+            );
+        }
+    }
+
     /// Run this trigger.
     fn run(
         self,
         trigger_app: TriggerApp<Self, F>,
     ) -> impl Future<Output = anyhow::Result<()>> + Send;
 
+    /// Run this trigger, replacing its app with each one received from `reloads`.
+    ///
+    /// Triggers which can't replace their app while running need not implement
+    /// this; by default `reloads` is dropped, which tells senders that reloading
+    /// is unsupported, and the trigger is run with [`Trigger::run`].
+    fn run_reloadable(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        reloads: AppReloads<Self, F>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        drop(reloads);
+        self.run(trigger_app)
+    }
+
     /// Returns a list of host requirements supported by this trigger specifically.
     ///
     /// See [`App::ensure_needs_only`].
@@ -79,8 +167,27 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
         Vec::new()
     }
 
+    /// Checks that the app's components for this trigger need only the host
+    /// features which this trigger supports.
+    fn ensure_host_requirements(app: &App) -> anyhow::Result<()> {
+        for trigger_type in Self::trigger_types() {
+            if let Err(unmet) =
+                app.ensure_needs_only(trigger_type, &Self::supported_host_requirements())
+            {
+                anyhow::bail!(
+                    "This application requires the following features that are not available in this version of the '{trigger_type}' trigger: {unmet}"
+                );
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the display name for the type of this trigger. Defaults to title case.
     fn display_name() -> String {
-        Self::TYPE.to_title_case()
+        Self::trigger_types()
+            .iter()
+            .map(|trigger_type| trigger_type.to_title_case())
+            .collect::<Vec<_>>()
+            .join(" and ")
     }
 }
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use spin_common::{ui::quoted_path, url::parse_file_url};
 use spin_compose::ComponentSourceLoaderFs;
 use spin_core::{Component, async_trait, wasmtime};
@@ -5,9 +7,14 @@ use spin_factors::{AppComponent, RuntimeFactors};
 use spin_factors_executor::TriggerDependencyData;
 use wasmtime::error::Context as _;
 
+use crate::precompile::{PRECOMPILED_KEY, PrecompiledComponent};
+
 #[derive(Default)]
 pub struct ComponentLoader {
     _private: (),
+    // The directory which precompiled artifacts' paths are relative to, if
+    // loading them is enabled
+    precompiled_artifacts_dir: Option<PathBuf>,
     #[cfg(feature = "unsafe-aot-compilation")]
     aot_compilation_enabled: bool,
 }
@@ -54,17 +61,54 @@ impl ComponentLoader {
         path: &std::path::Path,
     ) -> wasmtime::Result<Component> {
         assert!(self.aot_compilation_enabled);
-        match wasmtime::Engine::detect_precompiled_file(path)? {
-            Some(wasmtime::Precompiled::Component) => unsafe {
-                Component::deserialize_file(engine, path)
-            },
-            Some(wasmtime::Precompiled::Module) => {
-                wasmtime::bail!("expected AOT compiled component but found module");
-            }
-            None => {
-                wasmtime::bail!("expected AOT compiled component but found other data");
-            }
+        // SAFETY: the caller of `enable_loading_aot_compiled_components`
+        // guarantees that the component sources are trusted.
+        unsafe { deserialize_component(engine, path) }
+    }
+
+    /// Updates the loader to load components from the precompiled artifacts
+    /// which `spin compile` records in their metadata, when the artifacts are
+    /// compatible with the engine. Other components are compiled as usual.
+    ///
+    /// The artifacts' paths are relative to `lock_file_dir`, the directory of
+    /// the lock file which records them.
+    ///
+    /// **Warning: precompiled artifacts are native code, which bypasses the
+    /// Wasmtime security sandbox if it's malformed or malicious.**
+    ///
+    /// # Safety
+    ///
+    /// This is safe only if the metadata of every app loaded with this loader
+    /// is trusted, along with the artifacts that it refers to. The artifacts'
+    /// digests are checked, but that only guards against them being modified
+    /// after they were recorded.
+    pub unsafe fn enable_loading_precompiled_artifacts(&mut self, lock_file_dir: PathBuf) {
+        self.precompiled_artifacts_dir = Some(lock_file_dir);
+    }
+
+    fn load_precompiled_artifact(
+        &self,
+        engine: &wasmtime::Engine,
+        precompiled: &PrecompiledComponent,
+    ) -> anyhow::Result<Component> {
+        let lock_file_dir = self
+            .precompiled_artifacts_dir
+            .as_ref()
+            .expect("loading precompiled artifacts should be enabled");
+        let path = lock_file_dir.join(&precompiled.source);
+        let digest = format!(
+            "sha256:{}",
+            spin_common::sha256::hex_digest_from_file(&path)
+                .with_context(|| format!("failed to read {}", quoted_path(&path)))?
+        );
+        if digest != precompiled.digest {
+            anyhow::bail!("{} does not match its recorded digest", quoted_path(&path));
         }
+        // SAFETY: the caller of `enable_loading_precompiled_artifacts`
+        // guarantees that the artifact is trusted. Wasmtime checks that it's
+        // compatible with the engine.
+        let component = unsafe { deserialize_component(engine, &path) }?;
+        Ok(component)
     }
 
     pub(crate) async fn load_composed(
@@ -122,16 +166,54 @@ impl<T: RuntimeFactors, U> spin_factors_executor::ComponentLoader<T, U> for Comp
             return Ok(component);
         }
 
+        if self.precompiled_artifacts_dir.is_some()
+            && let Some(precompiled) = component.get_metadata(PRECOMPILED_KEY)?
+        {
+            match self.load_precompiled_artifact(engine, &precompiled) {
+                Ok(component) => return Ok(component),
+                Err(err) => tracing::warn!(
+                    "Compiling component {:?} because its precompiled artifact can't be used: {err:#}",
+                    component.id()
+                ),
+            }
+        }
+
         let composed = self
             .load_composed(component, trigger_dependencies_composer)
             .await?;
 
-        let component = spin_core::Component::new(engine, composed)
+        // Compiling is CPU bound, so keep it off the async runtime's threads;
+        // this also lets several components compile in parallel.
+        let engine = engine.clone();
+        let component = tokio::task::spawn_blocking(move || Component::new(&engine, composed))
+            .await?
             .with_context(|| format!("failed to compile component from {}", quoted_path(&path)))?;
         Ok(component)
     }
 }
 
+/// Deserializes a component precompiled by Wasmtime.
+///
+/// # Safety
+///
+/// The file must be trusted; see [`Component::deserialize_file`].
+unsafe fn deserialize_component(
+    engine: &wasmtime::Engine,
+    path: &std::path::Path,
+) -> wasmtime::Result<Component> {
+    match wasmtime::Engine::detect_precompiled_file(path)? {
+        Some(wasmtime::Precompiled::Component) => unsafe {
+            Component::deserialize_file(engine, path)
+        },
+        Some(wasmtime::Precompiled::Module) => {
+            wasmtime::bail!("expected AOT compiled component but found module");
+        }
+        None => {
+            wasmtime::bail!("expected AOT compiled component but found other data");
+        }
+    }
+}
+
 pub(crate) async fn load_trigger_dependencies(
     trigger_dependencies: &mut impl ExactSizeIterator<
         Item = (&String, &Vec<spin_app::locked::LockedComponentDependency>),
@@ -181,3 +263,129 @@ async fn load_trigger_dep_data(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use spin_app::App;
+    use spin_factor_wasi::WasiFactor;
+
+    use super::*;
+
+    #[derive(spin_factors::RuntimeFactors)]
+    struct TestFactors {
+        wasi: WasiFactor,
+    }
+
+    /// An empty component, in the binary format.
+    const EMPTY_COMPONENT: &[u8] = b"\0asm\x0d\0\x01\0";
+
+    /// Precompiles the empty component with `engine` into `dir`.
+    fn write_artifact(dir: &Path, engine: &wasmtime::Engine) -> PrecompiledComponent {
+        let artifact = engine.precompile_component(EMPTY_COMPONENT).unwrap();
+        std::fs::write(dir.join("hello.cwasm"), &artifact).unwrap();
+        PrecompiledComponent {
+            source: "hello.cwasm".into(),
+            digest: format!(
+                "sha256:{}",
+                spin_common::sha256::hex_digest_from_bytes(&artifact)
+            ),
+            target: None,
+            cpu_features: vec![],
+        }
+    }
+
+    /// An app whose one component is the empty component, which is recorded
+    /// as precompiled to `precompiled`.
+    fn test_app(dir: &Path, precompiled: &PrecompiledComponent) -> App {
+        let wasm_path = dir.join("hello.wasm");
+        std::fs::write(&wasm_path, EMPTY_COMPONENT).unwrap();
+        let source = url::Url::from_file_path(&wasm_path).unwrap().to_string();
+        let locked = serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 1,
+            "triggers": [],
+            "components": [{
+                "id": "hello",
+                "source": { "content_type": "application/wasm", "source": source },
+                "metadata": { "precompiled": precompiled },
+            }],
+        }))
+        .unwrap();
+        App::new("test-app", locked)
+    }
+
+    fn precompiled_loader(lock_file_dir: &Path) -> ComponentLoader {
+        let mut loader = ComponentLoader::new();
+        // SAFETY: the tests' artifacts are trusted.
+        unsafe {
+            loader.enable_loading_precompiled_artifacts(lock_file_dir.to_owned());
+        }
+        loader
+    }
+
+    #[test]
+    fn artifacts_are_found_relative_to_the_lock_file() {
+        let engine = wasmtime::Engine::default();
+        let compiled_dir = tempfile::tempdir().unwrap();
+        let precompiled = write_artifact(compiled_dir.path(), &engine);
+
+        // The directory can be moved once the artifacts are compiled.
+        let moved_dir = tempfile::tempdir().unwrap();
+        let moved_path = moved_dir.path().join("compiled");
+        std::fs::rename(compiled_dir.path(), &moved_path).unwrap();
+
+        precompiled_loader(&moved_path)
+            .load_precompiled_artifact(&engine, &precompiled)
+            .unwrap();
+    }
+
+    #[test]
+    fn modified_artifacts_are_rejected() {
+        let engine = wasmtime::Engine::default();
+        let dir = tempfile::tempdir().unwrap();
+        let precompiled = PrecompiledComponent {
+            digest: format!(
+                "sha256:{}",
+                spin_common::sha256::hex_digest_from_bytes(b"the original")
+            ),
+            ..write_artifact(dir.path(), &engine)
+        };
+
+        let Err(err) =
+            precompiled_loader(dir.path()).load_precompiled_artifact(&engine, &precompiled)
+        else {
+            panic!("a modified artifact was loaded");
+        };
+        assert!(
+            err.to_string()
+                .ends_with("does not match its recorded digest"),
+            "{err:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn incompatible_artifacts_are_compiled_instead() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let other_engine = wasmtime::Engine::new(wasmtime::Config::new().consume_fuel(true))?;
+        let precompiled = write_artifact(dir.path(), &other_engine);
+        let app = test_app(dir.path(), &precompiled);
+        let component = app.get_component("hello").unwrap();
+
+        let engine = wasmtime::Engine::default();
+        let loader = precompiled_loader(dir.path());
+        assert!(
+            loader
+                .load_precompiled_artifact(&engine, &precompiled)
+                .is_err()
+        );
+        <ComponentLoader as spin_factors_executor::ComponentLoader<TestFactors, ()>>::load_component(
+            &loader,
+            &engine,
+            &component,
+            &(),
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+use std::{collections::HashMap, sync::Arc};
+
+use clap::Args;
+use spin_core::{Linker, async_trait};
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ComponentLoader, TriggerDependenciesComposer, TriggerDependency};
+
+use crate::{App, AppReload, AppReloads, Trigger, TriggerApp, TriggerInstanceState};
+
+/// Runs two triggers in the same process, sharing one executor, Wasmtime
+/// engine and configured app. More triggers can be combined by nesting.
+///
+/// Each trigger only runs the components of its own trigger type, but
+/// components which several of them run are compiled once.
+pub struct MultiTrigger<A, B> {
+    first: A,
+    second: B,
+}
+
+/// The CLI arguments of a [`MultiTrigger`]: those of both of its triggers.
+#[derive(Args)]
+pub struct MultiTriggerCliArgs<A: Args, B: Args> {
+    #[clap(flatten)]
+    first: A,
+    #[clap(flatten)]
+    second: B,
+}
+
+impl<F, A, B> Trigger<F> for MultiTrigger<A, B>
+where
+    F: RuntimeFactors,
+    A: Trigger<F>,
+    B: Trigger<F, InstanceState = A::InstanceState>,
+{
+    /// A multi trigger runs the types of its triggers rather than one of its
+    /// own, so this only names it; see [`Trigger::trigger_types`].
+    const TYPE: &'static str = "multi";
+
+    type CliArgs = MultiTriggerCliArgs<A::CliArgs, B::CliArgs>;
+    type InstanceState = A::InstanceState;
+
+    fn trigger_types() -> Vec<&'static str> {
+        let mut trigger_types = A::trigger_types();
+        trigger_types.extend(B::trigger_types());
+        trigger_types
+    }
+
+    fn new(cli_args: Self::CliArgs, app: &App) -> anyhow::Result<Self> {
+        Ok(Self {
+            first: A::new(cli_args.first, app)?,
+            second: B::new(cli_args.second, app)?,
+        })
+    }
+
+    fn update_core_config(&mut self, config: &mut spin_core::Config) -> anyhow::Result<()> {
+        self.first.update_core_config(config)?;
+        self.second.update_core_config(config)
+    }
+
+    fn trigger_dependencies_composer() -> impl TriggerDependenciesComposer {
+        MultiComposer(
+            A::trigger_dependencies_composer(),
+            B::trigger_dependencies_composer(),
+        )
+    }
+
+    fn add_to_linker(
+        &mut self,
+        linker: &mut Linker<TriggerInstanceState<Self, F>>,
+    ) -> anyhow::Result<()> {
+        self.first.add_to_linker(linker)?;
+        self.second.add_to_linker(linker)
+    }
+
+    fn component_ids(app: &App) -> Vec<String> {
+        let mut component_ids = A::component_ids(app);
+        for component_id in B::component_ids(app) {
+            if !component_ids.contains(&component_id) {
+                component_ids.push(component_id);
+            }
+        }
+        component_ids
+    }
+
+    async fn load_components(
+        trigger_app: &TriggerApp<Self, F>,
+        loader: &impl ComponentLoader<F, Self::InstanceState>,
+    ) -> anyhow::Result<()> {
+        A::load_components(trigger_app, loader).await?;
+        B::load_components(trigger_app, loader).await
+    }
+
+    fn defer_components(
+        trigger_app: &TriggerApp<Self, F>,
+        loader: &Arc<impl ComponentLoader<F, Self::InstanceState> + Send + 'static>,
+    ) where
+        Self: 'static,
+    {
+        A::defer_components(trigger_app, loader);
+        B::defer_components(trigger_app, loader);
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // As when triggers run in separate processes, the app stops as soon
+        // as any of its triggers does.
+        tokio::select! {
+            res = self.first.run(trigger_app.clone()) => res,
+            res = self.second.run(trigger_app) => res,
+        }
+    }
+
+    async fn run_reloadable(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        mut reloads: AppReloads<Self, F>,
+    ) -> anyhow::Result<()> {
+        let (first_reloads, first_receiver) = tokio::sync::mpsc::channel(1);
+        let (second_reloads, second_receiver) = tokio::sync::mpsc::channel(1);
+        let first = self
+            .first
+            .run_reloadable(trigger_app.clone(), first_receiver);
+        let second = self.second.run_reloadable(trigger_app, second_receiver);
+
+        let forward_reloads = async move {
+            while let Some(AppReload {
+                trigger_app,
+                applied,
+            }) = reloads.recv().await
+            {
+                _ = applied.send(
+                    forward_reload::<F, A, B>(&first_reloads, &second_reloads, trigger_app).await,
+                );
+            }
+            std::future::pending().await
+        };
+
+        tokio::select! {
+            res = first => res,
+            res = second => res,
+            res = forward_reloads => res,
+        }
+    }
+
+    fn ensure_host_requirements(app: &App) -> anyhow::Result<()> {
+        A::ensure_host_requirements(app)?;
+        B::ensure_host_requirements(app)
+    }
+
+    fn display_name() -> String {
+        format!("{} and {}", A::display_name(), B::display_name())
+    }
+}
+
+/// Hands a reloaded app to each of the triggers which accept reloads.
+///
+/// A trigger which doesn't support reloading drops its receiver, and keeps
+/// running the app it was started with; for example, in `spin up` with HTTP
+/// and Redis triggers, only the HTTP routes are reloaded.
+async fn forward_reload<F, A, B>(
+    first_reloads: &tokio::sync::mpsc::Sender<AppReload<A, F>>,
+    second_reloads: &tokio::sync::mpsc::Sender<AppReload<B, F>>,
+    trigger_app: TriggerApp<MultiTrigger<A, B>, F>,
+) -> anyhow::Result<()>
+where
+    F: RuntimeFactors,
+    A: Trigger<F>,
+    B: Trigger<F, InstanceState = A::InstanceState>,
+{
+    let first_reloaded = reload(first_reloads, trigger_app.clone()).await?;
+    let second_reloaded = reload(second_reloads, trigger_app).await?;
+    match (first_reloaded, second_reloaded) {
+        (false, false) => anyhow::bail!(
+            "neither the {} nor the {} trigger supports reloading",
+            A::display_name(),
+            B::display_name()
+        ),
+        (true, false) => warn_not_reloaded::<B, F>(),
+        (false, true) => warn_not_reloaded::<A, F>(),
+        (true, true) => (),
+    }
+    Ok(())
+}
+
+/// Hands a reloaded app to a trigger, and waits for it to be swapped in.
+/// Returns whether the trigger accepts reloads.
+async fn reload<T: Trigger<F>, F: RuntimeFactors>(
+    reloads: &tokio::sync::mpsc::Sender<AppReload<T, F>>,
+    trigger_app: TriggerApp<T, F>,
+) -> anyhow::Result<bool> {
+    let (applied, outcome) = tokio::sync::oneshot::channel();
+    let reload = AppReload {
+        trigger_app,
+        applied,
+    };
+    if reloads.send(reload).await.is_err() {
+        return Ok(false);
+    }
+    outcome.await.map_err(|_| {
+        anyhow::anyhow!(
+            "the {} trigger stopped accepting reloads",
+            T::display_name()
+        )
+    })??;
+    Ok(true)
+}
+
+fn warn_not_reloaded<T: Trigger<F>, F: RuntimeFactors>() {
+    tracing::warn!(
+        "The {} trigger does not support reloading, and keeps running the application it was started with",
+        T::display_name()
+    );
+}
+
+/// Composes a component's trigger dependencies with whichever of the
+/// triggers' composers accepts them.
+struct MultiComposer<A, B>(A, B);
+
+#[async_trait]
+impl<A: TriggerDependenciesComposer, B: TriggerDependenciesComposer> TriggerDependenciesComposer
+    for MultiComposer<A, B>
+{
+    async fn compose_trigger_dependencies(
+        &self,
+        trigger_dependencies: &HashMap<String, Vec<TriggerDependency>>,
+        component: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        if trigger_dependencies.is_empty() {
+            return Ok(component);
+        }
+        match self
+            .0
+            .compose_trigger_dependencies(trigger_dependencies, component.clone())
+            .await
+        {
+            Ok(composed) => Ok(composed),
+            Err(first_err) => self
+                .1
+                .compose_trigger_dependencies(trigger_dependencies, component)
+                .await
+                .map_err(|second_err| {
+                    anyhow::anyhow!(
+                        "neither trigger could compose the component's dependencies: {first_err:#}; {second_err:#}"
+                    )
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
+    use spin_factors_executor::FactorsExecutor;
+    use spin_factors_test::TestEnvironment;
+
+    use super::*;
+    use crate::cli::NoCliArgs;
+
+    #[derive(RuntimeFactors)]
+    struct TestFactors {
+        wasi: WasiFactor,
+    }
+
+    /// Stands in for the HTTP trigger, which accepts reloads.
+    struct ReloadingTrigger(Arc<AtomicUsize>);
+
+    impl Trigger<TestFactors> for ReloadingTrigger {
+        const TYPE: &'static str = "http";
+        type CliArgs = NoCliArgs;
+        type InstanceState = ();
+
+        fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+            Ok(Self(Default::default()))
+        }
+
+        async fn run(self, _trigger_app: TriggerApp<Self, TestFactors>) -> anyhow::Result<()> {
+            std::future::pending().await
+        }
+
+        async fn run_reloadable(
+            self,
+            _trigger_app: TriggerApp<Self, TestFactors>,
+            mut reloads: AppReloads<Self, TestFactors>,
+        ) -> anyhow::Result<()> {
+            while let Some(reload) = reloads.recv().await {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                _ = reload.applied.send(Ok(()));
+            }
+            std::future::pending().await
+        }
+    }
+
+    /// Stands in for the Redis trigger, which doesn't accept reloads.
+    struct StaticTrigger;
+
+    impl Trigger<TestFactors> for StaticTrigger {
+        const TYPE: &'static str = "redis";
+        type CliArgs = NoCliArgs;
+        type InstanceState = ();
+
+        fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+            Ok(Self)
+        }
+
+        async fn run(self, _trigger_app: TriggerApp<Self, TestFactors>) -> anyhow::Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    /// Runs `trigger`, and asks it to reload its app once.
+    async fn reload_once<A, B>(trigger: MultiTrigger<A, B>) -> anyhow::Result<()>
+    where
+        A: Trigger<TestFactors, InstanceState = ()>,
+        B: Trigger<TestFactors, InstanceState = ()>,
+    {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors);
+        let locked = env.build_locked_app().await?;
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let trigger_app = executor
+            .configure_app(App::new("test-app", locked), Default::default())
+            .await?;
+
+        let (reloads, receiver) = tokio::sync::mpsc::channel(1);
+        let (applied, outcome) = tokio::sync::oneshot::channel();
+        let reload = AppReload {
+            trigger_app: trigger_app.clone(),
+            applied,
+        };
+        tokio::select! {
+            res = trigger.run_reloadable(trigger_app, receiver) => {
+                panic!("trigger stopped: {res:?}")
+            }
+            outcome = async {
+                reloads.send(reload).await.ok().unwrap();
+                outcome.await.unwrap()
+            } => outcome,
+        }
+    }
+
+    #[test]
+    fn multi_trigger_has_the_types_of_its_triggers() {
+        type Multi = MultiTrigger<ReloadingTrigger, StaticTrigger>;
+        assert_eq!(
+            <Multi as Trigger<TestFactors>>::trigger_types(),
+            ["http", "redis"]
+        );
+        assert_eq!(
+            <Multi as Trigger<TestFactors>>::display_name(),
+            "Http and Redis"
+        );
+    }
+
+    #[tokio::test]
+    async fn only_triggers_which_accept_reloads_are_reloaded() -> anyhow::Result<()> {
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let http_and_redis = MultiTrigger {
+            first: ReloadingTrigger(reloads.clone()),
+            second: StaticTrigger,
+        };
+        reload_once(http_and_redis).await?;
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+        let redis_and_http = MultiTrigger {
+            first: StaticTrigger,
+            second: ReloadingTrigger(reloads.clone()),
+        };
+        reload_once(redis_and_http).await?;
+        assert_eq!(reloads.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reload_fails_if_no_trigger_accepts_reloads() -> anyhow::Result<()> {
+        let err = reload_once(MultiTrigger {
+            first: StaticTrigger,
+            second: StaticTrigger,
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "neither the Redis nor the Redis trigger supports reloading"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn composer_reports_both_triggers_errors() {
+        let dependencies = HashMap::from([("dependency".to_owned(), vec![])]);
+        let err = MultiComposer((), ())
+            .compose_trigger_dependencies(&dependencies, vec![])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "neither trigger could compose the component's dependencies: this trigger should not have dependencies; this trigger should not have dependencies"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Ahead-of-time compilation of an app's components.
+//!
+//! `spin compile` runs each of an app's triggers with `--precompile-only`,
+//! which compiles the trigger's components with the trigger's own engine
+//! configuration and writes them to a directory. The artifacts are recorded
+//! in the components' metadata under [`PRECOMPILED_KEY`], and a trigger run
+//! with `--allow-precompiled` loads them instead of compiling the components.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use spin_app::{App, MetadataKey};
+use spin_common::ui::quoted_path;
+use spin_core::wasmtime;
+use spin_factors::RuntimeFactors;
+
+use crate::{Trigger, loader::ComponentLoader};
+
+/// The component metadata key under which a component's precompiled artifact
+/// is recorded.
+pub const PRECOMPILED_KEY: MetadataKey<PrecompiledComponent> = MetadataKey::new("precompiled");
+
+/// A component's precompiled artifact.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PrecompiledComponent {
+    /// The path of the artifact, relative to the directory of the lock file
+    /// which records it, so that the directory can be moved or copied to
+    /// another host as a whole.
+    pub source: String,
+    /// The SHA-256 digest of the artifact, as `sha256:<hex>`.
+    pub digest: String,
+    /// The target triple which the artifact was compiled for, if it wasn't
+    /// the host which compiled it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// The CPU features which were enabled when compiling the artifact.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu_features: Vec<String>,
+}
+
+/// Options for precompiling components.
+#[derive(Debug, Default)]
+pub struct PrecompileOptions {
+    /// The target triple to compile for. The default is the host.
+    pub target: Option<String>,
+    /// The CPU features to enable.
+    pub cpu_features: Vec<String>,
+}
+
+/// Compiles the components which the trigger `T` runs with the given engine
+/// config, writing each to `output_dir`. Returns the artifacts by component ID.
+///
+/// The artifacts' paths are relative to `output_dir`, so the lock file which
+/// records them must be written there too.
+pub(crate) async fn precompile_components<T: Trigger<F>, F: RuntimeFactors>(
+    engine_config: &mut spin_core::Config,
+    app: &App,
+    output_dir: &Path,
+    options: &PrecompileOptions,
+) -> anyhow::Result<BTreeMap<String, PrecompiledComponent>> {
+    if let Some(target) = &options.target {
+        engine_config
+            .target(target)
+            .with_context(|| format!("unsupported target {target:?}"))?;
+    }
+    for feature in &options.cpu_features {
+        // SAFETY: the compiled code isn't run here. Wasmtime checks that the
+        // host which loads it has the features it was compiled with.
+        unsafe {
+            engine_config.enable_cpu_feature(feature);
+        }
+    }
+    let engine = wasmtime::Engine::new(engine_config.wasmtime_config())?;
+
+    tokio::fs::create_dir_all(output_dir)
+        .await
+        .with_context(|| format!("failed to create {}", quoted_path(output_dir)))?;
+
+    let loader = ComponentLoader::new();
+    let composer = T::trigger_dependencies_composer();
+    let mut precompiled = BTreeMap::new();
+    for component_id in T::component_ids(app) {
+        if precompiled.contains_key(&component_id) {
+            continue;
+        }
+        let component = app
+            .get_component(&component_id)
+            .with_context(|| format!("no such component {component_id:?}"))?;
+        let composed = loader.load_composed(&component, &composer).await?;
+
+        let compile_engine = engine.clone();
+        let artifact =
+            tokio::task::spawn_blocking(move || compile_engine.precompile_component(&composed))
+                .await?
+                .map_err(anyhow::Error::from)
+                .with_context(|| format!("failed to compile component {component_id:?}"))?;
+
+        let file_name = sanitize_filename::sanitize(format!("{component_id}.cwasm"));
+        let path = output_dir.join(&file_name);
+        tokio::fs::write(&path, &artifact)
+            .await
+            .with_context(|| format!("failed to write {}", quoted_path(&path)))?;
+        precompiled.insert(
+            component_id,
+            PrecompiledComponent {
+                source: file_name,
+                digest: format!(
+                    "sha256:{}",
+                    spin_common::sha256::hex_digest_from_bytes(&artifact)
+                ),
+                target: options.target.clone(),
+                cpu_features: options.cpu_features.clone(),
+            },
+        );
+    }
+    Ok(precompiled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precompiled_component_round_trips_through_metadata() -> anyhow::Result<()> {
+        let precompiled = PrecompiledComponent {
+            source: "hello.cwasm".into(),
+            digest: "sha256:0123".into(),
+            target: Some("aarch64-unknown-linux-gnu".into()),
+            cpu_features: vec!["has_lse".into()],
+        };
+        let locked = serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 1,
+            "triggers": [],
+            "components": [{
+                "id": "hello",
+                "source": { "content_type": "application/wasm", "source": "file:///hello.wasm" },
+                "metadata": { "precompiled": precompiled },
+            }],
+        }))?;
+        let app = App::new("test-app", locked);
+
+        let component = app.get_component("hello").unwrap();
+        assert_eq!(component.get_metadata(PRECOMPILED_KEY)?, Some(precompiled));
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Time limits on handler invocations.
+//!
+//! A trigger which limits how long a handler may run sets the store's
+//! deadline with [`set_deadline`] and runs the invocation with
+//! [`run_with_timeout`]. The deadline interrupts guest code which runs past
+//! the limit, and the invocation is abandoned if it is still waiting on the
+//! host then. Either way, the invocation fails with an [`InvocationTimeout`].
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use spin_core::Trap;
+
+/// The error returned when a handler invocation exceeds its time limit.
+#[derive(Debug)]
+pub struct InvocationTimeout {
+    /// The time limit which was exceeded.
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for InvocationTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "handler did not complete within {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for InvocationTimeout {}
+
+/// Sets the store's execution deadline to `timeout` from now, if there is a
+/// time limit.
+pub fn set_deadline<T>(store: &mut spin_core::Store<T>, timeout: Option<Duration>) {
+    if let Some(timeout) = timeout {
+        store.set_deadline(Instant::now() + timeout);
+    }
+}
+
+/// Runs a handler invocation, failing with an [`InvocationTimeout`] if it
+/// doesn't complete within `timeout`.
+///
+/// The store which the invocation runs in should have its deadline set with
+/// [`set_deadline`], so that a guest which is interrupted by it is reported
+/// as timing out too. Timeouts are recorded in the
+/// `spin.invocation_timeout_count` metric.
+pub async fn run_with_timeout<T>(
+    timeout: Option<Duration>,
+    trigger_type: &str,
+    app_id: &str,
+    component_id: &str,
+    invocation: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let Some(timeout) = timeout else {
+        return invocation.await;
+    };
+    match tokio::time::timeout(timeout, invocation).await {
+        Ok(Err(err)) if !is_interrupt(&err) => Err(err),
+        Ok(Ok(output)) => Ok(output),
+        Ok(Err(_)) | Err(_) => {
+            Err(report_timeout(timeout, trigger_type, app_id, component_id).into())
+        }
+    }
+}
+
+/// Reports that an invocation didn't complete within `timeout`, returning
+/// the error to fail it with.
+///
+/// [`run_with_timeout`] does this itself; it is for handlers which enforce
+/// their time limit some other way.
+pub fn report_timeout(
+    timeout: Duration,
+    trigger_type: &str,
+    app_id: &str,
+    component_id: &str,
+) -> InvocationTimeout {
+    tracing::warn!(
+        trigger_type,
+        component_id,
+        "Component {component_id:?} did not complete within {timeout:?}"
+    );
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.invocation_timeout_count = 1,
+        trigger_type = trigger_type.to_owned(),
+        app_id = app_id.to_owned(),
+        component_id = component_id.to_owned()
+    );
+    InvocationTimeout { timeout }
+}
+
+/// Returns whether the error is the trap raised when a store's deadline
+/// interrupts the guest.
+fn is_interrupt(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<Trap>() == Some(&Trap::Interrupt))
+}
+
+/// Parses a duration, such as a time limit, given on the command line.
+///
+/// A number with no suffix or with an `s` suffix is interpreted as seconds;
+/// other accepted suffixes are `ms`, `us` or `μs`, and `ns`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let error = |e: std::num::ParseIntError| {
+        format!("expected integer suffixed by `s`, `ms`, `us`, `μs`, or `ns`; got {s:?}; {e}")
+    };
+    if let Some(num) = s.strip_suffix("ms") {
+        num.parse().map(Duration::from_millis).map_err(error)
+    } else if let Some(num) = s.strip_suffix("us").or(s.strip_suffix("μs")) {
+        num.parse().map(Duration::from_micros).map_err(error)
+    } else if let Some(num) = s.strip_suffix("ns") {
+        num.parse().map(Duration::from_nanos).map_err(error)
+    } else {
+        let num = s.strip_suffix("s").unwrap_or(s);
+        num.parse().map(Duration::from_secs).map_err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn slow_invocation_times_out() {
+        let err = run_with_timeout(
+            Some(Duration::from_millis(10)),
+            "test",
+            "app",
+            "slow",
+            std::future::pending::<anyhow::Result<()>>(),
+        )
+        .await
+        .unwrap_err();
+        let timeout = err.downcast_ref::<InvocationTimeout>().unwrap();
+        assert_eq!(timeout.timeout, Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn interrupted_guest_times_out() {
+        let err = run_with_timeout(
+            Some(Duration::from_secs(10)),
+            "test",
+            "app",
+            "busy",
+            async {
+                Err::<(), _>(anyhow::Error::from(Trap::Interrupt))
+                    .context("guest invocation failed")
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is::<InvocationTimeout>());
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_returned() {
+        let err = run_with_timeout(
+            Some(Duration::from_secs(10)),
+            "test",
+            "app",
+            "broken",
+            async { Err::<(), _>(anyhow::Error::from(Trap::UnreachableCodeReached)) },
+        )
+        .await
+        .unwrap_err();
+        assert!(!err.is::<InvocationTimeout>());
+    }
+
+    #[test]
+    fn parse_duration_suffixes() {
+        assert_eq!(parse_duration("5").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_duration("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("10us").unwrap(), Duration::from_micros(10));
+        assert_eq!(parse_duration("10μs").unwrap(), Duration::from_micros(10));
+        assert_eq!(parse_duration("100ns").unwrap(), Duration::from_nanos(100));
+        assert!(parse_duration("5m").is_err());
+        assert!(parse_duration("ms").is_err());
+    }
+}
@@ -0,0 +1,449 @@
+use anyhow::Context as _;
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_credential_types::{Credentials, provider::SharedCredentialsProvider};
+use serde::Deserialize;
+use spin_expressions::{Key, Provider};
+use spin_factors::anyhow;
+use spin_world::async_trait;
+use tokio::sync::OnceCell;
+use tracing::{Level, instrument};
+
+/// AWS connection options shared by the AWS variables providers.
+///
+/// If `access_key` and `secret_key` are not set, credentials are loaded from
+/// the standard AWS credential chain (environment variables, shared config
+/// and credentials files, SSO, container and instance metadata).
+#[derive(Clone, Default)]
+struct AwsConnectionConfig {
+    region: Option<String>,
+    endpoint_url: Option<String>,
+    access_key: Option<String>,
+    secret_key: Option<String>,
+    token: Option<String>,
+}
+
+impl AwsConnectionConfig {
+    async fn sdk_config(&self) -> SdkConfig {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &self.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        if let Some(endpoint_url) = &self.endpoint_url {
+            loader = loader.endpoint_url(endpoint_url);
+        }
+        if let (Some(access_key), Some(secret_key)) = (&self.access_key, &self.secret_key) {
+            loader = loader.credentials_provider(SharedCredentialsProvider::new(Credentials::new(
+                access_key,
+                secret_key,
+                self.token.clone(),
+                None,
+                "spin_variables_aws",
+            )));
+        }
+        loader.load().await
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.access_key.is_some() == self.secret_key.is_some(),
+            "AWS variables provider must specify both 'access_key' and 'secret_key', or neither to use the default credential chain"
+        );
+        Ok(())
+    }
+}
+
+/// Configuration for the AWS Secrets Manager variables provider.
+///
+/// By default each variable is read from the secret named `{prefix}{variable}`.
+/// If `secret_id` is set, all variables are instead read from the fields of
+/// that single JSON secret.
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwsSecretsManagerVariablesConfig {
+    /// The AWS region. If not set, the region is taken from the environment.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// A custom endpoint URL, e.g. `http://localhost:4566` for LocalStack.
+    #[serde(default)]
+    pub endpoint_url: Option<String>,
+    /// The access key ID to authenticate with. If not set, credentials are
+    /// loaded from the standard AWS credential chain.
+    #[serde(default)]
+    pub access_key: Option<String>,
+    /// The secret access key to authenticate with.
+    #[serde(default)]
+    pub secret_key: Option<String>,
+    /// The session token to authenticate with.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// A prefix prepended to variable names to form secret names.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// A single secret containing a JSON object whose fields are variables.
+    #[serde(default)]
+    pub secret_id: Option<String>,
+    /// If set, each secret is parsed as a JSON object and this field is used
+    /// as the variable value.
+    #[serde(default)]
+    pub json_key: Option<String>,
+}
+
+impl std::fmt::Debug for AwsSecretsManagerVariablesConfig {
+    // Credentials are omitted so that they can't leak into logs or errors.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSecretsManagerVariablesConfig")
+            .field("region", &self.region)
+            .field("endpoint_url", &self.endpoint_url)
+            .field("prefix", &self.prefix)
+            .field("secret_id", &self.secret_id)
+            .field("json_key", &self.json_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsSecretsManagerVariablesConfig {
+    fn connection(&self) -> AwsConnectionConfig {
+        AwsConnectionConfig {
+            region: self.region.clone(),
+            endpoint_url: self.endpoint_url.clone(),
+            access_key: self.access_key.clone(),
+            secret_key: self.secret_key.clone(),
+            token: self.token.clone(),
+        }
+    }
+}
+
+/// A provider that fetches variables from AWS Secrets Manager.
+pub struct AwsSecretsManagerProvider {
+    config: AwsSecretsManagerVariablesConfig,
+    client: OnceCell<aws_sdk_secretsmanager::Client>,
+}
+
+impl std::fmt::Debug for AwsSecretsManagerProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSecretsManagerProvider")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsSecretsManagerProvider {
+    pub fn new(config: AwsSecretsManagerVariablesConfig) -> anyhow::Result<Self> {
+        config.connection().validate()?;
+        anyhow::ensure!(
+            config.secret_id.is_none() || (config.prefix.is_none() && config.json_key.is_none()),
+            "AWS Secrets Manager 'secret_id' cannot be combined with 'prefix' or 'json_key'"
+        );
+        Ok(Self {
+            config,
+            client: OnceCell::new(),
+        })
+    }
+
+    async fn client(&self) -> &aws_sdk_secretsmanager::Client {
+        self.client
+            .get_or_init(|| async {
+                aws_sdk_secretsmanager::Client::new(&self.config.connection().sdk_config().await)
+            })
+            .await
+    }
+
+    async fn get_secret(&self, secret_id: &str) -> anyhow::Result<Option<String>> {
+        let result = self
+            .client()
+            .await
+            .get_secret_value()
+            .secret_id(secret_id)
+            .send()
+            .await;
+        match result {
+            Ok(output) => Ok(output.secret_string),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception()) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err).context("Failed to read variable from AWS Secrets Manager"),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for AwsSecretsManagerProvider {
+    #[instrument(name = "spin_variables.get_from_aws_secrets_manager", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        if let Some(secret_id) = &self.config.secret_id {
+            let Some(secret) = self.get_secret(secret_id).await? else {
+                return Ok(None);
+            };
+            return extract_json_key(&secret, key.as_str());
+        }
+        let secret_id = format!(
+            "{}{}",
+            self.config.prefix.as_deref().unwrap_or_default(),
+            key.as_str()
+        );
+        let Some(secret) = self.get_secret(&secret_id).await? else {
+            return Ok(None);
+        };
+        match &self.config.json_key {
+            Some(json_key) => extract_json_key(&secret, json_key),
+            None => Ok(Some(secret)),
+        }
+    }
+}
+
+/// Configuration for the AWS Systems Manager Parameter Store variables provider.
+///
+/// Each variable is read from the parameter named `{prefix}{variable}`,
+/// e.g. `/my-app/db_password` with `prefix = "/my-app/"`.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwsSsmVariablesConfig {
+    /// The AWS region. If not set, the region is taken from the environment.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// A custom endpoint URL, e.g. `http://localhost:4566` for LocalStack.
+    #[serde(default)]
+    pub endpoint_url: Option<String>,
+    /// The access key ID to authenticate with. If not set, credentials are
+    /// loaded from the standard AWS credential chain.
+    #[serde(default)]
+    pub access_key: Option<String>,
+    /// The secret access key to authenticate with.
+    #[serde(default)]
+    pub secret_key: Option<String>,
+    /// The session token to authenticate with.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// A prefix prepended to variable names to form parameter names.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Whether to decrypt `SecureString` parameters. Defaults to true.
+    #[serde(default = "default_true")]
+    pub with_decryption: bool,
+    /// If set, each parameter is parsed as a JSON object and this field is
+    /// used as the variable value.
+    #[serde(default)]
+    pub json_key: Option<String>,
+}
+
+impl std::fmt::Debug for AwsSsmVariablesConfig {
+    // Credentials are omitted so that they can't leak into logs or errors.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSsmVariablesConfig")
+            .field("region", &self.region)
+            .field("endpoint_url", &self.endpoint_url)
+            .field("prefix", &self.prefix)
+            .field("with_decryption", &self.with_decryption)
+            .field("json_key", &self.json_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsSsmVariablesConfig {
+    fn connection(&self) -> AwsConnectionConfig {
+        AwsConnectionConfig {
+            region: self.region.clone(),
+            endpoint_url: self.endpoint_url.clone(),
+            access_key: self.access_key.clone(),
+            secret_key: self.secret_key.clone(),
+            token: self.token.clone(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A provider that fetches variables from AWS Systems Manager Parameter Store.
+pub struct AwsSsmProvider {
+    config: AwsSsmVariablesConfig,
+    client: OnceCell<aws_sdk_ssm::Client>,
+}
+
+impl std::fmt::Debug for AwsSsmProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSsmProvider")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsSsmProvider {
+    pub fn new(config: AwsSsmVariablesConfig) -> anyhow::Result<Self> {
+        config.connection().validate()?;
+        Ok(Self {
+            config,
+            client: OnceCell::new(),
+        })
+    }
+
+    async fn client(&self) -> &aws_sdk_ssm::Client {
+        self.client
+            .get_or_init(|| async {
+                aws_sdk_ssm::Client::new(&self.config.connection().sdk_config().await)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl Provider for AwsSsmProvider {
+    #[instrument(name = "spin_variables.get_from_aws_ssm", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let name = format!(
+            "{}{}",
+            self.config.prefix.as_deref().unwrap_or_default(),
+            key.as_str()
+        );
+        let result = self
+            .client()
+            .await
+            .get_parameter()
+            .name(name)
+            .with_decryption(self.config.with_decryption)
+            .send()
+            .await;
+        let value = match result {
+            Ok(output) => output.parameter.and_then(|p| p.value),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_parameter_not_found()) =>
+            {
+                None
+            }
+            Err(err) => {
+                return Err(err).context("Failed to read variable from AWS SSM Parameter Store");
+            }
+        };
+        match (value, &self.config.json_key) {
+            (Some(value), Some(json_key)) => extract_json_key(&value, json_key),
+            (value, None) => Ok(value),
+            (None, Some(_)) => Ok(None),
+        }
+    }
+}
+
+/// Extracts the field `key` from a secret containing a JSON object.
+///
+/// String fields are returned as-is; other values are returned as JSON.
+fn extract_json_key(secret: &str, key: &str) -> anyhow::Result<Option<String>> {
+    // Don't include the secret in errors.
+    let value: serde_json::Value = serde_json::from_str(secret)
+        .map_err(|_| anyhow::anyhow!("secret is not valid JSON; cannot extract key {key:?}"))?;
+    let serde_json::Value::Object(mut fields) = value else {
+        anyhow::bail!("secret is not a JSON object; cannot extract key {key:?}");
+    };
+    Ok(fields.remove(key).map(|value| match value {
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_keys_are_extracted() {
+        let secret = r#"{"username": "admin", "port": 5432, "tls": {"enabled": true}}"#;
+        assert_eq!(
+            extract_json_key(secret, "username").unwrap().as_deref(),
+            Some("admin")
+        );
+        assert_eq!(
+            extract_json_key(secret, "port").unwrap().as_deref(),
+            Some("5432")
+        );
+        assert_eq!(
+            extract_json_key(secret, "tls").unwrap().as_deref(),
+            Some(r#"{"enabled":true}"#)
+        );
+        assert_eq!(extract_json_key(secret, "missing").unwrap(), None);
+        extract_json_key("not json", "username").unwrap_err();
+        extract_json_key("[1, 2]", "username").unwrap_err();
+    }
+
+    #[test]
+    fn partial_static_credentials_are_rejected() {
+        let config = AwsSecretsManagerVariablesConfig {
+            access_key: Some("key".into()),
+            ..Default::default()
+        };
+        AwsSecretsManagerProvider::new(config).unwrap_err();
+    }
+
+    #[test]
+    fn debug_output_omits_credentials() {
+        let secrets = AwsSecretsManagerProvider::new(AwsSecretsManagerVariablesConfig {
+            access_key: Some("AKIDEXAMPLE".into()),
+            secret_key: Some("wJalrXUtnFEMI".into()),
+            token: Some("session-token".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        let ssm = AwsSsmProvider::new(AwsSsmVariablesConfig {
+            region: None,
+            endpoint_url: None,
+            access_key: Some("AKIDEXAMPLE".into()),
+            secret_key: Some("wJalrXUtnFEMI".into()),
+            token: Some("session-token".into()),
+            prefix: None,
+            with_decryption: true,
+            json_key: None,
+        })
+        .unwrap();
+        for debug in [format!("{secrets:?}"), format!("{ssm:?}")] {
+            assert!(!debug.contains("wJalrXUtnFEMI"), "{debug}");
+            assert!(!debug.contains("session-token"), "{debug}");
+        }
+    }
+
+    /// Runs against LocalStack or another AWS-compatible endpoint given in
+    /// `SPIN_TEST_AWS_ENDPOINT_URL`, with a secret `spin-test-db` containing
+    /// `{"password": "hunter2"}` and a parameter `/spin-test/greeting`
+    /// containing `hello`.
+    #[tokio::test]
+    #[ignore = "requires LocalStack"]
+    async fn localstack_providers_resolve_variables() -> anyhow::Result<()> {
+        let endpoint_url = std::env::var("SPIN_TEST_AWS_ENDPOINT_URL")?;
+
+        let secrets = AwsSecretsManagerProvider::new(AwsSecretsManagerVariablesConfig {
+            region: Some("us-east-1".into()),
+            endpoint_url: Some(endpoint_url.clone()),
+            access_key: Some("test".into()),
+            secret_key: Some("test".into()),
+            token: None,
+            prefix: Some("spin-test-".into()),
+            json_key: Some("password".into()),
+            secret_id: None,
+        })?;
+        assert_eq!(
+            secrets.get(&Key::new("db")?).await?.as_deref(),
+            Some("hunter2")
+        );
+        assert_eq!(secrets.get(&Key::new("missing")?).await?, None);
+
+        let ssm = AwsSsmProvider::new(AwsSsmVariablesConfig {
+            region: Some("us-east-1".into()),
+            endpoint_url: Some(endpoint_url),
+            access_key: Some("test".into()),
+            secret_key: Some("test".into()),
+            token: None,
+            prefix: Some("/spin-test/".into()),
+            with_decryption: true,
+            json_key: None,
+        })?;
+        assert_eq!(
+            ssm.get(&Key::new("greeting")?).await?.as_deref(),
+            Some("hello")
+        );
+        assert_eq!(ssm.get(&Key::new("missing")?).await?, None);
+        Ok(())
+    }
+}
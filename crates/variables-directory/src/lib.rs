@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::Deserialize;
+use spin_common::ui::quoted_path;
+use spin_expressions::{Key, Provider};
+use spin_factors::anyhow::{self, Context as _};
+use spin_world::async_trait;
+use tracing::{Level, instrument};
+
+/// Configuration for the directory variables provider.
+///
+/// Each file in the directory provides the variable named after the file,
+/// e.g. as for secrets mounted into a Kubernetes pod:
+///
+/// ```toml
+/// [[variables_provider]]
+/// type = "directory"
+/// path = "/var/run/secrets/my-app"
+/// prefix = "myapp_"
+/// strip_extension = true
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectoryVariablesConfig {
+    /// The directory containing the variable files.
+    pub path: PathBuf,
+    /// If set, only files whose names start with this prefix are used, and
+    /// the prefix is removed to form the variable name.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Whether to remove the file extension to form the variable name,
+    /// e.g. `db_password.txt` provides `db_password`.
+    #[serde(default)]
+    pub strip_extension: bool,
+    /// Whether to watch the directory and pick up changed files without a
+    /// restart. Defaults to true.
+    #[serde(default = "default_true")]
+    pub watch: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A [`Provider`] that reads variables from the files in a directory.
+///
+/// File names are lowercased and `-` and `.` are replaced with `_` to form
+/// variable names; files whose names do not then form valid variable names
+/// are ignored, as are hidden files (such as the `..data` links Kubernetes
+/// uses to swap secrets atomically). A single trailing newline is removed
+/// from file contents.
+pub struct DirectoryVariablesProvider {
+    state: Arc<DirectoryState>,
+    // Dropping the watcher stops watching.
+    _watcher: Option<RecommendedWatcher>,
+}
+
+struct DirectoryState {
+    config: DirectoryVariablesConfig,
+    values: RwLock<HashMap<String, String>>,
+}
+
+impl DirectoryVariablesProvider {
+    /// Creates a provider for the given configuration, reading the directory
+    /// and, if configured, starting to watch it for changes.
+    pub fn new(config: DirectoryVariablesConfig) -> anyhow::Result<Self> {
+        let values = read_directory(&config)?;
+        let state = Arc::new(DirectoryState {
+            config,
+            values: RwLock::new(values),
+        });
+        let watcher = if state.config.watch {
+            Some(watch_directory(state.clone())?)
+        } else {
+            None
+        };
+        Ok(Self {
+            state,
+            _watcher: watcher,
+        })
+    }
+}
+
+impl std::fmt::Debug for DirectoryVariablesProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't include values; they are likely to be secrets.
+        f.debug_struct("DirectoryVariablesProvider")
+            .field("config", &self.state.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Provider for DirectoryVariablesProvider {
+    #[instrument(name = "spin_variables.get_from_directory", level = Level::DEBUG, skip(self), err(level = Level::INFO))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        Ok(self.state.values.read().unwrap().get(key.as_str()).cloned())
+    }
+
+    fn may_resolve(&self, key: &Key) -> bool {
+        self.state.values.read().unwrap().contains_key(key.as_str())
+    }
+}
+
+impl DirectoryState {
+    fn reload(&self) {
+        match read_directory(&self.config) {
+            Ok(values) => {
+                let mut current = self.values.write().unwrap();
+                if *current != values {
+                    tracing::info!("Reloaded variables from {}", quoted_path(&self.config.path));
+                    *current = values;
+                }
+            }
+            // Keep serving the previous values, e.g. if the directory is
+            // briefly unavailable while being remounted.
+            Err(err) => tracing::warn!("Failed to reload variables: {err:#}"),
+        }
+    }
+}
+
+fn watch_directory(state: Arc<DirectoryState>) -> anyhow::Result<RecommendedWatcher> {
+    let path = state.config.path.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.kind.is_access() => {}
+            Ok(_) => state.reload(),
+            Err(err) => tracing::warn!("Error watching variables directory: {err}"),
+        })
+        .context("Failed to create variables directory watcher")?;
+    watcher
+        .watch(&path, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", quoted_path(&path)))?;
+    Ok(watcher)
+}
+
+fn read_directory(config: &DirectoryVariablesConfig) -> anyhow::Result<HashMap<String, String>> {
+    let entries = std::fs::read_dir(&config.path)
+        .with_context(|| format!("Failed to read directory {}", quoted_path(&config.path)))?;
+    let mut values = HashMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        // Follows symlinks, which is how Kubernetes exposes secret files.
+        if !path.is_file() {
+            continue;
+        }
+        let Some(key) = variable_name(config, &path) else {
+            continue;
+        };
+        let mut value = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", quoted_path(&path)))?;
+        if value.ends_with('\n') {
+            value.pop();
+            if value.ends_with('\r') {
+                value.pop();
+            }
+        }
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+/// Returns the variable name provided by the file at `path`, if any.
+fn variable_name(config: &DirectoryVariablesConfig, path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    if file_name.starts_with('.') {
+        return None;
+    }
+    let name = match &config.prefix {
+        Some(prefix) => file_name.strip_prefix(prefix.as_str())?,
+        None => file_name,
+    };
+    let name = match name.rsplit_once('.') {
+        Some((stem, _)) if config.strip_extension && !stem.is_empty() => stem,
+        _ => name,
+    };
+    let name = name.to_lowercase().replace(['-', '.'], "_");
+    match Key::new(&name) {
+        Ok(_) => Some(name),
+        Err(_) => {
+            tracing::debug!("Ignoring {}: not a valid variable name", quoted_path(path));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn config(path: &Path) -> DirectoryVariablesConfig {
+        DirectoryVariablesConfig {
+            path: path.to_owned(),
+            prefix: None,
+            strip_extension: false,
+            watch: false,
+        }
+    }
+
+    async fn get(provider: &DirectoryVariablesProvider, key: &str) -> Option<String> {
+        provider.get(&Key::new(key).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn files_are_mapped_to_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("db-password"), "hunter2\n").unwrap();
+        std::fs::write(dir.path().join("API_KEY"), "abc").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "nope").unwrap();
+        std::fs::write(dir.path().join("1nvalid"), "nope").unwrap();
+        std::fs::create_dir(dir.path().join("..data")).unwrap();
+
+        let provider = DirectoryVariablesProvider::new(config(dir.path())).unwrap();
+        assert_eq!(get(&provider, "db_password").await.unwrap(), "hunter2");
+        assert_eq!(get(&provider, "api_key").await.unwrap(), "abc");
+        assert_eq!(provider.state.values.read().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn prefix_and_extension_are_stripped() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("myapp_token.txt"), "t").unwrap();
+        std::fs::write(dir.path().join("other_token.txt"), "o").unwrap();
+
+        let provider = DirectoryVariablesProvider::new(DirectoryVariablesConfig {
+            prefix: Some("myapp_".into()),
+            strip_extension: true,
+            ..config(dir.path())
+        })
+        .unwrap();
+        assert_eq!(get(&provider, "token").await.unwrap(), "t");
+        assert_eq!(get(&provider, "other_token").await, None);
+    }
+
+    #[tokio::test]
+    async fn changed_files_are_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("secret"), "old").unwrap();
+
+        let provider = DirectoryVariablesProvider::new(DirectoryVariablesConfig {
+            watch: true,
+            ..config(dir.path())
+        })
+        .unwrap();
+        assert_eq!(get(&provider, "secret").await.unwrap(), "old");
+
+        std::fs::write(dir.path().join("secret"), "new").unwrap();
+        for _ in 0..50 {
+            if get(&provider, "secret").await.as_deref() == Some("new") {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("secret was not reloaded");
+    }
+}
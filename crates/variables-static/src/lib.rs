@@ -8,11 +8,22 @@ pub use source::*;
 mod source;
 
 /// A [`Provider`] that reads variables from an static map.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Deserialize, Clone)]
 pub struct StaticVariablesProvider {
     values: Arc<HashMap<String, String>>,
 }
 
+impl std::fmt::Debug for StaticVariablesProvider {
+    // Values are omitted as they may be secrets.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut keys = self.values.keys().collect::<Vec<_>>();
+        keys.sort();
+        f.debug_struct("StaticVariablesProvider")
+            .field("keys", &keys)
+            .finish_non_exhaustive()
+    }
+}
+
 #[async_trait]
 impl Provider for StaticVariablesProvider {
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
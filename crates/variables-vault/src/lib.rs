@@ -10,7 +10,7 @@ use vaultrs::{
 
 use spin_expressions::{Key, Provider};
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 /// A config Provider that uses HashiCorp Vault.
 pub struct VaultVariablesProvider {
@@ -25,6 +25,17 @@ pub struct VaultVariablesProvider {
     prefix: Option<String>,
 }
 
+impl std::fmt::Debug for VaultVariablesProvider {
+    // The token is omitted so that it can't leak into logs or errors.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultVariablesProvider")
+            .field("url", &self.url)
+            .field("mount", &self.mount)
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
 #[async_trait]
 impl Provider for VaultVariablesProvider {
     #[instrument(name = "spin_variables.get_from_vault", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
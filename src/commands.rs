@@ -4,6 +4,8 @@
 pub mod build;
 /// Commands for publishing applications to the Fermyon Platform.
 pub mod cloud;
+/// Command for compiling an application's components ahead of time.
+pub mod compile;
 /// Command for running the Spin Doctor.
 pub mod doctor;
 /// Commands for external subcommands (i.e. plugins)
@@ -22,5 +24,7 @@ pub mod target_environments;
 pub mod templates;
 /// Commands for starting the runtime.
 pub mod up;
+/// Commands for inspecting application variables.
+pub mod variables;
 /// Command for rebuilding and restarting a Spin app when files change.
 pub mod watch;
@@ -1,4 +1,4 @@
-use std::{ffi::OsString, path::PathBuf};
+use std::{ffi::OsString, num::NonZeroUsize, path::PathBuf};
 
 use anyhow::Result;
 use clap::Parser;
@@ -47,6 +47,18 @@ pub struct BuildCommand {
     #[clap(long = "skip-generate-wits", alias = "skip-generate-wit")]
     skip_generate_wits: bool,
 
+    /// Build all components, even those whose build `inputs` and output are
+    /// unchanged since they were last built. Components which don't declare
+    /// build `inputs` are always built.
+    #[clap(long)]
+    pub force: bool,
+
+    /// The maximum number of components to build at once. Components are only
+    /// built once the components they depend on have been built. The default
+    /// is the number of CPUs.
+    #[clap(short = 'j', long = "jobs")]
+    pub jobs: Option<NonZeroUsize>,
+
     /// Run the application after building.
     #[clap(name = BUILD_UP_OPT, short = 'u', long = "up")]
     pub up: bool,
@@ -67,6 +79,10 @@ impl BuildCommand {
             &self.component_id,
             self.target_checking(),
             self.wit_generation(),
+            spin_build::BuildCommandOptions {
+                rebuild: self.rebuild(),
+                jobs: self.jobs(),
+            },
             None,
         )
         .await?;
@@ -86,6 +102,19 @@ impl BuildCommand {
         }
     }
 
+    fn rebuild(&self) -> spin_build::Rebuild {
+        if self.force {
+            spin_build::Rebuild::Always
+        } else {
+            spin_build::Rebuild::IfChanged
+        }
+    }
+
+    fn jobs(&self) -> usize {
+        self.jobs
+            .map_or_else(spin_build::default_jobs, NonZeroUsize::get)
+    }
+
     fn profile(&self) -> Option<&str> {
         self.profile.as_deref()
     }
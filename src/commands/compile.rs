@@ -0,0 +1,193 @@
+use std::{collections::BTreeMap, ffi::OsString, path::Path, path::PathBuf, process::Stdio};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Parser;
+use reqwest::Url;
+use spin_app::locked::LockedApp;
+use spin_common::{paths::parent_dir, ui::quoted_path};
+use spin_loader::FilesMountStrategy;
+use spin_trigger::{
+    cli::{SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR},
+    precompile::{PRECOMPILED_KEY, PrecompiledComponent},
+};
+
+use crate::{directory_rels::notify_if_nondefault_rel, opts::APP_MANIFEST_FILE_OPT};
+
+use super::up::{is_builtin_trigger_command, trigger_commands_for_trigger_types, trigger_types};
+
+const DEFAULT_OUTPUT_DIR: &str = ".spin/compiled";
+const LOCK_FILE_NAME: &str = "spin.lock";
+
+/// Compile the application's components ahead of time.
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Compile the application's components ahead of time",
+    long_about = "Compile the application's components ahead of time, so that the triggers don't have to compile them when the application starts.\n\nThe compiled components are written to the output directory, along with a lock file which records them in the components' metadata, relative to the lock file so that the directory can be moved as a whole. A trigger run with that lock file and `--allow-precompiled` loads the compiled components if they're compatible with the host, and compiles the others as usual."
+)]
+pub struct CompileCommand {
+    /// The application to compile. This may be a manifest (spin.toml) file, or
+    /// a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// The build profile to compile. The default is the anonymous profile
+    /// (usually the release build).
+    #[clap(long)]
+    #[arg(add = clap_complete::ArgValueCandidates::new(crate::completions::profiles))]
+    pub profile: Option<String>,
+
+    /// The target triple to compile for, such as `aarch64-unknown-linux-gnu`.
+    /// The default is this host.
+    #[clap(long)]
+    pub target: Option<String>,
+
+    /// CPU features to enable, as Cranelift ISA flags such as `has_avx2`. This
+    /// can be specified multiple times, or as a comma-separated list. When
+    /// compiling for this host, its own CPU features are always enabled.
+    #[clap(long = "cpu-features", value_delimiter = ',')]
+    pub cpu_features: Vec<String>,
+
+    /// The directory to write the compiled components and lock file to. The
+    /// default is `.spin/compiled` in the application directory.
+    #[clap(short = 'o', long = "output-dir")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Options to pass to the triggers. Only options which change how
+    /// components are compiled, such as `--debug-info`, make a difference. The
+    /// same options must be given to the triggers which load the components.
+    #[clap(last = true)]
+    pub trigger_args: Vec<OsString>,
+}
+
+impl CompileCommand {
+    pub async fn run(self) -> Result<()> {
+        let (manifest_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&manifest_file, distance);
+        let app_dir = parent_dir(&manifest_file)?;
+
+        let output_dir = match &self.output_dir {
+            Some(output_dir) => output_dir.clone(),
+            None => app_dir.join(DEFAULT_OUTPUT_DIR),
+        };
+        let output_dir = std::path::absolute(&output_dir)
+            .with_context(|| format!("invalid output directory {}", quoted_path(&output_dir)))?;
+        tokio::fs::create_dir_all(&output_dir)
+            .await
+            .with_context(|| format!("failed to create {}", quoted_path(&output_dir)))?;
+
+        let mut locked_app = spin_loader::from_file(
+            &manifest_file,
+            FilesMountStrategy::Direct,
+            self.profile.as_deref(),
+            None,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to load manifest from {}",
+                quoted_path(&manifest_file)
+            )
+        })?;
+
+        // The triggers read the app from the lock file, which is rewritten
+        // once the compiled components are recorded in it.
+        let locked_url = write_locked_app(&locked_app, &output_dir).await?;
+
+        let trigger_types = trigger_types(&locked_app);
+        let trigger_cmds =
+            trigger_commands_for_trigger_types(trigger_types.iter().map(String::as_str).collect())?;
+        let mut precompiled = BTreeMap::new();
+        for trigger_cmd in trigger_cmds {
+            match self
+                .precompile_with_trigger(&trigger_cmd, &locked_url, &app_dir, &output_dir)
+                .await
+            {
+                Ok(components) => precompiled.extend(components),
+                // A plugin trigger may not support precompiling; its
+                // components are compiled when the app starts instead.
+                Err(e) if !is_builtin_trigger_command(&trigger_cmd) => {
+                    terminal::warn!(
+                        "Could not compile components using `{}`, so they will be compiled when the application starts: {e:#}",
+                        trigger_cmd.join(" ")
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut compiled_count = 0;
+        for component in &mut locked_app.components {
+            if let Some(precompiled) = precompiled.remove(&component.id) {
+                component.metadata.insert(
+                    PRECOMPILED_KEY.as_ref().to_owned(),
+                    serde_json::to_value(precompiled)?,
+                );
+                compiled_count += 1;
+            }
+        }
+        write_locked_app(&locked_app, &output_dir).await?;
+
+        println!(
+            "Compiled {compiled_count} component(s) to {}",
+            quoted_path(&output_dir)
+        );
+        Ok(())
+    }
+
+    async fn precompile_with_trigger(
+        &self,
+        trigger_cmd: &[String],
+        locked_url: &str,
+        app_dir: &Path,
+        output_dir: &Path,
+    ) -> Result<BTreeMap<String, PrecompiledComponent>> {
+        // The docs for `current_exe` warn that this may be insecure because it could be executed
+        // via hard-link. I think it should be fine as long as we aren't `setuid`ing this binary.
+        let mut cmd = tokio::process::Command::new(std::env::current_exe().unwrap());
+        cmd.args(trigger_cmd)
+            .arg("--precompile-only")
+            .arg("--precompile-dir")
+            .arg(output_dir);
+        if let Some(target) = &self.target {
+            cmd.arg("--precompile-target").arg(target);
+        }
+        for cpu_feature in &self.cpu_features {
+            cmd.arg("--precompile-cpu-feature").arg(cpu_feature);
+        }
+        cmd.args(&self.trigger_args)
+            .env(SPIN_LOCKED_URL, locked_url)
+            .env(SPIN_WORKING_DIR, output_dir)
+            .env(SPIN_LOCAL_APP_DIR, app_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+
+        tracing::trace!("Running trigger executor: {:?}", cmd);
+
+        let output = cmd.output().await.context("Failed to execute trigger")?;
+        if !output.status.success() {
+            bail!("trigger exited with {}", output.status);
+        }
+        serde_json::from_slice(&output.stdout)
+            .context("trigger returned invalid precompiled components")
+    }
+}
+
+async fn write_locked_app(locked_app: &LockedApp, output_dir: &Path) -> Result<String> {
+    let locked_path = output_dir.join(LOCK_FILE_NAME);
+    let locked_app_contents =
+        serde_json::to_vec_pretty(&locked_app).context("failed to serialize locked app")?;
+    tokio::fs::write(&locked_path, locked_app_contents)
+        .await
+        .with_context(|| format!("failed to write {}", quoted_path(&locked_path)))?;
+    let locked_url = Url::from_file_path(&locked_path)
+        .map_err(|_| anyhow!("cannot convert to file URL: {}", quoted_path(&locked_path)))?
+        .to_string();
+    Ok(locked_url)
+}
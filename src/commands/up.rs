@@ -1,3 +1,4 @@
+mod app_reload;
 mod app_source;
 mod parsing;
 
@@ -17,11 +18,15 @@ use spin_common::ui::quoted_path;
 use spin_factor_outbound_networking::validate_service_chaining_for_components;
 use spin_loader::FilesMountStrategy;
 use spin_oci::{ExecutableArtifact, OciLoader};
-use spin_trigger::cli::{LaunchMetadata, SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR};
+use spin_trigger::cli::{
+    LaunchMetadata, SPIN_APP_RELOAD_ADDRESS, SPIN_APP_RELOAD_TOKEN, SPIN_LOCAL_APP_DIR,
+    SPIN_LOCKED_URL, SPIN_WORKING_DIR,
+};
 use tempfile::TempDir;
 
 use crate::{directory_rels::notify_if_nondefault_rel, opts::*};
 
+use self::app_reload::AppReloadChannel;
 use self::app_source::{AppSource, ResolvedAppSource};
 
 const APPLICATION_OPT: &str = "APPLICATION";
@@ -83,7 +88,11 @@ impl UpCommand {
 
 /// Argument parser for UpCommand.
 #[derive(Parser, Debug, Default)]
-#[clap(about = "Start the Spin application", disable_help_flag = true)]
+#[clap(
+    about = "Start the Spin application",
+    long_about = "Start the Spin application.\n\nThe built-in HTTP and Redis triggers run in one process, sharing the application's compiled components. Triggers provided by plugins are separate programs, so each of them runs in a process of its own, and doesn't share compiled components, key-value stores or database connections with the others.",
+    disable_help_flag = true
+)]
 pub(crate) struct UpCommandInner {
     #[clap(short = 'h', long = "help")]
     pub help: bool,
@@ -165,6 +174,16 @@ pub(crate) struct UpCommandInner {
     #[arg(add = clap_complete::ArgValueCandidates::new(crate::completions::components))]
     pub components: Vec<String>,
 
+    /// Reload the application when `spin up` receives SIGHUP, without restarting it.
+    ///
+    /// The manifest and runtime config are loaded again, and requests which are in
+    /// flight finish on the previous version. If the reloaded application is invalid,
+    /// the previous version keeps running. Only HTTP triggers are reloaded: Redis triggers
+    /// and triggers provided by plugins keep running the version they started with.
+    /// Unix only.
+    #[clap(long)]
+    pub hot_reload: bool,
+
     /// All other args, to be passed through to the trigger
     #[clap(skip)]
     pub trigger_args: Vec<OsString>,
@@ -174,6 +193,10 @@ impl UpCommandInner {
     async fn run(self) -> Result<()> {
         let app_source = self.app_source();
 
+        if self.hot_reload && cfg!(windows) {
+            bail!("--hot-reload is only supported on Unix");
+        }
+
         if app_source == AppSource::None {
             if self.help {
                 let mut child = self
@@ -226,49 +249,55 @@ impl UpCommandInner {
             app_source.warn_if_not_latest_build(self.profile());
         }
 
-        let mut locked_app = self
-            .load_resolved_app_source(resolved_app_source, &working_dir)
+        let locked_app = self
+            .load_resolved_app_source(resolved_app_source, &working_dir.join("assets"))
             .await
             .context("Failed to load application")?;
+        let locked_app = self.prepare_locked_app(locked_app)?;
 
-        if !self.components.is_empty() {
-            locked_app = spin_app::retain_components(
-                locked_app,
-                &self
-                    .components
-                    .iter()
-                    .map(|s| s.as_str())
-                    .collect::<Vec<&str>>(),
-                &[&validate_service_chaining_for_components],
-            )
-            .context(
-                "failed to resolve application with only components selected with --component",
-            )?;
-        }
-
-        let trigger_types: HashSet<&str> = locked_app
-            .triggers
-            .iter()
-            .map(|t| t.trigger_type.as_ref())
-            .collect();
-
+        let trigger_types = trigger_types(&locked_app);
         ensure!(!trigger_types.is_empty(), "No triggers in app");
 
-        let trigger_cmds = trigger_commands_for_trigger_types(trigger_types.into_iter().collect())
-            .with_context(|| format!("Couldn't find trigger executor for {app_source}"))?;
+        let trigger_cmds =
+            trigger_commands_for_trigger_types(trigger_types.iter().map(String::as_str).collect())
+                .with_context(|| format!("Couldn't find trigger executor for {app_source}"))?;
         let is_multi = trigger_cmds.len() > 1;
+        let app_reload_channel = if self.hot_reload {
+            let not_reloadable = trigger_cmds
+                .iter()
+                .filter(|cmd| !supports_hot_reload(cmd))
+                .filter_map(|cmd| cmd.last().map(String::as_str))
+                .collect::<Vec<_>>();
+            if !not_reloadable.is_empty() {
+                terminal::warn!(
+                    "These triggers don't support --hot-reload, and will keep running the application as it was when it started: {}",
+                    not_reloadable.join(", ")
+                );
+            }
+            if trigger_cmds.iter().any(|cmd| supports_hot_reload(cmd)) {
+                Some(AppReloadChannel::bind().await?)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
 
-        self.update_locked_app(&mut locked_app);
         let locked_url = self.write_locked_app(&locked_app, &working_dir).await?;
 
         let local_app_dir = app_source.local_app_dir().map(Into::into);
 
         let run_opts = RunTriggerOpts {
             locked_url,
-            working_dir,
+            working_dir: working_dir.clone(),
             local_app_dir,
+            app_reload: app_reload_channel
+                .as_ref()
+                .map(|channel| (channel.address(), channel.token().to_owned())),
         };
 
+        self.reset_allowed_hosts_audit_file()?;
+
         let trigger_processes = self.start_trigger_processes(trigger_cmds, run_opts).await?;
         let pids = get_pids(&trigger_processes);
 
@@ -283,7 +312,21 @@ impl UpCommandInner {
             tokio::time::sleep(MULTI_TRIGGER_LET_ALL_START).await;
         }
 
-        let (first_to_finish, _index, _rest) = futures::future::select_all(trigger_tasks).await;
+        let mut trigger_exit = futures::future::select_all(trigger_tasks);
+        let (first_to_finish, _index, _rest) = if let Some(channel) = &app_reload_channel {
+            let reloads = self.reload_on_sighup(&app_source, &working_dir, &trigger_types, channel);
+            tokio::select! {
+                finished = &mut trigger_exit => finished,
+                result = reloads => {
+                    if let Err(err) = result {
+                        terminal::warn!("Hot reload is unavailable: {err:#}");
+                    }
+                    trigger_exit.await
+                }
+            }
+        } else {
+            trigger_exit.await
+        };
 
         if let Ok(process_result) = first_to_finish {
             let status = process_result?;
@@ -335,7 +378,7 @@ impl UpCommandInner {
     }
 
     async fn start_trigger_processes(
-        self,
+        &self,
         trigger_cmds: Vec<Vec<String>>,
         run_opts: RunTriggerOpts,
     ) -> anyhow::Result<Vec<tokio::process::Child>> {
@@ -403,6 +446,7 @@ impl UpCommandInner {
             locked_url,
             working_dir,
             local_app_dir,
+            app_reload,
         }) = opts
         {
             cmd.env(SPIN_LOCKED_URL, locked_url)
@@ -413,6 +457,12 @@ impl UpCommandInner {
                 cmd.env(SPIN_LOCAL_APP_DIR, local_app_dir);
             }
 
+            if let Some((address, token)) = app_reload.filter(|_| supports_hot_reload(&trigger_cmd))
+            {
+                cmd.env(SPIN_APP_RELOAD_ADDRESS, address.to_string())
+                    .env(SPIN_APP_RELOAD_TOKEN, token);
+            }
+
             cmd.kill_on_drop(true);
         } else {
             cmd.env("SPIN_PLUGINS_SUPPRESS_COMPATIBILITY_WARNINGS", "1");
@@ -456,6 +506,32 @@ impl UpCommandInner {
         }
     }
 
+    /// Returns the file the triggers are to write their allowed hosts audit
+    /// to, if any.
+    fn allowed_hosts_audit_file(&self) -> Option<PathBuf> {
+        self.trigger_args.iter().find_map(|arg| {
+            arg.to_str()?
+                .strip_prefix("--audit-outbound-hosts=")
+                .map(PathBuf::from)
+        })
+    }
+
+    /// Empties the allowed hosts audit file, if any.
+    ///
+    /// Each trigger merges its audit into the file when it shuts down, so this
+    /// keeps the destinations recorded by previous runs out of this one's.
+    fn reset_allowed_hosts_audit_file(&self) -> Result<()> {
+        if let Some(path) = self.allowed_hosts_audit_file() {
+            std::fs::write(&path, "").with_context(|| {
+                format!(
+                    "failed to reset allowed hosts audit file {}",
+                    quoted_path(&path)
+                )
+            })?;
+        }
+        Ok(())
+    }
+
     fn trigger_args_look_file_like(&self) -> bool {
         // Heuristic for the user typing `spin up foo` instead of `spin up -f foo` - in the
         // first case `foo` gets interpreted as a trigger arg which is probably not what the
@@ -471,7 +547,13 @@ impl UpCommandInner {
         let locked_path = working_dir.join("spin.lock");
         let locked_app_contents =
             serde_json::to_vec_pretty(&locked_app).context("failed to serialize locked app")?;
-        tokio::fs::write(&locked_path, locked_app_contents)
+        // Write to a temporary file and rename it into place, so that a trigger
+        // which is reloading the app never reads a partly written lock file.
+        let temp_path = working_dir.join("spin.lock.tmp");
+        tokio::fs::write(&temp_path, locked_app_contents)
+            .await
+            .with_context(|| format!("failed to write {}", quoted_path(&temp_path)))?;
+        tokio::fs::rename(&temp_path, &locked_path)
             .await
             .with_context(|| format!("failed to write {}", quoted_path(&locked_path)))?;
         let locked_url = Url::from_file_path(&locked_path)
@@ -521,18 +603,19 @@ impl UpCommandInner {
         })
     }
 
-    // Finish preparing a ResolvedAppSource for execution.
+    // Finish preparing a ResolvedAppSource for execution. Unless the files
+    // are mounted directly, they are copied into `assets_dir`.
     async fn load_resolved_app_source(
         &self,
         resolved: ResolvedAppSource,
-        working_dir: &Path,
+        assets_dir: &Path,
     ) -> anyhow::Result<LockedApp> {
         match resolved {
             ResolvedAppSource::File { manifest_path, .. } => {
                 let files_mount_strategy = if self.direct_mounts {
                     FilesMountStrategy::Direct
                 } else {
-                    FilesMountStrategy::Copy(working_dir.join("assets"))
+                    FilesMountStrategy::Copy(assets_dir.to_owned())
                 };
                 spin_loader::from_file(
                     &manifest_path,
@@ -557,6 +640,104 @@ impl UpCommandInner {
         }
     }
 
+    // Apply the options which select and modify components.
+    fn prepare_locked_app(&self, mut locked_app: LockedApp) -> anyhow::Result<LockedApp> {
+        if !self.components.is_empty() {
+            locked_app = spin_app::retain_components(
+                locked_app,
+                &self
+                    .components
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<&str>>(),
+                &[&validate_service_chaining_for_components],
+            )
+            .context(
+                "failed to resolve application with only components selected with --component",
+            )?;
+        }
+        self.update_locked_app(&mut locked_app);
+        Ok(locked_app)
+    }
+
+    // Reload the application each time `spin up` receives SIGHUP.
+    #[cfg(not(windows))]
+    async fn reload_on_sighup(
+        &self,
+        app_source: &AppSource,
+        working_dir: &Path,
+        trigger_types: &HashSet<String>,
+        channel: &AppReloadChannel,
+    ) -> anyhow::Result<()> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangups = signal(SignalKind::hangup()).context("failed to listen for SIGHUP")?;
+        let mut reloads = HotReloads::new(working_dir);
+        while hangups.recv().await.is_some() {
+            terminal::step!("Reloading", "application");
+            if let Err(err) = self
+                .reload_app(app_source, trigger_types, channel, &mut reloads)
+                .await
+            {
+                terminal::error!(
+                    "Failed to reload application, keeping the running version: {err:#}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    async fn reload_on_sighup(
+        &self,
+        _app_source: &AppSource,
+        _working_dir: &Path,
+        _trigger_types: &HashSet<String>,
+        _channel: &AppReloadChannel,
+    ) -> anyhow::Result<()> {
+        bail!("--hot-reload is only supported on Unix")
+    }
+
+    // Load the application again, replace the lock file with it, and ask the
+    // trigger to run it. The files are copied to a new directory, so that the
+    // running version's files are left as they are until it's replaced.
+    #[cfg_attr(windows, allow(dead_code))]
+    async fn reload_app(
+        &self,
+        app_source: &AppSource,
+        running_trigger_types: &HashSet<String>,
+        channel: &AppReloadChannel,
+        reloads: &mut HotReloads,
+    ) -> anyhow::Result<()> {
+        let assets_dir = reloads.next_assets_dir();
+        let result = async {
+            let working_dir = &reloads.working_dir;
+            let resolved_app_source = self.resolve_app_source(app_source, working_dir).await?;
+            resolved_app_source.ensure_profile(self.profile())?;
+            let locked_app = self
+                .load_resolved_app_source(resolved_app_source, &assets_dir)
+                .await
+                .context("Failed to load application")?;
+            let locked_app = self.prepare_locked_app(locked_app)?;
+            ensure!(
+                &trigger_types(&locked_app) == running_trigger_types,
+                "the application's trigger types have changed. Restart `spin up` to apply this change."
+            );
+            self.write_locked_app(&locked_app, working_dir).await?;
+            channel.reload().await
+        }
+        .await;
+        // Whichever version isn't running any more, its files aren't needed.
+        match result {
+            Ok(()) => {
+                let previous_assets_dir = std::mem::replace(&mut reloads.assets_dir, assets_dir);
+                remove_assets_dir(&previous_assets_dir).await;
+            }
+            Err(_) => remove_assets_dir(&assets_dir).await,
+        }
+        result
+    }
+
     fn update_locked_app(&self, locked_app: &mut LockedApp) {
         // Apply --env to component environments
         if !self.env.is_empty() {
@@ -608,6 +789,62 @@ fn is_flag_arg(arg: &OsString) -> bool {
     }
 }
 
+pub(crate) fn trigger_types(locked_app: &LockedApp) -> HashSet<String> {
+    locked_app
+        .triggers
+        .iter()
+        .map(|t| t.trigger_type.clone())
+        .collect()
+}
+
+pub(crate) fn is_builtin_trigger_command(trigger_cmd: &[String]) -> bool {
+    trigger_cmd.first().is_some_and(|cmd| cmd == "trigger")
+}
+
+/// Whether the trigger command can reload the application while it's running.
+/// Of the built-in triggers, only HTTP can; when it runs alongside Redis, the
+/// Redis trigger keeps running the application it was started with.
+fn supports_hot_reload(trigger_cmd: &[String]) -> bool {
+    is_builtin_trigger_command(trigger_cmd)
+        && trigger_cmd
+            .get(1)
+            .is_some_and(|t| t == "http" || t == HTTP_REDIS_TRIGGER_TYPE)
+}
+
+/// The state of `spin up --hot-reload` between reloads.
+#[cfg_attr(windows, allow(dead_code))]
+struct HotReloads {
+    working_dir: PathBuf,
+    // The files of the running version of the application
+    assets_dir: PathBuf,
+    generation: usize,
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+impl HotReloads {
+    fn new(working_dir: &Path) -> Self {
+        Self {
+            working_dir: working_dir.to_owned(),
+            assets_dir: working_dir.join("assets"),
+            generation: 0,
+        }
+    }
+
+    fn next_assets_dir(&mut self) -> PathBuf {
+        self.generation += 1;
+        self.working_dir.join(format!("assets-{}", self.generation))
+    }
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+async fn remove_assets_dir(assets_dir: &Path) {
+    match tokio::fs::remove_dir_all(assets_dir).await {
+        Ok(()) => (),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+        Err(err) => tracing::warn!("Failed to remove {}: {err:#}", quoted_path(assets_dir)),
+    }
+}
+
 #[cfg(windows)]
 fn set_kill_on_ctrl_c(_pids: &[usize]) -> Result<(), anyhow::Error> {
     Ok(())
@@ -655,6 +892,8 @@ struct RunTriggerOpts {
     locked_url: String,
     working_dir: PathBuf,
     local_app_dir: Option<PathBuf>,
+    /// The address and token of the app reload channel, under `--hot-reload`.
+    app_reload: Option<(std::net::SocketAddr, String)>,
 }
 
 enum WorkingDirectory {
@@ -717,16 +956,31 @@ fn trigger_command(trigger_type: &str) -> Vec<String> {
     vec!["trigger".to_owned(), trigger_type.to_owned()]
 }
 
-fn trigger_commands_for_trigger_types(trigger_types: Vec<&str>) -> Result<Vec<Vec<String>>> {
-    trigger_types
-        .iter()
-        .map(|&t| match t {
-            "http" | "redis" => Ok(trigger_command(t)),
-            _ => {
-                let cmd = resolve_trigger_plugin(t)?;
-                Ok(vec![cmd])
-            }
-        })
+/// Returns the command for each trigger process needed to run the given
+/// trigger types.
+///
+/// The built-in triggers run in one process, so that they share the app's
+/// compiled components and connections. Plugin triggers are out of scope for
+/// that: they are separate executables with their own runtime, which can't be
+/// loaded into the `spin` process, so each runs in a process of its own.
+pub(crate) fn trigger_commands_for_trigger_types(
+    trigger_types: Vec<&str>,
+) -> Result<Vec<Vec<String>>> {
+    let (builtin, plugins): (Vec<&str>, Vec<&str>) = trigger_types
+        .into_iter()
+        .partition(|t| matches!(*t, "http" | "redis"));
+    let builtin_cmd = match builtin[..] {
+        [] => None,
+        [t] => Some(trigger_command(t)),
+        _ => Some(trigger_command(HTTP_REDIS_TRIGGER_TYPE)),
+    };
+    builtin_cmd
+        .map(Ok)
+        .into_iter()
+        .chain(plugins.into_iter().map(|t| {
+            let cmd = resolve_trigger_plugin(t)?;
+            Ok(vec![cmd])
+        }))
         .collect()
 }
 
@@ -1006,4 +1260,138 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn finds_allowed_hosts_audit_file_in_trigger_args() {
+        let cmd = UpCommand::try_parse_from([
+            "up",
+            "--listen",
+            "127.0.0.1:3000",
+            "--audit-outbound-hosts=audit.toml",
+        ])
+        .unwrap();
+        assert_eq!(
+            cmd.0.allowed_hosts_audit_file(),
+            Some(PathBuf::from("audit.toml"))
+        );
+
+        // Without a file, the audit is printed rather than written.
+        let cmd = UpCommand::try_parse_from(["up", "--audit-outbound-hosts"]).unwrap();
+        assert_eq!(cmd.0.allowed_hosts_audit_file(), None);
+    }
+
+    #[test]
+    fn builtin_triggers_share_a_process() {
+        let cmds = trigger_commands_for_trigger_types(vec!["redis", "http"]).unwrap();
+        assert_eq!(cmds, vec![trigger_command(HTTP_REDIS_TRIGGER_TYPE)]);
+
+        let cmds = trigger_commands_for_trigger_types(vec!["http"]).unwrap();
+        assert_eq!(cmds, vec![trigger_command("http")]);
+    }
+
+    #[test]
+    fn http_triggers_support_hot_reload() {
+        assert!(supports_hot_reload(&trigger_command("http")));
+        assert!(supports_hot_reload(&trigger_command(
+            HTTP_REDIS_TRIGGER_TYPE
+        )));
+        assert!(!supports_hot_reload(&trigger_command("redis")));
+        assert!(!supports_hot_reload(&["spin-trigger-cron".to_owned()]));
+    }
+
+    fn write_static_manifest(dir: &Path, triggers: &str) -> PathBuf {
+        let manifest = format!(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "hot-reload"
+            {triggers}
+            "#
+        );
+        let path = dir.join("spin.toml");
+        std::fs::write(&path, manifest).unwrap();
+        path
+    }
+
+    const OLD_ROUTE: &str = r#"
+        [[trigger.http]]
+        route = "/old"
+        static_response = { body = "old" }
+    "#;
+
+    const NEW_ROUTE: &str = r#"
+        [[trigger.http]]
+        route = "/new"
+        static_response = { body = "new" }
+    "#;
+
+    async fn reload_static_app(
+        triggers: &str,
+        trigger_response: &'static str,
+    ) -> (anyhow::Result<()>, tempfile::TempDir, HotReloads) {
+        let app_dir = tempfile::tempdir().unwrap();
+        let working_dir = tempfile::tempdir().unwrap();
+        let up = UpCommandInner {
+            app_source: Some(
+                write_static_manifest(app_dir.path(), OLD_ROUTE)
+                    .display()
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let app_source = up.app_source();
+
+        let mut reloads = HotReloads::new(working_dir.path());
+        std::fs::create_dir(&reloads.assets_dir).unwrap();
+        std::fs::write(working_dir.path().join("spin.lock"), "running").unwrap();
+
+        let channel = AppReloadChannel::bind().await.unwrap();
+        channel.connect_test_trigger(trigger_response).await;
+
+        write_static_manifest(app_dir.path(), triggers);
+        let running_trigger_types = HashSet::from(["http".to_owned()]);
+        let result = up
+            .reload_app(&app_source, &running_trigger_types, &channel, &mut reloads)
+            .await;
+        (result, working_dir, reloads)
+    }
+
+    #[tokio::test]
+    async fn reloaded_app_replaces_the_running_one() {
+        let (result, working_dir, reloads) = reload_static_app(NEW_ROUTE, "ok").await;
+        result.unwrap();
+
+        let lock = std::fs::read_to_string(working_dir.path().join("spin.lock")).unwrap();
+        assert!(lock.contains("/new"), "lock file wasn't rewritten: {lock}");
+        assert_eq!(reloads.assets_dir, working_dir.path().join("assets-1"));
+        assert!(!working_dir.path().join("assets").exists());
+    }
+
+    #[tokio::test]
+    async fn failed_reload_keeps_the_running_files() {
+        let (result, working_dir, reloads) =
+            reload_static_app(NEW_ROUTE, "error invalid route").await;
+        assert_eq!(result.unwrap_err().to_string(), "invalid route");
+
+        assert_eq!(reloads.assets_dir, working_dir.path().join("assets"));
+        assert!(working_dir.path().join("assets").exists());
+        assert!(!working_dir.path().join("assets-1").exists());
+    }
+
+    #[tokio::test]
+    async fn changed_trigger_types_are_not_reloaded() {
+        let triggers = format!(
+            r#"
+            {NEW_ROUTE}
+            [[trigger.redis]]
+            channel = "messages"
+            "#
+        );
+        let (result, working_dir, _) = reload_static_app(&triggers, "ok").await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("trigger types have changed"), "{err}");
+
+        let lock = std::fs::read_to_string(working_dir.path().join("spin.lock")).unwrap();
+        assert_eq!(lock, "running");
+    }
 }
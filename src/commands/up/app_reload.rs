@@ -0,0 +1,186 @@
+use std::{net::Ipv4Addr, net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        TcpListener, TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::Mutex,
+};
+
+// `spin up --hot-reload` uses the AppReloadChannel to ask the trigger process
+// to load the application again, once the lock file has been rewritten. The
+// trigger process connects to the channel's address, which is passed down to
+// it in the SPIN_APP_RELOAD_ADDRESS environment variable. Any local process
+// can connect to that address, so the trigger first sends the token passed in
+// SPIN_APP_RELOAD_TOKEN, and connections which don't are dropped. After that,
+// each request is a `reload` line, and the trigger replies with a line which is
+// either `ok` or `error <message>`. Only the built-in triggers support
+// reloading, and they share one process, so there is at most one connection.
+pub(crate) struct AppReloadChannel {
+    address: SocketAddr,
+    token: String,
+    trigger: Arc<Mutex<Option<TriggerConnection>>>,
+}
+
+/// How long a connection has to send the token before it is dropped.
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct TriggerConnection {
+    requests: OwnedWriteHalf,
+    responses: Lines<BufReader<OwnedReadHalf>>,
+}
+
+impl AppReloadChannel {
+    pub async fn bind() -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .context("failed to bind app reload channel")?;
+        let address = listener.local_addr()?;
+        let token = uuid::Uuid::new_v4().simple().to_string();
+
+        let trigger = Arc::new(Mutex::new(None));
+        let connected_trigger = trigger.clone();
+        let expected_token = token.clone();
+        tokio::task::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        // Each connection is checked on its own, so that one
+                        // which never sends anything can't hold up the others.
+                        tokio::task::spawn(authenticate(
+                            stream,
+                            expected_token.clone(),
+                            connected_trigger.clone(),
+                        ));
+                    }
+                    Err(e) => tracing::debug!("Error accepting app reload connection: {e:#}"),
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            token,
+            trigger,
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The token the trigger must send to be accepted.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Asks the trigger process to reload the application from the lock
+    /// file. Once this returns, the trigger is either running the reloaded
+    /// application or, if it returns an error, the previous one.
+    pub async fn reload(&self) -> Result<()> {
+        let mut trigger = self.trigger.lock().await;
+        let Some(connection) = trigger.as_mut() else {
+            bail!("the trigger has not connected yet");
+        };
+        connection.requests.write_all(b"reload\n").await?;
+        // Loading the application can take a while, but the trigger always
+        // responds, so there's no timeout: if it were to give up waiting, `spin
+        // up` couldn't tell which version of the application is running.
+        match connection.responses.next_line().await?.as_deref() {
+            Some("ok") => Ok(()),
+            Some(response) => bail!("{}", response.strip_prefix("error ").unwrap_or(response)),
+            None => bail!("trigger disconnected"),
+        }
+    }
+
+    /// Connects a fake trigger to the channel, which responds to each request
+    /// with `response`.
+    #[cfg(test)]
+    pub async fn connect_test_trigger(&self, response: &'static str) {
+        let mut stream = tokio::net::TcpStream::connect(self.address).await.unwrap();
+        let token = format!("{}\n", self.token);
+        stream.write_all(token.as_bytes()).await.unwrap();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(request)) = lines.next_line().await {
+                assert_eq!(request, "reload");
+                let response = format!("{response}\n");
+                writer.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        while self.trigger.lock().await.is_none() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+}
+
+/// Makes `stream` the trigger connection if its first line is `token`.
+async fn authenticate(
+    stream: TcpStream,
+    token: String,
+    trigger: Arc<Mutex<Option<TriggerConnection>>>,
+) {
+    let (reader, writer) = stream.into_split();
+    let mut responses = BufReader::new(reader).lines();
+    match tokio::time::timeout(TOKEN_TIMEOUT, responses.next_line()).await {
+        Ok(Ok(Some(line))) if line == token => {
+            *trigger.lock().await = Some(TriggerConnection {
+                requests: writer,
+                responses,
+            });
+        }
+        _ => tracing::warn!("Ignoring app reload connection which didn't send the expected token"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reload_succeeds_when_the_trigger_reloads() -> Result<()> {
+        let channel = AppReloadChannel::bind().await?;
+        channel.connect_test_trigger("ok").await;
+        channel.reload().await?;
+        channel.reload().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reload_fails_when_the_trigger_fails() -> Result<()> {
+        let channel = AppReloadChannel::bind().await?;
+        channel
+            .connect_test_trigger("error failed to load app: invalid route")
+            .await;
+        let err = channel.reload().await.unwrap_err();
+        assert_eq!(err.to_string(), "failed to load app: invalid route");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reload_fails_without_a_trigger() -> Result<()> {
+        let channel = AppReloadChannel::bind().await?;
+        let err = channel.reload().await.unwrap_err();
+        assert_eq!(err.to_string(), "the trigger has not connected yet");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn connections_without_the_token_are_ignored() -> Result<()> {
+        let channel = AppReloadChannel::bind().await?;
+        channel.connect_test_trigger("ok").await;
+
+        let mut intruder = tokio::net::TcpStream::connect(channel.address()).await?;
+        intruder.write_all(b"not-the-token\n").await?;
+        let mut rest = vec![];
+        // The channel drops the connection, rather than replacing the trigger.
+        tokio::io::AsyncReadExt::read_to_end(&mut intruder, &mut rest).await?;
+
+        channel.reload().await?;
+        Ok(())
+    }
+}
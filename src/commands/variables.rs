@@ -0,0 +1,585 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand};
+use comfy_table::Table;
+use spin_common::ui::quoted_path;
+use spin_expressions::{Key, Provider, ProviderResolver, Template};
+use spin_locked_app::Variable;
+use spin_runtime_config::variables::described_providers_from_toml;
+use spin_variables_static::{StaticVariablesProvider, VariableSource};
+
+use crate::opts::APP_MANIFEST_FILE_OPT;
+
+const RUNTIME_CONFIG_FILE_OPT: &str = "RUNTIME_CONFIG_FILE";
+
+/// Commands for inspecting how application variables are resolved.
+#[derive(Subcommand, Debug)]
+pub enum VariablesCommands {
+    /// Check that every required variable has a value.
+    Check(CheckCommand),
+    /// List the application's variables and where their values come from.
+    List(ListCommand),
+    /// Explain how a variable is resolved.
+    Explain(ExplainCommand),
+}
+
+impl VariablesCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            VariablesCommands::Check(cmd) => cmd.run().await,
+            VariablesCommands::List(cmd) => cmd.run().await,
+            VariablesCommands::Explain(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Options for locating an application and its variable providers.
+#[derive(Args, Debug)]
+pub struct VariablesOptions {
+    /// The application to inspect. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file"
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Runtime configuration file defining the variable providers.
+    #[clap(
+        name = RUNTIME_CONFIG_FILE_OPT,
+        long = "runtime-config-file",
+        env = RUNTIME_CONFIG_FILE_OPT,
+    )]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Variable(s) as they would be passed to `spin up`.
+    ///
+    /// A single key-value pair can be passed as `key=value`, or `key=@file` to
+    /// read the value from a text file. Alternatively, any number of key-value
+    /// pairs may be passed via a JSON or TOML file using the syntax `@file.json` or
+    /// `@file.toml`.
+    #[clap(long, value_parser = clap::value_parser!(VariableSource),
+        value_name = "KEY=VALUE | KEY=@FILE | @FILE.json | @FILE.toml")]
+    pub variable: Vec<VariableSource>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CheckCommand {
+    #[clap(flatten)]
+    options: VariablesOptions,
+}
+
+impl CheckCommand {
+    pub async fn run(self) -> Result<()> {
+        let app = AppVariables::load(&self.options)?;
+
+        // Catches required variables that no provider claims to resolve
+        // without contacting remote providers.
+        if let Err(err) = app.resolver.ensure_required_variables_resolvable() {
+            terminal::warn!("{err}");
+        }
+
+        let mut missing = vec![];
+        for (name, variable) in &app.variables {
+            match app.source(name, variable).await {
+                Source::Missing if !app.resolver.always_defaulted(name) => {
+                    terminal::error!("Required variable {name:?} has no value");
+                    missing.push(name.as_str());
+                }
+                Source::Error(index, err) => {
+                    terminal::error!(
+                        "Variable {name:?} could not be resolved: provider {} failed: {err:#}",
+                        app.providers[index].description
+                    );
+                    missing.push(name.as_str());
+                }
+                _ => {}
+            }
+        }
+
+        if !missing.is_empty() {
+            bail!(
+                "{} variable(s) could not be resolved: {}",
+                missing.len(),
+                missing.join(", ")
+            );
+        }
+        println!("All {} variable(s) resolved", app.variables.len());
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    #[clap(flatten)]
+    options: VariablesOptions,
+}
+
+impl ListCommand {
+    pub async fn run(self) -> Result<()> {
+        let app = AppVariables::load(&self.options)?;
+
+        println!("Variable providers, in order of precedence:");
+        app.print_providers();
+
+        if app.variables.is_empty() {
+            println!();
+            println!("The application has no variables");
+            return Ok(());
+        }
+
+        let mut table = Table::new();
+        table.set_header(vec!["Variable", "Required", "Secret", "Source", "Value"]);
+        table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+        for (name, variable) in &app.variables {
+            let source = app.source(name, variable).await;
+            table.add_row(vec![
+                name.clone(),
+                yes_no(variable.default.is_none()).into(),
+                yes_no(variable.secret).into(),
+                app.describe_source(&source),
+                display_value(variable, &source),
+            ]);
+        }
+        println!();
+        println!("{table}");
+
+        if !app.component_variables.is_empty() {
+            let mut table = Table::new();
+            table.set_header(vec!["Component", "Key", "Template"]);
+            table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+            for (component_id, key, template) in &app.component_variables {
+                table.add_row(vec![
+                    component_id.clone(),
+                    key.clone(),
+                    template.to_string(),
+                ]);
+            }
+            println!();
+            println!("{table}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct ExplainCommand {
+    /// The variable to explain.
+    key: String,
+
+    #[clap(flatten)]
+    options: VariablesOptions,
+}
+
+impl ExplainCommand {
+    pub async fn run(self) -> Result<()> {
+        let app = AppVariables::load(&self.options)?;
+        let name = &self.key;
+        let Some(variable) = app.variables.get(name) else {
+            bail!(
+                "The application has no variable {name:?}. Variables are: {}",
+                app.variable_names().join(", ")
+            );
+        };
+
+        println!("Variable: {name}");
+        if let Some(description) = &variable.description {
+            println!("Description: {description}");
+        }
+        println!("Required: {}", yes_no(variable.default.is_none()));
+        println!("Secret: {}", yes_no(variable.secret));
+        if let Some(default) = &variable.default {
+            println!("Default: {}", redact(variable, default));
+        }
+
+        println!();
+        println!("Providers, in order of precedence:");
+        let key = Key::new(name)?;
+        let mut source = None;
+        for (index, provider) in app.providers.iter().enumerate() {
+            let result = match provider.provider.get(&key).await {
+                Ok(Some(value)) => {
+                    let result = format!("has value {}", redact(variable, &value));
+                    source.get_or_insert(Source::Provider(index, value));
+                    result
+                }
+                Ok(None) => "no value".to_owned(),
+                Err(err) => {
+                    source.get_or_insert(Source::Error(index, err.to_string()));
+                    format!("error: {err:#}")
+                }
+            };
+            println!("  {}. {}: {result}", index + 1, provider.description);
+        }
+        let source = source.unwrap_or_else(|| match &variable.default {
+            Some(default) => Source::Default(default.clone()),
+            None => Source::Missing,
+        });
+
+        println!();
+        match &source {
+            Source::Missing if app.resolver.always_defaulted(name) => {
+                println!("Result: no value; every template referencing {name:?} supplies a default")
+            }
+            Source::Missing => println!("Result: required variable has no value"),
+            _ => println!(
+                "Result: {} from {}",
+                display_value(variable, &source),
+                app.describe_source(&source)
+            ),
+        }
+
+        let usages = app
+            .component_variables
+            .iter()
+            .filter(|(_, _, template)| template.variables().any(|var| var == name))
+            .collect::<Vec<_>>();
+        if !usages.is_empty() {
+            println!();
+            println!("Used by:");
+            for (component_id, key, template) in usages {
+                println!("  {component_id}.{key} = {:?}", template.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a variable's value comes from.
+enum Source {
+    /// The provider at the index supplies the value.
+    Provider(usize, String),
+    /// The provider at the index failed.
+    Error(usize, String),
+    /// The manifest default is used.
+    Default(String),
+    /// Nothing supplies a value.
+    Missing,
+}
+
+/// A provider shared between a [`ProviderResolver`] and the command.
+#[derive(Debug)]
+struct SharedProvider(Arc<dyn Provider>);
+
+#[spin_expressions::async_trait::async_trait]
+impl Provider for SharedProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        self.0.get(key).await
+    }
+
+    fn may_resolve(&self, key: &Key) -> bool {
+        self.0.may_resolve(key)
+    }
+}
+
+struct NamedProvider {
+    description: String,
+    provider: Arc<dyn Provider>,
+}
+
+/// An application's variables and variable providers.
+struct AppVariables {
+    variables: BTreeMap<String, Variable>,
+    /// (component ID, key, template), sorted.
+    component_variables: Vec<(String, String, Template)>,
+    providers: Vec<NamedProvider>,
+    /// A resolver equivalent to the one `spin up` would use.
+    resolver: ProviderResolver,
+}
+
+impl AppVariables {
+    fn load(options: &VariablesOptions) -> Result<Self> {
+        let (manifest_file, _) =
+            spin_common::paths::find_manifest_file_path(options.app_source.as_ref())?;
+        let mut manifest =
+            spin_manifest::manifest_from_file(&manifest_file).with_context(|| {
+                format!(
+                    "Failed to load manifest from {}",
+                    quoted_path(&manifest_file)
+                )
+            })?;
+        spin_manifest::normalize::normalize_manifest(&mut manifest, None)?;
+
+        let variables = manifest
+            .variables
+            .iter()
+            .map(|(name, variable)| {
+                (
+                    name.to_string(),
+                    Variable {
+                        description: variable.description.clone(),
+                        default: variable.default.clone(),
+                        secret: variable.secret,
+                    },
+                )
+            })
+            .collect();
+
+        let mut component_variables = manifest
+            .components
+            .iter()
+            .flat_map(|(id, component)| {
+                component
+                    .variables
+                    .iter()
+                    .map(move |(key, template)| (id.to_string(), key.to_string(), template))
+            })
+            .map(|(id, key, template)| {
+                let template = Template::new(template.as_str())
+                    .with_context(|| format!("Invalid variable template for {id}.{key}"))?;
+                Ok((id, key, template))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        component_variables.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        let allowed_outbound_hosts = manifest
+            .components
+            .values()
+            .flat_map(|component| component.allowed_outbound_hosts.iter().cloned())
+            .collect::<Vec<_>>();
+
+        let mut cli_variables = HashMap::new();
+        for source in &options.variable {
+            cli_variables.extend(source.get_variables()?);
+        }
+        let mut providers = vec![];
+        if !cli_variables.is_empty() {
+            providers.push(NamedProvider {
+                description: "--variable".into(),
+                provider: Arc::new(StaticVariablesProvider::new(cli_variables)),
+            });
+        }
+        let runtime_config = match &options.runtime_config_file {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", quoted_path(path)))?;
+                toml::from_str(&contents)
+                    .with_context(|| format!("Failed to parse {}", quoted_path(path)))?
+            }
+            None => toml::Table::new(),
+        };
+        providers.extend(
+            described_providers_from_toml(&runtime_config)?
+                .into_iter()
+                .map(|described| NamedProvider {
+                    description: described.description,
+                    provider: described.provider.into(),
+                }),
+        );
+
+        let resolver = resolver(
+            &variables,
+            &component_variables,
+            allowed_outbound_hosts,
+            &providers,
+        )?;
+        Ok(Self {
+            variables,
+            component_variables,
+            providers,
+            resolver,
+        })
+    }
+
+    /// Finds the source of a variable's value, in the same order as `spin up`.
+    async fn source(&self, name: &str, variable: &Variable) -> Source {
+        // Manifest variable names are always valid keys.
+        let Ok(key) = Key::new(name) else {
+            return Source::Missing;
+        };
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider.provider.get(&key).await {
+                Ok(Some(value)) => return Source::Provider(index, value),
+                Ok(None) => {}
+                Err(err) => return Source::Error(index, format!("{err:#}")),
+            }
+        }
+        match &variable.default {
+            Some(default) => Source::Default(default.clone()),
+            None => Source::Missing,
+        }
+    }
+
+    fn describe_source(&self, source: &Source) -> String {
+        match source {
+            Source::Provider(index, _) => self.providers[*index].description.clone(),
+            Source::Error(index, err) => {
+                format!("error in {}: {err}", self.providers[*index].description)
+            }
+            Source::Default(_) => "manifest default".into(),
+            Source::Missing => "(none)".into(),
+        }
+    }
+
+    fn print_providers(&self) {
+        for (index, provider) in self.providers.iter().enumerate() {
+            println!("  {}. {}", index + 1, provider.description);
+        }
+        println!("  {}. manifest default", self.providers.len() + 1);
+    }
+
+    fn variable_names(&self) -> Vec<&str> {
+        self.variables.keys().map(String::as_str).collect()
+    }
+}
+
+/// Builds a resolver equivalent to the one `spin up` would use.
+fn resolver(
+    variables: &BTreeMap<String, Variable>,
+    component_variables: &[(String, String, Template)],
+    allowed_outbound_hosts: Vec<String>,
+    providers: &[NamedProvider],
+) -> Result<ProviderResolver> {
+    let mut resolver = ProviderResolver::new(
+        variables
+            .iter()
+            .map(|(name, variable)| (name.clone(), variable.clone())),
+    )?;
+    let mut by_component: HashMap<&str, Vec<(String, String)>> = HashMap::new();
+    for (component_id, key, template) in component_variables {
+        by_component
+            .entry(component_id)
+            .or_default()
+            .push((key.clone(), template.to_string()));
+    }
+    for (component_id, variables) in by_component {
+        resolver.add_component_variables(component_id, variables)?;
+    }
+    resolver.add_templates(allowed_outbound_hosts)?;
+    for provider in providers {
+        resolver.add_provider(Box::new(SharedProvider(provider.provider.clone())));
+    }
+    Ok(resolver)
+}
+
+fn display_value(variable: &Variable, source: &Source) -> String {
+    match source {
+        Source::Provider(_, value) | Source::Default(value) => redact(variable, value),
+        Source::Error(..) | Source::Missing => "".into(),
+    }
+}
+
+fn redact(variable: &Variable, value: &str) -> String {
+    if variable.secret {
+        "<redacted>".into()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b { "yes" } else { "no" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+spin_manifest_version = 2
+
+[application]
+name = "variables-test"
+
+[variables]
+api_key = { required = true, secret = true }
+region = { default = "us-east" }
+log_level = { required = true }
+
+[[trigger.http]]
+route = "/..."
+component = "web"
+
+[component.web]
+source = "web.wasm"
+
+[component.web.variables]
+key = "{{ api_key }}"
+region = "{{ region | upper }}"
+log_level = "{{ log_level | default: \"info\" }}"
+"#;
+
+    fn options(dir: &tempfile::TempDir, variables: &[&str]) -> VariablesOptions {
+        let manifest = dir.path().join("spin.toml");
+        std::fs::write(&manifest, MANIFEST).unwrap();
+        VariablesOptions {
+            app_source: Some(manifest),
+            runtime_config_file: None,
+            variable: variables.iter().map(|v| v.parse().unwrap()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sources_follow_spin_up_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = AppVariables::load(&options(&dir, &["region=eu-west"])).unwrap();
+
+        assert!(matches!(
+            app.source("region", &app.variables["region"]).await,
+            Source::Provider(0, value) if value == "eu-west"
+        ));
+        for name in ["api_key", "log_level"] {
+            let source = app.source(name, &app.variables[name]).await;
+            assert!(matches!(source, Source::Missing), "{name}");
+        }
+
+        assert!(!app.resolver.always_defaulted("api_key"));
+        assert!(app.resolver.always_defaulted("log_level"));
+    }
+
+    #[tokio::test]
+    async fn check_requires_variables_without_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = |variables: &[&str]| CheckCommand {
+            options: options(&dir, variables),
+        };
+
+        let err = check(&[]).run().await.unwrap_err();
+        assert!(err.to_string().contains("api_key"), "{err}");
+        assert!(!err.to_string().contains("log_level"), "{err}");
+
+        check(&["api_key=secret"]).run().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_and_explain_succeed() {
+        let dir = tempfile::tempdir().unwrap();
+        ListCommand {
+            options: options(&dir, &[]),
+        }
+        .run()
+        .await
+        .unwrap();
+
+        let explain = |key: &str| ExplainCommand {
+            key: key.into(),
+            options: options(&dir, &["api_key=secret"]),
+        };
+        explain("api_key").run().await.unwrap();
+        explain("log_level").run().await.unwrap();
+        let err = explain("unknown").run().await.unwrap_err();
+        assert!(
+            err.to_string().contains("api_key, log_level, region"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let variable = |secret| Variable {
+            description: None,
+            default: None,
+            secret,
+        };
+        let source = Source::Provider(0, "hunter2".into());
+        assert_eq!(display_value(&variable(true), &source), "<redacted>");
+        assert_eq!(display_value(&variable(false), &source), "\"hunter2\"");
+    }
+}
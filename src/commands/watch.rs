@@ -1,6 +1,7 @@
 use std::{
+    collections::HashSet,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -20,10 +21,12 @@ use crate::{
 mod buildifier;
 mod filters;
 mod reconfiguriser;
+mod reloader;
 mod uppificator;
 
 use buildifier::Buildifier;
 use filters::{ArtifactFilterFactory, BuildFilterFactory, FilterFactory, ManifestFilterFactory};
+use reloader::ReloadChannel;
 use uppificator::{Pause, Uppificator};
 
 /// Build and run the Spin application, rebuilding and restarting it when files change.
@@ -83,6 +86,9 @@ impl WatchCommand {
         //   (and the manifest if build is not in play). When it detects a change, it restarts `spin up`.
         //   THAT'S ALL, THAT'S ALL IT DOES.
         //   * If `spin up` crashes, the Uppificator restarts it.  BUT APART FROM THAT THAT'S ALL IT DOES OKAY.
+        //   * Well, one more thing. If the only changes are to component Wasm files, the Uppificator first asks
+        //     the running triggers to reload just those components, over the ReloadChannel. This keeps the
+        //     other components serving. It falls back to restarting if that doesn't work out.
         // * The Buildifier, if in play, watches the manifest and component.build.watch collections. When it detects a
         //   change, it PAUSES the Uppificator, does the build, then unpauses the Uppificator.
         //   * It is on the Uppificator to recognise if any interesting files have changed when it unpauses.
@@ -114,6 +120,15 @@ impl WatchCommand {
         let (source_code_tx, source_code_rx) = tokio::sync::watch::channel(Uuid::new_v4());
         let (manifest_tx, manifest_rx) = tokio::sync::watch::channel(Uuid::new_v4());
         let (stop_tx, stop_rx) = tokio::sync::watch::channel(Uuid::new_v4());
+        let changed_artifacts = Arc::new(Mutex::new(HashSet::new()));
+
+        let reload_channel = match ReloadChannel::bind().await {
+            Ok(channel) => Some(channel),
+            Err(e) => {
+                tracing::warn!("Components will be restarted rather than reloaded: {e:#}");
+                None
+            }
+        };
 
         let mut buildifier = Buildifier {
             spin_bin: spin_bin.clone(),
@@ -132,6 +147,8 @@ impl WatchCommand {
             up_args: self.up_args.clone(),
             clear_screen: self.clear,
             watched_changes: artifact_rx,
+            changed_paths: changed_artifacts.clone(),
+            reload_channel,
             pause_feed: pause_rx,
             stopper: stop_rx,
         };
@@ -156,6 +173,7 @@ impl WatchCommand {
                 &manifest_dir,
                 artifact_filterer,
                 artifact_tx,
+                Some(changed_artifacts),
                 "reload",
             )
             .await
@@ -170,6 +188,7 @@ impl WatchCommand {
                 &manifest_dir,
                 build_filterer,
                 source_code_tx,
+                None,
                 "build",
             )
             .await
@@ -189,6 +208,7 @@ impl WatchCommand {
                 &manifest_dir,
                 manifest_filterer,
                 manifest_tx,
+                None,
                 "reconfigure",
             )
             .await
@@ -252,6 +272,7 @@ impl WatchCommand {
         manifest_dir: &Path,
         filter_factory: Box<dyn FilterFactory>,
         notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+        changed_paths: Option<ChangedPaths>,
         impact_description: &'static str,
     ) -> anyhow::Result<(ReconfigurableWatcher, tokio::task::JoinHandle<()>)> {
         let rtf = RuntimeConfigFactory {
@@ -260,6 +281,7 @@ impl WatchCommand {
             profile: self.profile.clone(),
             filter_factory,
             notifier,
+            changed_paths,
             impact_description,
             debounce: Duration::from_millis(self.debounce),
         };
@@ -284,6 +306,7 @@ pub struct RuntimeConfigFactory {
     profile: Option<String>,
     filter_factory: Box<dyn FilterFactory>,
     notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+    changed_paths: Option<ChangedPaths>,
     impact_description: &'static str,
     debounce: Duration,
 }
@@ -299,7 +322,11 @@ impl RuntimeConfigFactory {
             .build_filter(&self.manifest_file, &self.manifest_dir, &manifest)
             .await?;
 
-        let handler = NotifyOnFileChange::new(self.notifier.clone(), self.impact_description);
+        let handler = NotifyOnFileChange::new(
+            self.notifier.clone(),
+            self.changed_paths.clone(),
+            self.impact_description,
+        );
 
         rt.pathset([self.manifest_dir.as_path()]);
         rt.filterer(filterer);
@@ -313,6 +340,11 @@ impl RuntimeConfigFactory {
     }
 }
 
+// The paths that have changed since a notification was last handled. The
+// Uppificator uses these to decide whether it can reload components rather
+// than restarting.
+type ChangedPaths = Arc<Mutex<HashSet<PathBuf>>>;
+
 // This is the watchexec action handler that triggers the Uppificator
 // to reload or Builidifer to rebuild by sending a notification.
 // It is a struct rather than a closure because this makes it easier
@@ -321,17 +353,20 @@ impl RuntimeConfigFactory {
 struct NotifyOnFileChange {
     despurifier: despurifier::Despurifier,
     notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+    changed_paths: Option<ChangedPaths>,
     impact_description: &'static str,
 }
 
 impl NotifyOnFileChange {
     fn new(
         notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+        changed_paths: Option<ChangedPaths>,
         impact_description: &'static str,
     ) -> Self {
         Self {
             despurifier: despurifier::Despurifier::new(),
             notifier,
+            changed_paths,
             impact_description,
         }
     }
@@ -345,6 +380,15 @@ impl NotifyOnFileChange {
                 self.impact_description,
                 paths_of(&action)
             );
+            if let Some(changed_paths) = &self.changed_paths {
+                changed_paths.lock().unwrap().extend(
+                    action
+                        .events
+                        .iter()
+                        .flat_map(|e| e.tags.iter().filter_map(path_of_tag))
+                        .map(Path::to_path_buf),
+                );
+            }
             _ = self.notifier.send(Uuid::new_v4());
         }
 
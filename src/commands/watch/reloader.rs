@@ -0,0 +1,365 @@
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use path_absolutize::Absolutize;
+use spin_common::paths::parent_dir;
+use spin_manifest::schema::v2;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        TcpListener,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::Mutex,
+};
+
+/// How long to wait for a trigger to reload a component. Compiling a large
+/// component can take a while, especially in a debug build of Spin.
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+// The Uppificator uses the ReloadChannel to ask the triggers run by `spin up`
+// to reload rebuilt components in place, instead of restarting `spin up`.
+// Each trigger process connects to the channel's address, which is passed
+// down to it in the SPIN_RELOAD_ADDRESS environment variable. Requests are
+// component IDs, one per line, and the trigger replies to each with a line
+// which is either `ok` or `error <message>`.
+pub(crate) struct ReloadChannel {
+    address: SocketAddr,
+    triggers: Arc<Mutex<Vec<TriggerConnection>>>,
+}
+
+struct TriggerConnection {
+    requests: OwnedWriteHalf,
+    responses: Lines<BufReader<OwnedReadHalf>>,
+}
+
+impl ReloadChannel {
+    pub async fn bind() -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .context("failed to bind component reload channel")?;
+        let address = listener.local_addr()?;
+
+        let triggers = Arc::new(Mutex::new(vec![]));
+        let connected_triggers = triggers.clone();
+        tokio::task::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let (reader, writer) = stream.into_split();
+                        connected_triggers.lock().await.push(TriggerConnection {
+                            requests: writer,
+                            responses: BufReader::new(reader).lines(),
+                        });
+                    }
+                    Err(e) => tracing::debug!("Error accepting reload connection: {e:#}"),
+                }
+            }
+        });
+
+        Ok(Self { address, triggers })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Forgets all connected triggers. This must be called when `spin up`
+    /// is restarted.
+    pub async fn disconnect_all(&self) {
+        self.triggers.lock().await.clear();
+    }
+
+    /// Asks every connected trigger to reload the given components. If this
+    /// fails, the triggers may be running a mix of old and new components,
+    /// and `spin up` should be restarted.
+    pub async fn reload(&self, reload: &ComponentReload) -> Result<()> {
+        let mut triggers = self.triggers.lock().await;
+        // Triggers which don't support reloading never connect.
+        if triggers.len() != reload.trigger_count {
+            bail!(
+                "expected {} trigger(s) to be connected, but {} are",
+                reload.trigger_count,
+                triggers.len()
+            );
+        }
+        for trigger in triggers.iter_mut() {
+            for component_id in &reload.component_ids {
+                trigger
+                    .reload(component_id)
+                    .await
+                    .with_context(|| format!("failed to reload component {component_id:?}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TriggerConnection {
+    async fn reload(&mut self, component_id: &str) -> Result<()> {
+        self.requests
+            .write_all(format!("{component_id}\n").as_bytes())
+            .await?;
+        let response = tokio::time::timeout(RELOAD_TIMEOUT, self.responses.next_line())
+            .await
+            .context("timed out waiting for trigger")??;
+        match response.as_deref() {
+            Some("ok") => Ok(()),
+            Some(response) => bail!("{}", response.strip_prefix("error ").unwrap_or(response)),
+            None => bail!("trigger disconnected"),
+        }
+    }
+}
+
+/// The components to reload after some files changed.
+pub(crate) struct ComponentReload {
+    pub component_ids: Vec<String>,
+    /// The number of trigger processes that `spin up` runs for the application.
+    pub trigger_count: usize,
+}
+
+/// Works out which components to reload for the given changed files.
+///
+/// Returns `None` if any of the files is not a component's Wasm source (for
+/// example, if it's an asset file), in which case `spin up` must be restarted.
+pub(crate) fn components_to_reload(
+    manifest_file: &Path,
+    profile: Option<&str>,
+    changed_paths: &HashSet<PathBuf>,
+) -> Result<Option<ComponentReload>> {
+    if changed_paths.is_empty() {
+        return Ok(None);
+    }
+
+    let mut manifest = spin_manifest::manifest_from_file(manifest_file)?;
+    spin_manifest::normalize::normalize_manifest(&mut manifest, profile)?;
+    let manifest_dir = parent_dir(manifest_file)?;
+
+    let mut component_ids = vec![];
+    for changed_path in changed_paths {
+        let changed_path = changed_path.absolutize()?;
+        let mut is_source = false;
+        for (id, component) in &manifest.components {
+            let v2::ComponentSource::Local(source) = &component.source else {
+                continue;
+            };
+            if manifest_dir.join(source).absolutize()? == changed_path {
+                is_source = true;
+                component_ids.push(id.to_string());
+            }
+        }
+        if !is_source {
+            return Ok(None);
+        }
+    }
+    component_ids.sort();
+    component_ids.dedup();
+
+    // The built-in triggers share one process; see `spin up`.
+    let trigger_types = manifest
+        .triggers
+        .iter()
+        .filter(|(_, triggers)| !triggers.is_empty())
+        .map(|(trigger_type, _)| trigger_type.as_str());
+    let (builtin, plugins): (Vec<_>, Vec<_>) =
+        trigger_types.partition(|t| matches!(*t, "http" | "redis"));
+    let trigger_count = usize::from(!builtin.is_empty()) + plugins.len();
+
+    Ok(Some(ComponentReload {
+        component_ids,
+        trigger_count,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        spin_manifest_version = 2
+
+        [application]
+        name = "reload-test"
+
+        [[trigger.http]]
+        route = "/a"
+        component = "a"
+
+        [[trigger.http]]
+        route = "/shared"
+        component = "shared"
+
+        [[trigger.redis]]
+        channel = "messages"
+        component = "b"
+
+        [component.a]
+        source = "a.wasm"
+        files = ["assets/*"]
+
+        [component.shared]
+        source = "a.wasm"
+
+        [component.b]
+        source = "target/b.wasm"
+    "#;
+
+    fn write_manifest(dir: &Path, manifest: &str) -> PathBuf {
+        let manifest_file = dir.join("spin.toml");
+        std::fs::write(&manifest_file, manifest).unwrap();
+        manifest_file
+    }
+
+    fn paths(dir: &Path, paths: &[&str]) -> HashSet<PathBuf> {
+        paths.iter().map(|path| dir.join(path)).collect()
+    }
+
+    #[test]
+    fn rebuilt_sources_reload_their_components() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest_file = write_manifest(dir.path(), MANIFEST);
+
+        let reload = components_to_reload(
+            &manifest_file,
+            None,
+            &paths(dir.path(), &["a.wasm", "target/b.wasm"]),
+        )?
+        .expect("sources should be reloaded");
+        assert_eq!(reload.component_ids, ["a", "b", "shared"]);
+        // HTTP and Redis run in the same process.
+        assert_eq!(reload.trigger_count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn other_changes_restart_the_app() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest_file = write_manifest(dir.path(), MANIFEST);
+
+        let changed = paths(dir.path(), &["a.wasm", "assets/index.html"]);
+        assert!(components_to_reload(&manifest_file, None, &changed)?.is_none());
+        assert!(components_to_reload(&manifest_file, None, &HashSet::new())?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn plugin_triggers_run_in_their_own_processes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = format!(
+            r#"{MANIFEST}
+            [[trigger.cron]]
+            cron_expression = "* * * * *"
+            component = "b"
+            "#
+        );
+        let manifest_file = write_manifest(dir.path(), &manifest);
+
+        let reload = components_to_reload(&manifest_file, None, &paths(dir.path(), &["a.wasm"]))?
+            .expect("sources should be reloaded");
+        assert_eq!(reload.trigger_count, 2);
+        Ok(())
+    }
+
+    /// Connects a fake trigger to the channel, which responds to each request
+    /// with `respond(component_id)` and returns the requests it received once
+    /// the channel disconnects it.
+    async fn connect_trigger(
+        channel: &ReloadChannel,
+        respond: fn(&str) -> &'static str,
+    ) -> tokio::task::JoinHandle<Vec<String>> {
+        let expected_count = channel.triggers.lock().await.len() + 1;
+        let stream = TcpStream::connect(channel.address()).await.unwrap();
+        let trigger = tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut requests = vec![];
+            while let Ok(Some(component_id)) = lines.next_line().await {
+                let response = format!("{}\n", respond(&component_id));
+                writer.write_all(response.as_bytes()).await.unwrap();
+                requests.push(component_id);
+            }
+            requests
+        });
+        while channel.triggers.lock().await.len() < expected_count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        trigger
+    }
+
+    fn reload(component_ids: &[&str], trigger_count: usize) -> ComponentReload {
+        ComponentReload {
+            component_ids: component_ids.iter().map(|id| id.to_string()).collect(),
+            trigger_count,
+        }
+    }
+
+    #[tokio::test]
+    async fn every_trigger_is_asked_to_reload_every_component() -> Result<()> {
+        let channel = ReloadChannel::bind().await?;
+        let first = connect_trigger(&channel, |_| "ok").await;
+        let second = connect_trigger(&channel, |_| "ok").await;
+
+        channel.reload(&reload(&["a", "b"], 2)).await?;
+
+        channel.disconnect_all().await;
+        assert_eq!(first.await?, ["a", "b"]);
+        assert_eq!(second.await?, ["a", "b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn trigger_errors_fail_the_reload() -> Result<()> {
+        let channel = ReloadChannel::bind().await?;
+        let trigger = connect_trigger(&channel, |component_id| match component_id {
+            "a" => "ok",
+            _ => "error component is invalid",
+        })
+        .await;
+
+        let err = channel.reload(&reload(&["a", "b"], 1)).await.unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            r#"failed to reload component "b": component is invalid"#
+        );
+
+        channel.disconnect_all().await;
+        assert_eq!(trigger.await?, ["a", "b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_triggers_fail_the_reload() -> Result<()> {
+        let channel = ReloadChannel::bind().await?;
+        let _trigger = connect_trigger(&channel, |_| "ok").await;
+
+        let err = channel.reload(&reload(&["a"], 2)).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected 2 trigger(s) to be connected, but 1 are"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disconnected_triggers_fail_the_reload() -> Result<()> {
+        let channel = ReloadChannel::bind().await?;
+        let trigger = connect_trigger(&channel, |_| "ok").await;
+        trigger.abort();
+        _ = trigger.await;
+
+        let err = channel.reload(&reload(&["a"], 1)).await.unwrap_err();
+        assert!(
+            format!("{err:#}").starts_with(r#"failed to reload component "a": "#),
+            "unexpected error: {err:#}"
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,13 @@
 use command_group::AsyncCommandGroup;
-use std::path::PathBuf;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use uuid::Uuid;
 
+use super::reloader::{ReloadChannel, components_to_reload};
+
 pub(crate) struct Uppificator {
     pub spin_bin: PathBuf,
     pub up_args: Vec<String>,
@@ -9,6 +15,8 @@ pub(crate) struct Uppificator {
     pub profile: Option<String>,
     pub clear_screen: bool,
     pub watched_changes: tokio::sync::watch::Receiver<Uuid>,
+    pub changed_paths: Arc<Mutex<HashSet<PathBuf>>>,
+    pub reload_channel: Option<ReloadChannel>,
     pub pause_feed: tokio::sync::mpsc::Receiver<Pause>,
     pub stopper: tokio::sync::watch::Receiver<Uuid>,
 }
@@ -21,6 +29,7 @@ pub(crate) enum Pause {
 
 enum UppificatorAction {
     Restart,
+    Reloaded,
     Resume,
     Stop,
     Wait,
@@ -46,6 +55,15 @@ impl Uppificator {
             if let Some(profile) = &self.profile {
                 cmd.arg("--profile").arg(profile);
             }
+            if let Some(reload_channel) = &self.reload_channel {
+                reload_channel.disconnect_all().await;
+                cmd.env(
+                    spin_trigger::cli::SPIN_RELOAD_ADDRESS,
+                    reload_channel.address().to_string(),
+                );
+            }
+            // The new `spin up` loads everything afresh.
+            self.changed_paths.lock().unwrap().clear();
             let mut child = match cmd.group_spawn() {
                 Ok(ch) => ch,
                 Err(e) => {
@@ -59,6 +77,7 @@ impl Uppificator {
             loop {
                 match self.next_event(&mut child).await {
                     UppificatorAction::Restart => break,
+                    UppificatorAction::Reloaded => continue,
                     UppificatorAction::Resume => {
                         resuming_after_build = true;
                         continue;
@@ -83,8 +102,12 @@ impl Uppificator {
                 UppificatorAction::Wait
             },
             _ = self.watched_changes.changed() => {
-                stop(child).await;
-                UppificatorAction::Restart
+                if self.reload_changed_components().await {
+                    UppificatorAction::Reloaded
+                } else {
+                    stop(child).await;
+                    UppificatorAction::Restart
+                }
             },
             p = self.pause_feed.recv() => {
                 if matches!(p, Some(Pause::Pause)) {
@@ -104,6 +127,35 @@ impl Uppificator {
             }
         }
     }
+
+    // If only components' Wasm files have changed, asks the running triggers
+    // to reload just those components. Returns false if `spin up` needs to be
+    // restarted instead.
+    async fn reload_changed_components(&mut self) -> bool {
+        let changed_paths = std::mem::take(&mut *self.changed_paths.lock().unwrap());
+        let Some(reload_channel) = &self.reload_channel else {
+            return false;
+        };
+        let reload =
+            match components_to_reload(&self.manifest, self.profile.as_deref(), &changed_paths) {
+                Ok(Some(reload)) => reload,
+                Ok(None) => return false,
+                Err(e) => {
+                    tracing::debug!("Can't work out which components to reload: {e:#}");
+                    return false;
+                }
+            };
+        match reload_channel.reload(&reload).await {
+            Ok(()) => {
+                println!("Reloaded {}", reload.component_ids.join(", "));
+                true
+            }
+            Err(e) => {
+                tracing::debug!("Restarting instead of reloading components: {e:#}");
+                false
+            }
+        }
+    }
 }
 
 #[cfg(unix)]
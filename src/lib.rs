@@ -14,6 +14,7 @@ use commands::maintenance::MaintenanceCommands;
 use commands::{
     build::BuildCommand,
     cloud::{DeployCommand, LoginCommand},
+    compile::CompileCommand,
     doctor::DoctorCommand,
     external::execute_external_subcommand,
     new::{AddCommand, NewCommand},
@@ -21,9 +22,11 @@ use commands::{
     registry::RegistryCommands,
     templates::TemplateCommands,
     up::UpCommand,
+    variables::VariablesCommands,
     watch::WatchCommand,
 };
 use spin_runtime_factors::FactorsBuilder;
+use spin_trigger::MultiTrigger;
 use spin_trigger::cli::FactorsTriggerCommand;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
 use spin_trigger_http::HttpTrigger;
@@ -39,11 +42,12 @@ pub async fn run() -> anyhow::Result<()> {
     }
 
     let version = build_info();
-    spin_telemetry::init(
-        version.clone(),
-        spin_connection_semaphore::metric_histogram_buckets(),
-    )
-    .context("Failed to initialize telemetry")?;
+    let histogram_buckets = spin_connection_semaphore::metric_histogram_buckets()
+        .into_iter()
+        .chain(spin_trigger_http::metric_histogram_buckets())
+        .collect();
+    spin_telemetry::init(version.clone(), histogram_buckets)
+        .context("Failed to initialize telemetry")?;
 
     let plugin_help_entries = plugin_help_entries();
 
@@ -111,6 +115,7 @@ enum SpinApp {
     Registry(RegistryCommands),
     #[clap(alias = "b")]
     Build(BuildCommand),
+    Compile(CompileCommand),
     #[clap(subcommand, alias = "plugin")]
     Plugins(PluginCommands),
     #[clap(subcommand, alias = "environments")]
@@ -122,15 +127,19 @@ enum SpinApp {
     #[clap(alias = "w")]
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    #[clap(subcommand, alias = "vars")]
+    Variables(VariablesCommands),
     #[clap(subcommand, hide = true)]
     Maintenance(MaintenanceCommands),
 }
 
 #[derive(Subcommand)]
-#[expect(clippy::large_enum_variant)]
 enum TriggerCommands {
     Http(FactorsTriggerCommand<HttpTrigger, FactorsBuilder>),
     Redis(FactorsTriggerCommand<RedisTrigger, FactorsBuilder>),
+    // Runs the HTTP and Redis triggers in one process
+    #[clap(name = crate::opts::HTTP_REDIS_TRIGGER_TYPE, hide = true)]
+    HttpRedis(FactorsTriggerCommand<MultiTrigger<HttpTrigger, RedisTrigger>, FactorsBuilder>),
     #[clap(name = crate::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(FactorsTriggerCommand<HelpArgsOnlyTrigger, FactorsBuilder>),
 }
@@ -147,14 +156,17 @@ impl SpinApp {
             Self::Login(cmd) => cmd.run().await,
             Self::Registry(cmd) => cmd.run().await,
             Self::Build(cmd) => cmd.run().await,
+            Self::Compile(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::HttpRedis(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::Targets(cmd) => cmd.run().await,
             Self::External(args) => execute_external_subcommand(args, SpinApp::command()).await,
             Self::Watch(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Variables(cmd) => cmd.run().await,
             Self::Maintenance(cmd) => cmd.run().await,
         }
     }
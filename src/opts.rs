@@ -9,6 +9,7 @@ pub const PLUGIN_ALL_OPT: &str = "ALL";
 pub const PLUGIN_TARGET_ENV_OPT: &str = "TARGET_ENV";
 pub const PLUGIN_OVERRIDE_COMPATIBILITY_CHECK_FLAG: &str = "override-compatibility-check";
 pub const HELP_ARGS_ONLY_TRIGGER_TYPE: &str = "provide-help-args-no-app";
+pub const HTTP_REDIS_TRIGGER_TYPE: &str = "http+redis";
 pub const FROM_REGISTRY_OPT: &str = "REGISTRY_REFERENCE";
 pub const WATCH_CLEAR_OPT: &str = "CLEAR";
 pub const WATCH_DEBOUNCE_OPT: &str = "DEBOUNCE";
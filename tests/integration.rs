@@ -11,7 +11,8 @@ mod integration_tests {
     use testing_framework::runtimes::{SpinAppType, spin_cli::SpinConfig};
 
     pub use super::testcases::{
-        assert_spin_request, bootstap_env, http_smoke_test_template, preboot, run_test, spin_binary,
+        assert_spin_request, bootstap_env, http_smoke_test_template, preboot, run_test,
+        run_test_inited, spin_binary,
     };
 
     #[test]
@@ -37,7 +38,35 @@ mod integration_tests {
                     None,
                 ))?;
 
-        assert_eq!(response.status(), 500);
+        assert_eq!(response.status(), 504);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        Ok(())
+    }
+
+    #[test]
+    fn http_request_timeout_expires_wasip3_request() -> anyhow::Result<()> {
+        use spin_trigger_http::InstanceReuseConfig;
+        use std::time::{Duration, Instant};
+        use test_environment::{TestEnvironment, services::ServicesConfig};
+        use testing_framework::runtimes::in_process_spin::InProcessSpin;
+
+        let config = InProcessSpin::config_with_reuse_config(
+            ServicesConfig::none(),
+            InstanceReuseConfig::single_use_with_request_deadline(Duration::from_millis(100)),
+            |env| preboot("http-p3-never-responds", env),
+        );
+        let mut env = TestEnvironment::up(config, |_| Ok(()))?;
+        let started = Instant::now();
+        let response =
+            env.runtime_mut()
+                .make_http_request(test_environment::http::Request::full(
+                    test_environment::http::Method::Get,
+                    "/",
+                    &[("Host", "localhost")],
+                    None,
+                ))?;
+
+        assert_eq!(response.status(), 504);
         assert!(started.elapsed() < Duration::from_secs(2));
         Ok(())
     }
@@ -469,6 +498,46 @@ mod integration_tests {
         Ok(())
     }
 
+    #[test]
+    /// Test that `spin up` serves the trigger's metrics on the admin server
+    fn admin_server_serves_trigger_metrics() -> anyhow::Result<()> {
+        let admin_address = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        run_test_inited(
+            "http-smoke-test",
+            SpinConfig {
+                binary_path: spin_binary(),
+                spin_up_args: Vec::new(),
+                app_type: SpinAppType::Http,
+            },
+            ServicesConfig::none(),
+            move |env| {
+                env.set_env_var("SPIN_ADMIN_LISTEN_ADDRESS", admin_address.to_string());
+                Ok(())
+            },
+            move |env| {
+                let spin = env.runtime_mut();
+                assert_spin_request(
+                    spin,
+                    Request::new(Method::Get, "/hello"),
+                    Response::new_with_body(200, "I'm a teapot"),
+                )?;
+
+                let scrape = || -> anyhow::Result<String> {
+                    let url = format!("http://{admin_address}/metrics");
+                    Ok(reqwest::blocking::get(url)?.error_for_status()?.text()?)
+                };
+                let metrics = scrape().context("failed to scrape admin server")?;
+                assert!(
+                    metrics.contains("spin_http_server_request_duration"),
+                    "request metrics missing from scrape:\n{metrics}"
+                );
+                Ok(())
+            },
+        )?;
+
+        Ok(())
+    }
+
     #[test]
     /// Test that mounting works properly
     fn assets_routing_test() -> anyhow::Result<()> {
@@ -0,0 +1,27 @@
+#![deny(warnings)]
+
+wit_bindgen::generate!({
+    path: "../../../../wit",
+    world: "wasi:http/service@0.3.0",
+    generate_all,
+});
+
+use crate::{
+    exports::wasi::http0_3_0::handler::Guest,
+    wasi::{
+        clocks0_3_0::monotonic_clock,
+        http0_3_0::types::{ErrorCode, Request, Response},
+    },
+};
+
+struct Component;
+
+export!(Component);
+
+impl Guest for Component {
+    /// Waits far longer than any request timeout before responding.
+    async fn handle(_req: Request) -> Result<Response, ErrorCode> {
+        monotonic_clock::wait_for(u64::MAX).await;
+        Err(ErrorCode::InternalError(None))
+    }
+}
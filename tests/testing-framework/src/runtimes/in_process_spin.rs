@@ -5,7 +5,7 @@ use std::sync::Arc;
 use anyhow::Context as _;
 use spin_runtime_factors::{FactorsBuilder, TriggerAppArgs, TriggerFactors};
 use spin_trigger::{cli::TriggerAppBuilder, loader::ComponentLoader};
-use spin_trigger_http::{HttpServer, HttpTrigger, InstanceReuseConfig, OutputFormat};
+use spin_trigger_http::{HttpServer, HttpServerConfig, HttpTrigger, InstanceReuseConfig};
 use test_environment::{
     Runtime, TestEnvironment, TestEnvironmentConfig,
     http::{Request, Response},
@@ -120,9 +120,10 @@ async fn initialize_trigger(
         "127.0.0.1:80".parse().unwrap(),
         None,
         false,
-        None,
-        reuse_config,
-        OutputFormat::default(),
+        HttpServerConfig {
+            reuse_config,
+            ..Default::default()
+        },
     )?;
     let mut builder = TriggerAppBuilder::<_, FactorsBuilder>::new(trigger);
     let trigger_app = builder